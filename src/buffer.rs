@@ -19,7 +19,7 @@ impl<'a> Buffer<'a> {
         }
     }
 
-    pub fn get_bytes(&mut self, count: usize) -> &[u8] {
+    pub fn get_bytes(&mut self, count: usize) -> &'a [u8] {
         assert_eq!(self.bit_pos, 0);
 
         self.index += count;
@@ -102,19 +102,19 @@ impl<'a> Buffer<'a> {
     /// It is a requirement of bitstream conformance that the most significant
     /// bit of leb128_byte is equal to 0 if i is equal to 7. (This
     /// ensures that this syntax descriptor never uses more than 8 bytes.)
-    pub fn get_leb128(&mut self) -> u16 {
+    pub fn get_leb128(&mut self) -> u32 {
         assert_eq!(self.bit_pos, 0);
 
-        let mut value = 0;
+        let mut value: u64 = 0;
         for i in 0..8 {
-            let byte = self.get_bits(8) as u16;
+            let byte = self.get_bits(8) as u64;
             value |= (byte & 0x7f) << (i * 7);
             if byte & 0x80 == 0 {
                 break;
             }
         }
 
-        value
+        value as u32
     }
 
     /// Signed integer converted from an n bits unsigned integer in the
@@ -124,12 +124,58 @@ impl<'a> Buffer<'a> {
         let mut value = self.get_bits(count) as i32;
         let sign_mask = 1 << (count - 1) as i32;
 
-        if value > 0 && sign_mask > 0 {
-            value = value - 2 * sign_mask;
+        if value & sign_mask != 0 {
+            value -= 2 * sign_mask;
         }
 
         value
     }
+
+    /// Unsigned encoded integer with maximum number of values n (i.e. output in
+    /// range 0..n-1).
+    ///
+    /// This descriptor is similar to f(CeilLog2(n)), but reduces wastage
+    /// incurred when encoding non-power of two value ranges by encoding 1 fewer
+    /// bits for the lower part of the value range.
+    pub fn get_ns(&mut self, n: u32) -> u32 {
+        let w = 32 - n.leading_zeros() as usize;
+        let m = (1 << w) - n;
+        let v = if w > 1 { self.get_bits(w - 1) } else { 0 };
+        if v < m {
+            return v;
+        }
+
+        // extra_bit	f(1)
+        let extra_bit = self.get_bit() as u32;
+        (v << 1) - m + extra_bit
+    }
+
+    /// Bits read so far, counted from the start of the buffer.
+    pub fn get_position(&self) -> usize {
+        self.index * 8 + self.bit_pos
+    }
+
+    /// Bytes that have not been consumed yet, starting at the next byte
+    /// boundary.
+    pub fn get_remaining(&self) -> &'a [u8] {
+        let index = if self.bit_pos > 0 {
+            self.index + 1
+        } else {
+            self.index
+        };
+
+        &self.buf[index.min(self.buf.len())..]
+    }
+
+    /// Skip the padding bits up to the next byte boundary.
+    ///
+    /// It is a requirement of bitstream conformance that the skipped bits are
+    /// all equal to 0, this is not checked here.
+    pub fn byte_alignment(&mut self) {
+        while self.bit_pos != 0 {
+            self.advance();
+        }
+    }
 }
 
 impl<'a> Buffer<'a> {
@@ -158,3 +204,124 @@ impl<'a> AsMut<Buffer<'a>> for Buffer<'a> {
         self
     }
 }
+
+/// Writes the descriptors that [`Buffer`] reads, to build the bitstreams of
+/// the tests.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct BitWriter {
+    buf: Vec<u8>,
+    bit_pos: usize,
+}
+
+#[cfg(test)]
+impl BitWriter {
+    pub fn put_bit(&mut self, bit: bool) {
+        if self.bit_pos == 0 {
+            self.buf.push(0);
+        }
+
+        if bit {
+            *self.buf.last_mut().unwrap() |= 1 << (7 - self.bit_pos);
+        }
+
+        self.bit_pos = (self.bit_pos + 1) % 8;
+    }
+
+    pub fn put_bits(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            self.put_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    pub fn put_le(&mut self, value: u32, count: usize) {
+        for i in 0..count {
+            self.put_bits((value >> (i * 8)) & 0xff, 8);
+        }
+    }
+
+    pub fn put_leb128(&mut self, mut value: u32) {
+        loop {
+            let byte = value & 0x7f;
+            value >>= 7;
+            if value == 0 {
+                self.put_bits(byte, 8);
+                break;
+            }
+
+            self.put_bits(byte | 0x80, 8);
+        }
+    }
+
+    pub fn put_ns(&mut self, value: u32, n: u32) {
+        let w = 32 - n.leading_zeros() as usize;
+        let m = (1 << w) - n;
+        if value < m {
+            self.put_bits(value, w - 1);
+        } else {
+            // extra_bit
+            self.put_bits((value + m) >> 1, w - 1);
+            self.put_bit((value + m) & 1 == 1);
+        }
+    }
+
+    pub fn put_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.put_bits(byte as u32, 8);
+        }
+    }
+
+    pub fn byte_alignment(&mut self) {
+        while self.bit_pos != 0 {
+            self.put_bit(false);
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn su_extends_the_sign_bit() {
+        let mut buf = Buffer::new(&[0b1111_1110, 0b1000_0111, 0b0100_0000]);
+        assert_eq!(buf.get_su(7), -1);
+        assert_eq!(buf.get_su(1), 0);
+        assert_eq!(buf.get_su(4), -8);
+        assert_eq!(buf.get_su(4), 7);
+        assert_eq!(buf.get_su(7), 32);
+    }
+
+    #[test]
+    fn leb128_reads_values_wider_than_16_bits() {
+        let mut buf = Buffer::new(&[0xe5, 0x8e, 0x26, 0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert_eq!(buf.get_leb128(), 624485);
+        assert_eq!(buf.get_position(), 24);
+        assert_eq!(buf.get_leb128(), u32::MAX);
+        assert!(buf.get_remaining().is_empty());
+
+        // the encoding may be padded up to 8 bytes.
+        let mut buf = Buffer::new(&[0x81, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00, 0x2a]);
+        assert_eq!(buf.get_leb128(), 1);
+        assert_eq!(buf.get_position(), 64);
+    }
+
+    #[test]
+    fn ns_codes_the_low_values_with_one_bit_less() {
+        // n = 5: 0, 1 and 2 take 2 bits, 3 and 4 take 3 bits.
+        let mut buf = Buffer::new(&[0b0001_1011, 0b0111_0000]);
+        let values: Vec<_> = (0..5).map(|_| buf.get_ns(5)).collect();
+        assert_eq!(values, [0, 1, 2, 3, 4]);
+        assert_eq!(buf.get_position(), 12);
+
+        // a single value takes no bits, a power of two is a fixed length.
+        let mut buf = Buffer::new(&[0b1010_0000]);
+        assert_eq!(buf.get_ns(1), 0);
+        assert_eq!(buf.get_ns(8), 5);
+        assert_eq!(buf.get_position(), 3);
+    }
+}
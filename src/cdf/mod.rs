@@ -0,0 +1,257 @@
+pub mod default;
+
+use crate::constants::{
+    BLOCK_SIZES, BLOCK_SIZE_GROUPS, BWD_REFS, CFL_ALPHA_CONTEXTS, COMPOUND_IDX_CONTEXTS,
+    COMPOUND_MODE_CONTEXTS, COMP_GROUP_IDX_CONTEXTS, COMP_INTER_CONTEXTS, COMP_REF_TYPE_CONTEXTS,
+    DC_SIGN_CONTEXTS, DIRECTIONAL_MODES, DRL_MODE_CONTEXTS, EOB_COEF_CONTEXTS, FRAME_LF_COUNT,
+    FWD_REFS, INTERP_FILTER_CONTEXTS, INTRA_MODES, INTRA_MODE_CONTEXTS, IS_INTER_CONTEXTS,
+    LEVEL_CONTEXTS, MV_CONTEXTS, MV_OFFSET_BITS, NEW_MV_CONTEXTS, PALETTE_BLOCK_SIZE_CONTEXTS,
+    PALETTE_COLOR_CONTEXTS, PALETTE_UV_MODE_CONTEXTS, PALETTE_Y_MODE_CONTEXTS, PARTITION_CONTEXTS,
+    PLANE_TYPES, REF_CONTEXTS, REF_MV_CONTEXTS, SEGMENT_ID_CONTEXTS, SEGMENT_ID_PREDICTED_CONTEXTS,
+    SIG_COEF_CONTEXTS, SIG_COEF_CONTEXTS_EOB, SINGLE_REFS, SKIP_CONTEXTS, SKIP_MODE_CONTEXTS,
+    TXB_SKIP_CONTEXTS, TXFM_PARTITION_CONTEXTS, TX_SIZES, TX_SIZE_CONTEXTS, UNIDIR_COMP_REFS,
+    ZERO_MV_CONTEXTS,
+};
+
+use default::*;
+
+/// The last entry of each cdf array is the symbol counter, it is not part of
+/// the probabilities.
+pub trait CdfArray {
+    fn clear_counts(&mut self);
+}
+
+impl<const N: usize> CdfArray for [u16; N] {
+    fn clear_counts(&mut self) {
+        self[N - 1] = 0;
+    }
+}
+
+impl<T: CdfArray, const N: usize> CdfArray for [T; N] {
+    fn clear_counts(&mut self) {
+        for cdf in self.iter_mut() {
+            cdf.clear_counts();
+        }
+    }
+}
+
+macro_rules! cdf_context {
+    (
+        non_coeff {
+            $($field:ident: $ty:ty = $default:expr,)*
+        }
+        coeff {
+            $($coeff_field:ident: $coeff_ty:ty = $coeff_default:ident,)*
+        }
+    ) => {
+        /// All CDF arrays that are adapted while decoding the symbols of a
+        /// frame, and that are saved with a reference frame.
+        ///
+        /// see: https://aomediacodec.github.io/av1-spec/#cdf-selection-process
+        #[derive(Debug, Clone)]
+        pub struct CdfContext {
+            $(pub $field: $ty,)*
+            $(pub $coeff_field: $coeff_ty,)*
+        }
+
+        impl CdfContext {
+            /// Sets the CDF arrays that are not used by the coeffs( ) syntax
+            /// structure to their defaults.
+            pub fn init_non_coeff_cdfs(&mut self) {
+                $(self.$field = $default;)*
+            }
+
+            /// Sets the CDF arrays that are used by the coeffs( ) syntax
+            /// structure to the defaults selected by base_q_idx.
+            pub fn init_coeff_cdfs(&mut self, base_q_idx: u8) {
+                let idx = Self::coeff_cdf_q_ctx(base_q_idx);
+                $(self.$coeff_field = $coeff_default[idx];)*
+            }
+
+            /// Sets the symbol counter of every CDF array to 0.
+            pub fn clear_counts(&mut self) {
+                $(self.$field.clear_counts();)*
+                $(self.$coeff_field.clear_counts();)*
+            }
+
+            /// The CDF arrays of a frame that does not load them from a
+            /// reference frame, init_non_coeff_cdfs( ) followed by
+            /// init_coeff_cdfs( ).
+            pub fn new(base_q_idx: u8) -> Self {
+                Self {
+                    $($field: $default,)*
+                    $($coeff_field: $coeff_default[Self::coeff_cdf_q_ctx(base_q_idx)],)*
+                }
+            }
+        }
+    };
+}
+
+cdf_context! {
+    non_coeff {
+        intra_frame_y_mode_cdf: [[[u16; 14]; INTRA_MODE_CONTEXTS as usize]; INTRA_MODE_CONTEXTS as usize] = DEFAULT_INTRA_FRAME_Y_MODE_CDF,
+        y_mode_cdf: [[u16; 14]; BLOCK_SIZE_GROUPS as usize] = DEFAULT_Y_MODE_CDF,
+        uv_mode_cfl_not_allowed_cdf: [[u16; 14]; INTRA_MODES as usize] = DEFAULT_UV_MODE_CFL_NOT_ALLOWED_CDF,
+        uv_mode_cfl_allowed_cdf: [[u16; 15]; INTRA_MODES as usize] = DEFAULT_UV_MODE_CFL_ALLOWED_CDF,
+        angle_delta_cdf: [[u16; 8]; DIRECTIONAL_MODES as usize] = DEFAULT_ANGLE_DELTA_CDF,
+        intrabc_cdf: [u16; 3] = DEFAULT_INTRABC_CDF,
+        partition_w8_cdf: [[u16; 5]; PARTITION_CONTEXTS as usize] = DEFAULT_PARTITION_W8_CDF,
+        partition_w16_cdf: [[u16; 11]; PARTITION_CONTEXTS as usize] = DEFAULT_PARTITION_W16_CDF,
+        partition_w32_cdf: [[u16; 11]; PARTITION_CONTEXTS as usize] = DEFAULT_PARTITION_W32_CDF,
+        partition_w64_cdf: [[u16; 11]; PARTITION_CONTEXTS as usize] = DEFAULT_PARTITION_W64_CDF,
+        partition_w128_cdf: [[u16; 9]; PARTITION_CONTEXTS as usize] = DEFAULT_PARTITION_W128_CDF,
+        segment_id_cdf: [[u16; 9]; SEGMENT_ID_CONTEXTS as usize] = DEFAULT_SEGMENT_ID_CDF,
+        segment_id_predicted_cdf: [[u16; 3]; SEGMENT_ID_PREDICTED_CONTEXTS as usize] = DEFAULT_SEGMENT_ID_PREDICTED_CDF,
+        tx_8x8_cdf: [[u16; 3]; TX_SIZE_CONTEXTS as usize] = DEFAULT_TX_8X8_CDF,
+        tx_16x16_cdf: [[u16; 4]; TX_SIZE_CONTEXTS as usize] = DEFAULT_TX_16X16_CDF,
+        tx_32x32_cdf: [[u16; 4]; TX_SIZE_CONTEXTS as usize] = DEFAULT_TX_32X32_CDF,
+        tx_64x64_cdf: [[u16; 4]; TX_SIZE_CONTEXTS as usize] = DEFAULT_TX_64X64_CDF,
+        txfm_split_cdf: [[u16; 3]; TXFM_PARTITION_CONTEXTS as usize] = DEFAULT_TXFM_SPLIT_CDF,
+        filter_intra_mode_cdf: [u16; 6] = DEFAULT_FILTER_INTRA_MODE_CDF,
+        filter_intra_cdf: [[u16; 3]; BLOCK_SIZES as usize] = DEFAULT_FILTER_INTRA_CDF,
+        interp_filter_cdf: [[u16; 4]; INTERP_FILTER_CONTEXTS as usize] = DEFAULT_INTERP_FILTER_CDF,
+        motion_mode_cdf: [[u16; 4]; BLOCK_SIZES as usize] = DEFAULT_MOTION_MODE_CDF,
+        new_mv_cdf: [[u16; 3]; NEW_MV_CONTEXTS as usize] = DEFAULT_NEW_MV_CDF,
+        zero_mv_cdf: [[u16; 3]; ZERO_MV_CONTEXTS as usize] = DEFAULT_ZERO_MV_CDF,
+        ref_mv_cdf: [[u16; 3]; REF_MV_CONTEXTS as usize] = DEFAULT_REF_MV_CDF,
+        compound_mode_cdf: [[u16; 9]; COMPOUND_MODE_CONTEXTS as usize] = DEFAULT_COMPOUND_MODE_CDF,
+        drl_mode_cdf: [[u16; 3]; DRL_MODE_CONTEXTS as usize] = DEFAULT_DRL_MODE_CDF,
+        is_inter_cdf: [[u16; 3]; IS_INTER_CONTEXTS as usize] = DEFAULT_IS_INTER_CDF,
+        comp_mode_cdf: [[u16; 3]; COMP_INTER_CONTEXTS as usize] = DEFAULT_COMP_MODE_CDF,
+        skip_mode_cdf: [[u16; 3]; SKIP_MODE_CONTEXTS as usize] = DEFAULT_SKIP_MODE_CDF,
+        skip_cdf: [[u16; 3]; SKIP_CONTEXTS as usize] = DEFAULT_SKIP_CDF,
+        comp_ref_cdf: [[[u16; 3]; (FWD_REFS - 1) as usize]; REF_CONTEXTS as usize] = DEFAULT_COMP_REF_CDF,
+        comp_bwd_ref_cdf: [[[u16; 3]; (BWD_REFS - 1) as usize]; REF_CONTEXTS as usize] = DEFAULT_COMP_BWD_REF_CDF,
+        single_ref_cdf: [[[u16; 3]; (SINGLE_REFS - 1) as usize]; REF_CONTEXTS as usize] = DEFAULT_SINGLE_REF_CDF,
+        mv_joint_cdf: [[u16; 5]; MV_CONTEXTS as usize] = [DEFAULT_MV_JOINT_CDF; MV_CONTEXTS as usize],
+        mv_class_cdf: [[[u16; 12]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_CLASS_CDF; 2]; MV_CONTEXTS as usize],
+        mv_class0_bit_cdf: [[[u16; 3]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_CLASS0_BIT_CDF; 2]; MV_CONTEXTS as usize],
+        mv_class0_fr_cdf: [[[[u16; 5]; 2]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_CLASS0_FR_CDF; 2]; MV_CONTEXTS as usize],
+        mv_class0_hp_cdf: [[[u16; 3]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_CLASS0_HP_CDF; 2]; MV_CONTEXTS as usize],
+        mv_sign_cdf: [[[u16; 3]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_SIGN_CDF; 2]; MV_CONTEXTS as usize],
+        mv_bit_cdf: [[[[u16; 3]; MV_OFFSET_BITS as usize]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_BIT_CDF; 2]; MV_CONTEXTS as usize],
+        mv_fr_cdf: [[[u16; 5]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_FR_CDF; 2]; MV_CONTEXTS as usize],
+        mv_hp_cdf: [[[u16; 3]; 2]; MV_CONTEXTS as usize] = [[DEFAULT_MV_HP_CDF; 2]; MV_CONTEXTS as usize],
+        palette_y_mode_cdf: [[[u16; 3]; PALETTE_Y_MODE_CONTEXTS as usize]; PALETTE_BLOCK_SIZE_CONTEXTS as usize] = DEFAULT_PALETTE_Y_MODE_CDF,
+        palette_uv_mode_cdf: [[u16; 3]; PALETTE_UV_MODE_CONTEXTS as usize] = DEFAULT_PALETTE_UV_MODE_CDF,
+        palette_y_size_cdf: [[u16; 8]; PALETTE_BLOCK_SIZE_CONTEXTS as usize] = DEFAULT_PALETTE_Y_SIZE_CDF,
+        palette_uv_size_cdf: [[u16; 8]; PALETTE_BLOCK_SIZE_CONTEXTS as usize] = DEFAULT_PALETTE_UV_SIZE_CDF,
+        palette_size_2_y_color_cdf: [[u16; 3]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_2_Y_COLOR_CDF,
+        palette_size_3_y_color_cdf: [[u16; 4]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_3_Y_COLOR_CDF,
+        palette_size_4_y_color_cdf: [[u16; 5]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_4_Y_COLOR_CDF,
+        palette_size_5_y_color_cdf: [[u16; 6]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_5_Y_COLOR_CDF,
+        palette_size_6_y_color_cdf: [[u16; 7]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_6_Y_COLOR_CDF,
+        palette_size_7_y_color_cdf: [[u16; 8]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_7_Y_COLOR_CDF,
+        palette_size_8_y_color_cdf: [[u16; 9]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_8_Y_COLOR_CDF,
+        palette_size_2_uv_color_cdf: [[u16; 3]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_2_UV_COLOR_CDF,
+        palette_size_3_uv_color_cdf: [[u16; 4]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_3_UV_COLOR_CDF,
+        palette_size_4_uv_color_cdf: [[u16; 5]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_4_UV_COLOR_CDF,
+        palette_size_5_uv_color_cdf: [[u16; 6]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_5_UV_COLOR_CDF,
+        palette_size_6_uv_color_cdf: [[u16; 7]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_6_UV_COLOR_CDF,
+        palette_size_7_uv_color_cdf: [[u16; 8]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_7_UV_COLOR_CDF,
+        palette_size_8_uv_color_cdf: [[u16; 9]; PALETTE_COLOR_CONTEXTS as usize] = DEFAULT_PALETTE_SIZE_8_UV_COLOR_CDF,
+        delta_q_cdf: [u16; 5] = DEFAULT_DELTA_Q_CDF,
+        delta_lf_cdf: [u16; 5] = DEFAULT_DELTA_LF_CDF,
+        delta_lf_multi_cdf: [[u16; 5]; FRAME_LF_COUNT as usize] = [DEFAULT_DELTA_LF_CDF; FRAME_LF_COUNT as usize],
+        intra_tx_type_set1_cdf: [[[u16; 8]; INTRA_MODES as usize]; 2] = DEFAULT_INTRA_TX_TYPE_SET1_CDF,
+        intra_tx_type_set2_cdf: [[[u16; 6]; INTRA_MODES as usize]; 3] = DEFAULT_INTRA_TX_TYPE_SET2_CDF,
+        inter_tx_type_set1_cdf: [[u16; 17]; 2] = DEFAULT_INTER_TX_TYPE_SET1_CDF,
+        inter_tx_type_set2_cdf: [u16; 13] = DEFAULT_INTER_TX_TYPE_SET2_CDF,
+        inter_tx_type_set3_cdf: [[u16; 3]; 4] = DEFAULT_INTER_TX_TYPE_SET3_CDF,
+        comp_ref_type_cdf: [[u16; 3]; COMP_REF_TYPE_CONTEXTS as usize] = DEFAULT_COMP_REF_TYPE_CDF,
+        uni_comp_ref_cdf: [[[u16; 3]; (UNIDIR_COMP_REFS - 1) as usize]; REF_CONTEXTS as usize] = DEFAULT_UNI_COMP_REF_CDF,
+        comp_group_idx_cdf: [[u16; 3]; COMP_GROUP_IDX_CONTEXTS as usize] = DEFAULT_COMP_GROUP_IDX_CDF,
+        compound_idx_cdf: [[u16; 3]; COMPOUND_IDX_CONTEXTS as usize] = DEFAULT_COMPOUND_IDX_CDF,
+        compound_type_cdf: [[u16; 3]; BLOCK_SIZES as usize] = DEFAULT_COMPOUND_TYPE_CDF,
+        inter_intra_cdf: [[u16; 3]; BLOCK_SIZE_GROUPS as usize] = DEFAULT_INTER_INTRA_CDF,
+        inter_intra_mode_cdf: [[u16; 5]; BLOCK_SIZE_GROUPS as usize] = DEFAULT_INTER_INTRA_MODE_CDF,
+        wedge_index_cdf: [[u16; 17]; BLOCK_SIZES as usize] = DEFAULT_WEDGE_INDEX_CDF,
+        wedge_inter_intra_cdf: [[u16; 3]; BLOCK_SIZES as usize] = DEFAULT_WEDGE_INTER_INTRA_CDF,
+        use_obmc_cdf: [[u16; 3]; BLOCK_SIZES as usize] = DEFAULT_USE_OBMC_CDF,
+        cfl_sign_cdf: [u16; 9] = DEFAULT_CFL_SIGN_CDF,
+        cfl_alpha_cdf: [[u16; 17]; CFL_ALPHA_CONTEXTS as usize] = DEFAULT_CFL_ALPHA_CDF,
+        use_wiener_cdf: [u16; 3] = DEFAULT_USE_WIENER_CDF,
+        use_sgrproj_cdf: [u16; 3] = DEFAULT_USE_SGRPROJ_CDF,
+        restoration_type_cdf: [u16; 4] = DEFAULT_RESTORATION_TYPE_CDF,
+    }
+    coeff {
+        txb_skip_cdf: [[[u16; 3]; TXB_SKIP_CONTEXTS as usize]; TX_SIZES as usize] = DEFAULT_TXB_SKIP_CDF,
+        eob_pt_16_cdf: [[[u16; 6]; 2]; PLANE_TYPES as usize] = DEFAULT_EOB_PT_16_CDF,
+        eob_pt_32_cdf: [[[u16; 7]; 2]; PLANE_TYPES as usize] = DEFAULT_EOB_PT_32_CDF,
+        eob_pt_64_cdf: [[[u16; 8]; 2]; PLANE_TYPES as usize] = DEFAULT_EOB_PT_64_CDF,
+        eob_pt_128_cdf: [[[u16; 9]; 2]; PLANE_TYPES as usize] = DEFAULT_EOB_PT_128_CDF,
+        eob_pt_256_cdf: [[[u16; 10]; 2]; PLANE_TYPES as usize] = DEFAULT_EOB_PT_256_CDF,
+        eob_pt_512_cdf: [[u16; 11]; PLANE_TYPES as usize] = DEFAULT_EOB_PT_512_CDF,
+        eob_pt_1024_cdf: [[u16; 12]; PLANE_TYPES as usize] = DEFAULT_EOB_PT_1024_CDF,
+        eob_extra_cdf: [[[[u16; 3]; EOB_COEF_CONTEXTS as usize]; PLANE_TYPES as usize]; TX_SIZES as usize] = DEFAULT_EOB_EXTRA_CDF,
+        dc_sign_cdf: [[[u16; 3]; DC_SIGN_CONTEXTS as usize]; PLANE_TYPES as usize] = DEFAULT_DC_SIGN_CDF,
+        coeff_base_eob_cdf: [[[[u16; 4]; SIG_COEF_CONTEXTS_EOB as usize]; PLANE_TYPES as usize]; TX_SIZES as usize] = DEFAULT_COEFF_BASE_EOB_CDF,
+        coeff_base_cdf: [[[[u16; 5]; SIG_COEF_CONTEXTS as usize]; PLANE_TYPES as usize]; TX_SIZES as usize] = DEFAULT_COEFF_BASE_CDF,
+        coeff_br_cdf: [[[[u16; 5]; LEVEL_CONTEXTS as usize]; PLANE_TYPES as usize]; TX_SIZES as usize] = DEFAULT_COEFF_BR_CDF,
+    }
+}
+
+impl CdfContext {
+    /// The index into the default coefficient CDF tables.
+    fn coeff_cdf_q_ctx(base_q_idx: u8) -> usize {
+        match base_q_idx {
+            0..=20 => 0,
+            21..=60 => 1,
+            61..=120 => 2,
+            _ => 3,
+        }
+    }
+}
+
+impl Default for CdfContext {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coeff_cdfs_are_selected_by_base_q_idx() {
+        let mut cdf = CdfContext::default();
+        cdf.skip_cdf[0][0] = 1234;
+        for (base_q_idx, idx) in [
+            (0, 0),
+            (20, 0),
+            (21, 1),
+            (60, 1),
+            (61, 2),
+            (120, 2),
+            (121, 3),
+            (255, 3),
+        ] {
+            cdf.init_coeff_cdfs(base_q_idx);
+            assert_eq!(cdf.txb_skip_cdf, DEFAULT_TXB_SKIP_CDF[idx]);
+            assert_eq!(cdf.eob_pt_1024_cdf, DEFAULT_EOB_PT_1024_CDF[idx]);
+            assert_eq!(cdf.coeff_br_cdf, DEFAULT_COEFF_BR_CDF[idx]);
+            assert_eq!(
+                CdfContext::new(base_q_idx).coeff_base_cdf,
+                DEFAULT_COEFF_BASE_CDF[idx]
+            );
+        }
+
+        // the other CDF arrays are left alone.
+        assert_eq!(cdf.skip_cdf[0][0], 1234);
+        cdf.init_non_coeff_cdfs();
+        assert_eq!(cdf.skip_cdf, DEFAULT_SKIP_CDF);
+    }
+
+    #[test]
+    fn clear_counts_only_clears_the_last_entry() {
+        let mut cdf = CdfContext::default();
+        cdf.skip_cdf[1] = [1000, 0, 17];
+        cdf.mv_bit_cdf[1][0][9] = [2000, 0, 31];
+        cdf.coeff_base_cdf[4][1][41] = [3000, 2000, 1000, 0, 32];
+        cdf.clear_counts();
+        assert_eq!(cdf.skip_cdf[1], [1000, 0, 0]);
+        assert_eq!(cdf.mv_bit_cdf[1][0][9], [2000, 0, 0]);
+        assert_eq!(cdf.coeff_base_cdf[4][1][41], [3000, 2000, 1000, 0, 0]);
+    }
+}
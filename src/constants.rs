@@ -13,6 +13,7 @@ pub const MAX_TILE_COLS: u8 = 64; // Maximum number of tile columns
 pub const INTRABC_DELAY_PIXELS: u16 = 256; // Number of horizontal luma samples before intra block copy can be used
 pub const INTRABC_DELAY_SB64: u8 = 4; // Number of 64 by 64 blocks before intra block copy can be used
 pub const NUM_REF_FRAMES: u8 = 8; // Number of frames that can be stored for future reference
+pub const MAX_ANCHOR_FRAME_IDX: u8 = 127; // Largest index of an anchor frame of the large scale tile anchor set
 pub const IS_INTER_CONTEXTS: u8 = 4; // Number of contexts for is_inter
 pub const REF_CONTEXTS: u8 = 3; // Number of contexts for single_ref, comp_ref, comp_bwdref, uni_comp_ref,
                                 // uni_comp_ref_p1 and uni_comp_ref_p2
@@ -51,6 +52,7 @@ pub const H_ADST: u8 = 13; // Inverse transform rows with ADST and columns with
 pub const V_FLIPADST: u8 = 14; // Inverse transform rows with identity and columns with FLIPADST
 pub const H_FLIPADST: u8 = 15; // Inverse transform rows with FLIPADST and columns with identity
 pub const TX_TYPES: u8 = 16; // Number of inverse transform types
+pub const WHT_WHT: u8 = 16; // Inverse transform rows and columns with the Walsh-Hadamard transform of lossless blocks
 pub const MB_MODE_COUNT: u8 = 17; // Number of values for YMode
 pub const INTRA_MODES: u8 = 13; // Number of values for y_mode
 pub const UV_INTRA_MODES_CFL_NOT_ALLOWED: u8 = 13; // Number of values for uv_mode when chroma from luma is not allowed
@@ -86,11 +88,19 @@ pub const PALETTE_NUM_NEIGHBORS: u8 = 3; // Number of neighbors considered withi
 pub const DELTA_Q_SMALL: u8 = 3; // Value indicating alternative encoding of quantizer index delta values
 pub const DELTA_LF_SMALL: u8 = 3; // Value indicating alternative encoding of loop filter delta values
 pub const QM_TOTAL_SIZE: u16 = 3344; // Number of values in the quantizer matrix
+pub const AOM_QM_BITS: u8 = 5; // Number of fractional bits of the quantizer matrix values
+pub const NUM_QM_LEVELS: u8 = 16; // Number of quantizer matrix levels, the last level does not use a matrix
 pub const MAX_ANGLE_DELTA: u8 = 3; // Maximum magnitude of AngleDeltaY and AngleDeltaUV
 pub const DIRECTIONAL_MODES: u8 = 8; // Number of directional intra modes
 pub const ANGLE_STEP: u8 = 3; // Number of degrees of step per unit increase in AngleDeltaY or AngleDeltaUV.
 pub const TX_SET_TYPES_INTRA: u8 = 3; // Number of intra transform set types
 pub const TX_SET_TYPES_INTER: u8 = 4; // Number of inter transform set types
+pub const TX_SET_DCTONLY: u8 = 0; // Transform set that only contains DCT_DCT
+pub const TX_SET_INTRA_1: u8 = 1; // Intra transform set with the 1D DCT and identity transforms
+pub const TX_SET_INTRA_2: u8 = 2; // Reduced intra transform set
+pub const TX_SET_INTER_1: u8 = 1; // Inter transform set with all 16 transform types
+pub const TX_SET_INTER_2: u8 = 2; // Inter transform set without the 1D ADST and FLIPADST transforms
+pub const TX_SET_INTER_3: u8 = 3; // Inter transform set with only DCT_DCT and IDTX
 pub const WARPEDMODEL_PREC_BITS: u8 = 16; // Internal precision of warped motion models
 pub const IDENTITY: u8 = 0; // Warp model is just an identity transform
 pub const TRANSLATION: u8 = 1; // Warp model is a pure translation
@@ -200,3 +210,106 @@ pub const COEFF_CDF_Q_CTXS: u8 = 4; // Number of selectable context types for th
 pub const PRIMARY_REF_NONE: u8 = 7; // Value of primary_ref_frame indicating that there is no primary reference
                                     // frame
 pub const BUFFER_POOL_MAX_SIZE: u8 = 10; // Number of frames in buffer pool
+pub const INTRA_FRAME: u8 = 0; // Reference frame type of intra blocks
+pub const LAST_FRAME: u8 = 1; // First forward reference frame
+pub const LAST2_FRAME: u8 = 2; // Second forward reference frame
+pub const LAST3_FRAME: u8 = 3; // Third forward reference frame
+pub const GOLDEN_FRAME: u8 = 4; // Golden forward reference frame
+pub const BWDREF_FRAME: u8 = 5; // First backward reference frame
+pub const ALTREF2_FRAME: u8 = 6; // Second backward reference frame
+pub const ALTREF_FRAME: u8 = 7; // Alternative backward reference frame
+pub const BLOCK_4X4: u8 = 0; // 4x4 luma samples block size
+pub const BLOCK_4X8: u8 = 1; // 4x8 luma samples block size
+pub const BLOCK_8X4: u8 = 2; // 8x4 luma samples block size
+pub const BLOCK_8X8: u8 = 3; // 8x8 luma samples block size
+pub const BLOCK_8X16: u8 = 4; // 8x16 luma samples block size
+pub const BLOCK_16X8: u8 = 5; // 16x8 luma samples block size
+pub const BLOCK_16X16: u8 = 6; // 16x16 luma samples block size
+pub const BLOCK_16X32: u8 = 7; // 16x32 luma samples block size
+pub const BLOCK_32X16: u8 = 8; // 32x16 luma samples block size
+pub const BLOCK_32X32: u8 = 9; // 32x32 luma samples block size
+pub const BLOCK_32X64: u8 = 10; // 32x64 luma samples block size
+pub const BLOCK_64X32: u8 = 11; // 64x32 luma samples block size
+pub const BLOCK_64X64: u8 = 12; // 64x64 luma samples block size
+pub const BLOCK_64X128: u8 = 13; // 64x128 luma samples block size
+pub const BLOCK_128X64: u8 = 14; // 128x64 luma samples block size
+pub const BLOCK_128X128: u8 = 15; // 128x128 luma samples block size
+pub const BLOCK_4X16: u8 = 16; // 4x16 luma samples block size
+pub const BLOCK_16X4: u8 = 17; // 16x4 luma samples block size
+pub const BLOCK_8X32: u8 = 18; // 8x32 luma samples block size
+pub const BLOCK_32X8: u8 = 19; // 32x8 luma samples block size
+pub const BLOCK_16X64: u8 = 20; // 16x64 luma samples block size
+pub const BLOCK_64X16: u8 = 21; // 64x16 luma samples block size
+pub const DC_PRED: u8 = 0; // Intra prediction from the average of the above and left edges
+pub const V_PRED: u8 = 1; // Vertical intra prediction
+pub const H_PRED: u8 = 2; // Horizontal intra prediction
+pub const D45_PRED: u8 = 3; // Directional intra prediction at 45 degrees
+pub const D135_PRED: u8 = 4; // Directional intra prediction at 135 degrees
+pub const D113_PRED: u8 = 5; // Directional intra prediction at 113 degrees
+pub const D157_PRED: u8 = 6; // Directional intra prediction at 157 degrees
+pub const D203_PRED: u8 = 7; // Directional intra prediction at 203 degrees
+pub const D67_PRED: u8 = 8; // Directional intra prediction at 67 degrees
+pub const SMOOTH_PRED: u8 = 9; // Smooth intra prediction in both directions
+pub const SMOOTH_V_PRED: u8 = 10; // Smooth intra prediction in the vertical direction
+pub const SMOOTH_H_PRED: u8 = 11; // Smooth intra prediction in the horizontal direction
+pub const PAETH_PRED: u8 = 12; // Paeth intra prediction
+pub const UV_CFL_PRED: u8 = 13; // Chroma from luma prediction, only used by uv_mode
+pub const CFL_SIGN_ZERO: u8 = 0; // Chroma from luma alpha is zero
+pub const CFL_SIGN_NEG: u8 = 1; // Chroma from luma alpha is negative
+pub const CFL_SIGN_POS: u8 = 2; // Chroma from luma alpha is positive
+pub const NONE: i8 = -1; // Reference frame type of the second reference of a block that uses a single reference
+pub const NEARESTMV: u8 = 13; // Inter prediction from the nearest motion vector candidate
+pub const NEARMV: u8 = 14; // Inter prediction from a near motion vector candidate
+pub const GLOBALMV: u8 = 15; // Inter prediction from the global motion vector
+pub const NEWMV: u8 = 16; // Inter prediction from a motion vector coded as a difference
+pub const NEAREST_NEARESTMV: u8 = 17; // Compound prediction, NEARESTMV for both references
+pub const NEAR_NEARMV: u8 = 18; // Compound prediction, NEARMV for both references
+pub const NEAREST_NEWMV: u8 = 19; // Compound prediction, NEARESTMV for the first and NEWMV for the second reference
+pub const NEW_NEARESTMV: u8 = 20; // Compound prediction, NEWMV for the first and NEARESTMV for the second reference
+pub const NEAR_NEWMV: u8 = 21; // Compound prediction, NEARMV for the first and NEWMV for the second reference
+pub const NEW_NEARMV: u8 = 22; // Compound prediction, NEWMV for the first and NEARMV for the second reference
+pub const GLOBAL_GLOBALMV: u8 = 23; // Compound prediction, GLOBALMV for both references
+pub const NEW_NEWMV: u8 = 24; // Compound prediction, NEWMV for both references
+pub const SINGLE_REFERENCE: u8 = 0; // The block uses a single reference frame
+pub const COMPOUND_REFERENCE: u8 = 1; // The block uses a pair of reference frames
+pub const UNIDIR_COMP_REFERENCE: u8 = 0; // Both reference frames of the block are in the same direction
+pub const BIDIR_COMP_REFERENCE: u8 = 1; // One forward and one backward reference frame
+pub const COMPOUND_WEDGE: u8 = 0; // Compound prediction blended with a wedge mask
+pub const COMPOUND_DIFFWTD: u8 = 1; // Compound prediction blended with a mask from the difference of the predictions
+pub const COMPOUND_AVERAGE: u8 = 2; // Compound prediction that averages the two predictions
+pub const COMPOUND_INTRA: u8 = 3; // Inter intra prediction blended with a smooth mask
+pub const COMPOUND_DISTANCE: u8 = 4; // Compound prediction weighted by the distance to the reference frames
+pub const II_DC_PRED: u8 = 0; // Inter intra prediction with DC intra prediction
+pub const II_V_PRED: u8 = 1; // Inter intra prediction with vertical intra prediction
+pub const II_H_PRED: u8 = 2; // Inter intra prediction with horizontal intra prediction
+pub const II_SMOOTH_PRED: u8 = 3; // Inter intra prediction with smooth intra prediction
+pub const WEDGE_HORIZONTAL: u8 = 0; // Horizontal wedge mask boundary
+pub const WEDGE_VERTICAL: u8 = 1; // Vertical wedge mask boundary
+pub const WEDGE_OBLIQUE27: u8 = 2; // Wedge mask boundary at 27 degrees
+pub const WEDGE_OBLIQUE63: u8 = 3; // Wedge mask boundary at 63 degrees
+pub const WEDGE_OBLIQUE117: u8 = 4; // Wedge mask boundary at 117 degrees
+pub const WEDGE_OBLIQUE153: u8 = 5; // Wedge mask boundary at 153 degrees
+pub const MV_JOINT_ZERO: u8 = 0; // Both motion vector components are zero
+pub const MV_JOINT_HNZVZ: u8 = 1; // Horizontal component is nonzero, vertical component is zero
+pub const MV_JOINT_HZVNZ: u8 = 2; // Horizontal component is zero, vertical component is nonzero
+pub const MV_JOINT_HNZVNZ: u8 = 3; // Both motion vector components are nonzero
+pub const MV_CLASS_0: u8 = 0; // Smallest motion vector class
+pub const TX_4X4: u8 = 0; // 4x4 transform size
+pub const TX_8X8: u8 = 1; // 8x8 transform size
+pub const TX_16X16: u8 = 2; // 16x16 transform size
+pub const TX_32X32: u8 = 3; // 32x32 transform size
+pub const TX_64X64: u8 = 4; // 64x64 transform size
+pub const TX_4X8: u8 = 5; // 4x8 transform size
+pub const TX_8X4: u8 = 6; // 8x4 transform size
+pub const TX_8X16: u8 = 7; // 8x16 transform size
+pub const TX_16X8: u8 = 8; // 16x8 transform size
+pub const TX_16X32: u8 = 9; // 16x32 transform size
+pub const TX_32X16: u8 = 10; // 32x16 transform size
+pub const TX_32X64: u8 = 11; // 32x64 transform size
+pub const TX_64X32: u8 = 12; // 64x32 transform size
+pub const TX_4X16: u8 = 13; // 4x16 transform size
+pub const TX_16X4: u8 = 14; // 16x4 transform size
+pub const TX_8X32: u8 = 15; // 8x32 transform size
+pub const TX_32X8: u8 = 16; // 32x8 transform size
+pub const TX_16X64: u8 = 17; // 16x64 transform size
+pub const TX_64X16: u8 = 18; // 64x16 transform size
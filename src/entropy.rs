@@ -0,0 +1,422 @@
+use crate::{
+    constants::{EC_MIN_PROB, EC_PROB_SHIFT},
+    util::floor_log2,
+    Av1DecodeError, Av1DecodeInvalidError, Buffer,
+};
+
+/// The arithmetic decoder used for all symbols in the tile data.
+///
+/// A cdf array for a symbol with N values has N + 1 entries, the entries
+/// 0..N are the cumulative probabilities scaled by 1 << 15 (the entry N - 1
+/// is always 32768) and the entry N is the counter used by the adaptation.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#symbol-decoding-process
+pub struct SymbolDecoder<'a> {
+    data: &'a [u8],
+    buf: Buffer<'a>,
+    symbol_value: u32,
+    symbol_range: u32,
+    symbol_max_bits: i32,
+    disable_cdf_update: bool,
+}
+
+impl<'a> SymbolDecoder<'a> {
+    /// init_symbol( sz ), the whole slice is the tile data, sz is the length of
+    /// the slice.
+    ///
+    /// It is a requirement of bitstream conformance that the tile data is not
+    /// empty.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#initialization-process-for-symbol-decoder
+    pub fn new(data: &'a [u8], disable_cdf_update: bool) -> Self {
+        let mut buf = Buffer::new(data);
+        let num_bits = (data.len() * 8).min(15);
+
+        // buf	f(numBits)
+        let value = if num_bits > 0 {
+            buf.get_bits(num_bits)
+        } else {
+            0
+        };
+
+        let padded_buf = value << (15 - num_bits);
+        Self {
+            data,
+            buf,
+            symbol_value: ((1 << 15) - 1) ^ padded_buf,
+            symbol_range: 1 << 15,
+            symbol_max_bits: 8 * data.len() as i32 - 15,
+            disable_cdf_update,
+        }
+    }
+
+    /// read_symbol( cdf ), decodes a symbol and adapts the cdf to it unless
+    /// disable_cdf_update is equal to 1.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#decoding-process-for-symbols
+    pub fn read_symbol(&mut self, cdf: &mut [u16]) -> usize {
+        let n = cdf.len() - 1;
+
+        let mut cur = self.symbol_range;
+        let mut prev;
+        let mut symbol = 0;
+        loop {
+            prev = cur;
+            let f = (1 << 15) - cdf[symbol] as u32;
+            cur = ((self.symbol_range >> 8) * (f >> EC_PROB_SHIFT)) >> (7 - EC_PROB_SHIFT);
+            cur += EC_MIN_PROB as u32 * (n - symbol - 1) as u32;
+            if self.symbol_value >= cur {
+                break;
+            }
+
+            symbol += 1;
+        }
+
+        self.symbol_range = prev - cur;
+        self.symbol_value -= cur;
+
+        // renormalization
+        let bits = 15 - floor_log2(self.symbol_range) as usize;
+        self.symbol_range <<= bits;
+
+        let num_bits = bits.min(self.symbol_max_bits.max(0) as usize);
+        let new_data = if num_bits > 0 {
+            self.buf.get_bits(num_bits)
+        } else {
+            0
+        };
+
+        let padded_data = new_data << (bits - num_bits);
+        self.symbol_value = padded_data ^ (((self.symbol_value + 1) << bits) - 1);
+        self.symbol_max_bits -= bits as i32;
+
+        if !self.disable_cdf_update {
+            adapt_cdf(cdf, symbol);
+        }
+
+        symbol
+    }
+
+    /// read_bool( ), a symbol with two equally likely values, the cdf is not
+    /// kept between calls.
+    pub fn read_bool(&mut self) -> bool {
+        let mut cdf = [1 << 14, 1 << 15, 0];
+        self.read_symbol(&mut cdf) == 1
+    }
+
+    /// read_literal( n ), an unsigned n-bit number made of n booleans, most
+    /// significant bit first.
+    pub fn read_literal(&mut self, n: usize) -> u32 {
+        let mut x = 0;
+        for _ in 0..n {
+            x = 2 * x + self.read_bool() as u32;
+        }
+
+        x
+    }
+
+    /// NS( n ), a non-symmetric unsigned value in the range 0..n made of
+    /// literals.
+    pub fn read_ns(&mut self, n: u32) -> u32 {
+        let w = 32 - n.leading_zeros() as usize;
+        let m = (1 << w) - n;
+        let v = self.read_literal(w - 1);
+        if v < m {
+            return v;
+        }
+
+        // extra_bit	L(1)
+        let extra_bit = self.read_literal(1);
+        (v << 1) - m + extra_bit
+    }
+
+    /// exit_symbol( ), skips the padding at the end of the tile data.
+    ///
+    /// It is a requirement of bitstream conformance that the decoder has not
+    /// read more than 14 bits past the end of the tile data, that the bit at
+    /// trailingBitPosition is equal to 1 and that all bits after it up to the
+    /// end of the tile data are equal to 0.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#exit-process-for-symbol-decoder
+    pub fn exit(mut self) -> Result<(), Av1DecodeError> {
+        if self.symbol_max_bits < -14 {
+            return Err(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::SymbolPadding,
+            ));
+        }
+
+        let trailing_bit_position =
+            self.buf.get_position() - (self.symbol_max_bits + 15).min(15) as usize;
+
+        self.buf.seek_bits(self.symbol_max_bits.max(0) as usize);
+        let padding_end_position = self.buf.get_position();
+
+        let bit = |position: usize| (self.data[position / 8] >> (7 - position % 8)) & 1;
+        if bit(trailing_bit_position) != 1
+            || (trailing_bit_position + 1..padding_end_position).any(|p| bit(p) != 0)
+        {
+            return Err(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::SymbolPadding,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The adaptation of the cdf to a decoded symbol, the probabilities move
+/// towards the symbol at a rate that slows down as the counter grows.
+fn adapt_cdf(cdf: &mut [u16], symbol: usize) {
+    let n = cdf.len() - 1;
+    let rate = 3 + (cdf[n] > 15) as u32 + (cdf[n] > 31) as u32 + floor_log2(n as u32).min(2);
+
+    let mut tmp = 0;
+    for (i, value) in cdf.iter_mut().enumerate().take(n - 1) {
+        if i == symbol {
+            tmp = 1 << 15;
+        }
+
+        if tmp < *value as u32 {
+            *value -= ((*value as u32 - tmp) >> rate) as u16;
+        } else {
+            *value += ((tmp - *value as u32) >> rate) as u16;
+        }
+    }
+
+    cdf[n] += (cdf[n] < 32) as u16;
+}
+
+/// Encodes the symbols that [`SymbolDecoder`] decodes, to build the tile
+/// data of the tests. The cdfs are adapted as the decoder adapts them.
+#[cfg(test)]
+pub(crate) struct SymbolWriter {
+    /// The bits of the low end of the range above the last 16, most
+    /// significant first, only a carry still changes them.
+    bits: Vec<bool>,
+    /// The last 16 bits of the low end of the range.
+    low: u32,
+    range: u32,
+    /// The number of bits of the low end of the range.
+    num_bits: usize,
+}
+
+#[cfg(test)]
+impl Default for SymbolWriter {
+    fn default() -> Self {
+        Self {
+            bits: Vec::new(),
+            low: 0,
+            range: 1 << 15,
+            num_bits: 15,
+        }
+    }
+}
+
+#[cfg(test)]
+impl SymbolWriter {
+    pub fn write_symbol(&mut self, symbol: usize, cdf: &mut [u16]) {
+        let n = cdf.len() - 1;
+        let bound = |range: u32, s: usize| {
+            let f = (1 << 15) - cdf[s] as u32;
+            (((range >> 8) * (f >> EC_PROB_SHIFT)) >> (7 - EC_PROB_SHIFT))
+                + EC_MIN_PROB as u32 * (n - s - 1) as u32
+        };
+
+        // the decoder compares SymbolValue, the range minus one minus the
+        // offset of the data from the low end, with the bounds of the
+        // symbols from the first one down.
+        let upper = if symbol > 0 {
+            bound(self.range, symbol - 1)
+        } else {
+            self.range
+        };
+
+        let lower = bound(self.range, symbol);
+        self.low += self.range - upper;
+        self.range = upper - lower;
+        if self.low >= 1 << 16 {
+            self.low -= 1 << 16;
+            self.carry();
+        }
+
+        while self.range < 1 << 15 {
+            self.range <<= 1;
+            self.low <<= 1;
+            self.num_bits += 1;
+            if self.num_bits > 16 {
+                self.bits.push(self.low >> 16 == 1);
+                self.low &= 0xffff;
+            }
+        }
+
+        adapt_cdf(cdf, symbol);
+    }
+
+    /// Adds the carry out of the last 16 bits to the bits above them.
+    fn carry(&mut self) {
+        for bit in self.bits.iter_mut().rev() {
+            *bit = !*bit;
+            if *bit {
+                return;
+            }
+        }
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_symbol(value as usize, &mut [1 << 14, 1 << 15, 0]);
+    }
+
+    pub fn write_literal(&mut self, value: u32, n: usize) {
+        for i in (0..n).rev() {
+            self.write_bool((value >> i) & 1 == 1);
+        }
+    }
+
+    pub fn write_ns(&mut self, value: u32, n: u32) {
+        let w = 32 - n.leading_zeros() as usize;
+        let m = (1 << w) - n;
+        if value < m {
+            self.write_literal(value, w - 1);
+        } else {
+            // extra_bit
+            self.write_literal((value + m) >> 1, w - 1);
+            self.write_literal((value + m) & 1, 1);
+        }
+    }
+
+    /// The low end of the final range, followed by zeros.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        let window = self.num_bits.min(16);
+        self.bits
+            .extend((0..window).rev().map(|i| (self.low >> i) & 1 == 1));
+        let mut bytes = vec![0; self.bits.len().div_ceil(8) + 2];
+        for (i, &bit) in self.bits.iter().enumerate() {
+            bytes[i / 8] |= (bit as u8) << (7 - i % 8);
+        }
+
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_bool_from_zeros() {
+        // SymbolValue = 32767, cur = ((32768 >> 8) * (16384 >> 6) >> 1) + 4 =
+        // 16388, SymbolValue >= cur so the symbol is 0.
+        let data = [0x00, 0x00];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        assert!(!decoder.read_bool());
+        assert_eq!(decoder.symbol_range, 65520);
+        assert_eq!(decoder.symbol_value, 65519);
+        assert_eq!(decoder.symbol_max_bits, -1);
+    }
+
+    #[test]
+    fn read_bool_from_ones() {
+        // SymbolValue = 0 is less than 16388 for symbol 0, and not less than
+        // cur = 0 for symbol 1.
+        let data = [0xFF, 0xFF];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        assert!(decoder.read_bool());
+        assert_eq!(decoder.symbol_range, 32776);
+    }
+
+    #[test]
+    fn read_literal_sequence() {
+        // Equally likely booleans return the leading bits of the data until
+        // EC_MIN_PROB skews the split of the range.
+        let data = [0xA5, 0x3C, 0x0F, 0x80];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        assert_eq!(decoder.read_literal(4), 0xA);
+        assert_eq!(decoder.read_literal(4), 0x5);
+        assert_eq!(decoder.read_literal(8), 0x41);
+    }
+
+    #[test]
+    fn read_symbol_adapts_cdf() {
+        // N = 2 so rate = 3 + 0 + 0 + Min(FloorLog2(2), 2) = 4, decoding symbol
+        // 0 moves cdf[0] towards 32768 by (32768 - 16384) >> 4.
+        let data = [0x00, 0x00];
+        let mut cdf = [16384, 32768, 0];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        assert_eq!(decoder.read_symbol(&mut cdf), 0);
+        assert_eq!(cdf, [17408, 32768, 1]);
+
+        let mut cdf = [16384, 32768, 0];
+        let mut decoder = SymbolDecoder::new(&data, true);
+        assert_eq!(decoder.read_symbol(&mut cdf), 0);
+        assert_eq!(cdf, [16384, 32768, 0]);
+    }
+
+    #[test]
+    fn read_symbol_multi() {
+        // A uniform 4 symbol cdf, SymbolValue = 32767 ^ (0x7FFF >> 0) selects
+        // the last symbol for all ones.
+        let data = [0xFF, 0xFF, 0xFF];
+        let mut cdf = [8192, 16384, 24576, 32768, 0];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        assert_eq!(decoder.read_symbol(&mut cdf), 3);
+
+        // rate = 3 + Min(FloorLog2(4), 2) = 5, all entries before the symbol
+        // move towards 0.
+        assert_eq!(cdf, [8192 - 256, 16384 - 512, 24576 - 768, 32768, 1]);
+    }
+
+    #[test]
+    fn exit_checks_trailing_bits() {
+        // After one boolean 16 bits have been read and SymbolMaxBits is -1, so
+        // trailingBitPosition is 16 - 14 = 2.
+        let data = [0x20, 0x00];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        assert!(!decoder.read_bool());
+        assert!(decoder.exit().is_ok());
+
+        let data = [0x00, 0x00];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        decoder.read_bool();
+        assert_eq!(
+            decoder.exit().err(),
+            Some(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::SymbolPadding
+            ))
+        );
+
+        let data = [0x20, 0x01];
+        let mut decoder = SymbolDecoder::new(&data, false);
+        decoder.read_bool();
+        assert!(decoder.exit().is_err());
+    }
+
+    #[test]
+    fn written_symbols_are_read_back() {
+        let mut w = SymbolWriter::default();
+        let mut cdfs = [
+            vec![16384, 32768, 0],
+            vec![4000, 30000, 32000, 32768, 0],
+            vec![100, 200, 32700, 32768, 0],
+        ];
+
+        let symbols: Vec<_> = (0..2000usize).map(|i| (i % 3, i * 7 % 5 % 4)).collect();
+        let mut write_cdfs = cdfs.clone();
+        for &(c, symbol) in &symbols {
+            let symbol = symbol.min(write_cdfs[c].len() - 2);
+            w.write_symbol(symbol, &mut write_cdfs[c]);
+        }
+
+        w.write_literal(0x2a5, 10);
+        w.write_ns(4, 5);
+        let data = w.into_bytes();
+        let mut decoder = SymbolDecoder::new(&data, false);
+        for &(c, symbol) in &symbols {
+            let symbol = symbol.min(cdfs[c].len() - 2);
+            assert_eq!(decoder.read_symbol(&mut cdfs[c]), symbol);
+        }
+
+        assert_eq!(cdfs, write_cdfs);
+        assert_eq!(decoder.read_literal(10), 0x2a5);
+        assert_eq!(decoder.read_ns(5), 4);
+    }
+}
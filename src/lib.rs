@@ -1,12 +1,33 @@
 pub mod buffer;
+pub mod cdf;
 pub mod constants;
+pub mod entropy;
+pub mod motion_field;
 pub mod obu;
+pub mod picture;
+pub mod predict;
+pub mod quant;
+pub mod tables;
+pub mod tile;
+pub mod transform;
 pub mod util;
 
+use std::sync::Arc;
+
 use buffer::Buffer;
-use constants::NUM_REF_FRAMES;
-use obu::ObuHeaderExtension;
-use obu::{frame_header::FrameHeader, sequence_header::SequenceHeader};
+use cdf::CdfContext;
+use constants::{MAX_ANCHOR_FRAME_IDX, NUM_REF_FRAMES, PRIMARY_REF_NONE};
+use motion_field::{MotionFieldMvs, SavedMvs};
+use obu::{
+    frame::Frame,
+    frame_header::{FilmGrainParams, FrameHeader, FrameType},
+    sequence_header::SequenceHeader,
+    tile_group::TileGroup,
+    tile_list::TileList,
+    Obu, ObuDecodeRet, ObuHeaderExtension, ObuKind,
+};
+use picture::Picture;
+use tile::{mode_info::ModeInfoGrid, partition::PartitionNode, restoration::LrUnits, TileDecoder};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Av1DecodeUnknownError {
@@ -22,27 +43,131 @@ pub enum Av1DecodeUnknownError {
     InterpolationFilter,
 }
 
+/// The bitstream violates a requirement of bitstream conformance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Av1DecodeInvalidError {
+    /// A reference slot that is not valid (RefValid is equal to 0) is used.
+    MissingReference,
+    /// A frame_header_copy is not bit for bit identical to the frame header
+    /// it repeats.
+    FrameHeaderCopyMismatch,
+    /// A frame OBU contains a frame header with show_existing_frame equal to
+    /// 1.
+    ShowExistingFrameInFrame,
+    /// A tile group is not preceded by a frame header in the same temporal
+    /// unit, or a tile list is not preceded by any frame header.
+    MissingFrameHeader,
+    /// tg_start and tg_end do not describe a valid range of tiles.
+    TileGroupRange,
+    /// A tile size points past the end of the tile group or tile list OBU.
+    TileSize,
+    /// anchor_frame_idx is outside of the large scale tile anchor set, or
+    /// no reference slot holds the decoded anchor frame.
+    AnchorFrameIdx,
+    /// anchor_tile_row or anchor_tile_col is outside of the anchor frame.
+    AnchorTile,
+    /// A tile list contains more than 512 tiles.
+    TileCount,
+    /// The symbol decoder read past the end of the tile data, or the padding
+    /// at the end of the tile data is not a single 1 bit followed by 0 bits.
+    SymbolPadding,
+    /// A motion vector is out of range, or an intra block copy motion vector
+    /// refers to samples that may not be used for prediction.
+    MotionVector,
+    /// The Exp-Golomb coded remainder of a coefficient is longer than 20
+    /// bits.
+    GolombLength,
+    /// An OBU header, obu_size or the OBU data extends past the end of the
+    /// buffer.
+    ObuSize,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Av1DecodeError {
     Unknown(Av1DecodeUnknownError),
+    Invalid(Av1DecodeInvalidError),
 }
 
 impl std::error::Error for Av1DecodeError {}
 
 impl std::fmt::Display for Av1DecodeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        write!(f, "{:?}", self)
     }
 }
 
-#[derive(Debug, Clone)]
+/// What to do when a copy of the frame header (in a redundant frame header
+/// OBU, or a frame header OBU repeated in the same temporal unit) does not
+/// match the frame header it repeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedundantFrameHeaderPolicy {
+    /// Copies are not verified.
+    Ignore,
+    /// A [`Av1DecoderEvent::FrameHeaderCopyMismatch`] event is produced and
+    /// decoding continues with the original frame header.
+    #[default]
+    Warn,
+    /// Decoding fails with [`Av1DecodeInvalidError::FrameHeaderCopyMismatch`].
+    Error,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Av1DecoderOptions {
     pub obu_size: Option<usize>,
+    pub redundant_frame_header: RedundantFrameHeaderPolicy,
+}
+
+/// A frame that is ready to be displayed, produced by the output process.
+#[derive(Debug, Clone)]
+pub struct Av1Frame {
+    pub picture: Arc<Picture>,
+    /// The film grain params to apply to the picture before it is displayed,
+    /// the picture itself never has film grain applied.
+    pub film_grain_params: Option<FilmGrainParams>,
+    pub frame_type: FrameType,
+    pub order_hint: u32,
+    pub upscaled_width: u16,
+    pub frame_height: u16,
+    pub render_width: u16,
+    pub render_height: u16,
+    /// The frame was displayed by a frame header with show_existing_frame
+    /// equal to 1.
+    pub show_existing_frame: bool,
 }
 
+#[derive(Debug, Clone)]
+pub enum Av1DecoderEvent {
+    Frame(Box<Av1Frame>),
+    /// A copy of the frame header did not match the frame header of the
+    /// current frame, the copy was ignored.
+    FrameHeaderCopyMismatch,
+}
+
+/// The state saved into a reference slot by the reference frame update
+/// process.
+#[derive(Debug, Clone)]
 pub struct Av1DecoderContextRef {
     pub sequence_header: SequenceHeader,
     pub frame_header: FrameHeader,
+    pub frame_id: u32,
+    pub upscaled_width: u16,
+    pub frame_width: u16,
+    pub frame_height: u16,
+    pub render_width: u16,
+    pub render_height: u16,
+    pub mi_cols: u32,
+    pub mi_rows: u32,
+    pub order_hint: u32,
+    pub bit_depth: u8,
+    pub picture: Arc<Picture>,
+    pub cdf: CdfContext,
+    /// SavedRefFrames and SavedMvs
+    pub saved_mvs: Arc<SavedMvs>,
+    /// SavedSegmentIds
+    pub saved_segment_ids: Arc<Vec<u8>>,
+    /// The index of the anchor frame held by the slot, None when the frame
+    /// is outside of the large scale tile anchor set.
+    pub anchor_frame_idx: Option<u8>,
 }
 
 pub struct Av1DecoderContext {
@@ -54,10 +179,12 @@ pub struct Av1DecoderContext {
     pub num_planes: u8,
     pub seen_frame_header: bool,
     pub sequence_header: Option<SequenceHeader>,
+    pub frame_header: Option<FrameHeader>,
     pub frame_is_intra: bool,
     pub refs: [Option<Av1DecoderContextRef>; NUM_REF_FRAMES as usize],
     pub order_hint: u32,
     pub obu_header_extension: Option<ObuHeaderExtension>,
+    pub current_frame_id: u32,
     pub frame_width: u16,
     pub frame_height: u16,
     pub superres_denom: u8,
@@ -67,4 +194,664 @@ pub struct Av1DecoderContext {
     pub render_width: u16,
     pub render_height: u16,
     pub delta_frame_id: u32,
+    /// CurrFrame, allocated once the frame header of a new frame is decoded.
+    pub current_frame: Option<Picture>,
+    /// The anchor frame index of the current frame. The frames decoded since
+    /// the last shown key frame are the anchor frames that anchor_frame_idx
+    /// of a tile list indexes, in decoding order.
+    pub anchor_frame_idx: Option<u8>,
+    /// The anchor frame index of the next decoded frame.
+    pub next_anchor_frame_idx: u8,
+    /// The CDF arrays of the current frame, each tile starts decoding from a
+    /// copy of them.
+    pub cdf: CdfContext,
+    /// The CDF arrays at the end of tile context_update_tile_id.
+    pub saved_cdf: Option<CdfContext>,
+    pub mode_info: ModeInfoGrid,
+    /// MotionFieldMvs of the current frame.
+    pub motion_field_mvs: MotionFieldMvs,
+    /// MfRefFrames and MfMvs of the current frame, stored at the end of the
+    /// frame for the motion field estimation of later frames.
+    pub mf_mvs: Arc<SavedMvs>,
+    /// PrevSegmentIds, the segmentation map that the segment ids of the
+    /// current frame are predicted from.
+    pub prev_segment_ids: Vec<u8>,
+    /// SegmentIds of the current frame, stored at the end of the frame.
+    pub segment_ids: Arc<Vec<u8>>,
+    /// cdef_idx of each 64x64 block of the current frame, in raster order.
+    pub cdef_idx: Vec<i8>,
+    /// The partition trees of the superblocks of the current frame, in
+    /// decoding order.
+    pub superblocks: Vec<PartitionNode>,
+    /// The loop restoration units of each plane of the current frame.
+    pub lr_units: [LrUnits; 3],
+    pub events: Vec<Av1DecoderEvent>,
+}
+
+impl Av1DecoderContext {
+    pub fn new(options: Av1DecoderOptions) -> Self {
+        Self {
+            options,
+            operating_point_idc: 0,
+            operating_point: 0,
+            order_hint_bits: 0,
+            bit_depth: 8,
+            num_planes: 3,
+            seen_frame_header: false,
+            sequence_header: None,
+            frame_header: None,
+            frame_is_intra: false,
+            refs: Default::default(),
+            order_hint: 0,
+            obu_header_extension: None,
+            current_frame_id: 0,
+            frame_width: 0,
+            frame_height: 0,
+            superres_denom: 0,
+            upscaled_width: 0,
+            mi_cols: 0,
+            mi_rows: 0,
+            render_width: 0,
+            render_height: 0,
+            delta_frame_id: 0,
+            current_frame: None,
+            anchor_frame_idx: None,
+            next_anchor_frame_idx: 0,
+            cdf: CdfContext::default(),
+            saved_cdf: None,
+            mode_info: ModeInfoGrid::default(),
+            motion_field_mvs: MotionFieldMvs::default(),
+            mf_mvs: Default::default(),
+            prev_segment_ids: Vec::new(),
+            segment_ids: Default::default(),
+            cdef_idx: Vec::new(),
+            superblocks: Vec::new(),
+            lr_units: Default::default(),
+            events: Vec::new(),
+        }
+    }
+
+    /// see: https://aomediacodec.github.io/av1-spec/#reference-frame-update-process
+    pub fn reference_frame_update(&mut self, picture: Arc<Picture>) {
+        let (Some(sequence_header), Some(frame_header)) =
+            (&self.sequence_header, &self.frame_header)
+        else {
+            return;
+        };
+
+        let refresh_frame_flags = frame_header.uncompressed_header.refresh_frame_flags;
+        for i in 0..NUM_REF_FRAMES as usize {
+            if (refresh_frame_flags >> i) & 1 == 1 {
+                self.refs[i] = Some(Av1DecoderContextRef {
+                    sequence_header: sequence_header.clone(),
+                    frame_header: frame_header.clone(),
+                    frame_id: self.current_frame_id,
+                    upscaled_width: self.upscaled_width,
+                    frame_width: self.frame_width,
+                    frame_height: self.frame_height,
+                    render_width: self.render_width,
+                    render_height: self.render_height,
+                    mi_cols: self.mi_cols,
+                    mi_rows: self.mi_rows,
+                    order_hint: self.order_hint,
+                    bit_depth: self.bit_depth,
+                    picture: picture.clone(),
+                    cdf: self.cdf.clone(),
+                    saved_mvs: self.mf_mvs.clone(),
+                    saved_segment_ids: self.segment_ids.clone(),
+                    anchor_frame_idx: self.anchor_frame_idx,
+                });
+            }
+        }
+    }
+
+    /// Loads the saved state of a reference slot back into the current frame
+    /// state, the slot must be valid.
+    ///
+    /// The frame header is not loaded, the frame header with
+    /// show_existing_frame equal to 1 already holds the saved header with
+    /// refresh_frame_flags set to allFrames.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#reference-frame-loading-process
+    pub fn load_reference_frame(&mut self, idx: usize) {
+        let Some(r) = self.refs[idx].as_ref() else {
+            return;
+        };
+
+        self.current_frame_id = r.frame_id;
+        self.upscaled_width = r.upscaled_width;
+        self.frame_width = r.frame_width;
+        self.frame_height = r.frame_height;
+        self.render_width = r.render_width;
+        self.render_height = r.render_height;
+        self.mi_cols = r.mi_cols;
+        self.mi_rows = r.mi_rows;
+        self.order_hint = r.order_hint;
+        self.bit_depth = r.bit_depth;
+        self.cdf = r.cdf.clone();
+        self.mf_mvs = r.saved_mvs.clone();
+        self.segment_ids = r.saved_segment_ids.clone();
+        self.anchor_frame_idx = r.anchor_frame_idx;
+    }
+
+    /// Allocates CurrFrame and the state that is decoded into by the tiles,
+    /// once the frame header of a new frame is decoded.
+    pub fn setup_current_frame(&mut self) {
+        let (Some(sequence_header), Some(frame_header)) =
+            (&self.sequence_header, &self.frame_header)
+        else {
+            return;
+        };
+
+        let use_ref_frame_mvs = frame_header.uncompressed_header.use_ref_frame_mvs;
+        let color_config = &sequence_header.color_config;
+        self.current_frame = Some(Picture::new(
+            self.frame_width as usize,
+            self.frame_height as usize,
+            self.bit_depth,
+            color_config,
+        ));
+
+        let lr_params = &frame_header.uncompressed_header.lr_params;
+        for plane in 0..self.num_planes as usize {
+            let (sub_x, sub_y) = if plane == 0 {
+                (0, 0)
+            } else {
+                (
+                    color_config.subsampling_x as u32,
+                    color_config.subsampling_y as u32,
+                )
+            };
+
+            self.lr_units[plane] = if lr_params.loop_restoration_size[plane] > 0 {
+                LrUnits::new(
+                    lr_params.loop_restoration_size[plane],
+                    self.upscaled_width,
+                    self.frame_height,
+                    sub_x,
+                    sub_y,
+                )
+            } else {
+                LrUnits::default()
+            };
+        }
+
+        self.mode_info = ModeInfoGrid::new(self.mi_rows, self.mi_cols);
+        self.motion_field_mvs = MotionFieldMvs::new(self.mi_rows, self.mi_cols);
+        if use_ref_frame_mvs {
+            self.motion_field_estimation();
+        }
+
+        self.prev_segment_ids = self.load_previous_segment_ids();
+
+        self.cdef_idx = vec![-1; (self.mi_rows.div_ceil(16) * self.mi_cols.div_ceil(16)) as usize];
+        self.superblocks.clear();
+        self.saved_cdf = None;
+    }
+
+    /// load_previous_segment_ids( ), the segmentation map of the primary
+    /// reference frame when segmentation is enabled and the frame has the
+    /// same size, otherwise all zero.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#load-previous-segment-ids-process
+    fn load_previous_segment_ids(&self) -> Vec<u8> {
+        let len = (self.mi_rows * self.mi_cols) as usize;
+        let Some(frame_header) = &self.frame_header else {
+            return vec![0; len];
+        };
+
+        let header = &frame_header.uncompressed_header;
+        if header.primary_ref_frame == PRIMARY_REF_NONE
+            || !header.segmentation_params.segmentation_enabled
+        {
+            return vec![0; len];
+        }
+
+        let prev_frame = header.ref_frame_idx[header.primary_ref_frame as usize] as usize;
+        match &self.refs[prev_frame] {
+            Some(r)
+                if r.mi_rows == self.mi_rows
+                    && r.mi_cols == self.mi_cols
+                    && r.saved_segment_ids.len() == len =>
+            {
+                r.saved_segment_ids.to_vec()
+            }
+            _ => vec![0; len],
+        }
+    }
+
+    /// The CDF arrays saved at the end of tile context_update_tile_id become
+    /// the CDF arrays of the frame, with the symbol counters cleared.
+    pub fn frame_end_update_cdf(&mut self) {
+        if let Some(mut cdf) = self.saved_cdf.take() {
+            cdf.clear_counts();
+            self.cdf = cdf;
+        }
+    }
+
+    /// see: https://aomediacodec.github.io/av1-spec/#decode-frame-wrapup-process
+    pub fn decode_frame_wrapup(&mut self) -> Result<(), Av1DecodeError> {
+        let Some(frame_header) = self.frame_header.clone() else {
+            return Ok(());
+        };
+
+        let header = &frame_header.uncompressed_header;
+        if header.show_existing_frame {
+            let idx = header.frame_to_show_map_idx as usize;
+            let r = self.refs[idx].as_ref().ok_or(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::MissingReference,
+            ))?;
+
+            // see: https://aomediacodec.github.io/av1-spec/#output-process
+            let picture = r.picture.clone();
+            self.events.push(Av1DecoderEvent::Frame(Box::new(Av1Frame {
+                picture: picture.clone(),
+                film_grain_params: header.film_grain_params.clone(),
+                frame_type: header.frame_type,
+                order_hint: r.order_hint,
+                upscaled_width: r.upscaled_width,
+                frame_height: r.frame_height,
+                render_width: r.render_width,
+                render_height: r.render_height,
+                show_existing_frame: true,
+            })));
+
+            // Showing a key frame resets the decoding process, the key frame is
+            // loaded and then saved into all reference slots
+            // (refresh_frame_flags is equal to allFrames).
+            if header.frame_type == FrameType::KeyFrame {
+                self.load_reference_frame(idx);
+                self.reference_frame_update(picture);
+            }
+        } else if let Some(picture) = self.current_frame.take() {
+            let picture = Arc::new(picture);
+            self.anchor_frame_idx = (self.next_anchor_frame_idx <= MAX_ANCHOR_FRAME_IDX)
+                .then_some(self.next_anchor_frame_idx);
+            self.next_anchor_frame_idx =
+                (self.next_anchor_frame_idx + 1).min(MAX_ANCHOR_FRAME_IDX + 1);
+            self.mf_mvs = Arc::new(self.motion_field_motion_vector_storage());
+            let segmentation_params = &header.segmentation_params;
+            self.segment_ids = Arc::new(
+                if segmentation_params.segmentation_enabled
+                    && !segmentation_params.segmentation_update_map
+                {
+                    self.prev_segment_ids.clone()
+                } else {
+                    self.mode_info.segment_ids()
+                },
+            );
+            if header.show_frame {
+                // see: https://aomediacodec.github.io/av1-spec/#output-process
+                self.events.push(Av1DecoderEvent::Frame(Box::new(Av1Frame {
+                    picture: picture.clone(),
+                    film_grain_params: header.film_grain_params.clone(),
+                    frame_type: header.frame_type,
+                    order_hint: header.order_hint,
+                    upscaled_width: self.upscaled_width,
+                    frame_height: self.frame_height,
+                    render_width: self.render_width,
+                    render_height: self.render_height,
+                    show_existing_frame: false,
+                })));
+            }
+
+            self.reference_frame_update(picture);
+        }
+
+        Ok(())
+    }
+}
+
+/// A decoder that accepts a sequence of OBUs (for example a temporal unit)
+/// and produces the frames to be displayed.
+pub struct Av1Decoder {
+    ctx: Av1DecoderContext,
+}
+
+impl Av1Decoder {
+    pub fn new(options: Av1DecoderOptions) -> Self {
+        Self {
+            ctx: Av1DecoderContext::new(options),
+        }
+    }
+
+    pub fn context(&self) -> &Av1DecoderContext {
+        &self.ctx
+    }
+
+    /// Decodes all OBUs contained in the buffer and returns the events that
+    /// were produced, in decoding order.
+    pub fn decode(&mut self, buf: &[u8]) -> Result<Vec<Av1DecoderEvent>, Av1DecodeError> {
+        let mut buf = Buffer::new(buf);
+        while !buf.get_remaining().is_empty() {
+            let obu = match Obu::decode(&mut self.ctx, &mut buf)? {
+                ObuDecodeRet::Obu(obu) => obu,
+                ObuDecodeRet::Drop => continue,
+            };
+
+            let mut payload = Buffer::new(buf.get_bytes(obu.size));
+            self.decode_obu(&obu, &mut payload)?;
+        }
+
+        Ok(std::mem::take(&mut self.ctx.events))
+    }
+
+    fn decode_obu(&mut self, obu: &Obu, buf: &mut Buffer) -> Result<(), Av1DecodeError> {
+        let ctx = &mut self.ctx;
+        match obu.header.kind {
+            ObuKind::SequenceHeader => {
+                ctx.sequence_header = Some(SequenceHeader::decode(ctx, buf)?);
+            }
+            // A redundant frame header is a copy of the frame header of the
+            // current frame, when the original frame header OBU was lost it
+            // is decoded as the frame header instead. A copy of the frame
+            // header of a frame that is already finished is ignored, no frame
+            // is in progress.
+            ObuKind::FrameHeader | ObuKind::RedundantFrameHeader => {
+                if ctx.sequence_header.is_none() {
+                    return Ok(());
+                }
+
+                if obu.header.kind == ObuKind::RedundantFrameHeader
+                    && !ctx.seen_frame_header
+                    && ctx
+                        .frame_header
+                        .as_ref()
+                        .is_some_and(|it| it.is_copy(buf.get_remaining()))
+                {
+                    return Ok(());
+                }
+
+                // A frame header that shows an existing frame has no tile
+                // groups, the frame is finished straight away.
+                let frame_header = FrameHeader::decode(ctx, buf)?;
+                if frame_header.uncompressed_header.show_existing_frame {
+                    ctx.decode_frame_wrapup()?;
+                }
+            }
+            ObuKind::Frame => {
+                if ctx.sequence_header.is_none() {
+                    return Ok(());
+                }
+
+                let frame = Frame::decode(ctx, buf)?;
+                self.decode_tile_group(&frame.tile_group)?;
+            }
+            ObuKind::TileGroup => {
+                // The frame header of this frame was lost or dropped, the tile
+                // group cannot be decoded.
+                if !ctx.seen_frame_header {
+                    return Ok(());
+                }
+
+                let tile_group = TileGroup::decode(ctx, buf)?;
+                self.decode_tile_group(&tile_group)?;
+            }
+            ObuKind::TileList => {
+                // The tile list is only checked, decoding of large scale tiles
+                // is not supported.
+                TileList::decode(ctx, buf)?;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn decode_tile_group(&mut self, tile_group: &TileGroup) -> Result<(), Av1DecodeError> {
+        let ctx = &mut self.ctx;
+        let Some(frame_header) = &ctx.frame_header else {
+            return Err(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::MissingFrameHeader,
+            ));
+        };
+
+        let header = &frame_header.uncompressed_header;
+        let context_update_tile_id = header.tile_info.context_update_tile_id;
+        let disable_frame_end_update_cdf = header.disable_frame_end_update_cdf;
+        for tile in &tile_group.tiles {
+            let mut decoder = TileDecoder::new(ctx, tile)?;
+            decoder.decode()?;
+
+            let cdf = decoder.finish()?;
+            if tile.tile_num == context_update_tile_id {
+                ctx.saved_cdf = Some(cdf);
+            }
+        }
+
+        if tile_group.last_tile_group {
+            if !disable_frame_end_update_cdf {
+                ctx.frame_end_update_cdf();
+            }
+
+            ctx.decode_frame_wrapup()?;
+            ctx.seen_frame_header = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::BitWriter,
+        obu::test_streams::{
+            key_frame_header, obu, sequence_header, show_existing_frame_header, OBU_FRAME_HEADER,
+            OBU_REDUNDANT_FRAME_HEADER, OBU_SEQUENCE_HEADER, OBU_TEMPORAL_DELIMITER,
+        },
+    };
+
+    fn decoder(redundant_frame_header: RedundantFrameHeaderPolicy) -> Av1Decoder {
+        let mut decoder = Av1Decoder::new(Av1DecoderOptions {
+            redundant_frame_header,
+            ..Default::default()
+        });
+
+        decoder
+            .decode(&obu(OBU_SEQUENCE_HEADER, &sequence_header(64, 64)))
+            .unwrap();
+        decoder
+    }
+
+    fn key_frame(show_frame: bool) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        key_frame_header(&mut w, show_frame, 0xff);
+        w.into_bytes()
+    }
+
+    /// Decodes a shown key frame header followed by a copy of it, the copy
+    /// is a hidden key frame header when it mismatches.
+    fn decode_copy(
+        policy: RedundantFrameHeaderPolicy,
+        mismatch: bool,
+    ) -> Result<Vec<Av1DecoderEvent>, Av1DecodeError> {
+        let mut decoder = decoder(policy);
+        decoder
+            .decode(&obu(OBU_FRAME_HEADER, &key_frame(true)))
+            .unwrap();
+
+        let copy = obu(OBU_REDUNDANT_FRAME_HEADER, &key_frame(!mismatch));
+        let result = decoder.decode(&copy);
+        let header = &decoder
+            .ctx
+            .frame_header
+            .as_ref()
+            .unwrap()
+            .uncompressed_header;
+        assert!(header.show_frame);
+        result
+    }
+
+    #[test]
+    fn showing_a_hidden_key_frame_refreshes_every_slot() {
+        let mut decoder = decoder(RedundantFrameHeaderPolicy::default());
+        let mut w = BitWriter::default();
+        key_frame_header(&mut w, false, 0b100);
+        decoder
+            .decode(&obu(OBU_FRAME_HEADER, &w.into_bytes()))
+            .unwrap();
+
+        // the tile data is left out, only the reference slots are checked.
+        decoder.ctx.decode_frame_wrapup().unwrap();
+        let valid = decoder.ctx.refs.each_ref().map(|r| r.is_some());
+        assert_eq!(
+            valid,
+            [false, false, true, false, false, false, false, false]
+        );
+
+        let mut temporal_unit = obu(OBU_TEMPORAL_DELIMITER, &[]);
+        temporal_unit.extend(obu(OBU_FRAME_HEADER, &show_existing_frame_header(2)));
+        let events = decoder.decode(&temporal_unit).unwrap();
+        let [Av1DecoderEvent::Frame(frame)] = &events[..] else {
+            panic!("expected a single frame, got {:?}", events);
+        };
+
+        assert!(frame.show_existing_frame);
+        assert_eq!(frame.frame_type, FrameType::KeyFrame);
+        // every slot holds the header of the shown key frame, with
+        // refresh_frame_flags equal to allFrames.
+        for r in &decoder.ctx.refs {
+            let r = r.as_ref().unwrap();
+            let header = &r.frame_header.uncompressed_header;
+            assert!(Arc::ptr_eq(&r.picture, &frame.picture));
+            assert_eq!(header.frame_type, FrameType::KeyFrame);
+            assert!(header.show_existing_frame && header.show_frame);
+            assert_eq!(header.frame_to_show_map_idx, 2);
+            assert_eq!(header.refresh_frame_flags, 0xFF);
+        }
+    }
+
+    #[test]
+    fn truncated_obus_are_rejected() {
+        let truncated = Av1DecodeError::Invalid(Av1DecodeInvalidError::ObuSize);
+        let sequence_header = obu(OBU_SEQUENCE_HEADER, &sequence_header(64, 64));
+        for n in 1..sequence_header.len() {
+            let mut decoder = Av1Decoder::new(Av1DecoderOptions::default());
+            assert_eq!(
+                decoder.decode(&sequence_header[..n]).unwrap_err(),
+                truncated,
+                "{n}"
+            );
+        }
+
+        // obu_size larger than the data, and leb128 and extension bytes
+        // missing at the end of the data.
+        let mut decoder = decoder(RedundantFrameHeaderPolicy::default());
+        assert_eq!(decoder.decode(&[0x12, 0x01]).unwrap_err(), truncated);
+        assert_eq!(decoder.decode(&[0x12, 0x80, 0x80]).unwrap_err(), truncated);
+        assert_eq!(decoder.decode(&[0x16]).unwrap_err(), truncated);
+
+        // OBUs outside of the operating point are dropped without reading
+        // past the end of the data either.
+        decoder.ctx.operating_point_idc = 0x101;
+        assert!(decoder
+            .decode(&[0x36, 0x20, 0x01, 0x00])
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            decoder.decode(&[0x36, 0x20, 0x02, 0x00]).unwrap_err(),
+            truncated
+        );
+    }
+
+    #[test]
+    fn matching_frame_header_copies_are_accepted() {
+        for policy in [
+            RedundantFrameHeaderPolicy::Ignore,
+            RedundantFrameHeaderPolicy::Warn,
+            RedundantFrameHeaderPolicy::Error,
+        ] {
+            assert!(decode_copy(policy, false).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn mismatching_frame_header_copies_follow_the_policy() {
+        let events = decode_copy(RedundantFrameHeaderPolicy::Ignore, true).unwrap();
+        assert!(events.is_empty());
+
+        let events = decode_copy(RedundantFrameHeaderPolicy::Warn, true).unwrap();
+        assert!(matches!(
+            events[..],
+            [Av1DecoderEvent::FrameHeaderCopyMismatch]
+        ));
+
+        assert_eq!(
+            decode_copy(RedundantFrameHeaderPolicy::Error, true).unwrap_err(),
+            Av1DecodeError::Invalid(Av1DecodeInvalidError::FrameHeaderCopyMismatch)
+        );
+    }
+
+    #[test]
+    fn a_redundant_frame_header_replaces_a_lost_frame_header() {
+        let mut decoder = decoder(RedundantFrameHeaderPolicy::Error);
+        decoder
+            .decode(&obu(OBU_REDUNDANT_FRAME_HEADER, &key_frame(false)))
+            .unwrap();
+
+        assert!(decoder.ctx.seen_frame_header);
+        assert!(decoder.ctx.current_frame.is_some());
+        let header = &decoder
+            .ctx
+            .frame_header
+            .as_ref()
+            .unwrap()
+            .uncompressed_header;
+        assert_eq!(header.frame_type, FrameType::KeyFrame);
+        assert!(!header.show_frame);
+    }
+
+    #[test]
+    fn redundant_frame_headers_of_a_finished_frame_are_ignored() {
+        let mut decoder = decoder(RedundantFrameHeaderPolicy::Error);
+        decoder
+            .decode(&obu(OBU_FRAME_HEADER, &key_frame(true)))
+            .unwrap();
+
+        // the tile data is left out, the frame is finished as after its last
+        // tile group.
+        decoder.ctx.decode_frame_wrapup().unwrap();
+        decoder.ctx.seen_frame_header = false;
+        let events = std::mem::take(&mut decoder.ctx.events);
+        assert!(matches!(events[..], [Av1DecoderEvent::Frame(_)]));
+
+        let copy = obu(OBU_REDUNDANT_FRAME_HEADER, &key_frame(true));
+        assert!(decoder.decode(&copy).unwrap().is_empty());
+        assert!(!decoder.ctx.seen_frame_header);
+        assert!(decoder.ctx.current_frame.is_none());
+
+        // a different header is the header of the next frame.
+        let copy = obu(OBU_REDUNDANT_FRAME_HEADER, &key_frame(false));
+        assert!(decoder.decode(&copy).unwrap().is_empty());
+        assert!(decoder.ctx.seen_frame_header);
+        assert!(decoder.ctx.current_frame.is_some());
+    }
+
+    #[test]
+    fn adapted_cdfs_are_saved_and_loaded_without_symbol_counters() {
+        let mut decoder = decoder(RedundantFrameHeaderPolicy::default());
+        let mut w = BitWriter::default();
+        key_frame_header(&mut w, false, 0b10);
+        decoder
+            .decode(&obu(OBU_FRAME_HEADER, &w.into_bytes()))
+            .unwrap();
+
+        // the CDF arrays at the end of tile context_update_tile_id.
+        let mut cdf = CdfContext::default();
+        cdf.skip_cdf[1] = [12345, 0, 9];
+        cdf.txb_skip_cdf[0][0][2] = 31;
+        decoder.ctx.saved_cdf = Some(cdf);
+        decoder.ctx.frame_end_update_cdf();
+        assert!(decoder.ctx.saved_cdf.is_none());
+        assert_eq!(decoder.ctx.cdf.skip_cdf[1], [12345, 0, 0]);
+        assert_eq!(decoder.ctx.cdf.txb_skip_cdf[0][0][2], 0);
+
+        decoder.ctx.decode_frame_wrapup().unwrap();
+        decoder.ctx.cdf = CdfContext::default();
+        decoder.ctx.load_reference_frame(1);
+        assert_eq!(decoder.ctx.cdf.skip_cdf[1], [12345, 0, 0]);
+        assert_eq!(decoder.ctx.cdf.txb_skip_cdf[0][0][2], 0);
+    }
 }
@@ -0,0 +1,426 @@
+//! The motion field of the current frame, the motion vectors of the
+//! reference frames projected onto the current frame, which are used as the
+//! temporal candidates of motion vector prediction, and the motion vectors
+//! that each frame saves for the motion fields of later frames.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#motion-field-estimation-process
+
+use crate::{
+    constants::{
+        ALTREF2_FRAME, ALTREF_FRAME, BWDREF_FRAME, GOLDEN_FRAME, INTRA_FRAME, LAST2_FRAME,
+        LAST_FRAME, MAX_FRAME_DISTANCE, MAX_OFFSET_HEIGHT, MAX_OFFSET_WIDTH, MFMV_STACK_SIZE,
+        MI_SIZE_LOG2, NONE, REFMVS_LIMIT, REFS_PER_FRAME, TOTAL_REFS_PER_FRAME,
+    },
+    obu::frame_header::get_relative_dist,
+    tile::mvpred::Mv,
+    util::clip3,
+    Av1DecoderContext,
+};
+
+/// The value of a motion vector component that marks a motion vector as not
+/// valid (-1 << 15).
+pub const INVALID_MV: i32 = -1 << 15;
+
+/// Div_Mult, 2^14 divided by the frame distance.
+const DIV_MULT: [i32; 32] = [
+    0, 16384, 8192, 5461, 4096, 3276, 2730, 2340, 2048, 1820, 1638, 1489, 1365, 1260, 1170, 1092,
+    1024, 963, 910, 862, 819, 780, 744, 712, 682, 655, 630, 606, 585, 564, 546, 528,
+];
+
+/// MfRefFrames and MfMvs, the motion vector of each 4x4 block of a frame
+/// that can be projected by the motion field estimation of later frames.
+/// A reference slot keeps them as SavedRefFrames and SavedMvs.
+#[derive(Debug, Clone, Default)]
+pub struct SavedMvs {
+    pub mi_rows: u32,
+    pub mi_cols: u32,
+    ref_frames: Vec<i8>,
+    mvs: Vec<Mv>,
+}
+
+impl SavedMvs {
+    /// Motion vectors where no position has a reference frame (NONE).
+    pub fn new(mi_rows: u32, mi_cols: u32) -> Self {
+        Self {
+            mi_rows,
+            mi_cols,
+            ref_frames: vec![NONE; (mi_rows * mi_cols) as usize],
+            mvs: vec![[0; 2]; (mi_rows * mi_cols) as usize],
+        }
+    }
+
+    /// MfRefFrames[ row ][ col ] and MfMvs[ row ][ col ]
+    #[inline]
+    pub fn get(&self, row: u32, col: u32) -> (i8, Mv) {
+        let idx = (row * self.mi_cols + col) as usize;
+        (self.ref_frames[idx], self.mvs[idx])
+    }
+
+    #[inline]
+    pub fn set(&mut self, row: u32, col: u32, ref_frame: i8, mv: Mv) {
+        let idx = (row * self.mi_cols + col) as usize;
+        self.ref_frames[idx] = ref_frame;
+        self.mvs[idx] = mv;
+    }
+}
+
+/// MotionFieldMvs, one motion vector for each reference frame from
+/// LAST_FRAME to ALTREF_FRAME and each 8x8 block of the current frame.
+#[derive(Debug, Clone, Default)]
+pub struct MotionFieldMvs {
+    /// MiRows >> 1
+    pub rows: u32,
+    /// MiCols >> 1
+    pub cols: u32,
+    data: Vec<[Mv; REFS_PER_FRAME as usize]>,
+}
+
+impl MotionFieldMvs {
+    /// A motion field where every motion vector is not valid.
+    pub fn new(mi_rows: u32, mi_cols: u32) -> Self {
+        let (rows, cols) = (mi_rows >> 1, mi_cols >> 1);
+        Self {
+            rows,
+            cols,
+            data: vec![[[INVALID_MV; 2]; REFS_PER_FRAME as usize]; (rows * cols) as usize],
+        }
+    }
+
+    /// MotionFieldMvs[ ref ][ row ][ col ], the position is in units of 8x8
+    /// luma samples.
+    #[inline]
+    pub fn get(&self, ref_frame: u8, row: u32, col: u32) -> Mv {
+        self.data[(row * self.cols + col) as usize][(ref_frame - LAST_FRAME) as usize]
+    }
+
+    #[inline]
+    pub fn set(&mut self, ref_frame: u8, row: u32, col: u32, mv: Mv) {
+        self.data[(row * self.cols + col) as usize][(ref_frame - LAST_FRAME) as usize] = mv;
+    }
+
+    /// The projection part of projection( src, dstSign ), the saved motion
+    /// vectors at the center of every 8x8 block of the source frame are
+    /// followed along their trajectory to the current frame, and the
+    /// position they land on gets the motion vector scaled to each of the
+    /// reference frames of the current frame.
+    ///
+    /// `src_offset` is the distance from the source frame to the current
+    /// frame in the direction of dstSign, `ref_offsets` the distance from
+    /// the source frame to each of its own reference frames and
+    /// `dst_offsets` the distance from the current frame to each of its
+    /// reference frames.
+    pub fn project(
+        &mut self,
+        saved_mvs: &SavedMvs,
+        dst_sign: i32,
+        src_offset: i32,
+        ref_offsets: &[i32; TOTAL_REFS_PER_FRAME as usize],
+        dst_offsets: &[i32; TOTAL_REFS_PER_FRAME as usize],
+    ) {
+        let max_frame_distance = MAX_FRAME_DISTANCE as i32;
+        for y8 in 0..self.rows {
+            for x8 in 0..self.cols {
+                let (src_ref, mv) = saved_mvs.get(2 * y8 + 1, 2 * x8 + 1);
+                if src_ref <= INTRA_FRAME as i8 {
+                    continue;
+                }
+
+                let ref_offset = ref_offsets[src_ref as usize];
+                if ref_offset <= 0
+                    || ref_offset > max_frame_distance
+                    || src_offset.abs() > max_frame_distance
+                {
+                    continue;
+                }
+
+                let proj_mv = get_mv_projection(mv, src_offset, ref_offset);
+                let pos_y8 = project(y8, proj_mv[0], dst_sign, self.rows, MAX_OFFSET_HEIGHT);
+                let pos_x8 = project(x8, proj_mv[1], dst_sign, self.cols, MAX_OFFSET_WIDTH);
+                let (Some(pos_y8), Some(pos_x8)) = (pos_y8, pos_x8) else {
+                    continue;
+                };
+
+                for dst in LAST_FRAME..=ALTREF_FRAME {
+                    let proj_mv = get_mv_projection(mv, dst_offsets[dst as usize], ref_offset);
+                    self.set(dst, pos_y8, pos_x8, proj_mv);
+                }
+            }
+        }
+    }
+}
+
+/// get_mv_projection( mv, numerator, denominator ), scales a motion vector
+/// that spans `denominator` frames to one that spans `numerator` frames.
+pub fn get_mv_projection(mv: Mv, numerator: i32, denominator: i32) -> Mv {
+    let max_frame_distance = MAX_FRAME_DISTANCE as i32;
+    let clipped_denominator = denominator.min(max_frame_distance);
+    let clipped_numerator = clip3(-max_frame_distance, max_frame_distance, numerator);
+    mv.map(|v| {
+        let scaled =
+            v as i64 * clipped_numerator as i64 * DIV_MULT[clipped_denominator as usize] as i64;
+        let v = (scaled.abs() + (1 << 13)) >> 14;
+        let scaled = if scaled < 0 { -v } else { v } as i32;
+        clip3(-(1 << 14) + 1, (1 << 14) - 1, scaled)
+    })
+}
+
+/// project( v8, delta, dstSign, max8, maxOff8 ), the position in units of
+/// 8x8 blocks that a projected motion vector points to, None when it is
+/// outside of the frame or too far from the 64x64 block of the source
+/// position.
+fn project(v8: u32, delta: i32, dst_sign: i32, max8: u32, max_off8: u8) -> Option<u32> {
+    let shift = 3 + 1 + MI_SIZE_LOG2;
+    let base8 = ((v8 >> 3) << 3) as i32;
+    let offset8 = if delta >= 0 {
+        delta >> shift
+    } else {
+        -((-delta) >> shift)
+    };
+
+    let v8 = v8 as i32 + dst_sign * offset8;
+    let max_off8 = max_off8 as i32;
+    if v8 < 0 || v8 >= max8 as i32 || v8 < base8 - max_off8 || v8 >= base8 + 8 + max_off8 {
+        return None;
+    }
+
+    Some(v8 as u32)
+}
+
+impl Av1DecoderContext {
+    /// Builds MotionFieldMvs from the saved motion vectors of the reference
+    /// frames. At most MFMV_STACK_SIZE reference frames are projected:
+    /// LAST_FRAME (unless its ALTREF_FRAME is the current GOLDEN_FRAME, as
+    /// happens for an overlay of that frame), the backward reference frames
+    /// and then LAST2_FRAME.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#motion-field-estimation-process
+    pub fn motion_field_estimation(&mut self) {
+        let mut motion_field_mvs = MotionFieldMvs::new(self.mi_rows, self.mi_cols);
+        let Some(frame_header) = &self.frame_header else {
+            return;
+        };
+
+        let header = &frame_header.uncompressed_header;
+        let order_hints = &header.order_hints;
+        let last_idx = header.ref_frame_idx[0] as usize;
+        let cur_gold_hint = order_hints[GOLDEN_FRAME as usize];
+        let last_alt_hint = self.refs[last_idx]
+            .as_ref()
+            .map(|r| r.frame_header.uncompressed_header.order_hints[ALTREF_FRAME as usize]);
+
+        let mut ref_stamp = MFMV_STACK_SIZE as i32 - 2;
+        if last_alt_hint != Some(cur_gold_hint) {
+            self.projection(&mut motion_field_mvs, LAST_FRAME, -1);
+        }
+
+        for src in [BWDREF_FRAME, ALTREF2_FRAME, ALTREF_FRAME] {
+            if get_relative_dist(self, order_hints[src as usize], self.order_hint) > 0
+                && (src != ALTREF_FRAME || ref_stamp >= 0)
+                && self.projection(&mut motion_field_mvs, src, 1)
+            {
+                ref_stamp -= 1;
+            }
+        }
+
+        if ref_stamp >= 0 {
+            self.projection(&mut motion_field_mvs, LAST2_FRAME, -1);
+        }
+
+        self.motion_field_mvs = motion_field_mvs;
+    }
+
+    /// projection( src, dstSign ), returns false when the reference frame
+    /// cannot be projected because it has another size or it is an intra
+    /// frame (which has no motion vectors).
+    fn projection(&self, motion_field_mvs: &mut MotionFieldMvs, src: u8, dst_sign: i32) -> bool {
+        let Some(frame_header) = &self.frame_header else {
+            return false;
+        };
+
+        let header = &frame_header.uncompressed_header;
+        let src_idx = header.ref_frame_idx[(src - LAST_FRAME) as usize] as usize;
+        let Some(r) = &self.refs[src_idx] else {
+            return false;
+        };
+
+        let src_header = &r.frame_header.uncompressed_header;
+        if r.mi_rows != self.mi_rows || r.mi_cols != self.mi_cols || src_header.frame_is_intra {
+            return false;
+        }
+
+        let src_offset = dst_sign * get_relative_dist(self, r.order_hint, self.order_hint);
+        let mut ref_offsets = [0; TOTAL_REFS_PER_FRAME as usize];
+        let mut dst_offsets = [0; TOTAL_REFS_PER_FRAME as usize];
+        for rf in LAST_FRAME as usize..=ALTREF_FRAME as usize {
+            ref_offsets[rf] = get_relative_dist(self, r.order_hint, src_header.order_hints[rf]);
+            dst_offsets[rf] = get_relative_dist(self, self.order_hint, header.order_hints[rf]);
+        }
+
+        motion_field_mvs.project(
+            &r.saved_mvs,
+            dst_sign,
+            src_offset,
+            &ref_offsets,
+            &dst_offsets,
+        );
+        true
+    }
+
+    /// Saves the motion vectors of the current frame that point backwards
+    /// in time and are small enough into MfRefFrames and MfMvs, the last
+    /// such reference of a block wins.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#motion-field-motion-vector-storage-process
+    pub fn motion_field_motion_vector_storage(&self) -> SavedMvs {
+        let mut saved_mvs = SavedMvs::new(self.mi_rows, self.mi_cols);
+        let Some(frame_header) = &self.frame_header else {
+            return saved_mvs;
+        };
+
+        let order_hints = &frame_header.uncompressed_header.order_hints;
+        for row in 0..self.mi_rows {
+            for col in 0..self.mi_cols {
+                let mi = self.mode_info.get(row, col);
+                for list in 0..2 {
+                    let r = mi.ref_frame[list];
+                    if r <= INTRA_FRAME as i8
+                        || get_relative_dist(self, order_hints[r as usize], self.order_hint) >= 0
+                    {
+                        continue;
+                    }
+
+                    let mv = mi.mv[list];
+                    if mv.iter().all(|v| v.unsigned_abs() <= REFMVS_LIMIT) {
+                        saved_mvs.set(row, col, r, mv);
+                    }
+                }
+            }
+        }
+
+        saved_mvs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::BLOCK_8X8,
+        obu::frame_header::{FrameHeader, UncompressedHeader},
+        tile::{block::Block, mode_info::ModeInfo, mode_info::ModeInfoGrid},
+        Av1DecoderOptions,
+    };
+
+    #[test]
+    fn mv_projection() {
+        // the same distance keeps the motion vector, and half of it halves
+        // the motion vector with rounding away from zero.
+        assert_eq!(get_mv_projection([64, -64], 2, 2), [64, -64]);
+        assert_eq!(get_mv_projection([5, -5], 1, 2), [3, -3]);
+        assert_eq!(get_mv_projection([30, 6], -3, 1), [-90, -18]);
+        // the distances are clipped to MAX_FRAME_DISTANCE and the result to
+        // 15 bits.
+        assert_eq!(get_mv_projection([8, 0], 40, 1), [8 * 31, 0]);
+        assert_eq!(get_mv_projection([8, 0], 31, 40), [8, 0]);
+        assert_eq!(get_mv_projection([4000, 0], 31, 1), [(1 << 14) - 1, 0]);
+    }
+
+    #[test]
+    fn projected_positions() {
+        // 64 1/8 samples are one 8x8 block, and the offset is rounded
+        // towards zero.
+        assert_eq!(project(3, 64, 1, 16, 0), Some(4));
+        assert_eq!(project(3, -127, 1, 16, 0), Some(2));
+        assert_eq!(project(3, 64, -1, 16, 0), Some(2));
+        // rows can not leave the 64 sample high stripe of the source.
+        assert_eq!(project(7, 64, 1, 16, 0), None);
+        assert_eq!(project(8, -64, 1, 16, 0), None);
+        // columns can move by up to 64 samples out of the 64x64 block.
+        assert_eq!(project(7, 64 * 8, 1, 32, 8), Some(15));
+        assert_eq!(project(7, 64 * 9, 1, 32, 8), None);
+        assert_eq!(project(1, -128, 1, 32, 8), None);
+    }
+
+    #[test]
+    fn projection_onto_every_reference_frame() {
+        let mut saved_mvs = SavedMvs::new(8, 8);
+        // the center of 8x8 block (1, 2) moved down by two 8x8 blocks since
+        // LAST_FRAME of the source, which is 2 frames before it.
+        saved_mvs.set(3, 5, LAST_FRAME as i8, [-128, 0]);
+        // positions that are not at the center of an 8x8 block are ignored.
+        saved_mvs.set(2, 2, LAST_FRAME as i8, [0, 0]);
+
+        let mut ref_offsets = [0; TOTAL_REFS_PER_FRAME as usize];
+        ref_offsets[LAST_FRAME as usize] = 2;
+        let mut dst_offsets = [0; TOTAL_REFS_PER_FRAME as usize];
+        dst_offsets[LAST_FRAME as usize] = 1;
+        dst_offsets[ALTREF_FRAME as usize] = -2;
+
+        // the current frame is 2 frames after the source, so the block
+        // moved down by two more 8x8 blocks.
+        let mut motion_field_mvs = MotionFieldMvs::new(8, 8);
+        motion_field_mvs.project(&saved_mvs, -1, 2, &ref_offsets, &dst_offsets);
+        assert_eq!(motion_field_mvs.get(LAST_FRAME, 3, 2), [-64, 0]);
+        assert_eq!(motion_field_mvs.get(ALTREF_FRAME, 3, 2), [128, 0]);
+        assert_eq!(motion_field_mvs.get(LAST2_FRAME, 3, 2), [0, 0]);
+        assert_eq!(motion_field_mvs.get(LAST_FRAME, 1, 2), [INVALID_MV; 2]);
+        assert_eq!(motion_field_mvs.get(LAST_FRAME, 1, 1), [INVALID_MV; 2]);
+
+        // a source reference frame that is not before the source is not
+        // projected.
+        ref_offsets[LAST_FRAME as usize] = -2;
+        let mut motion_field_mvs = MotionFieldMvs::new(8, 8);
+        motion_field_mvs.project(&saved_mvs, -1, 2, &ref_offsets, &dst_offsets);
+        assert_eq!(motion_field_mvs.get(LAST_FRAME, 3, 2), [INVALID_MV; 2]);
+    }
+
+    #[test]
+    fn motion_vector_storage() {
+        let mut ctx = Av1DecoderContext::new(Av1DecoderOptions::default());
+        let mut header = UncompressedHeader::default();
+        header.order_hints[LAST_FRAME as usize] = 4;
+        header.order_hints[BWDREF_FRAME as usize] = 12;
+        ctx.frame_header = Some(FrameHeader {
+            uncompressed_header: header,
+            raw: Vec::new(),
+            bits: 0,
+        });
+        ctx.order_hint_bits = 7;
+        ctx.order_hint = 8;
+        ctx.mi_rows = 4;
+        ctx.mi_cols = 4;
+        ctx.mode_info = ModeInfoGrid::new(4, 4);
+
+        let mut fill = |mi_row, mi_col, ref_frame: [i8; 2], mv: [Mv; 2]| {
+            let block = Block {
+                mi_row,
+                mi_col,
+                mi_size: BLOCK_8X8,
+                ..Default::default()
+            };
+
+            let mi = ModeInfo {
+                mi_size: BLOCK_8X8,
+                is_inter: true,
+                ref_frame,
+                mv,
+                ..Default::default()
+            };
+
+            ctx.mode_info.fill(&block, &mi);
+        };
+
+        let last = LAST_FRAME as i8;
+        let bwdref = BWDREF_FRAME as i8;
+        fill(0, 0, [last, NONE], [[8, 8], [0, 0]]);
+        fill(0, 2, [bwdref, NONE], [[8, 8], [0, 0]]);
+        fill(2, 0, [bwdref, last], [[8, 8], [-16, 16]]);
+        fill(2, 2, [last, NONE], [[REFMVS_LIMIT as i32 + 1, 0], [0, 0]]);
+
+        let saved_mvs = ctx.motion_field_motion_vector_storage();
+        assert_eq!(saved_mvs.get(1, 1), (last, [8, 8]));
+        assert_eq!(saved_mvs.get(0, 3).0, NONE);
+        assert_eq!(saved_mvs.get(3, 0), (last, [-16, 16]));
+        assert_eq!(saved_mvs.get(2, 3).0, NONE);
+    }
+}
@@ -1,6 +1,104 @@
-use super::frame_header::FrameHeader;
+use crate::{Av1DecodeError, Av1DecodeInvalidError, Av1DecoderContext, Buffer};
 
+use super::{frame_header::FrameHeader, tile_group::TileGroup};
+
+/// A frame header and its first tile group carried in a single OBU.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#frame-obu-syntax
 #[derive(Debug, Clone)]
-pub struct Frame {
+pub struct Frame<'a> {
     pub frame_header: FrameHeader,
+    pub tile_group: TileGroup<'a>,
+}
+
+impl<'a> Frame<'a> {
+    pub fn decode(
+        ctx: &mut Av1DecoderContext,
+        buf: &mut Buffer<'a>,
+    ) -> Result<Self, Av1DecodeError> {
+        let frame_header = FrameHeader::decode(ctx, buf)?;
+
+        // It is a requirement of bitstream conformance that a frame OBU does
+        // not show an existing frame, such a frame header has no tile data.
+        if frame_header.uncompressed_header.show_existing_frame {
+            return Err(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::ShowExistingFrameInFrame,
+            ));
+        }
+
+        buf.byte_alignment();
+
+        // The tile group takes up the remainder of the OBU, sz is reduced by
+        // headerBytes.
+        let tile_group = TileGroup::decode(ctx, buf)?;
+        Ok(Self {
+            frame_header,
+            tile_group,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        buffer::BitWriter,
+        obu::test_streams::{context, key_frame_header, show_existing_frame_header},
+    };
+
+    #[test]
+    fn the_tile_group_starts_at_the_byte_after_the_frame_header() {
+        let mut ctx = context(64, 64);
+        let mut w = BitWriter::default();
+        key_frame_header(&mut w, true, 0xff);
+        w.byte_alignment();
+        w.put_bytes(&[0xab, 0xcd, 0xef]);
+        let payload = w.into_bytes();
+
+        let frame = Frame::decode(&mut ctx, &mut Buffer::new(&payload)).unwrap();
+        // the header ends one bit before a byte boundary.
+        assert_eq!(frame.frame_header.bits, 31);
+        assert!(frame.tile_group.last_tile_group);
+        let [tile] = &frame.tile_group.tiles[..] else {
+            panic!("expected a single tile");
+        };
+
+        assert_eq!(tile.data, [0xab, 0xcd, 0xef]);
+        assert_eq!((tile.mi_row_end, tile.mi_col_end), (16, 16));
+    }
+
+    #[test]
+    fn a_frame_cannot_show_an_existing_frame() {
+        let mut ctx = context(64, 64);
+        let mut w = BitWriter::default();
+        key_frame_header(&mut w, false, 0b1);
+        let payload = w.into_bytes();
+        FrameHeader::decode(&mut ctx, &mut Buffer::new(&payload)).unwrap();
+        ctx.decode_frame_wrapup().unwrap();
+        ctx.seen_frame_header = false;
+
+        let refs = |ctx: &Av1DecoderContext| {
+            ctx.refs
+                .each_ref()
+                .map(|r| r.as_ref().map(|r| (Arc::as_ptr(&r.picture), r.order_hint)))
+        };
+
+        let saved_refs = refs(&ctx);
+        let mut payload = show_existing_frame_header(0);
+        payload.extend([0xab, 0xcd]);
+        assert_eq!(
+            Frame::decode(&mut ctx, &mut Buffer::new(&payload)).unwrap_err(),
+            Av1DecodeError::Invalid(Av1DecodeInvalidError::ShowExistingFrameInFrame)
+        );
+
+        // the hidden key frame is neither shown nor saved into every slot.
+        assert!(ctx.events.is_empty());
+        assert_eq!(refs(&ctx), saved_refs);
+        assert_eq!(
+            saved_refs.map(|r| r.is_some()),
+            [true, false, false, false, false, false, false, false]
+        );
+    }
 }
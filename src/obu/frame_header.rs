@@ -1,14 +1,23 @@
 use crate::{
     constants::{
-        NUM_REF_FRAMES, PRIMARY_REF_NONE, REFS_PER_FRAME, SELECT_INTEGER_MV,
-        SELECT_SCREEN_CONTENT_TOOLS, SUPERRES_DENOM_BITS, SUPERRES_DENOM_MIN, SUPERRES_NUM,
+        AFFINE, ALTREF2_FRAME, ALTREF_FRAME, BWDREF_FRAME, GM_ABS_ALPHA_BITS, GM_ABS_TRANS_BITS,
+        GM_ABS_TRANS_ONLY_BITS, GM_ALPHA_PREC_BITS, GM_TRANS_ONLY_PREC_BITS, GM_TRANS_PREC_BITS,
+        GOLDEN_FRAME, IDENTITY, INTRA_FRAME, LAST2_FRAME, LAST3_FRAME, LAST_FRAME, MAX_LOOP_FILTER,
+        MAX_SEGMENTS, MAX_TILE_AREA, MAX_TILE_COLS, MAX_TILE_ROWS, MAX_TILE_WIDTH, NUM_REF_FRAMES,
+        PRIMARY_REF_NONE, REFS_PER_FRAME, RESTORATION_TILESIZE_MAX, ROTZOOM, SEG_LVL_MAX,
+        SEG_LVL_REF_FRAME, SELECT_INTEGER_MV, SELECT_SCREEN_CONTENT_TOOLS, SUPERRES_DENOM_BITS,
+        SUPERRES_DENOM_MIN, SUPERRES_NUM, TOTAL_REFS_PER_FRAME, TRANSLATION, WARPEDMODEL_PREC_BITS,
     },
-    obu::sequence_header::FrameIdNumbersPresent,
-    Av1DecodeError, Av1DecodeUnknownError, Av1DecoderContext, Buffer,
+    obu::sequence_header::SequenceHeader,
+    quant,
+    util::{clip3, inverse_recenter, tile_log2},
+    Av1DecodeError, Av1DecodeInvalidError, Av1DecodeUnknownError, Av1DecoderContext,
+    Av1DecoderEvent, Buffer, RedundantFrameHeaderPolicy,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum FrameType {
+    #[default]
     KeyFrame,
     InterFrame,
     InterOnlyFrame,
@@ -43,10 +52,23 @@ impl TemporalPointInfo {
     }
 }
 
+/// The distance between two order hints, a positive value means that a is
+/// displayed after b.
+#[inline]
+pub fn get_relative_dist(ctx: &Av1DecoderContext, a: u32, b: u32) -> i32 {
+    if ctx.order_hint_bits == 0 {
+        return 0;
+    }
+
+    let diff = a as i32 - b as i32;
+    let m = 1 << (ctx.order_hint_bits - 1);
+    (diff & (m - 1)) - (diff & m)
+}
+
 #[inline]
 pub fn compute_image_size(ctx: &mut Av1DecoderContext) {
-    ctx.mi_cols = 2 * ((ctx.frame_width + 7) >> 3) as u32;
-    ctx.mi_rows = 2 * ((ctx.frame_height + 7) >> 3) as u32;
+    ctx.mi_cols = 2 * ((ctx.frame_width as u32 + 7) >> 3);
+    ctx.mi_rows = 2 * ((ctx.frame_height as u32 + 7) >> 3);
 }
 
 #[inline]
@@ -100,8 +122,9 @@ pub fn superres_params(ctx: &mut Av1DecoderContext, buf: &mut Buffer) {
     };
 
     ctx.upscaled_width = ctx.frame_width;
-    ctx.frame_width = (ctx.upscaled_width * SUPERRES_NUM as u16 + (ctx.superres_denom as u16 / 2))
-        / ctx.superres_denom as u16;
+    ctx.frame_width = ((ctx.upscaled_width as u32 * SUPERRES_NUM as u32
+        + (ctx.superres_denom as u32 / 2))
+        / ctx.superres_denom as u32) as u16;
 }
 
 #[inline]
@@ -127,21 +150,27 @@ pub fn render_size(ctx: &mut Av1DecoderContext, buf: &mut Buffer) {
 pub fn frame_size_with_refs(
     ctx: &mut Av1DecoderContext,
     frame_size_override: bool,
+    ref_frame_idx: &[u8; REFS_PER_FRAME as usize],
     buf: &mut Buffer,
-) {
+) -> Result<(), Av1DecodeError> {
     let mut found_ref = false;
-    for _ in 0..REFS_PER_FRAME {
+    for idx in ref_frame_idx {
         // found_ref	f(1)
         found_ref = buf.get_bit();
-
-        // if ( found_ref == 1 ) {
-        //     UpscaledWidth = RefUpscaledWidth[ ref_frame_idx[ i ] ]
-        //     FrameWidth = UpscaledWidth
-        //     FrameHeight = RefFrameHeight[ ref_frame_idx[ i ] ]
-        //     RenderWidth = RefRenderWidth[ ref_frame_idx[ i ] ]
-        //     RenderHeight = RefRenderHeight[ ref_frame_idx[ i ] ]
-        //     break
-        // }
+        if found_ref {
+            let r = ctx.refs[*idx as usize]
+                .as_ref()
+                .ok_or(Av1DecodeError::Invalid(
+                    Av1DecodeInvalidError::MissingReference,
+                ))?;
+
+            ctx.upscaled_width = r.upscaled_width;
+            ctx.frame_width = ctx.upscaled_width;
+            ctx.frame_height = r.frame_height;
+            ctx.render_width = r.render_width;
+            ctx.render_height = r.render_height;
+            break;
+        }
     }
 
     if !found_ref {
@@ -151,10 +180,13 @@ pub fn frame_size_with_refs(
         superres_params(ctx, buf);
         compute_image_size(ctx);
     }
+
+    Ok(())
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum InterpolationFilter {
+    #[default]
     Eighttap,
     EighttapSmooth,
     EighttapSharp,
@@ -193,115 +225,1376 @@ pub fn read_interpolation_filter(buf: &mut Buffer) -> Result<InterpolationFilter
     })
 }
 
+/// see: https://aomediacodec.github.io/av1-spec/#tile-info-syntax
+#[derive(Debug, Clone, Default)]
+pub struct TileInfo {
+    pub tile_cols: u32,
+    pub tile_rows: u32,
+    pub tile_cols_log2: u32,
+    pub tile_rows_log2: u32,
+    /// MiColStarts, has tile_cols + 1 entries, the last one is MiCols.
+    pub mi_col_starts: Vec<u32>,
+    /// MiRowStarts, has tile_rows + 1 entries, the last one is MiRows.
+    pub mi_row_starts: Vec<u32>,
+    pub context_update_tile_id: u32,
+    pub tile_size_bytes: usize,
+}
+
+impl TileInfo {
+    pub fn decode(
+        ctx: &Av1DecoderContext,
+        sequence_header: &SequenceHeader,
+        buf: &mut Buffer,
+    ) -> Self {
+        let (sb_cols, sb_rows, sb_shift) = if sequence_header.use_128x128_superblock {
+            ((ctx.mi_cols + 31) >> 5, (ctx.mi_rows + 31) >> 5, 5)
+        } else {
+            ((ctx.mi_cols + 15) >> 4, (ctx.mi_rows + 15) >> 4, 4)
+        };
+
+        let sb_size = sb_shift + 2;
+        let max_tile_width_sb = MAX_TILE_WIDTH as u32 >> sb_size;
+        let mut max_tile_area_sb = MAX_TILE_AREA >> (2 * sb_size);
+        let min_log2_tile_cols = tile_log2(max_tile_width_sb, sb_cols);
+        let max_log2_tile_cols = tile_log2(1, sb_cols.min(MAX_TILE_COLS as u32));
+        let max_log2_tile_rows = tile_log2(1, sb_rows.min(MAX_TILE_ROWS as u32));
+        let min_log2_tiles = min_log2_tile_cols.max(tile_log2(max_tile_area_sb, sb_rows * sb_cols));
+
+        let mut mi_col_starts = Vec::with_capacity(MAX_TILE_COLS as usize + 1);
+        let mut mi_row_starts = Vec::with_capacity(MAX_TILE_ROWS as usize + 1);
+        let tile_cols_log2;
+        let tile_rows_log2;
+
+        // uniform_tile_spacing_flag	f(1)
+        let uniform_tile_spacing = buf.get_bit();
+        if uniform_tile_spacing {
+            let mut cols_log2 = min_log2_tile_cols;
+            while cols_log2 < max_log2_tile_cols {
+                // increment_tile_cols_log2	f(1)
+                if buf.get_bit() {
+                    cols_log2 += 1;
+                } else {
+                    break;
+                }
+            }
+
+            let tile_width_sb = (sb_cols + (1 << cols_log2) - 1) >> cols_log2;
+            let mut start_sb = 0;
+            while start_sb < sb_cols {
+                mi_col_starts.push(start_sb << sb_shift);
+                start_sb += tile_width_sb;
+            }
+
+            let min_log2_tile_rows = min_log2_tiles.saturating_sub(cols_log2);
+            let mut rows_log2 = min_log2_tile_rows;
+            while rows_log2 < max_log2_tile_rows {
+                // increment_tile_rows_log2	f(1)
+                if buf.get_bit() {
+                    rows_log2 += 1;
+                } else {
+                    break;
+                }
+            }
+
+            let tile_height_sb = (sb_rows + (1 << rows_log2) - 1) >> rows_log2;
+            let mut start_sb = 0;
+            while start_sb < sb_rows {
+                mi_row_starts.push(start_sb << sb_shift);
+                start_sb += tile_height_sb;
+            }
+
+            tile_cols_log2 = cols_log2;
+            tile_rows_log2 = rows_log2;
+        } else {
+            let mut widest_tile_sb = 0;
+            let mut start_sb = 0;
+            while start_sb < sb_cols {
+                mi_col_starts.push(start_sb << sb_shift);
+                let max_width = (sb_cols - start_sb).min(max_tile_width_sb);
+
+                // width_in_sbs_minus_1	ns(maxWidth)
+                let size_sb = buf.get_ns(max_width) + 1;
+                widest_tile_sb = widest_tile_sb.max(size_sb);
+                start_sb += size_sb;
+            }
+
+            tile_cols_log2 = tile_log2(1, mi_col_starts.len() as u32);
+
+            max_tile_area_sb = if min_log2_tiles > 0 {
+                (sb_rows * sb_cols) >> (min_log2_tiles + 1)
+            } else {
+                sb_rows * sb_cols
+            };
+
+            let max_tile_height_sb = (max_tile_area_sb / widest_tile_sb).max(1);
+            let mut start_sb = 0;
+            while start_sb < sb_rows {
+                mi_row_starts.push(start_sb << sb_shift);
+                let max_height = (sb_rows - start_sb).min(max_tile_height_sb);
+
+                // height_in_sbs_minus_1	ns(maxHeight)
+                start_sb += buf.get_ns(max_height) + 1;
+            }
+
+            tile_rows_log2 = tile_log2(1, mi_row_starts.len() as u32);
+        }
+
+        let tile_cols = mi_col_starts.len() as u32;
+        let tile_rows = mi_row_starts.len() as u32;
+        mi_col_starts.push(ctx.mi_cols);
+        mi_row_starts.push(ctx.mi_rows);
+
+        let (context_update_tile_id, tile_size_bytes) = if tile_cols_log2 > 0 || tile_rows_log2 > 0
+        {
+            (
+                // context_update_tile_id	f(TileRowsLog2 + TileColsLog2)
+                buf.get_bits((tile_rows_log2 + tile_cols_log2) as usize),
+                // tile_size_bytes_minus_1	f(2)
+                buf.get_bits(2) as usize + 1,
+            )
+        } else {
+            (0, 4)
+        };
+
+        Self {
+            tile_cols,
+            tile_rows,
+            tile_cols_log2,
+            tile_rows_log2,
+            mi_col_starts,
+            mi_row_starts,
+            context_update_tile_id,
+            tile_size_bytes,
+        }
+    }
+}
+
+#[inline]
+fn read_delta_q(buf: &mut Buffer) -> i8 {
+    // delta_coded	f(1)
+    if buf.get_bit() {
+        // delta_q	su(1+6)
+        buf.get_su(7) as i8
+    } else {
+        0
+    }
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#quantization-params-syntax
+#[derive(Debug, Clone, Default)]
+pub struct QuantizationParams {
+    pub base_q_idx: u8,
+    pub delta_q_y_dc: i8,
+    pub delta_q_u_dc: i8,
+    pub delta_q_u_ac: i8,
+    pub delta_q_v_dc: i8,
+    pub delta_q_v_ac: i8,
+    pub using_qmatrix: bool,
+    pub qm_y: u8,
+    pub qm_u: u8,
+    pub qm_v: u8,
+}
+
+impl QuantizationParams {
+    pub fn decode(
+        ctx: &Av1DecoderContext,
+        sequence_header: &SequenceHeader,
+        buf: &mut Buffer,
+    ) -> Self {
+        // base_q_idx	f(8)
+        let base_q_idx = buf.get_bits(8) as u8;
+        let delta_q_y_dc = read_delta_q(buf);
+
+        let mut delta_q_u_dc = 0;
+        let mut delta_q_u_ac = 0;
+        let mut delta_q_v_dc = 0;
+        let mut delta_q_v_ac = 0;
+        if ctx.num_planes > 1 {
+            let diff_uv_delta = if sequence_header.color_config.separate_uv_delta_q {
+                // diff_uv_delta	f(1)
+                buf.get_bit()
+            } else {
+                false
+            };
+
+            delta_q_u_dc = read_delta_q(buf);
+            delta_q_u_ac = read_delta_q(buf);
+            if diff_uv_delta {
+                delta_q_v_dc = read_delta_q(buf);
+                delta_q_v_ac = read_delta_q(buf);
+            } else {
+                delta_q_v_dc = delta_q_u_dc;
+                delta_q_v_ac = delta_q_u_ac;
+            }
+        }
+
+        // using_qmatrix	f(1)
+        let using_qmatrix = buf.get_bit();
+
+        let mut qm_y = 0;
+        let mut qm_u = 0;
+        let mut qm_v = 0;
+        if using_qmatrix {
+            // qm_y	f(4)
+            qm_y = buf.get_bits(4) as u8;
+
+            // qm_u	f(4)
+            qm_u = buf.get_bits(4) as u8;
+
+            qm_v = if sequence_header.color_config.separate_uv_delta_q {
+                // qm_v	f(4)
+                buf.get_bits(4) as u8
+            } else {
+                qm_u
+            };
+        }
+
+        Self {
+            base_q_idx,
+            delta_q_y_dc,
+            delta_q_u_dc,
+            delta_q_u_ac,
+            delta_q_v_dc,
+            delta_q_v_ac,
+            using_qmatrix,
+            qm_y,
+            qm_u,
+            qm_v,
+        }
+    }
+}
+
+const SEGMENTATION_FEATURE_BITS: [usize; SEG_LVL_MAX as usize] = [8, 6, 6, 6, 6, 3, 0, 0];
+const SEGMENTATION_FEATURE_SIGNED: [bool; SEG_LVL_MAX as usize] =
+    [true, true, true, true, true, false, false, false];
+const SEGMENTATION_FEATURE_MAX: [i16; SEG_LVL_MAX as usize] = [
+    255,
+    MAX_LOOP_FILTER as i16,
+    MAX_LOOP_FILTER as i16,
+    MAX_LOOP_FILTER as i16,
+    MAX_LOOP_FILTER as i16,
+    7,
+    0,
+    0,
+];
+
+/// see: https://aomediacodec.github.io/av1-spec/#segmentation-params-syntax
+#[derive(Debug, Clone, Default)]
+pub struct SegmentationParams {
+    pub segmentation_enabled: bool,
+    pub segmentation_update_map: bool,
+    pub segmentation_temporal_update: bool,
+    pub segmentation_update_data: bool,
+    pub feature_enabled: [[bool; SEG_LVL_MAX as usize]; MAX_SEGMENTS as usize],
+    pub feature_data: [[i16; SEG_LVL_MAX as usize]; MAX_SEGMENTS as usize],
+    pub seg_id_pre_skip: bool,
+    pub last_active_seg_id: u8,
+}
+
+impl SegmentationParams {
+    /// The segmentation features of the previous frame are passed in as
+    /// `prev`, they are kept when segmentation_update_data is equal to 0.
+    pub fn decode(primary_ref_frame: u8, prev: &Self, buf: &mut Buffer) -> Self {
+        let mut params = Self {
+            // segmentation_enabled	f(1)
+            segmentation_enabled: buf.get_bit(),
+            ..Self::default()
+        };
+
+        if params.segmentation_enabled {
+            if primary_ref_frame == PRIMARY_REF_NONE {
+                params.segmentation_update_map = true;
+                params.segmentation_temporal_update = false;
+                params.segmentation_update_data = true;
+            } else {
+                // segmentation_update_map	f(1)
+                params.segmentation_update_map = buf.get_bit();
+                if params.segmentation_update_map {
+                    // segmentation_temporal_update	f(1)
+                    params.segmentation_temporal_update = buf.get_bit();
+                }
+
+                // segmentation_update_data	f(1)
+                params.segmentation_update_data = buf.get_bit();
+            }
+
+            if params.segmentation_update_data {
+                for i in 0..MAX_SEGMENTS as usize {
+                    for j in 0..SEG_LVL_MAX as usize {
+                        // feature_enabled	f(1)
+                        let feature_enabled = buf.get_bit();
+                        params.feature_enabled[i][j] = feature_enabled;
+
+                        let mut clipped_value = 0;
+                        if feature_enabled {
+                            let bits_to_read = SEGMENTATION_FEATURE_BITS[j];
+                            let limit = SEGMENTATION_FEATURE_MAX[j];
+                            clipped_value = if SEGMENTATION_FEATURE_SIGNED[j] {
+                                // feature_value	su(1+bitsToRead)
+                                let feature_value = buf.get_su(1 + bits_to_read) as i16;
+                                clip3(-limit, limit, feature_value)
+                            } else if bits_to_read > 0 {
+                                // feature_value	f(bitsToRead)
+                                let feature_value = buf.get_bits(bits_to_read) as i16;
+                                clip3(0, limit, feature_value)
+                            } else {
+                                0
+                            };
+                        }
+
+                        params.feature_data[i][j] = clipped_value;
+                    }
+                }
+            } else {
+                params.feature_enabled = prev.feature_enabled;
+                params.feature_data = prev.feature_data;
+            }
+        }
+
+        for i in 0..MAX_SEGMENTS as usize {
+            for j in 0..SEG_LVL_MAX as usize {
+                if params.feature_enabled[i][j] {
+                    params.last_active_seg_id = i as u8;
+                    if j >= SEG_LVL_REF_FRAME as usize {
+                        params.seg_id_pre_skip = true;
+                    }
+                }
+            }
+        }
+
+        params
+    }
+
+    /// seg_feature_active_idx( idx, feature )
+    #[inline]
+    pub fn feature_active(&self, segment_id: u8, feature: u8) -> bool {
+        self.segmentation_enabled && self.feature_enabled[segment_id as usize][feature as usize]
+    }
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#delta-quantizer-index-parameters-syntax
+#[derive(Debug, Clone, Default)]
+pub struct DeltaQParams {
+    pub delta_q_present: bool,
+    pub delta_q_res: u8,
+}
+
+impl DeltaQParams {
+    pub fn decode(base_q_idx: u8, buf: &mut Buffer) -> Self {
+        let mut delta_q_present = false;
+        let mut delta_q_res = 0;
+        if base_q_idx > 0 {
+            // delta_q_present	f(1)
+            delta_q_present = buf.get_bit();
+        }
+
+        if delta_q_present {
+            // delta_q_res	f(2)
+            delta_q_res = buf.get_bits(2) as u8;
+        }
+
+        Self {
+            delta_q_present,
+            delta_q_res,
+        }
+    }
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#delta-loop-filter-params-syntax
+#[derive(Debug, Clone, Default)]
+pub struct DeltaLfParams {
+    pub delta_lf_present: bool,
+    pub delta_lf_res: u8,
+    pub delta_lf_multi: bool,
+}
+
+impl DeltaLfParams {
+    pub fn decode(delta_q_present: bool, allow_intrabc: bool, buf: &mut Buffer) -> Self {
+        let mut params = Self::default();
+        if delta_q_present {
+            if !allow_intrabc {
+                // delta_lf_present	f(1)
+                params.delta_lf_present = buf.get_bit();
+            }
+
+            if params.delta_lf_present {
+                // delta_lf_res	f(2)
+                params.delta_lf_res = buf.get_bits(2) as u8;
+
+                // delta_lf_multi	f(1)
+                params.delta_lf_multi = buf.get_bit();
+            }
+        }
+
+        params
+    }
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#loop-filter-params-syntax
 #[derive(Debug, Clone)]
-pub struct UncompressedHeader {}
+pub struct LoopFilterParams {
+    pub loop_filter_level: [u8; 4],
+    pub loop_filter_sharpness: u8,
+    pub loop_filter_delta_enabled: bool,
+    pub loop_filter_delta_update: bool,
+    pub loop_filter_ref_deltas: [i8; TOTAL_REFS_PER_FRAME as usize],
+    pub loop_filter_mode_deltas: [i8; 2],
+}
 
-impl UncompressedHeader {
-    // TODO
-    fn mark_ref_frames(f: &FrameIdNumbersPresent, id_len: usize, current_frame_id: u32) {
-        let diff_len = f.delta_frame_id_length;
-        for i in 0..NUM_REF_FRAMES as usize {
-            if current_frame_id > (1 << diff_len) {
+impl Default for LoopFilterParams {
+    /// The loop filter deltas set by setup_past_independence.
+    fn default() -> Self {
+        let mut loop_filter_ref_deltas = [0; TOTAL_REFS_PER_FRAME as usize];
+        loop_filter_ref_deltas[INTRA_FRAME as usize] = 1;
+        loop_filter_ref_deltas[LAST_FRAME as usize] = 0;
+        loop_filter_ref_deltas[LAST2_FRAME as usize] = 0;
+        loop_filter_ref_deltas[LAST3_FRAME as usize] = 0;
+        loop_filter_ref_deltas[BWDREF_FRAME as usize] = 0;
+        loop_filter_ref_deltas[GOLDEN_FRAME as usize] = -1;
+        loop_filter_ref_deltas[ALTREF_FRAME as usize] = -1;
+        loop_filter_ref_deltas[ALTREF2_FRAME as usize] = -1;
+
+        Self {
+            loop_filter_level: [0; 4],
+            loop_filter_sharpness: 0,
+            loop_filter_delta_enabled: true,
+            loop_filter_delta_update: false,
+            loop_filter_ref_deltas,
+            loop_filter_mode_deltas: [0; 2],
+        }
+    }
+}
+
+impl LoopFilterParams {
+    /// The deltas of the previous frame are passed in as `prev`, each of them
+    /// is only replaced when an update is coded.
+    pub fn decode(
+        ctx: &Av1DecoderContext,
+        coded_lossless: bool,
+        allow_intrabc: bool,
+        prev: &Self,
+        buf: &mut Buffer,
+    ) -> Self {
+        if coded_lossless || allow_intrabc {
+            return Self {
+                loop_filter_delta_enabled: prev.loop_filter_delta_enabled,
+                ..Self::default()
+            };
+        }
+
+        let mut params = prev.clone();
+        params.loop_filter_level = [0; 4];
+
+        // loop_filter_level[ 0 ]	f(6)
+        params.loop_filter_level[0] = buf.get_bits(6) as u8;
+
+        // loop_filter_level[ 1 ]	f(6)
+        params.loop_filter_level[1] = buf.get_bits(6) as u8;
+
+        if ctx.num_planes > 1
+            && (params.loop_filter_level[0] > 0 || params.loop_filter_level[1] > 0)
+        {
+            // loop_filter_level[ 2 ]	f(6)
+            params.loop_filter_level[2] = buf.get_bits(6) as u8;
+
+            // loop_filter_level[ 3 ]	f(6)
+            params.loop_filter_level[3] = buf.get_bits(6) as u8;
+        }
+
+        // loop_filter_sharpness	f(3)
+        params.loop_filter_sharpness = buf.get_bits(3) as u8;
+
+        // loop_filter_delta_enabled	f(1)
+        params.loop_filter_delta_enabled = buf.get_bit();
+        params.loop_filter_delta_update = false;
+        if params.loop_filter_delta_enabled {
+            // loop_filter_delta_update	f(1)
+            params.loop_filter_delta_update = buf.get_bit();
+            if params.loop_filter_delta_update {
+                for delta in params.loop_filter_ref_deltas.iter_mut() {
+                    // update_ref_delta	f(1)
+                    if buf.get_bit() {
+                        // loop_filter_ref_deltas[ i ]	su(1+6)
+                        *delta = buf.get_su(7) as i8;
+                    }
+                }
+
+                for delta in params.loop_filter_mode_deltas.iter_mut() {
+                    // update_mode_delta	f(1)
+                    if buf.get_bit() {
+                        // loop_filter_mode_deltas[ i ]	su(1+6)
+                        *delta = buf.get_su(7) as i8;
+                    }
+                }
+            }
+        }
+
+        params
+    }
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#cdef-params-syntax
+#[derive(Debug, Clone, Default)]
+pub struct CdefParams {
+    pub cdef_damping: u8,
+    pub cdef_bits: u8,
+    pub cdef_y_pri_strength: [u8; 8],
+    pub cdef_y_sec_strength: [u8; 8],
+    pub cdef_uv_pri_strength: [u8; 8],
+    pub cdef_uv_sec_strength: [u8; 8],
+}
+
+impl CdefParams {
+    pub fn decode(
+        ctx: &Av1DecoderContext,
+        sequence_header: &SequenceHeader,
+        coded_lossless: bool,
+        allow_intrabc: bool,
+        buf: &mut Buffer,
+    ) -> Self {
+        let mut params = Self {
+            cdef_damping: 3,
+            ..Self::default()
+        };
+
+        if coded_lossless || allow_intrabc || !sequence_header.enable_cdef {
+            return params;
+        }
+
+        // cdef_damping_minus_3	f(2)
+        params.cdef_damping = buf.get_bits(2) as u8 + 3;
+
+        // cdef_bits	f(2)
+        params.cdef_bits = buf.get_bits(2) as u8;
+        for i in 0..(1 << params.cdef_bits) {
+            // cdef_y_pri_strength[i]	f(4)
+            params.cdef_y_pri_strength[i] = buf.get_bits(4) as u8;
+
+            // cdef_y_sec_strength[i]	f(2)
+            params.cdef_y_sec_strength[i] = buf.get_bits(2) as u8;
+            if params.cdef_y_sec_strength[i] == 3 {
+                params.cdef_y_sec_strength[i] += 1;
+            }
+
+            if ctx.num_planes > 1 {
+                // cdef_uv_pri_strength[i]	f(4)
+                params.cdef_uv_pri_strength[i] = buf.get_bits(4) as u8;
+
+                // cdef_uv_sec_strength[i]	f(2)
+                params.cdef_uv_sec_strength[i] = buf.get_bits(2) as u8;
+                if params.cdef_uv_sec_strength[i] == 3 {
+                    params.cdef_uv_sec_strength[i] += 1;
+                }
+            }
+        }
+
+        params
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestorationType {
+    #[default]
+    None,
+    Wiener,
+    Sgrproj,
+    Switchable,
+}
+
+impl RestorationType {
+    /// Remap_Lr_Type
+    fn from_lr_type(lr_type: u32) -> Self {
+        match lr_type {
+            0 => Self::None,
+            1 => Self::Switchable,
+            2 => Self::Wiener,
+            _ => Self::Sgrproj,
+        }
+    }
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#loop-restoration-params-syntax
+#[derive(Debug, Clone, Default)]
+pub struct LrParams {
+    pub frame_restoration_type: [RestorationType; 3],
+    pub loop_restoration_size: [u16; 3],
+    pub uses_lr: bool,
+}
+
+impl LrParams {
+    pub fn decode(
+        ctx: &Av1DecoderContext,
+        sequence_header: &SequenceHeader,
+        all_lossless: bool,
+        allow_intrabc: bool,
+        buf: &mut Buffer,
+    ) -> Self {
+        let mut params = Self::default();
+        if all_lossless || allow_intrabc || !sequence_header.enable_restoration {
+            return params;
+        }
+
+        let mut uses_chroma_lr = false;
+        for i in 0..ctx.num_planes as usize {
+            // lr_type	f(2)
+            let lr_type = RestorationType::from_lr_type(buf.get_bits(2));
+            params.frame_restoration_type[i] = lr_type;
+            if lr_type != RestorationType::None {
+                params.uses_lr = true;
+                if i > 0 {
+                    uses_chroma_lr = true;
+                }
+            }
+        }
+
+        if params.uses_lr {
+            let mut lr_unit_shift;
+            if sequence_header.use_128x128_superblock {
+                // lr_unit_shift	f(1)
+                lr_unit_shift = buf.get_bits(1);
+                lr_unit_shift += 1;
             } else {
+                // lr_unit_shift	f(1)
+                lr_unit_shift = buf.get_bits(1);
+                if lr_unit_shift > 0 {
+                    // lr_unit_extra_shift	f(1)
+                    lr_unit_shift += buf.get_bits(1);
+                }
             }
+
+            params.loop_restoration_size[0] = RESTORATION_TILESIZE_MAX >> (2 - lr_unit_shift);
+
+            let color_config = &sequence_header.color_config;
+            let lr_uv_shift =
+                if color_config.subsampling_x && color_config.subsampling_y && uses_chroma_lr {
+                    // lr_uv_shift	f(1)
+                    buf.get_bits(1)
+                } else {
+                    0
+                };
+
+            params.loop_restoration_size[1] = params.loop_restoration_size[0] >> lr_uv_shift;
+            params.loop_restoration_size[2] = params.loop_restoration_size[0] >> lr_uv_shift;
         }
+
+        params
     }
+}
 
-    pub fn decode(ctx: &mut Av1DecoderContext, buf: &mut Buffer) -> Result<Self, Av1DecodeError> {
-        let sequence_header = ctx
-            .sequence_header
-            .clone()
-            .expect("sequence header cannot be found, this is a undefined behavior!");
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TxMode {
+    Only4x4,
+    #[default]
+    Largest,
+    Select,
+}
 
-        let mut id_len = 0;
-        if let Some(value) = &sequence_header.frame_id_numbers_present {
-            id_len = value.additional_frame_id_length as usize
-                + value.delta_frame_id_length as usize
-                + 3;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlobalMotionParams {
+    pub gm_type: [u8; TOTAL_REFS_PER_FRAME as usize],
+    pub gm_params: [[i32; 6]; TOTAL_REFS_PER_FRAME as usize],
+}
+
+impl Default for GlobalMotionParams {
+    fn default() -> Self {
+        let mut gm_params = [[0; 6]; TOTAL_REFS_PER_FRAME as usize];
+        for params in gm_params.iter_mut() {
+            for (i, param) in params.iter_mut().enumerate() {
+                if i % 3 == 2 {
+                    *param = 1 << WARPEDMODEL_PREC_BITS;
+                }
+            }
         }
 
-        let all_frames = (1 << NUM_REF_FRAMES) - 1;
+        Self {
+            gm_type: [IDENTITY; TOTAL_REFS_PER_FRAME as usize],
+            gm_params,
+        }
+    }
+}
 
-        let mut show_existing_frame = false;
-        let mut frame_type = FrameType::KeyFrame;
-        let mut show_frame = true;
-        let mut showable_frame = false;
-        let mut refresh_frame_flags = 0;
-        let mut error_resilient_mode = false;
+fn decode_subexp(num_syms: i32, buf: &mut Buffer) -> i32 {
+    let mut i = 0;
+    let mut mk = 0;
+    let k = 3;
+    loop {
+        let b2 = if i > 0 { k + i - 1 } else { k };
+        let a = 1 << b2;
+        if num_syms <= mk + 3 * a {
+            // subexp_final_bits	ns(numSyms - mk)
+            return buf.get_ns((num_syms - mk) as u32) as i32 + mk;
+        }
 
-        if sequence_header.reduced_still_picture_header {
-            ctx.frame_is_intra = true;
+        // subexp_more_bits	f(1)
+        if buf.get_bit() {
+            i += 1;
+            mk += a;
         } else {
-            // show_existing_frame	f(1)
-            show_existing_frame = buf.get_bit();
+            // subexp_bits	f(b2)
+            return buf.get_bits(b2 as usize) as i32 + mk;
+        }
+    }
+}
 
-            if show_existing_frame {
-                // frame_to_show_map_idx	f(3)
-                let frame_to_show_map_idx = buf.get_bits(3) as u8;
-                if let Some(decoder_model_info) = &sequence_header.decoder_model_info {
-                    if !sequence_header
-                        .timing_info
-                        .map(|v| v.equal_picture_interval.is_some())
-                        .unwrap_or(false)
+fn decode_unsigned_subexp_with_ref(mx: i32, r: i32, buf: &mut Buffer) -> i32 {
+    let v = decode_subexp(mx, buf);
+    if (r << 1) <= mx {
+        inverse_recenter(r, v)
+    } else {
+        mx - 1 - inverse_recenter(mx - 1 - r, v)
+    }
+}
+
+fn decode_signed_subexp_with_ref(low: i32, high: i32, r: i32, buf: &mut Buffer) -> i32 {
+    decode_unsigned_subexp_with_ref(high - low, r - low, buf) + low
+}
+
+impl GlobalMotionParams {
+    /// The parameters of the previous frame (PrevGmParams) are used as the
+    /// reference when decoding the new ones.
+    pub fn decode(
+        frame_is_intra: bool,
+        allow_high_precision_mv: bool,
+        prev: &Self,
+        buf: &mut Buffer,
+    ) -> Self {
+        let mut params = Self::default();
+        if frame_is_intra {
+            return params;
+        }
+
+        for rf in LAST_FRAME as usize..=ALTREF_FRAME as usize {
+            // is_global	f(1)
+            let gm_type = if buf.get_bit() {
+                // is_rot_zoom	f(1)
+                if buf.get_bit() {
+                    ROTZOOM
+                } else {
+                    // is_translation	f(1)
+                    if buf.get_bit() {
+                        TRANSLATION
+                    } else {
+                        AFFINE
+                    }
+                }
+            } else {
+                IDENTITY
+            };
+
+            params.gm_type[rf] = gm_type;
+            if gm_type >= ROTZOOM {
+                params.read_global_param(gm_type, rf, 2, allow_high_precision_mv, prev, buf);
+                params.read_global_param(gm_type, rf, 3, allow_high_precision_mv, prev, buf);
+                if gm_type == AFFINE {
+                    params.read_global_param(gm_type, rf, 4, allow_high_precision_mv, prev, buf);
+                    params.read_global_param(gm_type, rf, 5, allow_high_precision_mv, prev, buf);
+                } else {
+                    params.gm_params[rf][4] = -params.gm_params[rf][3];
+                    params.gm_params[rf][5] = params.gm_params[rf][2];
+                }
+            }
+
+            if gm_type >= TRANSLATION {
+                params.read_global_param(gm_type, rf, 0, allow_high_precision_mv, prev, buf);
+                params.read_global_param(gm_type, rf, 1, allow_high_precision_mv, prev, buf);
+            }
+        }
+
+        params
+    }
+
+    fn read_global_param(
+        &mut self,
+        gm_type: u8,
+        rf: usize,
+        idx: usize,
+        allow_high_precision_mv: bool,
+        prev: &Self,
+        buf: &mut Buffer,
+    ) {
+        let mut abs_bits = GM_ABS_ALPHA_BITS as i32;
+        let mut prec_bits = GM_ALPHA_PREC_BITS as i32;
+        if idx < 2 {
+            if gm_type == TRANSLATION {
+                let hp = !allow_high_precision_mv as i32;
+                abs_bits = GM_ABS_TRANS_ONLY_BITS as i32 - hp;
+                prec_bits = GM_TRANS_ONLY_PREC_BITS as i32 - hp;
+            } else {
+                abs_bits = GM_ABS_TRANS_BITS as i32;
+                prec_bits = GM_TRANS_PREC_BITS as i32;
+            }
+        }
+
+        let prec_diff = WARPEDMODEL_PREC_BITS as i32 - prec_bits;
+        let round = if idx % 3 == 2 {
+            1 << WARPEDMODEL_PREC_BITS
+        } else {
+            0
+        };
+
+        let sub = if idx % 3 == 2 { 1 << prec_bits } else { 0 };
+        let mx = 1 << abs_bits;
+        let r = (prev.gm_params[rf][idx] >> prec_diff) - sub;
+        self.gm_params[rf][idx] =
+            (decode_signed_subexp_with_ref(-mx, mx + 1, r, buf) << prec_diff) + round;
+    }
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#film-grain-params-syntax
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilmGrainParams {
+    pub grain_seed: u16,
+    pub update_grain: bool,
+    pub point_y_value: Vec<u8>,
+    pub point_y_scaling: Vec<u8>,
+    pub chroma_scaling_from_luma: bool,
+    pub point_cb_value: Vec<u8>,
+    pub point_cb_scaling: Vec<u8>,
+    pub point_cr_value: Vec<u8>,
+    pub point_cr_scaling: Vec<u8>,
+    pub grain_scaling_minus_8: u8,
+    pub ar_coeff_lag: u8,
+    pub ar_coeffs_y_plus_128: Vec<u8>,
+    pub ar_coeffs_cb_plus_128: Vec<u8>,
+    pub ar_coeffs_cr_plus_128: Vec<u8>,
+    pub ar_coeff_shift_minus_6: u8,
+    pub grain_scale_shift: u8,
+    pub cb_mult: u8,
+    pub cb_luma_mult: u8,
+    pub cb_offset: u16,
+    pub cr_mult: u8,
+    pub cr_luma_mult: u8,
+    pub cr_offset: u16,
+    pub overlap_flag: bool,
+    pub clip_to_restricted_range: bool,
+}
+
+impl FilmGrainParams {
+    /// Returns None when apply_grain is equal to 0, in which case the film
+    /// grain synthesis process is not invoked for this frame.
+    pub fn decode(
+        ctx: &Av1DecoderContext,
+        sequence_header: &SequenceHeader,
+        frame_type: FrameType,
+        show_frame: bool,
+        showable_frame: bool,
+        buf: &mut Buffer,
+    ) -> Result<Option<Self>, Av1DecodeError> {
+        if !sequence_header.film_grain_params_present || (!show_frame && !showable_frame) {
+            return Ok(None);
+        }
+
+        // apply_grain	f(1)
+        if !buf.get_bit() {
+            return Ok(None);
+        }
+
+        // grain_seed	f(16)
+        let grain_seed = buf.get_bits(16) as u16;
+        let update_grain = if frame_type == FrameType::InterFrame {
+            // update_grain	f(1)
+            buf.get_bit()
+        } else {
+            true
+        };
+
+        if !update_grain {
+            // film_grain_params_ref_idx	f(3)
+            let film_grain_params_ref_idx = buf.get_bits(3) as usize;
+
+            // load_grain_params( film_grain_params_ref_idx ), it is a requirement
+            // of bitstream conformance that film_grain_params_ref_idx is equal
+            // to ref_frame_idx[ j ] for some j.
+            let params = ctx.refs[film_grain_params_ref_idx]
+                .as_ref()
+                .ok_or(Av1DecodeError::Invalid(
+                    Av1DecodeInvalidError::MissingReference,
+                ))?
+                .frame_header
+                .uncompressed_header
+                .film_grain_params
+                .clone();
+
+            return Ok(params.map(|params| Self {
+                grain_seed,
+                update_grain,
+                ..params
+            }));
+        }
+
+        let mut params = Self {
+            grain_seed,
+            update_grain,
+            ..Self::default()
+        };
+
+        // num_y_points	f(4)
+        let num_y_points = buf.get_bits(4) as usize;
+        for _ in 0..num_y_points {
+            // point_y_value[ i ]	f(8)
+            params.point_y_value.push(buf.get_bits(8) as u8);
+
+            // point_y_scaling[ i ]	f(8)
+            params.point_y_scaling.push(buf.get_bits(8) as u8);
+        }
+
+        let color_config = &sequence_header.color_config;
+        params.chroma_scaling_from_luma = if color_config.mono_chrome {
+            false
+        } else {
+            // chroma_scaling_from_luma	f(1)
+            buf.get_bit()
+        };
+
+        if !(color_config.mono_chrome
+            || params.chroma_scaling_from_luma
+            || (color_config.subsampling_x && color_config.subsampling_y && num_y_points == 0))
+        {
+            // num_cb_points	f(4)
+            let num_cb_points = buf.get_bits(4) as usize;
+            for _ in 0..num_cb_points {
+                // point_cb_value[ i ]	f(8)
+                params.point_cb_value.push(buf.get_bits(8) as u8);
+
+                // point_cb_scaling[ i ]	f(8)
+                params.point_cb_scaling.push(buf.get_bits(8) as u8);
+            }
+
+            // num_cr_points	f(4)
+            let num_cr_points = buf.get_bits(4) as usize;
+            for _ in 0..num_cr_points {
+                // point_cr_value[ i ]	f(8)
+                params.point_cr_value.push(buf.get_bits(8) as u8);
+
+                // point_cr_scaling[ i ]	f(8)
+                params.point_cr_scaling.push(buf.get_bits(8) as u8);
+            }
+        }
+
+        // grain_scaling_minus_8	f(2)
+        params.grain_scaling_minus_8 = buf.get_bits(2) as u8;
+
+        // ar_coeff_lag	f(2)
+        params.ar_coeff_lag = buf.get_bits(2) as u8;
+
+        let num_pos_luma = 2 * params.ar_coeff_lag as usize * (params.ar_coeff_lag as usize + 1);
+        let num_pos_chroma = if num_y_points > 0 {
+            for _ in 0..num_pos_luma {
+                // ar_coeffs_y_plus_128[ i ]	f(8)
+                params.ar_coeffs_y_plus_128.push(buf.get_bits(8) as u8);
+            }
+
+            num_pos_luma + 1
+        } else {
+            num_pos_luma
+        };
+
+        if params.chroma_scaling_from_luma || !params.point_cb_value.is_empty() {
+            for _ in 0..num_pos_chroma {
+                // ar_coeffs_cb_plus_128[ i ]	f(8)
+                params.ar_coeffs_cb_plus_128.push(buf.get_bits(8) as u8);
+            }
+        }
+
+        if params.chroma_scaling_from_luma || !params.point_cr_value.is_empty() {
+            for _ in 0..num_pos_chroma {
+                // ar_coeffs_cr_plus_128[ i ]	f(8)
+                params.ar_coeffs_cr_plus_128.push(buf.get_bits(8) as u8);
+            }
+        }
+
+        // ar_coeff_shift_minus_6	f(2)
+        params.ar_coeff_shift_minus_6 = buf.get_bits(2) as u8;
+
+        // grain_scale_shift	f(2)
+        params.grain_scale_shift = buf.get_bits(2) as u8;
+
+        if !params.point_cb_value.is_empty() {
+            // cb_mult	f(8)
+            params.cb_mult = buf.get_bits(8) as u8;
+
+            // cb_luma_mult	f(8)
+            params.cb_luma_mult = buf.get_bits(8) as u8;
+
+            // cb_offset	f(9)
+            params.cb_offset = buf.get_bits(9) as u16;
+        }
+
+        if !params.point_cr_value.is_empty() {
+            // cr_mult	f(8)
+            params.cr_mult = buf.get_bits(8) as u8;
+
+            // cr_luma_mult	f(8)
+            params.cr_luma_mult = buf.get_bits(8) as u8;
+
+            // cr_offset	f(9)
+            params.cr_offset = buf.get_bits(9) as u16;
+        }
+
+        // overlap_flag	f(1)
+        params.overlap_flag = buf.get_bit();
+
+        // clip_to_restricted_range	f(1)
+        params.clip_to_restricted_range = buf.get_bit();
+
+        Ok(Some(params))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UncompressedHeader {
+    pub show_existing_frame: bool,
+    pub frame_to_show_map_idx: u8,
+    pub temporal_point_info: Option<TemporalPointInfo>,
+    pub display_frame_id: u32,
+    pub frame_type: FrameType,
+    pub frame_is_intra: bool,
+    pub show_frame: bool,
+    pub showable_frame: bool,
+    pub error_resilient_mode: bool,
+    pub disable_cdf_update: bool,
+    pub allow_screen_content_tools: bool,
+    pub force_integer_mv: bool,
+    pub current_frame_id: u32,
+    pub frame_size_override: bool,
+    pub order_hint: u32,
+    pub primary_ref_frame: u8,
+    pub buffer_removal_times: Vec<u32>,
+    pub refresh_frame_flags: u8,
+    pub ref_frame_idx: [u8; REFS_PER_FRAME as usize],
+    pub allow_intrabc: bool,
+    pub allow_high_precision_mv: bool,
+    pub interpolation_filter: InterpolationFilter,
+    pub is_motion_mode_switchable: bool,
+    pub use_ref_frame_mvs: bool,
+    /// OrderHints, the order hint of each reference frame type.
+    pub order_hints: [u32; TOTAL_REFS_PER_FRAME as usize],
+    pub ref_frame_sign_bias: [bool; TOTAL_REFS_PER_FRAME as usize],
+    pub disable_frame_end_update_cdf: bool,
+    pub tile_info: TileInfo,
+    pub quantization_params: QuantizationParams,
+    pub segmentation_params: SegmentationParams,
+    pub delta_q_params: DeltaQParams,
+    pub delta_lf_params: DeltaLfParams,
+    pub coded_lossless: bool,
+    pub all_lossless: bool,
+    pub lossless_array: [bool; MAX_SEGMENTS as usize],
+    pub seg_qm_level: [[u8; MAX_SEGMENTS as usize]; 3],
+    pub loop_filter_params: LoopFilterParams,
+    pub cdef_params: CdefParams,
+    pub lr_params: LrParams,
+    pub tx_mode: TxMode,
+    pub reference_select: bool,
+    pub skip_mode_present: bool,
+    pub skip_mode_frame: [u8; 2],
+    pub allow_warped_motion: bool,
+    pub reduced_tx_set: bool,
+    pub global_motion_params: GlobalMotionParams,
+    pub film_grain_params: Option<FilmGrainParams>,
+}
+
+impl UncompressedHeader {
+    /// Invalidates the reference frames whose frame id cannot be correct,
+    /// given the id of the current frame.
+    fn mark_ref_frames(ctx: &mut Av1DecoderContext, id_len: usize, delta_frame_id_length: u8) {
+        let diff_len = delta_frame_id_length as u32;
+        let current_frame_id = ctx.current_frame_id;
+        for r in ctx.refs.iter_mut() {
+            if let Some(ref_frame_id) = r.as_ref().map(|r| r.frame_id) {
+                let invalid = if current_frame_id > (1 << diff_len) {
+                    ref_frame_id > current_frame_id
+                        || ref_frame_id < (current_frame_id - (1 << diff_len))
+                } else {
+                    ref_frame_id > current_frame_id
+                        && ref_frame_id < ((1 << id_len) + current_frame_id - (1 << diff_len))
+                };
+
+                if invalid {
+                    *r = None;
+                }
+            }
+        }
+    }
+
+    /// see: https://aomediacodec.github.io/av1-spec/#set-frame-refs-process
+    fn set_frame_refs(
+        ctx: &Av1DecoderContext,
+        last_frame_idx: u8,
+        gold_frame_idx: u8,
+    ) -> [u8; REFS_PER_FRAME as usize] {
+        const REF_FRAME_LIST: [u8; REFS_PER_FRAME as usize - 2] = [
+            LAST2_FRAME,
+            LAST3_FRAME,
+            BWDREF_FRAME,
+            ALTREF2_FRAME,
+            ALTREF_FRAME,
+        ];
+
+        let mut ref_frame_idx = [-1i32; REFS_PER_FRAME as usize];
+        ref_frame_idx[0] = last_frame_idx as i32;
+        ref_frame_idx[(GOLDEN_FRAME - LAST_FRAME) as usize] = gold_frame_idx as i32;
+
+        let mut used_frame = [false; NUM_REF_FRAMES as usize];
+        used_frame[last_frame_idx as usize] = true;
+        used_frame[gold_frame_idx as usize] = true;
+
+        let cur_frame_hint = 1 << (ctx.order_hint_bits - 1);
+        let mut shifted_order_hints = [0; NUM_REF_FRAMES as usize];
+        for (i, hint) in shifted_order_hints.iter_mut().enumerate() {
+            let ref_order_hint = ctx.refs[i].as_ref().map(|r| r.order_hint).unwrap_or(0);
+            *hint = cur_frame_hint + get_relative_dist(ctx, ref_order_hint, ctx.order_hint);
+        }
+
+        let find_backward = |used_frame: &[bool], latest: bool| {
+            let mut rf = -1;
+            let mut best = 0;
+            for (i, hint) in shifted_order_hints.iter().copied().enumerate() {
+                if !used_frame[i]
+                    && hint >= cur_frame_hint
+                    && (rf < 0 || if latest { hint >= best } else { hint < best })
+                {
+                    rf = i as i32;
+                    best = hint;
+                }
+            }
+
+            rf
+        };
+
+        let rf = find_backward(&used_frame, true);
+        if rf >= 0 {
+            ref_frame_idx[(ALTREF_FRAME - LAST_FRAME) as usize] = rf;
+            used_frame[rf as usize] = true;
+        }
+
+        let rf = find_backward(&used_frame, false);
+        if rf >= 0 {
+            ref_frame_idx[(BWDREF_FRAME - LAST_FRAME) as usize] = rf;
+            used_frame[rf as usize] = true;
+        }
+
+        let rf = find_backward(&used_frame, false);
+        if rf >= 0 {
+            ref_frame_idx[(ALTREF2_FRAME - LAST_FRAME) as usize] = rf;
+            used_frame[rf as usize] = true;
+        }
+
+        for ref_frame in REF_FRAME_LIST {
+            let i = (ref_frame - LAST_FRAME) as usize;
+            if ref_frame_idx[i] < 0 {
+                // find_latest_forward
+                let mut rf = -1;
+                let mut latest_order_hint = 0;
+                for (j, hint) in shifted_order_hints.iter().copied().enumerate() {
+                    if !used_frame[j]
+                        && hint < cur_frame_hint
+                        && (rf < 0 || hint >= latest_order_hint)
                     {
-                        let temporal_point_info = TemporalPointInfo::decode(
-                            buf.as_mut(),
-                            decoder_model_info.frame_presentation_time_length as usize,
-                        );
+                        rf = j as i32;
+                        latest_order_hint = hint;
                     }
                 }
 
-                if sequence_header.frame_id_numbers_present.is_some() {
-                    // display_frame_id	f(idLen)
-                    let display_frame_id = buf.get_bits(id_len);
+                if rf >= 0 {
+                    ref_frame_idx[i] = rf;
+                    used_frame[rf as usize] = true;
                 }
+            }
+        }
+
+        let mut rf = 0;
+        let mut earliest_order_hint = 0;
+        for (i, hint) in shifted_order_hints.iter().copied().enumerate() {
+            if i == 0 || hint < earliest_order_hint {
+                rf = i;
+                earliest_order_hint = hint;
+            }
+        }
+
+        let mut ret = [0; REFS_PER_FRAME as usize];
+        for (i, idx) in ref_frame_idx.iter().enumerate() {
+            ret[i] = if *idx < 0 { rf as u8 } else { *idx as u8 };
+        }
 
-                // TODO
-                // frame_type = RefFrameType[ frame_to_show_map_idx ]
+        ret
+    }
 
-                if frame_type == FrameType::KeyFrame {
-                    refresh_frame_flags = all_frames;
+    /// see: https://aomediacodec.github.io/av1-spec/#skip-mode-params-syntax
+    fn skip_mode_frames(ctx: &Av1DecoderContext, ref_frame_idx: &[u8]) -> Option<[u8; 2]> {
+        let ref_hint = |i: usize| {
+            ctx.refs[ref_frame_idx[i] as usize]
+                .as_ref()
+                .map(|r| r.order_hint)
+                .unwrap_or(0)
+        };
+
+        let mut forward_idx = -1i32;
+        let mut backward_idx = -1i32;
+        let mut forward_hint = 0;
+        let mut backward_hint = 0;
+        for i in 0..REFS_PER_FRAME as usize {
+            let ref_hint = ref_hint(i);
+            if get_relative_dist(ctx, ref_hint, ctx.order_hint) < 0 {
+                if forward_idx < 0 || get_relative_dist(ctx, ref_hint, forward_hint) > 0 {
+                    forward_idx = i as i32;
+                    forward_hint = ref_hint;
                 }
+            } else if get_relative_dist(ctx, ref_hint, ctx.order_hint) > 0
+                && (backward_idx < 0 || get_relative_dist(ctx, ref_hint, backward_hint) < 0)
+            {
+                backward_idx = i as i32;
+                backward_hint = ref_hint;
+            }
+        }
+
+        if forward_idx < 0 {
+            return None;
+        }
 
-                if sequence_header.film_grain_params_present {
-                    // TODO
-                    // load_grain_params( frame_to_show_map_idx )
+        let second_idx = if backward_idx >= 0 {
+            backward_idx
+        } else {
+            let mut second_forward_idx = -1i32;
+            let mut second_forward_hint = 0;
+            for i in 0..REFS_PER_FRAME as usize {
+                let ref_hint = ref_hint(i);
+                if get_relative_dist(ctx, ref_hint, forward_hint) < 0
+                    && (second_forward_idx < 0
+                        || get_relative_dist(ctx, ref_hint, second_forward_hint) > 0)
+                {
+                    second_forward_idx = i as i32;
+                    second_forward_hint = ref_hint;
                 }
+            }
 
-                // TODO
-                // return
+            if second_forward_idx < 0 {
+                return None;
+            }
+
+            second_forward_idx
+        };
+
+        Some([
+            LAST_FRAME + forward_idx.min(second_idx) as u8,
+            LAST_FRAME + forward_idx.max(second_idx) as u8,
+        ])
+    }
+
+    /// Handles a frame header with show_existing_frame equal to 1, the state
+    /// of the frame to show is taken from the reference slot.
+    fn show_existing_frame(
+        ctx: &mut Av1DecoderContext,
+        sequence_header: &SequenceHeader,
+        id_len: usize,
+        buf: &mut Buffer,
+    ) -> Result<Self, Av1DecodeError> {
+        // frame_to_show_map_idx	f(3)
+        let frame_to_show_map_idx = buf.get_bits(3) as u8;
+
+        let mut temporal_point_info = None;
+        if let Some(decoder_model_info) = &sequence_header.decoder_model_info {
+            if !sequence_header
+                .timing_info
+                .map(|v| v.equal_picture_interval.is_some())
+                .unwrap_or(false)
+            {
+                temporal_point_info = Some(TemporalPointInfo::decode(
+                    buf,
+                    decoder_model_info.frame_presentation_time_length as usize,
+                ));
+            }
+        }
+
+        let display_frame_id = if sequence_header.frame_id_numbers_present.is_some() {
+            // display_frame_id	f(idLen)
+            buf.get_bits(id_len)
+        } else {
+            0
+        };
+
+        // It is a requirement of bitstream conformance that RefValid[
+        // frame_to_show_map_idx ] is equal to 1.
+        let r =
+            ctx.refs[frame_to_show_map_idx as usize]
+                .as_ref()
+                .ok_or(Av1DecodeError::Invalid(
+                    Av1DecodeInvalidError::MissingReference,
+                ))?;
+
+        // frame_type = RefFrameType[ frame_to_show_map_idx ], everything else
+        // including the film grain params (load_grain_params) is also loaded
+        // from the reference slot.
+        let mut header = r.frame_header.uncompressed_header.clone();
+        header.show_existing_frame = true;
+        header.frame_to_show_map_idx = frame_to_show_map_idx;
+        header.temporal_point_info = temporal_point_info;
+        header.display_frame_id = display_frame_id;
+        header.show_frame = true;
+        header.refresh_frame_flags = if header.frame_type == FrameType::KeyFrame {
+            0xFF
+        } else {
+            0
+        };
+
+        if !sequence_header.film_grain_params_present {
+            header.film_grain_params = None;
+        }
+
+        Ok(header)
+    }
+
+    pub fn decode(ctx: &mut Av1DecoderContext, buf: &mut Buffer) -> Result<Self, Av1DecodeError> {
+        let sequence_header = ctx
+            .sequence_header
+            .clone()
+            .expect("sequence header cannot be found, this is a undefined behavior!");
+
+        let mut id_len = 0;
+        if let Some(value) = &sequence_header.frame_id_numbers_present {
+            id_len =
+                value.additional_frame_id_length as usize + value.delta_frame_id_length as usize;
+        }
+
+        let all_frames = ((1u16 << NUM_REF_FRAMES) - 1) as u8;
+
+        let mut header = Self::default();
+        if sequence_header.reduced_still_picture_header {
+            header.frame_type = FrameType::KeyFrame;
+            header.show_frame = true;
+        } else {
+            // show_existing_frame	f(1)
+            if buf.get_bit() {
+                return Self::show_existing_frame(ctx, &sequence_header, id_len, buf);
             }
 
             // frame_type	f(2)
-            frame_type = FrameType::try_from(buf.get_bits(2) as u8)?;
-            ctx.frame_is_intra =
-                frame_type == FrameType::InterOnlyFrame || frame_type == FrameType::KeyFrame;
+            header.frame_type = FrameType::try_from(buf.get_bits(2) as u8)?;
 
             // show_frame	f(1)
-            show_frame = buf.get_bit();
+            header.show_frame = buf.get_bit();
 
-            if show_frame {
+            if header.show_frame {
                 if let Some(decoder_model_info) = &sequence_header.decoder_model_info {
                     if !sequence_header
                         .timing_info
                         .map(|v| v.equal_picture_interval.is_some())
                         .unwrap_or(false)
                     {
-                        let temporal_point_info = TemporalPointInfo::decode(
-                            buf.as_mut(),
+                        header.temporal_point_info = Some(TemporalPointInfo::decode(
+                            buf,
                             decoder_model_info.frame_presentation_time_length as usize,
-                        );
+                        ));
                     }
                 }
 
-                showable_frame = frame_type != FrameType::KeyFrame;
+                header.showable_frame = header.frame_type != FrameType::KeyFrame;
             } else {
                 // showable_frame	f(1)
-                showable_frame = buf.get_bit();
+                header.showable_frame = buf.get_bit();
             }
 
-            error_resilient_mode = if frame_type == FrameType::SwitchFrame
-                || frame_type == FrameType::KeyFrame && show_frame
+            header.error_resilient_mode = if header.frame_type == FrameType::SwitchFrame
+                || header.frame_type == FrameType::KeyFrame && header.show_frame
             {
                 true
             } else {
@@ -310,20 +1603,22 @@ impl UncompressedHeader {
             };
         }
 
-        // TODO
-        // if ( frame_type == KEY_FRAME && show_frame ) {
-        //     for ( i = 0; i < NUM_REF_FRAMES; i++ ) {
-        //         RefValid[ i ] = 0
-        //         RefOrderHint[ i ] = 0
-        //     }
-        //     for ( i = 0; i < REFS_PER_FRAME; i++ ) {
-        //         OrderHints[ LAST_FRAME + i ] = 0
-        //     }
-        // }
+        let frame_type = header.frame_type;
+        header.frame_is_intra =
+            frame_type == FrameType::InterOnlyFrame || frame_type == FrameType::KeyFrame;
+        ctx.frame_is_intra = header.frame_is_intra;
+
+        if frame_type == FrameType::KeyFrame && header.show_frame {
+            for r in ctx.refs.iter_mut() {
+                *r = None;
+            }
+
+            ctx.next_anchor_frame_idx = 0;
+        }
 
         // disable_cdf_update	f(1)
-        let disable_cdf_update = buf.get_bit();
-        let allow_screen_content_tools =
+        header.disable_cdf_update = buf.get_bit();
+        header.allow_screen_content_tools =
             if sequence_header.seq_force_screen_content_tools == SELECT_SCREEN_CONTENT_TOOLS {
                 // allow_screen_content_tools	f(1)
                 buf.get_bit()
@@ -331,7 +1626,7 @@ impl UncompressedHeader {
                 sequence_header.seq_force_screen_content_tools != 0
             };
 
-        let mut force_integer_mv = if allow_screen_content_tools {
+        header.force_integer_mv = if header.allow_screen_content_tools {
             if sequence_header.seq_force_integer_mv == SELECT_INTEGER_MV {
                 // force_integer_mv	f(1)
                 buf.get_bit()
@@ -342,21 +1637,20 @@ impl UncompressedHeader {
             false
         };
 
-        if ctx.frame_is_intra {
-            force_integer_mv = true;
+        if header.frame_is_intra {
+            header.force_integer_mv = true;
         }
 
-        let current_frame_id = if let Some(f) = &sequence_header.frame_id_numbers_present {
+        if let Some(f) = &sequence_header.frame_id_numbers_present {
             // current_frame_id	f(idLen)
-            buf.get_bits(id_len)
-
-            // TODO
-            // mark_ref_frames( idLen )
+            header.current_frame_id = buf.get_bits(id_len);
+            ctx.current_frame_id = header.current_frame_id;
+            Self::mark_ref_frames(ctx, id_len, f.delta_frame_id_length);
         } else {
-            0
-        };
+            ctx.current_frame_id = 0;
+        }
 
-        let frame_size_override = if frame_type == FrameType::SwitchFrame {
+        header.frame_size_override = if frame_type == FrameType::SwitchFrame {
             true
         } else if sequence_header.reduced_still_picture_header {
             false
@@ -366,173 +1660,553 @@ impl UncompressedHeader {
         };
 
         // order_hint	f(OrderHintBits)
-        let order_hint = buf.get_bits(ctx.order_hint_bits);
-        ctx.order_hint = order_hint;
+        header.order_hint = if ctx.order_hint_bits > 0 {
+            buf.get_bits(ctx.order_hint_bits)
+        } else {
+            0
+        };
 
-        let primary_ref_frame = if ctx.frame_is_intra || error_resilient_mode {
+        ctx.order_hint = header.order_hint;
+
+        header.primary_ref_frame = if header.frame_is_intra || header.error_resilient_mode {
             PRIMARY_REF_NONE
         } else {
             // primary_ref_frame	f(3)
             buf.get_bits(3) as u8
         };
 
-        let mut buffer_removal_times = Vec::with_capacity(sequence_header.operating_points.len());
-        if let Some(decoder_model_info) = sequence_header.decoder_model_info {
+        if let Some(decoder_model_info) = &sequence_header.decoder_model_info {
             // buffer_removal_time_present_flag	f(1)
             let buffer_removal_time_present_flag = buf.get_bit();
             if buffer_removal_time_present_flag {
+                let (temporal_id, spatial_id) = ctx
+                    .obu_header_extension
+                    .map(|ext| (ext.temporal_id, ext.spatial_id))
+                    .unwrap_or((0, 0));
+
                 for operating_point in &sequence_header.operating_points {
                     if operating_point.operating_parameters_info.is_some() {
-                        if let Some(extension) = ctx.obu_header_extension {
-                            let op_pt_dic = operating_point.idc;
-                            let in_temporal_layer = ((op_pt_dic >> extension.temporal_id) & 1) != 0;
-                            let in_spatial_layer =
-                                ((op_pt_dic >> (extension.spatial_id + 8)) & 1) != 0;
-                            if op_pt_dic == 0 || (in_temporal_layer && in_spatial_layer) {
-                                // buffer_removal_time[ opNum ]	f(n)
-                                buffer_removal_times.push(buf.get_bits(
+                        let op_pt_idc = operating_point.idc;
+                        let in_temporal_layer = ((op_pt_idc >> temporal_id) & 1) != 0;
+                        let in_spatial_layer = ((op_pt_idc >> (spatial_id + 8)) & 1) != 0;
+                        if op_pt_idc == 0 || (in_temporal_layer && in_spatial_layer) {
+                            // buffer_removal_time[ opNum ]	f(n)
+                            header.buffer_removal_times.push(
+                                buf.get_bits(
                                     decoder_model_info.buffer_removal_time_length as usize,
-                                ));
-                            }
+                                ),
+                            );
                         }
                     }
                 }
             }
         }
 
-        let mut allow_high_precision_mv = false;
-        let mut use_ref_frame_mvs = false;
-        let mut allow_intrabc = false;
-
-        refresh_frame_flags = if frame_type == FrameType::SwitchFrame
-            || frame_type == FrameType::KeyFrame && show_frame
+        header.refresh_frame_flags = if frame_type == FrameType::SwitchFrame
+            || frame_type == FrameType::KeyFrame && header.show_frame
         {
             all_frames
         } else {
-            buf.get_bits(8)
+            // refresh_frame_flags	f(8)
+            buf.get_bits(8) as u8
         };
 
-        let mut ref_order_hints = None;
-        if (!ctx.frame_is_intra || refresh_frame_flags != all_frames)
-            && error_resilient_mode
+        if (!header.frame_is_intra || header.refresh_frame_flags != all_frames)
+            && header.error_resilient_mode
             && sequence_header.enable_order_hint
         {
-            let mut hints = [0u32; NUM_REF_FRAMES as usize];
             for i in 0..NUM_REF_FRAMES as usize {
                 // ref_order_hint[ i ]	f(OrderHintBits)
-                hints[i] = buf.get_bits(ctx.order_hint_bits);
+                let ref_order_hint = buf.get_bits(ctx.order_hint_bits);
+                if ctx.refs[i].as_ref().map(|r| r.order_hint) != Some(ref_order_hint) {
+                    ctx.refs[i] = None;
+                }
             }
-
-            ref_order_hints = Some(hints);
         }
 
-        let mut allow_intrabc = false;
-        if ctx.frame_is_intra {
-            frame_size(ctx, frame_size_override, buf);
+        if header.frame_is_intra {
+            frame_size(ctx, header.frame_size_override, buf);
             render_size(ctx, buf);
-            if allow_screen_content_tools && ctx.upscaled_width == ctx.frame_width {
+            if header.allow_screen_content_tools && ctx.upscaled_width == ctx.frame_width {
                 // allow_intrabc	f(1)
-                allow_intrabc = buf.get_bit();
+                header.allow_intrabc = buf.get_bit();
             }
         } else {
             let mut frame_refs_short_signaling = false;
-            if !sequence_header.enable_order_hint {
+            if sequence_header.enable_order_hint {
                 // frame_refs_short_signaling	f(1)
                 frame_refs_short_signaling = buf.get_bit();
                 if frame_refs_short_signaling {
                     // last_frame_idx	f(3)
-                    let last_frame_idx = buf.get_bits(3);
+                    let last_frame_idx = buf.get_bits(3) as u8;
 
                     // gold_frame_idx	f(3)
-                    let gold_frame_idx = buf.get_bits(3);
+                    let gold_frame_idx = buf.get_bits(3) as u8;
 
-                    // TODO
-                    // set_frame_refs()
+                    header.ref_frame_idx =
+                        Self::set_frame_refs(ctx, last_frame_idx, gold_frame_idx);
                 }
             }
 
-            for _ in 0..REFS_PER_FRAME {
-                if frame_refs_short_signaling {
+            for i in 0..REFS_PER_FRAME as usize {
+                if !frame_refs_short_signaling {
                     // ref_frame_idx[ i ]	f(3)
-                    let ref_frame_idx = buf.get_bits(3);
+                    header.ref_frame_idx[i] = buf.get_bits(3) as u8;
                 }
 
                 if let Some(frame_id_numbers_present) = &sequence_header.frame_id_numbers_present {
                     let n = frame_id_numbers_present.delta_frame_id_length;
-                    // delta_frame_id_minus_1	f(n)
-                    let delta_frame_id = buf.get_bits(n as usize) + 1;
-                    ctx.delta_frame_id = delta_frame_id;
 
-                    // expectedFrameId[ i ] = ((current_frame_id + (1 << idLen)
-                    // - DeltaFrameId ) % (1 << idLen))
+                    // delta_frame_id_minus_1	f(n)
+                    ctx.delta_frame_id = buf.get_bits(n as usize) + 1;
                 }
             }
 
-            if frame_size_override && !error_resilient_mode {
-                frame_size_with_refs(ctx, frame_size_override, buf);
+            // It is a requirement of bitstream conformance that RefValid[
+            // ref_frame_idx[ i ] ] is equal to 1.
+            if header
+                .ref_frame_idx
+                .iter()
+                .any(|idx| ctx.refs[*idx as usize].is_none())
+            {
+                return Err(Av1DecodeError::Invalid(
+                    Av1DecodeInvalidError::MissingReference,
+                ));
+            }
+
+            if header.frame_size_override && !header.error_resilient_mode {
+                frame_size_with_refs(ctx, header.frame_size_override, &header.ref_frame_idx, buf)?;
             } else {
-                frame_size(ctx, frame_size_override, buf);
+                frame_size(ctx, header.frame_size_override, buf);
                 render_size(ctx, buf);
             }
 
-            let allow_high_precision_mv = if force_integer_mv {
+            header.allow_high_precision_mv = if header.force_integer_mv {
                 false
             } else {
                 // allow_high_precision_mv	f(1)
                 buf.get_bit()
             };
 
-            let interpolation_filter = read_interpolation_filter(buf)?;
+            header.interpolation_filter = read_interpolation_filter(buf)?;
 
             // is_motion_mode_switchable	f(1)
-            let is_motion_mode_switchable = buf.get_bit();
-            use_ref_frame_mvs = if error_resilient_mode || !sequence_header.enable_ref_frame_mvs {
-                false
+            header.is_motion_mode_switchable = buf.get_bit();
+            header.use_ref_frame_mvs =
+                if header.error_resilient_mode || !sequence_header.enable_ref_frame_mvs {
+                    false
+                } else {
+                    // use_ref_frame_mvs	f(1)
+                    buf.get_bit()
+                };
+
+            for i in 0..REFS_PER_FRAME as usize {
+                let ref_frame = LAST_FRAME as usize + i;
+                let hint = ctx.refs[header.ref_frame_idx[i] as usize]
+                    .as_ref()
+                    .map(|r| r.order_hint)
+                    .unwrap_or(0);
+
+                header.order_hints[ref_frame] = hint;
+                header.ref_frame_sign_bias[ref_frame] = sequence_header.enable_order_hint
+                    && get_relative_dist(ctx, hint, header.order_hint) > 0;
+            }
+        }
+
+        header.disable_frame_end_update_cdf =
+            if sequence_header.reduced_still_picture_header || header.disable_cdf_update {
+                true
             } else {
-                // use_ref_frame_mvs	f(1)
+                // disable_frame_end_update_cdf	f(1)
                 buf.get_bit()
             };
 
-            // for ( i = 0; i < REFS_PER_FRAME; i++ ) {	 
-            //     refFrame = LAST_FRAME + i	 
-            //     hint = RefOrderHint[ ref_frame_idx[ i ] ]	 
-            //     OrderHints[ refFrame ] = hint	 
-            //     if ( !enable_order_hint ) {	 
-            //         RefFrameSignBias[ refFrame ] = 0	 
-            //     } else {	 
-            //         RefFrameSignBias[ refFrame ] = get_relative_dist( hint, OrderHint) > 0	 
-            //     }	 
-            // }
+        // init_non_coeff_cdfs and setup_past_independence, or load_cdfs and
+        // load_previous, the state of the previous frame that the rest of the
+        // header is coded against.
+        let (prev_segmentation_params, prev_loop_filter_params, prev_gm_params) =
+            if header.primary_ref_frame == PRIMARY_REF_NONE {
+                ctx.cdf.init_non_coeff_cdfs();
+
+                Default::default()
+            } else {
+                let prev_frame = header.ref_frame_idx[header.primary_ref_frame as usize];
+                let prev_ref =
+                    ctx.refs[prev_frame as usize]
+                        .as_ref()
+                        .ok_or(Av1DecodeError::Invalid(
+                            Av1DecodeInvalidError::MissingReference,
+                        ))?;
+
+                let prev = &prev_ref.frame_header.uncompressed_header;
+                let prev_params = (
+                    prev.segmentation_params.clone(),
+                    prev.loop_filter_params.clone(),
+                    prev.global_motion_params,
+                );
+
+                ctx.cdf = prev_ref.cdf.clone();
+                prev_params
+            };
+
+        header.tile_info = TileInfo::decode(ctx, &sequence_header, buf);
+        header.quantization_params = QuantizationParams::decode(ctx, &sequence_header, buf);
+        if header.primary_ref_frame == PRIMARY_REF_NONE {
+            ctx.cdf
+                .init_coeff_cdfs(header.quantization_params.base_q_idx);
         }
 
-        let disable_frame_end_update_cdf = if sequence_header.reduced_still_picture_header || disable_cdf_update {
-            true
+        header.segmentation_params =
+            SegmentationParams::decode(header.primary_ref_frame, &prev_segmentation_params, buf);
+        header.delta_q_params = DeltaQParams::decode(header.quantization_params.base_q_idx, buf);
+        header.delta_lf_params = DeltaLfParams::decode(
+            header.delta_q_params.delta_q_present,
+            header.allow_intrabc,
+            buf,
+        );
+
+        let qp = &header.quantization_params;
+        header.coded_lossless = true;
+        for segment_id in 0..MAX_SEGMENTS {
+            let lossless = quant::is_lossless(qp, &header.segmentation_params, segment_id);
+
+            header.lossless_array[segment_id as usize] = lossless;
+            if !lossless {
+                header.coded_lossless = false;
+            }
+
+            if qp.using_qmatrix {
+                let levels = if lossless {
+                    [15; 3]
+                } else {
+                    [qp.qm_y, qp.qm_u, qp.qm_v]
+                };
+
+                for (plane, level) in levels.into_iter().enumerate() {
+                    header.seg_qm_level[plane][segment_id as usize] = level;
+                }
+            }
+        }
+
+        header.all_lossless = header.coded_lossless && ctx.frame_width == ctx.upscaled_width;
+        header.loop_filter_params = LoopFilterParams::decode(
+            ctx,
+            header.coded_lossless,
+            header.allow_intrabc,
+            &prev_loop_filter_params,
+            buf,
+        );
+
+        header.cdef_params = CdefParams::decode(
+            ctx,
+            &sequence_header,
+            header.coded_lossless,
+            header.allow_intrabc,
+            buf,
+        );
+
+        header.lr_params = LrParams::decode(
+            ctx,
+            &sequence_header,
+            header.all_lossless,
+            header.allow_intrabc,
+            buf,
+        );
+
+        // read_tx_mode
+        header.tx_mode = if header.coded_lossless {
+            TxMode::Only4x4
         } else {
-            // disable_frame_end_update_cdf	f(1)
-            buf.get_bit()
+            // tx_mode_select	f(1)
+            if buf.get_bit() {
+                TxMode::Select
+            } else {
+                TxMode::Largest
+            }
         };
 
-        if primary_ref_frame == PRIMARY_REF_NONE {
+        // frame_reference_mode
+        header.reference_select = if header.frame_is_intra {
+            false
+        } else {
+            // reference_select	f(1)
+            buf.get_bit()
+        };
 
+        // skip_mode_params
+        let skip_mode_frame = if header.frame_is_intra
+            || !header.reference_select
+            || !sequence_header.enable_order_hint
+        {
+            None
         } else {
-            
+            Self::skip_mode_frames(ctx, &header.ref_frame_idx)
+        };
+
+        if let Some(skip_mode_frame) = skip_mode_frame {
+            header.skip_mode_frame = skip_mode_frame;
+
+            // skip_mode_present	f(1)
+            header.skip_mode_present = buf.get_bit();
         }
 
-        Ok(Self {})
+        header.allow_warped_motion = if header.frame_is_intra
+            || header.error_resilient_mode
+            || !sequence_header.enable_warped_motion
+        {
+            false
+        } else {
+            // allow_warped_motion	f(1)
+            buf.get_bit()
+        };
+
+        // reduced_tx_set	f(1)
+        header.reduced_tx_set = buf.get_bit();
+
+        header.global_motion_params = GlobalMotionParams::decode(
+            header.frame_is_intra,
+            header.allow_high_precision_mv,
+            &prev_gm_params,
+            buf,
+        );
+
+        header.film_grain_params = FilmGrainParams::decode(
+            ctx,
+            &sequence_header,
+            frame_type,
+            header.show_frame,
+            header.showable_frame,
+            buf,
+        )?;
+
+        Ok(header)
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct FrameHeader {}
+pub struct FrameHeader {
+    pub uncompressed_header: UncompressedHeader,
+    /// The bytes holding uncompressed_header as it appeared in the bitstream,
+    /// starting at the first bit of the header, the bits past `bits` are
+    /// not part of the header.
+    pub raw: Vec<u8>,
+    /// The length of uncompressed_header in bits.
+    pub bits: usize,
+}
 
 impl FrameHeader {
+    /// Decodes a frame_header_obu, a copy of the frame header that was already
+    /// seen in this temporal unit is verified against it according to
+    /// [`RedundantFrameHeaderPolicy`] and the stored header is returned.
+    ///
+    /// A new frame header becomes the frame header of the current frame and
+    /// CurrFrame is allocated. A frame header that shows an existing frame
+    /// only becomes the frame header of the current frame, the caller invokes
+    /// the decode frame wrapup process once the OBU is known to be valid.
+    ///
+    /// Note: The frame header always starts at a byte boundary.
     pub fn decode(ctx: &mut Av1DecoderContext, buf: &mut Buffer) -> Result<Self, Av1DecodeError> {
         if ctx.seen_frame_header {
-            // frame_header_copy
-        } else {
-            ctx.seen_frame_header = true;
+            if let Some(frame_header) = ctx.frame_header.clone() {
+                // frame_header_copy
+                frame_header.verify_copy(ctx, buf)?;
+                return Ok(frame_header);
+            }
+        }
+
+        ctx.seen_frame_header = true;
+
+        let data = buf.get_remaining();
+        let start = buf.get_position();
+        let uncompressed_header = UncompressedHeader::decode(ctx, buf)?;
+        if uncompressed_header.show_existing_frame {
+            ctx.seen_frame_header = false;
+        }
+
+        let bits = buf.get_position() - start;
+        let frame_header = Self {
+            uncompressed_header,
+            raw: data[..bits.div_ceil(8).min(data.len())].to_vec(),
+            bits,
+        };
+
+        let show_existing_frame = frame_header.uncompressed_header.show_existing_frame;
+        ctx.frame_header = Some(frame_header.clone());
+        if !show_existing_frame {
+            ctx.setup_current_frame();
+        }
+
+        Ok(frame_header)
+    }
+
+    /// It is a requirement of bitstream conformance that a copy of the frame
+    /// header contains the same content as the original frame header, bit
+    /// for bit.
+    ///
+    /// The copy is skipped under every policy, the data following it (the
+    /// tile group of a frame OBU) starts after the bits of the original
+    /// frame header.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#frame-header-copy-semantics
+    fn verify_copy(
+        &self,
+        ctx: &mut Av1DecoderContext,
+        buf: &mut Buffer,
+    ) -> Result<(), Av1DecodeError> {
+        let policy = ctx.options.redundant_frame_header;
+        if policy != RedundantFrameHeaderPolicy::Ignore && !self.is_copy(buf.get_remaining()) {
+            match policy {
+                RedundantFrameHeaderPolicy::Error => {
+                    return Err(Av1DecodeError::Invalid(
+                        Av1DecodeInvalidError::FrameHeaderCopyMismatch,
+                    ));
+                }
+                _ => ctx.events.push(Av1DecoderEvent::FrameHeaderCopyMismatch),
+            }
+        }
+
+        buf.seek_bits(self.bits);
+        Ok(())
+    }
+
+    /// The data starts with the bits of this frame header.
+    pub fn is_copy(&self, data: &[u8]) -> bool {
+        let bytes = self.bits / 8;
+        let rest = self.bits % 8;
+        data.len() >= self.bits.div_ceil(8)
+            && data[..bytes] == self.raw[..bytes]
+            && (rest == 0 || {
+                let mask = 0xFFu8 << (8 - rest);
+                data[bytes] & mask == self.raw[bytes] & mask
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::BitWriter,
+        obu::test_streams::{context, key_frame_header},
+    };
+
+    fn tile_info(width: u16, height: u16, bits: BitWriter) -> TileInfo {
+        let mut ctx = context(width, height);
+        let sequence_header = ctx.sequence_header.clone().unwrap();
+        ctx.frame_width = width;
+        ctx.frame_height = height;
+        compute_image_size(&mut ctx);
+
+        let bytes = bits.into_bytes();
+        let mut buf = Buffer::new(&bytes);
+        let tile_info = TileInfo::decode(&ctx, &sequence_header, &mut buf);
+        assert_eq!(buf.get_position().div_ceil(8), bytes.len());
+        tile_info
+    }
+
+    #[test]
+    fn frame_header_copies_are_skipped_under_every_policy() {
+        let header = |show_frame| {
+            let mut w = BitWriter::default();
+            key_frame_header(&mut w, show_frame, 0xff);
+            // the tile group that follows the copy in a frame OBU.
+            w.put_bits(0xa5, 8);
+            w.into_bytes()
+        };
+
+        for policy in [
+            RedundantFrameHeaderPolicy::Ignore,
+            RedundantFrameHeaderPolicy::Warn,
+            RedundantFrameHeaderPolicy::Error,
+        ] {
+            for mismatch in [false, true] {
+                let mut ctx = context(64, 64);
+                ctx.options.redundant_frame_header = policy;
+                let original = header(true);
+                let frame_header = FrameHeader::decode(&mut ctx, &mut Buffer::new(&original));
+                let bits = frame_header.unwrap().bits;
+
+                let copy = header(!mismatch);
+                let mut buf = Buffer::new(&copy);
+                let result = FrameHeader::decode(&mut ctx, &mut buf);
+                if mismatch && policy == RedundantFrameHeaderPolicy::Error {
+                    assert!(result.is_err());
+                } else {
+                    assert_eq!(result.unwrap().bits, bits);
+                    assert_eq!(buf.get_position(), bits, "{policy:?} {mismatch}");
+                }
+            }
         }
+    }
+
+    #[test]
+    fn uniform_tile_spacing() {
+        // 16x8 superblocks, 4 tile columns and 2 tile rows.
+        let mut w = BitWriter::default();
+        w.put_bit(true);
+        w.put_bits(0b110, 3);
+        w.put_bits(0b10, 2);
+        // context_update_tile_id, tile_size_bytes_minus_1
+        w.put_bits(5, 3);
+        w.put_bits(1, 2);
+
+        let info = tile_info(1024, 512, w);
+        assert_eq!((info.tile_cols, info.tile_rows), (4, 2));
+        assert_eq!((info.tile_cols_log2, info.tile_rows_log2), (2, 1));
+        assert_eq!(info.mi_col_starts, [0, 64, 128, 192, 256]);
+        assert_eq!(info.mi_row_starts, [0, 64, 128]);
+        assert_eq!(info.context_update_tile_id, 5);
+        assert_eq!(info.tile_size_bytes, 2);
+    }
+
+    #[test]
+    fn uniform_tile_spacing_rounds_the_tile_size_up() {
+        // 5 superblocks split 4 ways are 3 tiles of 2, 2 and 1 superblocks,
+        // a single superblock row cannot be split.
+        let mut w = BitWriter::default();
+        w.put_bit(true);
+        w.put_bits(0b110, 3);
+        // context_update_tile_id, tile_size_bytes_minus_1
+        w.put_bits(0, 2);
+        w.put_bits(0, 2);
+
+        let info = tile_info(320, 64, w);
+        assert_eq!((info.tile_cols, info.tile_rows), (3, 1));
+        assert_eq!((info.tile_cols_log2, info.tile_rows_log2), (2, 0));
+        assert_eq!(info.mi_col_starts, [0, 32, 64, 80]);
+        assert_eq!(info.mi_row_starts, [0, 16]);
+    }
+
+    #[test]
+    fn a_single_tile_codes_no_tile_size_bytes() {
+        let mut w = BitWriter::default();
+        w.put_bits(0b100, 3);
+
+        let info = tile_info(256, 256, w);
+        assert_eq!((info.tile_cols, info.tile_rows), (1, 1));
+        assert_eq!((info.context_update_tile_id, info.tile_size_bytes), (0, 4));
+    }
 
-        Ok(Self {})
+    #[test]
+    fn non_uniform_tile_spacing() {
+        let mut w = BitWriter::default();
+        w.put_bit(false);
+        // width_in_sbs_minus_1 of 10 and 6 superblocks.
+        w.put_ns(9, 16);
+        w.put_ns(5, 6);
+        // height_in_sbs_minus_1 of 3 and 5 superblocks.
+        w.put_ns(2, 8);
+        w.put_ns(4, 5);
+        // context_update_tile_id, tile_size_bytes_minus_1
+        w.put_bits(3, 2);
+        w.put_bits(3, 2);
+
+        let info = tile_info(1024, 512, w);
+        assert_eq!((info.tile_cols, info.tile_rows), (2, 2));
+        assert_eq!((info.tile_cols_log2, info.tile_rows_log2), (1, 1));
+        assert_eq!(info.mi_col_starts, [0, 160, 256]);
+        assert_eq!(info.mi_row_starts, [0, 48, 128]);
+        assert_eq!(info.context_update_tile_id, 3);
+        assert_eq!(info.tile_size_bytes, 4);
     }
 }
@@ -5,7 +5,9 @@ pub mod sequence_header;
 pub mod tile_group;
 pub mod tile_list;
 
-use crate::{Av1DecodeError, Av1DecodeUnknownError, Av1DecoderContext, Buffer};
+use crate::{
+    Av1DecodeError, Av1DecodeInvalidError, Av1DecodeUnknownError, Av1DecoderContext, Buffer,
+};
 
 /// see: https://aomediacodec.github.io/av1-spec/#obu-header-semantics
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -92,6 +94,10 @@ pub struct ObuHeader {
 
 impl ObuHeader {
     pub fn decode(buf: &mut Buffer<'_>) -> Result<Self, Av1DecodeError> {
+        if buf.get_remaining().is_empty() {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::ObuSize));
+        }
+
         // obu_forbidden_bit f(1)
         buf.seek_bits(1);
 
@@ -107,8 +113,12 @@ impl ObuHeader {
         // obu_reserved_1bit
         buf.seek_bits(1);
 
+        if obu_extension_flag && buf.get_remaining().is_empty() {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::ObuSize));
+        }
+
         let extension = if obu_extension_flag {
-            Some(ObuHeaderExtension::decode(buf.as_mut())?)
+            Some(ObuHeaderExtension::decode(buf)?)
         } else {
             None
         };
@@ -140,8 +150,20 @@ impl Obu {
         ctx: &mut Av1DecoderContext,
         buf: &mut Buffer,
     ) -> Result<ObuDecodeRet, Av1DecodeError> {
-        let header = ObuHeader::decode(buf.as_mut())?;
+        let header = ObuHeader::decode(buf)?;
         let size = if header.has_size_field {
+            // the last byte of leb128() has the most significant bit equal
+            // to 0, or is the 8th byte.
+            let remaining = buf.get_remaining();
+            let leb128_bytes = remaining
+                .iter()
+                .take(8)
+                .position(|byte| byte & 0x80 == 0)
+                .map_or(8, |i| i + 1);
+            if leb128_bytes > remaining.len() {
+                return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::ObuSize));
+            }
+
             // obu_size leb128()
             buf.get_leb128() as usize
         } else {
@@ -152,19 +174,26 @@ impl Obu {
                 - if header.extension.is_some() { 1 } else { 0 }
         };
 
+        if size > buf.get_remaining().len() {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::ObuSize));
+        }
+
         if header.kind != ObuKind::SequenceHeader
             && header.kind != ObuKind::TemporalDelimiter
             && ctx.operating_point_idc > 0
         {
             if let Some(ext) = header.extension {
-                let in_temporal_layer = (1 >> ext.temporal_id) & 1;
-                let in_spatial_layer = (1 >> (ext.spatial_id + 8)) & 1;
+                let in_temporal_layer = (ctx.operating_point_idc >> ext.temporal_id) & 1;
+                let in_spatial_layer = (ctx.operating_point_idc >> (ext.spatial_id + 8)) & 1;
                 if in_temporal_layer == 0 || in_spatial_layer == 0 {
+                    buf.get_bytes(size);
                     return Ok(ObuDecodeRet::Drop);
                 }
             }
         }
 
+        ctx.obu_header_extension = header.extension;
+
         if header.kind == ObuKind::TemporalDelimiter {
             ctx.seen_frame_header = false;
         }
@@ -172,3 +201,111 @@ impl Obu {
         Ok(ObuDecodeRet::Obu(Self { header, size }))
     }
 }
+
+/// Hand built OBUs for the tests of the decoding of frames, they describe
+/// 8-bit 4:2:0 frames with 7 bit order hints and 64x64 superblocks.
+#[cfg(test)]
+pub(crate) mod test_streams {
+    use crate::{
+        buffer::BitWriter, obu::sequence_header::SequenceHeader, Av1DecoderContext,
+        Av1DecoderOptions, Buffer,
+    };
+
+    pub const OBU_SEQUENCE_HEADER: u8 = 1;
+    pub const OBU_TEMPORAL_DELIMITER: u8 = 2;
+    pub const OBU_FRAME_HEADER: u8 = 3;
+    pub const OBU_REDUNDANT_FRAME_HEADER: u8 = 7;
+
+    /// An OBU with obu_has_size_field equal to 1 and no extension.
+    pub fn obu(obu_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.put_bits((obu_type as u32) << 3 | 0b010, 8);
+        w.put_leb128(payload.len() as u32);
+        w.put_bytes(payload);
+        w.into_bytes()
+    }
+
+    /// A main profile sequence header for frames of up to width x height.
+    pub fn sequence_header(width: u16, height: u16) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        // seq_profile, still_picture, reduced_still_picture_header
+        w.put_bits(0, 5);
+        // timing_info_present_flag, initial_display_delay_present_flag
+        w.put_bits(0, 2);
+        // operating_points_cnt_minus_1, operating_point_idc[ 0 ],
+        // seq_level_idx[ 0 ]
+        w.put_bits(0, 5 + 12 + 5);
+        // frame_width_bits_minus_1, frame_height_bits_minus_1
+        w.put_bits(15, 4);
+        w.put_bits(15, 4);
+        w.put_bits(width as u32 - 1, 16);
+        w.put_bits(height as u32 - 1, 16);
+        // frame_id_numbers_present_flag, use_128x128_superblock,
+        // enable_filter_intra, enable_intra_edge_filter,
+        // enable_interintra_compound, enable_masked_compound,
+        // enable_warped_motion, enable_dual_filter
+        w.put_bits(0, 8);
+        // enable_order_hint, enable_jnt_comp, enable_ref_frame_mvs
+        w.put_bits(0b100, 3);
+        // seq_choose_screen_content_tools, seq_choose_integer_mv
+        w.put_bits(0b11, 2);
+        // order_hint_bits_minus_1
+        w.put_bits(6, 3);
+        // enable_superres, enable_cdef, enable_restoration
+        w.put_bits(0, 3);
+        // high_bitdepth, color_description_present_flag, color_range,
+        // chroma_sample_position, separate_uv_delta_q
+        w.put_bits(0, 6);
+        // film_grain_params_present
+        w.put_bit(false);
+        w.into_bytes()
+    }
+
+    /// A decoder context that has seen sequence_header( width, height ).
+    pub fn context(width: u16, height: u16) -> Av1DecoderContext {
+        let mut ctx = Av1DecoderContext::new(Av1DecoderOptions::default());
+        let payload = sequence_header(width, height);
+        let sequence_header = SequenceHeader::decode(&mut ctx, &mut Buffer::new(&payload));
+        ctx.sequence_header = Some(sequence_header.unwrap());
+        ctx
+    }
+
+    /// The uncompressed header of a lossless key frame of the size of the
+    /// sequence header, with a single tile.
+    pub fn key_frame_header(w: &mut BitWriter, show_frame: bool, refresh_frame_flags: u8) {
+        // show_existing_frame, frame_type
+        w.put_bits(0, 3);
+        w.put_bit(show_frame);
+        if !show_frame {
+            // showable_frame, error_resilient_mode
+            w.put_bits(0b10, 2);
+        }
+
+        // disable_cdf_update, allow_screen_content_tools,
+        // frame_size_override_flag
+        w.put_bits(0, 3);
+        // order_hint
+        w.put_bits(0, 7);
+        if !show_frame {
+            w.put_bits(refresh_frame_flags as u32, 8);
+        }
+
+        // render_and_frame_size_different, disable_frame_end_update_cdf
+        w.put_bits(0, 2);
+        // uniform_tile_spacing_flag
+        w.put_bit(true);
+        // base_q_idx, the delta_coded of DeltaQYDc, DeltaQUDc and DeltaQUAc,
+        // using_qmatrix
+        w.put_bits(0, 8 + 3 + 1);
+        // segmentation_enabled, reduced_tx_set
+        w.put_bits(0, 2);
+    }
+
+    /// A frame header OBU payload with show_existing_frame equal to 1.
+    pub fn show_existing_frame_header(frame_to_show_map_idx: u8) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.put_bit(true);
+        w.put_bits(frame_to_show_map_idx as u32, 3);
+        w.into_bytes()
+    }
+}
@@ -238,10 +238,9 @@ impl ColorConfig {
                 )
             };
 
-        let mut separate_uv_delta_q = false;
-        let mut color_range = false;
-        let mut subsampling_x = false;
-        let mut subsampling_y = false;
+        let color_range;
+        let subsampling_x;
+        let subsampling_y;
         let mut chroma_sample_position = None;
 
         if mono_chrome {
@@ -250,9 +249,6 @@ impl ColorConfig {
             subsampling_x = true;
             subsampling_y = true;
             chroma_sample_position = Some(ChromaSamplePosition::Unknown);
-
-            // TODO:
-            // return
         } else if color_primaries == ColorPrimaries::Bt709
             && transfer_characteristics == TransferCharacteristics::Srgb
             && matrix_coefficients == MatrixCoefficients::Identity
@@ -269,20 +265,18 @@ impl ColorConfig {
             } else if profile == SequenceProfile::High {
                 subsampling_x = false;
                 subsampling_y = false;
-            } else {
-                if ctx.bit_depth == 12 {
-                    // subsampling_x	f(1)
-                    subsampling_x = buf.get_bit();
-                    subsampling_y = if subsampling_x {
-                        // subsampling_y	f(1)
-                        buf.get_bit()
-                    } else {
-                        false
-                    };
+            } else if ctx.bit_depth == 12 {
+                // subsampling_x	f(1)
+                subsampling_x = buf.get_bit();
+                subsampling_y = if subsampling_x {
+                    // subsampling_y	f(1)
+                    buf.get_bit()
                 } else {
-                    subsampling_x = true;
-                    subsampling_y = false;
-                }
+                    false
+                };
+            } else {
+                subsampling_x = true;
+                subsampling_y = false;
             }
 
             if subsampling_x && subsampling_y {
@@ -292,8 +286,12 @@ impl ColorConfig {
             }
         };
 
-        // separate_uv_delta_q	f(1)
-        separate_uv_delta_q = buf.get_bit();
+        let separate_uv_delta_q = if mono_chrome {
+            false
+        } else {
+            // separate_uv_delta_q	f(1)
+            buf.get_bit()
+        };
 
         Ok(Self {
             high_bitdepth,
@@ -435,7 +433,7 @@ impl FrameIdNumbersPresent {
             // delta_frame_id_length_minus_2	f(4)
             delta_frame_id_length: buf.get_bits(4) as u8 + 2,
             // additional_frame_id_length_minus_1	f(3)
-            additional_frame_id_length: buf.get_bits(4) as u8 + 1,
+            additional_frame_id_length: buf.get_bits(3) as u8 + 1,
         }
     }
 }
@@ -503,12 +501,12 @@ impl SequenceHeader {
             // timing_info_present_flag f(1)
             let timing_info_present_flag = buf.get_bit();
             if timing_info_present_flag {
-                timing_info = Some(TimingInfo::decode(buf.as_mut()));
+                timing_info = Some(TimingInfo::decode(buf));
 
                 // decoder_model_info_present_flag f(1)
                 decoder_model_info_present_flag = buf.get_bit();
                 if decoder_model_info_present_flag {
-                    decoder_model_info = Some(DecoderModelInfo::decode(buf.as_mut()));
+                    decoder_model_info = Some(DecoderModelInfo::decode(buf));
                 }
             }
 
@@ -536,7 +534,7 @@ impl SequenceHeader {
                     let ecoder_model_present = buf.get_bit();
                     if ecoder_model_present {
                         operating_parameters_info = Some(OperatingParametersInfo::decode(
-                            buf.as_mut(),
+                            buf,
                             &decoder_model_info.unwrap(),
                         ));
                     }
@@ -588,7 +586,7 @@ impl SequenceHeader {
         let frame_id_numbers_present = if !reduced_still_picture_header {
             // frame_id_numbers_present_flag	f(1)
             if buf.get_bit() {
-                Some(FrameIdNumbersPresent::decode(buf.as_mut()))
+                Some(FrameIdNumbersPresent::decode(buf))
             } else {
                 None
             }
@@ -1,2 +1,228 @@
+use crate::{Av1DecodeError, Av1DecodeInvalidError, Av1DecoderContext, Buffer};
+
+/// A tile and the range of mode info units it covers.
 #[derive(Debug, Clone)]
-pub struct TileGroup {}
+pub struct Tile<'a> {
+    pub tile_num: u32,
+    pub tile_row: u32,
+    pub tile_col: u32,
+    pub mi_row_start: u32,
+    pub mi_row_end: u32,
+    pub mi_col_start: u32,
+    pub mi_col_end: u32,
+    /// The coded tile data, tileSize bytes that are passed to init_symbol.
+    pub data: &'a [u8],
+}
+
+/// see: https://aomediacodec.github.io/av1-spec/#tile-group-obu-syntax
+#[derive(Debug, Clone)]
+pub struct TileGroup<'a> {
+    pub tg_start: u32,
+    pub tg_end: u32,
+    pub tiles: Vec<Tile<'a>>,
+    /// tg_end is equal to NumTiles - 1, the frame is complete once this tile
+    /// group is decoded.
+    pub last_tile_group: bool,
+}
+
+impl<'a> TileGroup<'a> {
+    pub fn decode(
+        ctx: &mut Av1DecoderContext,
+        buf: &mut Buffer<'a>,
+    ) -> Result<Self, Av1DecodeError> {
+        let tile_info = &ctx
+            .frame_header
+            .as_ref()
+            .ok_or(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::MissingFrameHeader,
+            ))?
+            .uncompressed_header
+            .tile_info;
+
+        let num_tiles = tile_info.tile_cols * tile_info.tile_rows;
+
+        // tile_start_and_end_present_flag	f(1)
+        let tile_start_and_end_present = num_tiles > 1 && buf.get_bit();
+
+        let (tg_start, tg_end) = if num_tiles == 1 || !tile_start_and_end_present {
+            (0, num_tiles - 1)
+        } else {
+            let tile_bits = (tile_info.tile_cols_log2 + tile_info.tile_rows_log2) as usize;
+
+            // tg_start	f(tileBits)
+            let tg_start = buf.get_bits(tile_bits);
+
+            // tg_end	f(tileBits)
+            let tg_end = buf.get_bits(tile_bits);
+
+            (tg_start, tg_end)
+        };
+
+        // It is a requirement of bitstream conformance that the value of tg_end
+        // is greater than or equal to tg_start and less than NumTiles.
+        if tg_end < tg_start || tg_end >= num_tiles {
+            return Err(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::TileGroupRange,
+            ));
+        }
+
+        buf.byte_alignment();
+
+        let mut tiles = Vec::with_capacity((tg_end - tg_start + 1) as usize);
+        for tile_num in tg_start..=tg_end {
+            let tile_row = tile_num / tile_info.tile_cols;
+            let tile_col = tile_num % tile_info.tile_cols;
+
+            // The last tile takes up the remainder of the OBU.
+            let tile_size = if tile_num == tg_end {
+                buf.get_remaining().len()
+            } else {
+                if buf.get_remaining().len() < tile_info.tile_size_bytes {
+                    return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::TileSize));
+                }
+
+                // tile_size_minus_1	le(TileSizeBytes)
+                buf.get_le(tile_info.tile_size_bytes) as usize + 1
+            };
+
+            if buf.get_remaining().len() < tile_size {
+                return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::TileSize));
+            }
+
+            tiles.push(Tile {
+                tile_num,
+                tile_row,
+                tile_col,
+                mi_row_start: tile_info.mi_row_starts[tile_row as usize],
+                mi_row_end: tile_info.mi_row_starts[tile_row as usize + 1],
+                mi_col_start: tile_info.mi_col_starts[tile_col as usize],
+                mi_col_end: tile_info.mi_col_starts[tile_col as usize + 1],
+                data: buf.get_bytes(tile_size),
+            });
+        }
+
+        Ok(Self {
+            tg_start,
+            tg_end,
+            tiles,
+            last_tile_group: tg_end == num_tiles - 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::BitWriter,
+        obu::frame_header::{FrameHeader, TileInfo, UncompressedHeader},
+        Av1DecoderOptions,
+    };
+
+    /// A context with a frame of 3x2 tiles of 64x64 samples.
+    fn context(tile_size_bytes: usize) -> Av1DecoderContext {
+        let mut ctx = Av1DecoderContext::new(Av1DecoderOptions::default());
+        ctx.frame_header = Some(FrameHeader {
+            uncompressed_header: UncompressedHeader {
+                tile_info: TileInfo {
+                    tile_cols: 3,
+                    tile_rows: 2,
+                    tile_cols_log2: 2,
+                    tile_rows_log2: 1,
+                    mi_col_starts: vec![0, 16, 32, 48],
+                    mi_row_starts: vec![0, 16, 32],
+                    context_update_tile_id: 0,
+                    tile_size_bytes,
+                },
+                ..Default::default()
+            },
+            raw: Vec::new(),
+            bits: 0,
+        });
+
+        ctx
+    }
+
+    /// A tile group of the tiles tg_start to tg_end, tile n has n + 1 bytes
+    /// of data.
+    fn tile_group(tg_start: u32, tg_end: u32, tile_size_bytes: usize) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        // tile_start_and_end_present_flag
+        w.put_bit(true);
+        w.put_bits(tg_start, 3);
+        w.put_bits(tg_end, 3);
+        w.byte_alignment();
+        for tile_num in tg_start..=tg_end {
+            if tile_num != tg_end {
+                w.put_le(tile_num, tile_size_bytes);
+            }
+
+            w.put_bytes(&vec![tile_num as u8; tile_num as usize + 1]);
+        }
+
+        w.into_bytes()
+    }
+
+    #[test]
+    fn tile_sizes_of_every_tile_size_bytes() {
+        for tile_size_bytes in 1..=4 {
+            let mut ctx = context(tile_size_bytes);
+            let payload = tile_group(0, 5, tile_size_bytes);
+            let tile_group = TileGroup::decode(&mut ctx, &mut Buffer::new(&payload)).unwrap();
+            assert_eq!(tile_group.tiles.len(), 6);
+            for (tile_num, tile) in tile_group.tiles.iter().enumerate() {
+                assert_eq!(tile.tile_num, tile_num as u32);
+                assert_eq!(tile.data, vec![tile_num as u8; tile_num + 1]);
+                assert_eq!(
+                    (tile.tile_row, tile.tile_col),
+                    (tile.tile_num / 3, tile.tile_num % 3)
+                );
+                assert_eq!(tile.mi_row_start, tile.tile_row * 16);
+                assert_eq!(tile.mi_col_end, tile.tile_col * 16 + 16);
+            }
+        }
+    }
+
+    #[test]
+    fn tile_group_ranges_are_checked() {
+        let mut ctx = context(4);
+        for (tg_start, tg_end) in [(3, 2), (4, 6), (0, 7)] {
+            let mut w = BitWriter::default();
+            w.put_bit(true);
+            w.put_bits(tg_start, 3);
+            w.put_bits(tg_end, 3);
+            let payload = w.into_bytes();
+            assert_eq!(
+                TileGroup::decode(&mut ctx, &mut Buffer::new(&payload)).unwrap_err(),
+                Av1DecodeError::Invalid(Av1DecodeInvalidError::TileGroupRange)
+            );
+        }
+    }
+
+    #[test]
+    fn truncated_tile_data_is_rejected() {
+        let mut ctx = context(2);
+        let payload = tile_group(2, 4, 2);
+        // cut inside the data of tile 3, then inside the size of tile 3.
+        for len in [payload.len() - 6, 7] {
+            assert_eq!(
+                TileGroup::decode(&mut ctx, &mut Buffer::new(&payload[..len])).unwrap_err(),
+                Av1DecodeError::Invalid(Av1DecodeInvalidError::TileSize)
+            );
+        }
+    }
+
+    #[test]
+    fn the_last_tile_group_ends_with_the_last_tile() {
+        let mut ctx = context(4);
+        let first = tile_group(0, 2, 4);
+        let first = TileGroup::decode(&mut ctx, &mut Buffer::new(&first)).unwrap();
+        assert_eq!((first.tg_start, first.tg_end), (0, 2));
+        assert!(!first.last_tile_group);
+
+        let last = tile_group(3, 5, 4);
+        let last = TileGroup::decode(&mut ctx, &mut Buffer::new(&last)).unwrap();
+        assert_eq!(last.tiles.len(), 3);
+        assert!(last.last_tile_group);
+    }
+}
@@ -1,16 +1,32 @@
-use crate::Buffer;
+use crate::{
+    constants::MAX_ANCHOR_FRAME_IDX, Av1DecodeError, Av1DecodeInvalidError, Av1DecoderContext,
+    Buffer,
+};
 
+/// The largest value of tile_count_minus_1 + 1.
+const MAX_TILE_COUNT: usize = 512;
+
+/// The bytes of a tile list entry before coded_tile_data.
+const TILE_LIST_ENTRY_HEADER_BYTES: usize = 5;
+
+/// see: https://aomediacodec.github.io/av1-spec/#tile-list-entry-syntax
 #[derive(Debug, Clone, Copy)]
-pub struct TileListEntry {
+pub struct TileListEntry<'a> {
     pub anchor_frame_idx: u8,
     pub anchor_tile_row: u8,
     pub anchor_tile_col: u8,
-    pub tile_data_size: u16,
-    pub coded_tile_data: u32,
+    pub tile_data_size: usize,
+    pub coded_tile_data: &'a [u8],
 }
 
-impl TileListEntry {
-    pub fn decode(buf: &mut Buffer) -> Self {
+impl<'a> TileListEntry<'a> {
+    /// The anchor tile is checked against the tiles of the anchor frames,
+    /// which all use the tile info of the current frame header.
+    pub fn decode(ctx: &Av1DecoderContext, buf: &mut Buffer<'a>) -> Result<Self, Av1DecodeError> {
+        if buf.get_remaining().len() < TILE_LIST_ENTRY_HEADER_BYTES {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::TileSize));
+        }
+
         // anchor_frame_idx	f(8)
         let anchor_frame_idx = buf.get_bits(8) as u8;
 
@@ -21,46 +37,303 @@ impl TileListEntry {
         let anchor_tile_col = buf.get_bits(8) as u8;
 
         // tile_data_size_minus_1	f(16)
-        let tile_data_size = buf.get_bits(16) as u16 + 1;
+        let tile_data_size = buf.get_bits(16) as usize + 1;
+
+        // It is a requirement of bitstream conformance that anchor_frame_idx is
+        // less than or equal to 127, the decoded anchor frame must also still
+        // be held by a reference slot.
+        if anchor_frame_idx > MAX_ANCHOR_FRAME_IDX
+            || !ctx
+                .refs
+                .iter()
+                .flatten()
+                .any(|r| r.anchor_frame_idx == Some(anchor_frame_idx))
+        {
+            return Err(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::AnchorFrameIdx,
+            ));
+        }
+
+        // It is a requirement of bitstream conformance that anchor_tile_row is
+        // less than TileRows and anchor_tile_col is less than TileCols.
+        let tile_info = &ctx
+            .frame_header
+            .as_ref()
+            .ok_or(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::MissingFrameHeader,
+            ))?
+            .uncompressed_header
+            .tile_info;
+
+        if anchor_tile_row as u32 >= tile_info.tile_rows
+            || anchor_tile_col as u32 >= tile_info.tile_cols
+        {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::AnchorTile));
+        }
+
+        if buf.get_remaining().len() < tile_data_size {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::TileSize));
+        }
 
         // coded_tile_data	f(N)
-        let coded_tile_data = buf.get_bits(8 * tile_data_size as usize);
-        Self {
+        let coded_tile_data = buf.get_bytes(tile_data_size);
+
+        Ok(Self {
             anchor_frame_idx,
-            anchor_tile_col,
             anchor_tile_row,
+            anchor_tile_col,
             tile_data_size,
             coded_tile_data,
-        }
+        })
     }
 }
 
+/// see: https://aomediacodec.github.io/av1-spec/#tile-list-obu-syntax
 #[derive(Debug, Clone)]
-pub struct TileList {
-    pub output_frame_width_in_tiles: u8,
-    pub output_frame_height_in_tiles: u8,
-    pub tile_list_entrys: Vec<TileListEntry>,
+pub struct TileList<'a> {
+    pub output_frame_width_in_tiles: u16,
+    pub output_frame_height_in_tiles: u16,
+    pub tile_list_entrys: Vec<TileListEntry<'a>>,
 }
 
-impl TileList {
-    pub fn decode(buf: &mut Buffer) -> Self {
+impl<'a> TileList<'a> {
+    pub fn decode(ctx: &Av1DecoderContext, buf: &mut Buffer<'a>) -> Result<Self, Av1DecodeError> {
         // output_frame_width_in_tiles_minus_1	f(8)
-        let output_frame_width_in_tiles = buf.get_bits(8) as u8;
+        let output_frame_width_in_tiles = buf.get_bits(8) as u16 + 1;
 
         // output_frame_height_in_tiles_minus_1	f(8)
-        let output_frame_height_in_tiles = buf.get_bits(8) as u8;
+        let output_frame_height_in_tiles = buf.get_bits(8) as u16 + 1;
 
         // tile_count_minus_1	f(16)
-        let tile_count = buf.get_bits(16) as usize;
+        let tile_count = buf.get_bits(16) as usize + 1;
+
+        // It is a requirement of bitstream conformance that tile_count_minus_1
+        // is less than or equal to 511.
+        if tile_count > MAX_TILE_COUNT {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::TileCount));
+        }
+
         let mut tile_list_entrys = Vec::with_capacity(tile_count);
         for _ in 0..tile_count {
-            tile_list_entrys.push(TileListEntry::decode(buf));
+            tile_list_entrys.push(TileListEntry::decode(ctx, buf)?);
         }
 
-        Self {
+        Ok(Self {
             output_frame_height_in_tiles,
             output_frame_width_in_tiles,
             tile_list_entrys,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        buffer::BitWriter,
+        obu::{
+            frame_header::{FrameHeader, TileInfo},
+            test_streams::{self, key_frame_header},
+        },
+    };
+
+    /// Decodes a key frame into the reference slots of refresh_frame_flags,
+    /// or into every slot when it is shown. The tile data is left out and
+    /// the tile info of the current frame header is kept.
+    fn decode_frame(ctx: &mut Av1DecoderContext, show_frame: bool, refresh_frame_flags: u8) {
+        let tile_info = ctx
+            .frame_header
+            .as_ref()
+            .map(|it| it.uncompressed_header.tile_info.clone());
+
+        let mut w = BitWriter::default();
+        key_frame_header(&mut w, show_frame, refresh_frame_flags);
+        let payload = w.into_bytes();
+        FrameHeader::decode(ctx, &mut Buffer::new(&payload)).unwrap();
+        ctx.decode_frame_wrapup().unwrap();
+        ctx.seen_frame_header = false;
+
+        if let (Some(frame_header), Some(tile_info)) = (ctx.frame_header.as_mut(), tile_info) {
+            frame_header.uncompressed_header.tile_info = tile_info;
         }
     }
+
+    /// A context with the anchor frames 0 and 1 decoded into the reference
+    /// slots 0 and 1, the anchor tiles are checked against the 4x2 tiles of
+    /// the current frame header.
+    fn context() -> Av1DecoderContext {
+        let mut ctx = test_streams::context(64, 64);
+        decode_frame(&mut ctx, false, 0b01);
+        decode_frame(&mut ctx, false, 0b10);
+        ctx.frame_header
+            .as_mut()
+            .unwrap()
+            .uncompressed_header
+            .tile_info = TileInfo {
+            tile_cols: 4,
+            tile_rows: 2,
+            ..Default::default()
+        };
+
+        ctx
+    }
+
+    /// A tile list of entries of anchor_frame_idx, anchor_tile_row,
+    /// anchor_tile_col and the coded tile data.
+    fn tile_list(entries: &[(u8, u8, u8, &[u8])]) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.put_bits(1, 8);
+        w.put_bits(0, 8);
+        w.put_bits(entries.len() as u32 - 1, 16);
+        for &(anchor_frame_idx, row, col, data) in entries {
+            w.put_bits(anchor_frame_idx as u32, 8);
+            w.put_bits(row as u32, 8);
+            w.put_bits(col as u32, 8);
+            w.put_bits(data.len() as u32 - 1, 16);
+            w.put_bytes(data);
+        }
+
+        w.into_bytes()
+    }
+
+    fn decode_err(ctx: &Av1DecoderContext, payload: &[u8]) -> Av1DecodeError {
+        TileList::decode(ctx, &mut Buffer::new(payload)).unwrap_err()
+    }
+
+    #[test]
+    fn entries_take_tile_data_size_minus_1_plus_1_bytes() {
+        let ctx = context();
+        let large = vec![0x5a; 0x1234];
+        let payload = tile_list(&[(0, 1, 3, &[1, 2, 3]), (1, 0, 0, &large), (1, 1, 2, &[4])]);
+        let tile_list = TileList::decode(&ctx, &mut Buffer::new(&payload)).unwrap();
+        assert_eq!(
+            (
+                tile_list.output_frame_width_in_tiles,
+                tile_list.output_frame_height_in_tiles
+            ),
+            (2, 1)
+        );
+
+        let entries = &tile_list.tile_list_entrys;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            (
+                entries[0].anchor_frame_idx,
+                entries[0].anchor_tile_row,
+                entries[0].anchor_tile_col
+            ),
+            (0, 1, 3)
+        );
+        assert_eq!(entries[0].coded_tile_data, [1, 2, 3]);
+        assert_eq!(entries[1].tile_data_size, 0x1234);
+        assert_eq!(entries[1].coded_tile_data, large);
+        assert_eq!(entries[2].coded_tile_data, [4]);
+    }
+
+    #[test]
+    fn anchor_tiles_must_be_inside_the_anchor_frame() {
+        let ctx = context();
+        for (row, col) in [(2, 0), (0, 4), (255, 255)] {
+            assert_eq!(
+                decode_err(&ctx, &tile_list(&[(0, row, col, &[0])])),
+                Av1DecodeError::Invalid(Av1DecodeInvalidError::AnchorTile)
+            );
+        }
+    }
+
+    #[test]
+    fn anchor_frames_must_be_held_by_a_reference_slot() {
+        let mut ctx = context();
+        let anchor_frame = |ctx: &Av1DecoderContext, anchor_frame_idx| {
+            let payload = tile_list(&[(anchor_frame_idx, 0, 0, &[0])]);
+            match TileList::decode(ctx, &mut Buffer::new(&payload)) {
+                Ok(_) => true,
+                Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::AnchorFrameIdx)) => false,
+                Err(err) => panic!("unexpected error {err:?}"),
+            }
+        };
+
+        assert_eq!(
+            [0, 1, 2, 128].map(|i| anchor_frame(&ctx, i)),
+            [true, true, false, false]
+        );
+
+        // the third anchor frame replaces the first one in slot 0.
+        decode_frame(&mut ctx, false, 0b01);
+        assert_eq!(
+            [0, 1, 2].map(|i| anchor_frame(&ctx, i)),
+            [false, true, true]
+        );
+
+        // a shown key frame starts a new anchor set in every slot.
+        decode_frame(&mut ctx, true, 0);
+        assert_eq!(
+            [0, 1, 2].map(|i| anchor_frame(&ctx, i)),
+            [true, false, false]
+        );
+        assert!(ctx
+            .refs
+            .iter()
+            .flatten()
+            .all(|r| r.anchor_frame_idx == Some(0)));
+    }
+
+    #[test]
+    fn frames_past_the_anchor_set_are_not_anchor_frames() {
+        let mut ctx = context();
+        for _ in 2..=MAX_ANCHOR_FRAME_IDX {
+            decode_frame(&mut ctx, false, 0b100);
+        }
+
+        decode_frame(&mut ctx, false, 0b1000);
+        let anchor_frames = ctx
+            .refs
+            .each_ref()
+            .map(|r| r.as_ref().map(|r| r.anchor_frame_idx));
+        assert_eq!(
+            anchor_frames[..5],
+            [
+                Some(Some(0)),
+                Some(Some(1)),
+                Some(Some(127)),
+                Some(None),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn anchor_tiles_need_a_frame_header() {
+        let mut ctx = context();
+        ctx.frame_header = None;
+        assert_eq!(
+            decode_err(&ctx, &tile_list(&[(0, 0, 0, &[0])])),
+            Av1DecodeError::Invalid(Av1DecodeInvalidError::MissingFrameHeader)
+        );
+    }
+
+    #[test]
+    fn truncated_tile_data_is_rejected() {
+        let ctx = context();
+        let payload = tile_list(&[(0, 0, 0, &[1, 2, 3]), (1, 1, 1, &[4, 5])]);
+        // cut inside the data and inside the header of the second entry.
+        for len in [payload.len() - 1, payload.len() - 4] {
+            assert_eq!(
+                decode_err(&ctx, &payload[..len]),
+                Av1DecodeError::Invalid(Av1DecodeInvalidError::TileSize)
+            );
+        }
+    }
+
+    #[test]
+    fn tile_counts_above_512_are_rejected() {
+        let ctx = context();
+        let mut w = BitWriter::default();
+        w.put_bits(0, 16);
+        w.put_bits(512, 16);
+        assert_eq!(
+            decode_err(&ctx, &w.into_bytes()),
+            Av1DecodeError::Invalid(Av1DecodeInvalidError::TileCount)
+        );
+    }
 }
@@ -0,0 +1,70 @@
+use crate::obu::sequence_header::ColorConfig;
+
+/// A single plane of samples.
+///
+/// Samples are always stored as u16 regardless of the bit depth, the
+/// allocated area is aligned up to the largest superblock size so that blocks
+/// crossing the right or bottom frame edge can be written without checks.
+#[derive(Debug, Clone)]
+pub struct Plane {
+    pub width: usize,
+    pub height: usize,
+    pub stride: usize,
+    pub rows: usize,
+    pub data: Vec<u16>,
+}
+
+impl Plane {
+    pub fn new(width: usize, height: usize) -> Self {
+        let stride = (width + 127) & !127;
+        let rows = (height + 127) & !127;
+        Self {
+            width,
+            height,
+            stride,
+            rows,
+            data: vec![0; stride * rows],
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> u16 {
+        self.data[y * self.stride + x]
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, value: u16) {
+        self.data[y * self.stride + x] = value;
+    }
+}
+
+/// Decoded frame samples, this is CurrFrame or one of the FrameStore slots in
+/// the specification.
+#[derive(Debug, Clone)]
+pub struct Picture {
+    pub bit_depth: u8,
+    pub subsampling_x: bool,
+    pub subsampling_y: bool,
+    pub planes: Vec<Plane>,
+}
+
+impl Picture {
+    pub fn new(width: usize, height: usize, bit_depth: u8, color_config: &ColorConfig) -> Self {
+        let mut planes = Vec::with_capacity(3);
+        planes.push(Plane::new(width, height));
+        if !color_config.mono_chrome {
+            let ss_x = color_config.subsampling_x as usize;
+            let ss_y = color_config.subsampling_y as usize;
+            for _ in 0..2 {
+                planes.push(Plane::new((width + ss_x) >> ss_x, (height + ss_y) >> ss_y));
+            }
+        }
+
+        Self {
+            bit_depth,
+            subsampling_x: color_config.subsampling_x,
+            subsampling_y: color_config.subsampling_y,
+            planes,
+        }
+    }
+}
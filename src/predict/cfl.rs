@@ -0,0 +1,227 @@
+//! The predict chroma from luma process, which adds the scaled AC component
+//! of the reconstructed luma samples to the DC prediction of a chroma block.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#predict-chroma-from-luma-process
+
+use crate::{
+    picture::Plane,
+    tables::{TX_HEIGHT, TX_HEIGHT_LOG2, TX_WIDTH, TX_WIDTH_LOG2},
+    util::{clip1, round2, round2signed},
+};
+
+/// A chroma transform block that is predicted from luma.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CflBlock {
+    /// startX, the position of the block in samples of the chroma plane.
+    pub start_x: usize,
+    /// startY
+    pub start_y: usize,
+    /// txSz
+    pub tx_size: u8,
+    /// subsampling_x
+    pub sub_x: u32,
+    /// subsampling_y
+    pub sub_y: u32,
+    /// MaxLumaW, the luma samples to the right of it are not decoded and
+    /// are replaced by the last decoded column.
+    pub max_luma_w: usize,
+    /// MaxLumaH
+    pub max_luma_h: usize,
+    /// CflAlphaU or CflAlphaV
+    pub alpha: i32,
+    pub bit_depth: u8,
+}
+
+/// The predict chroma from luma process, the luma samples covered by the
+/// block are subsampled to the chroma resolution, their average is removed
+/// and they are added to the chroma samples scaled by alpha.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#predict-chroma-from-luma-process
+pub fn predict_chroma_from_luma(luma: &Plane, chroma: &mut Plane, block: &CflBlock) {
+    let w = TX_WIDTH[block.tx_size as usize] as usize;
+    let h = TX_HEIGHT[block.tx_size as usize] as usize;
+    let (sub_x, sub_y) = (block.sub_x, block.sub_y);
+
+    let mut l = vec![0; w * h];
+    let mut luma_avg = 0;
+    for i in 0..h {
+        let luma_y = ((block.start_y + i) << sub_y).min(block.max_luma_h - (1 << sub_y));
+        for j in 0..w {
+            let luma_x = ((block.start_x + j) << sub_x).min(block.max_luma_w - (1 << sub_x));
+            let mut t = 0;
+            for dy in 0..=sub_y as usize {
+                for dx in 0..=sub_x as usize {
+                    t += luma.get(luma_x + dx, luma_y + dy) as i32;
+                }
+            }
+
+            let v = t << (3 - sub_x - sub_y);
+            l[i * w + j] = v;
+            luma_avg += v;
+        }
+    }
+
+    let luma_avg = round2(
+        luma_avg,
+        (TX_WIDTH_LOG2[block.tx_size as usize] + TX_HEIGHT_LOG2[block.tx_size as usize]) as u32,
+    );
+
+    for i in 0..h {
+        for j in 0..w {
+            let (x, y) = (block.start_x + j, block.start_y + i);
+            let dc = chroma.get(x, y) as i32;
+            let scaled_luma = round2signed(block.alpha * (l[i * w + j] - luma_avg), 6);
+            chroma.set(x, y, clip1(dc + scaled_luma, block.bit_depth));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{TX_4X4, TX_4X8, TX_8X8};
+
+    fn planes(luma: impl Fn(usize, usize) -> u16, dc: u16) -> (Plane, Plane) {
+        let mut y = Plane::new(64, 64);
+        let mut uv = Plane::new(64, 64);
+        for i in 0..64 {
+            for j in 0..64 {
+                y.set(j, i, luma(j, i));
+                uv.set(j, i, dc);
+            }
+        }
+        (y, uv)
+    }
+
+    fn cfl_block(tx_size: u8, sub_x: u32, sub_y: u32, alpha: i32) -> CflBlock {
+        CflBlock {
+            start_x: 4,
+            start_y: 4,
+            tx_size,
+            sub_x,
+            sub_y,
+            max_luma_w: 64,
+            max_luma_h: 64,
+            alpha,
+            bit_depth: 8,
+        }
+    }
+
+    fn block_samples(plane: &Plane, block: &CflBlock) -> Vec<u16> {
+        let w = TX_WIDTH[block.tx_size as usize] as usize;
+        let h = TX_HEIGHT[block.tx_size as usize] as usize;
+        (0..h)
+            .flat_map(|i| (0..w).map(move |j| (i, j)))
+            .map(|(i, j)| plane.get(block.start_x + j, block.start_y + i))
+            .collect()
+    }
+
+    #[test]
+    fn subsampled_420() {
+        // the left half of the luma block is 100 and the right half 200.
+        let (luma, mut chroma) = planes(|x, _| if x < 12 { 100 } else { 200 }, 128);
+        let block = cfl_block(TX_4X4, 1, 1, 8);
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        // L is 800 or 1600 around an average of 1200, the AC is scaled by
+        // 8 / 64 = 1 / 8.
+        let row = [78, 78, 178, 178];
+        assert_eq!(block_samples(&chroma, &block), row.repeat(4));
+
+        let block = cfl_block(TX_4X4, 1, 1, -3);
+        let (luma, mut chroma) = planes(|x, _| if x < 12 { 100 } else { 200 }, 128);
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        let v = round2signed(-3 * 400, 6);
+        let row = [128 - v, 128 - v, 128 + v, 128 + v].map(|v| v as u16);
+        assert_eq!(block_samples(&chroma, &block), row.repeat(4));
+    }
+
+    #[test]
+    fn subsampled_422_and_444() {
+        // 4:2:2 averages pairs of luma columns.
+        let (luma, mut chroma) = planes(|x, y| (10 * x + y) as u16, 100);
+        let block = cfl_block(TX_4X8, 1, 0, 16);
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        let l = |i: usize, j: usize| {
+            let (x, y) = ((4 + j) * 2, 4 + i);
+            ((10 * x + y) + (10 * (x + 1) + y)) as i32 * 4
+        };
+        let avg = round2((0..8).flat_map(|i| (0..4).map(move |j| l(i, j))).sum(), 5);
+        let expected: Vec<u16> = (0..8)
+            .flat_map(|i| (0..4).map(move |j| (i, j)))
+            .map(|(i, j)| clip1(100 + round2signed(16 * (l(i, j) - avg), 6), 8))
+            .collect();
+        assert_eq!(block_samples(&chroma, &block), expected);
+
+        // 4:4:4 uses the luma samples at the same position.
+        let (luma, mut chroma) = planes(|x, y| (x * y % 50) as u16, 100);
+        let block = cfl_block(TX_8X8, 0, 0, -5);
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        let l = |i: usize, j: usize| ((4 + j) * (4 + i) % 50) as i32 * 8;
+        let avg = round2((0..8).flat_map(|i| (0..8).map(move |j| l(i, j))).sum(), 6);
+        let expected: Vec<u16> = (0..8)
+            .flat_map(|i| (0..8).map(move |j| (i, j)))
+            .map(|(i, j)| clip1(100 + round2signed(-5 * (l(i, j) - avg), 6), 8))
+            .collect();
+        assert_eq!(block_samples(&chroma, &block), expected);
+    }
+
+    #[test]
+    fn flat_luma_or_zero_alpha_keep_the_dc() {
+        let (luma, mut chroma) = planes(|_, _| 77, 90);
+        let block = cfl_block(TX_8X8, 1, 1, 16);
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        assert!(block_samples(&chroma, &block).iter().all(|&v| v == 90));
+
+        let (luma, mut chroma) = planes(|x, y| (x * 7 + y * 3) as u16, 90);
+        let block = cfl_block(TX_8X8, 1, 1, 0);
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        assert!(block_samples(&chroma, &block).iter().all(|&v| v == 90));
+    }
+
+    #[test]
+    fn undecoded_luma_is_padded() {
+        // only the luma samples up to MaxLumaW and MaxLumaH are decoded, the
+        // luma past them is garbage that must not be read.
+        let (luma, mut chroma) = planes(|x, y| if x < 12 && y < 14 { 60 } else { 255 }, 128);
+        let block = CflBlock {
+            max_luma_w: 12,
+            max_luma_h: 14,
+            ..cfl_block(TX_4X4, 1, 1, 16)
+        };
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        assert!(block_samples(&chroma, &block).iter().all(|&v| v == 128));
+
+        let (luma, mut chroma) = planes(|x, _| if x < 10 { 40 } else { 80 }, 128);
+        let block = CflBlock {
+            max_luma_w: 12,
+            ..cfl_block(TX_4X4, 1, 1, 8)
+        };
+        predict_chroma_from_luma(&luma, &mut chroma, &block);
+        // the luma columns are 40, 80, 80, 80 after the padding.
+        let v = round2signed(8 * (320 - 560), 6);
+        let w = round2signed(8 * (640 - 560), 6);
+        let row = [128 + v, 128 + w, 128 + w, 128 + w].map(|v| v as u16);
+        assert_eq!(block_samples(&chroma, &block), row.repeat(4));
+    }
+
+    #[test]
+    fn high_bit_depths_clip() {
+        for bit_depth in [10, 12] {
+            let max = (1 << bit_depth) - 1;
+            let (luma, mut chroma) = planes(|x, _| if x < 12 { 0 } else { max }, max - 10);
+            let block = CflBlock {
+                bit_depth,
+                ..cfl_block(TX_4X4, 1, 1, 16)
+            };
+            predict_chroma_from_luma(&luma, &mut chroma, &block);
+            let scaled = round2signed(16 * (max as i32 * 4), 6);
+            let row = [
+                clip1(max as i32 - 10 - scaled, bit_depth),
+                clip1(max as i32 - 10 - scaled, bit_depth),
+                max,
+                max,
+            ];
+            assert_eq!(block_samples(&chroma, &block), row.repeat(4));
+        }
+    }
+}
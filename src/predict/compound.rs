@@ -0,0 +1,296 @@
+//! The masks and weights that blend the two predictions of a compound block,
+//! or the inter and the intra prediction of an inter intra block.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#wedge-mask-process
+//! see: https://aomediacodec.github.io/av1-spec/#mask-blend-process
+
+use std::sync::OnceLock;
+
+use crate::{
+    constants::{
+        II_H_PRED, II_SMOOTH_PRED, II_V_PRED, MASK_MASTER_SIZE, MAX_SB_SIZE, WEDGE_HORIZONTAL,
+        WEDGE_OBLIQUE117, WEDGE_OBLIQUE153, WEDGE_OBLIQUE27, WEDGE_OBLIQUE63, WEDGE_VERTICAL,
+    },
+    tables::{
+        BLOCK_HEIGHT, BLOCK_WIDTH, II_WEIGHTS_1D, QUANT_DIST_LOOKUP, QUANT_DIST_WEIGHT,
+        WEDGE_CODEBOOK, WEDGE_MASTER_OBLIQUE_EVEN, WEDGE_MASTER_OBLIQUE_ODD, WEDGE_MASTER_VERTICAL,
+    },
+    util::round2,
+};
+
+const MASTER_SIZE: usize = MASK_MASTER_SIZE as usize;
+
+/// Mask, the weight of the first prediction in 1/64 units for each sample
+/// of the block.
+pub type Mask = [[u8; MAX_SB_SIZE as usize]; MAX_SB_SIZE as usize];
+
+/// MasterMask, one mask for each wedge direction.
+type MasterMask = [[[u8; MASTER_SIZE]; MASTER_SIZE]; 6];
+
+/// The MasterMask part of the initialisation of the wedge masks, the
+/// WedgeMasks are cut out of it when a block uses them.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#wedge-mask-process
+fn master_mask() -> &'static MasterMask {
+    static MASTER_MASK: OnceLock<Box<MasterMask>> = OnceLock::new();
+    MASTER_MASK.get_or_init(|| {
+        let mut master = Box::new([[[0; MASTER_SIZE]; MASTER_SIZE]; 6]);
+        let clip = |j: i32| j.clamp(0, MASTER_SIZE as i32 - 1) as usize;
+        for j in 0..MASTER_SIZE {
+            let mut shift = MASTER_SIZE as i32 / 4;
+            for i in (0..MASTER_SIZE).step_by(2) {
+                let oblique = &mut master[WEDGE_OBLIQUE63 as usize];
+                oblique[i][j] = WEDGE_MASTER_OBLIQUE_EVEN[clip(j as i32 - shift)];
+                shift -= 1;
+                oblique[i + 1][j] = WEDGE_MASTER_OBLIQUE_ODD[clip(j as i32 - shift)];
+
+                let vertical = &mut master[WEDGE_VERTICAL as usize];
+                vertical[i][j] = WEDGE_MASTER_VERTICAL[j];
+                vertical[i + 1][j] = WEDGE_MASTER_VERTICAL[j];
+            }
+        }
+
+        for i in 0..MASTER_SIZE {
+            for j in 0..MASTER_SIZE {
+                let msk = master[WEDGE_OBLIQUE63 as usize][i][j];
+                master[WEDGE_OBLIQUE27 as usize][j][i] = msk;
+                master[WEDGE_OBLIQUE117 as usize][i][MASTER_SIZE - 1 - j] = 64 - msk;
+                master[WEDGE_OBLIQUE153 as usize][MASTER_SIZE - 1 - j][i] = 64 - msk;
+                master[WEDGE_HORIZONTAL as usize][j][i] = master[WEDGE_VERTICAL as usize][i][j];
+            }
+        }
+
+        master
+    })
+}
+
+/// The wedge mask process, WedgeMasks[ bsize ][ sign ][ wedge ] for a block
+/// size with Wedge_Bits greater than 0.
+///
+/// The direction of a wedge only defines the boundary, the sign that keeps
+/// the master mask is chosen so that the average over the top row and the
+/// left column of the block is at least 32.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#wedge-mask-process
+pub fn wedge_mask(mask: &mut Mask, bsize: u8, sign: u8, wedge: u8) {
+    let w = BLOCK_WIDTH[bsize as usize] as usize;
+    let h = BLOCK_HEIGHT[bsize as usize] as usize;
+    let shape = match h.cmp(&w) {
+        std::cmp::Ordering::Equal => 0,
+        std::cmp::Ordering::Greater => 1,
+        _ => 2,
+    };
+
+    let [dir, x_code, y_code] = WEDGE_CODEBOOK[shape][wedge as usize];
+    let x_off = MASTER_SIZE / 2 - ((x_code as usize * w) >> 3);
+    let y_off = MASTER_SIZE / 2 - ((y_code as usize * h) >> 3);
+    let master = &master_mask()[dir as usize];
+
+    let sum = (0..w)
+        .map(|i| master[y_off][x_off + i] as usize)
+        .sum::<usize>()
+        + (1..h)
+            .map(|i| master[y_off + i][x_off] as usize)
+            .sum::<usize>();
+    let avg = (sum + (w + h - 1) / 2) / (w + h - 1);
+    let flip_sign = (avg < 32) as u8;
+
+    for (row, master_row) in mask.iter_mut().zip(&master[y_off..]).take(h) {
+        for (m, &msk) in row.iter_mut().zip(&master_row[x_off..]).take(w) {
+            *m = if sign == flip_sign { msk } else { 64 - msk };
+        }
+    }
+}
+
+/// The difference weight mask process, the mask follows the difference of
+/// the two predictions, mask_type selects which of them is weighted up
+/// where they differ.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#difference-weight-mask-process
+pub fn difference_weight_mask(
+    mask: &mut Mask,
+    preds: [&[i32]; 2],
+    w: usize,
+    mask_type: u8,
+    bit_depth: u8,
+    post_round: u32,
+) {
+    let shift = (bit_depth as u32 - 8) + post_round;
+    let rows = preds[0].chunks_exact(w).zip(preds[1].chunks_exact(w));
+    for (row, (pred0, pred1)) in mask.iter_mut().zip(rows) {
+        for (m, (p0, p1)) in row.iter_mut().zip(pred0.iter().zip(pred1)) {
+            let diff = round2((p0 - p1).abs(), shift);
+            let value = (38 + diff / 16).clamp(0, 64) as u8;
+            *m = if mask_type == 1 { 64 - value } else { value };
+        }
+    }
+}
+
+/// The intra mode variant mask process, the smooth masks of inter intra
+/// blocks weight the intra prediction up near the edges it is predicted
+/// from.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#intra-mode-variant-mask-process
+pub fn intra_mode_variant_mask(mask: &mut Mask, w: usize, h: usize, interintra_mode: u8) {
+    let size_scale = MAX_SB_SIZE as usize / w.max(h);
+    for (i, row) in mask.iter_mut().enumerate().take(h) {
+        for (j, m) in row.iter_mut().enumerate().take(w) {
+            *m = match interintra_mode {
+                II_V_PRED => II_WEIGHTS_1D[i * size_scale],
+                II_H_PRED => II_WEIGHTS_1D[j * size_scale],
+                II_SMOOTH_PRED => II_WEIGHTS_1D[i.min(j) * size_scale],
+                _ => 32,
+            };
+        }
+    }
+}
+
+/// The value of the mask for a sample of a plane subsampled by sub_x and
+/// sub_y, the mask has the resolution of the luma plane and is averaged
+/// over the luma samples that are collocated with the sample.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#mask-blend-process
+pub fn blend_mask(mask: &Mask, x: usize, y: usize, sub_x: u32, sub_y: u32) -> i32 {
+    let m = |i: usize, j: usize| mask[i][j] as i32;
+    match (sub_x, sub_y) {
+        (0, 0) => m(y, x),
+        (1, 0) => round2(m(y, 2 * x) + m(y, 2 * x + 1), 1),
+        (0, 1) => round2(m(2 * y, x) + m(2 * y + 1, x), 1),
+        _ => round2(
+            m(2 * y, 2 * x) + m(2 * y, 2 * x + 1) + m(2 * y + 1, 2 * x) + m(2 * y + 1, 2 * x + 1),
+            2,
+        ),
+    }
+}
+
+/// The distance weights process, FwdWeight and BckWeight from the
+/// distances between the current frame and the two reference frames of a
+/// block, the prediction from the closer reference is weighted up.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#distance-weights-process
+pub fn distance_weights(dist: [i32; 2]) -> [i32; 2] {
+    let (d0, d1) = (dist[1], dist[0]);
+    let order = (d0 <= d1) as usize;
+    if d0 == 0 || d1 == 0 {
+        return [QUANT_DIST_LOOKUP[3][order], QUANT_DIST_LOOKUP[3][1 - order]];
+    }
+
+    let i = (0..3)
+        .find(|&i| {
+            let c0 = QUANT_DIST_WEIGHT[i][order];
+            let c1 = QUANT_DIST_WEIGHT[i][1 - order];
+            if order == 1 {
+                d0 * c0 > d1 * c1
+            } else {
+                d0 * c0 < d1 * c1
+            }
+        })
+        .unwrap_or(3);
+
+    [QUANT_DIST_LOOKUP[i][order], QUANT_DIST_LOOKUP[i][1 - order]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{BLOCK_16X16, BLOCK_32X32, BLOCK_8X32, BLOCK_SIZES, II_DC_PRED, WEDGE_TYPES},
+        tables::WEDGE_BITS,
+    };
+
+    fn empty_mask() -> Box<Mask> {
+        Box::new([[0; MAX_SB_SIZE as usize]; MAX_SB_SIZE as usize])
+    }
+
+    #[test]
+    fn wedge_signs_are_complementary() {
+        let (mut mask0, mut mask1) = (empty_mask(), empty_mask());
+        for bsize in (0..BLOCK_SIZES).filter(|&b| WEDGE_BITS[b as usize] > 0) {
+            let w = BLOCK_WIDTH[bsize as usize] as usize;
+            let h = BLOCK_HEIGHT[bsize as usize] as usize;
+            for wedge in 0..WEDGE_TYPES {
+                wedge_mask(&mut mask0, bsize, 0, wedge);
+                wedge_mask(&mut mask1, bsize, 1, wedge);
+                for i in 0..h {
+                    for j in 0..w {
+                        assert!(mask0[i][j] <= 64);
+                        assert_eq!(mask0[i][j] + mask1[i][j], 64);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wedges_split_the_block() {
+        let mut mask = empty_mask();
+
+        // WEDGE_VERTICAL at 2/8 of the width of a square block.
+        wedge_mask(&mut mask, BLOCK_16X16, 0, 6);
+        assert!((0..16).all(|i| mask[i][0] == 64 && mask[i][15] == 0));
+
+        // WEDGE_HORIZONTAL through the middle of a tall block.
+        wedge_mask(&mut mask, BLOCK_8X32, 0, 5);
+        assert_eq!(mask[0][0] + mask[31][0], 64);
+        assert!(mask[0][0] == 0 || mask[0][0] == 64);
+
+        // WEDGE_OBLIQUE63 through the centre, the opposite corners are on
+        // opposite sides of the boundary.
+        wedge_mask(&mut mask, BLOCK_32X32, 1, 1);
+        assert_eq!(mask[0][0] + mask[31][31], 64);
+        assert!(mask[0][0] == 0 || mask[0][0] == 64);
+    }
+
+    #[test]
+    fn difference_weights_follow_the_difference() {
+        let mut mask = empty_mask();
+        let pred0 = [100 << 4, 100 << 4, 100 << 4, 100 << 4];
+        let pred1 = [100 << 4, 116 << 4, 132 << 4, 1000 << 4];
+        difference_weight_mask(&mut mask, [&pred0, &pred1], 4, 0, 8, 4);
+        assert_eq!(mask[0][..4], [38, 39, 40, 64]);
+
+        difference_weight_mask(&mut mask, [&pred0, &pred1], 2, 1, 10, 4);
+        assert_eq!(mask[0][..2], [26, 26]);
+        assert_eq!(mask[1][..2], [26, 12]);
+    }
+
+    #[test]
+    fn smooth_inter_intra_masks() {
+        let mut mask = empty_mask();
+        intra_mode_variant_mask(&mut mask, 32, 32, II_V_PRED);
+        assert!((0..32).all(|i| mask[i][7] == II_WEIGHTS_1D[i * 4]));
+        assert_eq!(mask[0][0], 60);
+
+        intra_mode_variant_mask(&mut mask, 16, 8, II_SMOOTH_PRED);
+        assert_eq!(mask[3][5], II_WEIGHTS_1D[3 * 8]);
+
+        intra_mode_variant_mask(&mut mask, 8, 8, II_DC_PRED);
+        assert!(mask[..8]
+            .iter()
+            .all(|row| row[..8].iter().all(|&m| m == 32)));
+    }
+
+    #[test]
+    fn chroma_masks_average_the_luma_mask() {
+        let mut mask = empty_mask();
+        mask[0][..2].copy_from_slice(&[10, 20]);
+        mask[1][..2].copy_from_slice(&[30, 41]);
+        assert_eq!(blend_mask(&mask, 0, 0, 0, 0), 10);
+        assert_eq!(blend_mask(&mask, 0, 0, 1, 0), 15);
+        assert_eq!(blend_mask(&mask, 0, 0, 0, 1), 20);
+        assert_eq!(blend_mask(&mask, 0, 0, 1, 1), 25);
+    }
+
+    #[test]
+    fn closer_references_are_weighted_up() {
+        assert_eq!(distance_weights([1, 1]), [7, 9]);
+        assert_eq!(distance_weights([0, 4]), [13, 3]);
+        assert_eq!(distance_weights([4, 0]), [3, 13]);
+        // the first reference is closer.
+        assert_eq!(distance_weights([1, 2]), [11, 5]);
+        assert_eq!(distance_weights([1, 16]), [13, 3]);
+        // the second reference is closer.
+        assert_eq!(distance_weights([3, 2]), [5, 11]);
+        assert_eq!(distance_weights([8, 2]), [3, 13]);
+    }
+}
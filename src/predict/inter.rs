@@ -0,0 +1,296 @@
+//! The block inter prediction process, which predicts a block from a
+//! reference frame with the separable sub-sample interpolation filters.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#motion-vector-scaling-process
+//! see: https://aomediacodec.github.io/av1-spec/#block-inter-prediction-process
+
+use crate::{
+    constants::{FILTER_BITS, REF_SCALE_SHIFT, SCALE_SUBPEL_BITS, SUBPEL_BITS, SUBPEL_MASK},
+    obu::frame_header::InterpolationFilter,
+    picture::Plane,
+    tables::SUBPEL_FILTERS,
+    util::{round2, round2signed},
+};
+
+use crate::tile::mvpred::Mv;
+
+/// The rounding variables of the prediction of a block.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#rounding-variables-derivation-process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterRound {
+    /// InterRound0, the rounding of the horizontal filter.
+    pub round0: u32,
+    /// InterRound1, the rounding of the vertical filter.
+    pub round1: u32,
+    /// InterPostRound, the rounding that is left to be done once the
+    /// predictions are combined.
+    pub post_round: u32,
+}
+
+impl InterRound {
+    pub fn new(bit_depth: u8, is_compound: bool) -> Self {
+        let mut round0 = 3;
+        let mut round1 = if is_compound { 7 } else { 11 };
+        if bit_depth == 12 {
+            round0 += 2;
+            if !is_compound {
+                round1 -= 2;
+            }
+        }
+
+        Self {
+            round0,
+            round1,
+            post_round: 2 * FILTER_BITS as u32 - (round0 + round1),
+        }
+    }
+}
+
+/// xScale and yScale, the ratio between the size of a reference frame and
+/// the size of the current frame in 1 / (1 << REF_SCALE_SHIFT) units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleFactors {
+    pub x_scale: i64,
+    pub y_scale: i64,
+}
+
+impl ScaleFactors {
+    /// RefUpscaledWidth[ refIdx ] and RefFrameHeight[ refIdx ] against
+    /// FrameWidth and FrameHeight.
+    pub fn new(ref_width: u32, ref_height: u32, frame_width: u32, frame_height: u32) -> Self {
+        let scale = |r: u32, f: u32| (((r as i64) << REF_SCALE_SHIFT) + f as i64 / 2) / f as i64;
+        Self {
+            x_scale: scale(ref_width, frame_width),
+            y_scale: scale(ref_height, frame_height),
+        }
+    }
+
+    /// The reference frame has another size than the current frame.
+    pub fn is_scaled(&self) -> bool {
+        let no_scale = 1 << REF_SCALE_SHIFT;
+        self.x_scale != no_scale || self.y_scale != no_scale
+    }
+
+    /// The motion vector scaling process, the position of the block at x, y
+    /// in the samples of a plane of the current frame that mv points to in
+    /// the reference frame, in 1 / (1 << SCALE_SUBPEL_BITS) sample units.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#motion-vector-scaling-process
+    pub fn scale(&self, x: i32, y: i32, mv: Mv, sub_x: u32, sub_y: u32) -> ScaledPosition {
+        let half_sample = 1 << (SUBPEL_BITS - 1);
+        let orig_x = ((x << SUBPEL_BITS) + ((2 * mv[1]) >> sub_x) + half_sample) as i64;
+        let orig_y = ((y << SUBPEL_BITS) + ((2 * mv[0]) >> sub_y) + half_sample) as i64;
+        let base_x = orig_x * self.x_scale - ((half_sample as i64) << REF_SCALE_SHIFT);
+        let base_y = orig_y * self.y_scale - ((half_sample as i64) << REF_SCALE_SHIFT);
+
+        // the positions are within a few times the frame size, which fits
+        // once the precision is reduced to SCALE_SUBPEL_BITS.
+        let shift = (REF_SCALE_SHIFT + SUBPEL_BITS - SCALE_SUBPEL_BITS) as u32;
+        let round = |v: i64| {
+            if v >= 0 {
+                (v + (1 << (shift - 1))) >> shift
+            } else {
+                -((-v + (1 << (shift - 1))) >> shift)
+            }
+        };
+
+        let off = (1 << (SCALE_SUBPEL_BITS - SUBPEL_BITS)) / 2;
+        let step_shift = (REF_SCALE_SHIFT - SCALE_SUBPEL_BITS) as u32;
+        ScaledPosition {
+            start_x: round(base_x) as i32 + off,
+            start_y: round(base_y) as i32 + off,
+            x_step: round2signed(self.x_scale as i32, step_shift),
+            y_step: round2signed(self.y_scale as i32, step_shift),
+        }
+    }
+}
+
+/// The output of the motion vector scaling process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScaledPosition {
+    /// startX
+    pub start_x: i32,
+    /// startY
+    pub start_y: i32,
+    /// xStep
+    pub x_step: i32,
+    /// yStep
+    pub y_step: i32,
+}
+
+/// The index into SUBPEL_FILTERS of the kernel of interpFilter[ dir ], the
+/// 4-tap variants are used for the blocks of 4 samples or less in the
+/// direction of the filter.
+fn filter_idx(interp_filter: InterpolationFilter, size: usize) -> usize {
+    match interp_filter {
+        InterpolationFilter::Eighttap | InterpolationFilter::EighttapSharp if size <= 4 => 4,
+        InterpolationFilter::EighttapSmooth if size <= 4 => 5,
+        _ => interp_filter as usize,
+    }
+}
+
+/// The block inter prediction process, predicts a w x h block from the
+/// reference plane with the horizontal filter interpFilter[ 1 ] followed by
+/// the vertical filter interpFilter[ 0 ].
+///
+/// The positions outside of the reference plane are clamped to its edges,
+/// motion vectors are clamped to at most MV_BORDER plus the block size
+/// outside of the frame, so a block never needs more than the replicated
+/// edge samples. The predictions are returned with InterPostRound bits of
+/// extra precision.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#block-inter-prediction-process
+pub fn block_inter_prediction(
+    reference: &Plane,
+    pos: &ScaledPosition,
+    w: usize,
+    h: usize,
+    interp_filter: [InterpolationFilter; 2],
+    round: &InterRound,
+) -> Vec<i32> {
+    // lastX and lastY, the reference planes are stored at the upscaled
+    // width and the height of the reference frame.
+    let last_x = reference.width as i32 - 1;
+    let last_y = reference.height as i32 - 1;
+    let scale_mask = (1 << SCALE_SUBPEL_BITS) - 1;
+    let filter_shift = (SCALE_SUBPEL_BITS - SUBPEL_BITS) as u32;
+    let intermediate_height =
+        ((((h as i32 - 1) * pos.y_step + scale_mask) >> SCALE_SUBPEL_BITS) + 8) as usize;
+
+    let filter = &SUBPEL_FILTERS[filter_idx(interp_filter[1], w)];
+    let mut intermediate = vec![0; intermediate_height * w];
+    for r in 0..intermediate_height {
+        let y = ((pos.start_y >> SCALE_SUBPEL_BITS) + r as i32 - 3).clamp(0, last_y);
+        for c in 0..w {
+            let p = pos.start_x + pos.x_step * c as i32;
+            let kernel = &filter[((p >> filter_shift) & SUBPEL_MASK as i32) as usize];
+            let mut sum = 0;
+            for (t, &k) in kernel.iter().enumerate() {
+                let x = ((p >> SCALE_SUBPEL_BITS) + t as i32 - 3).clamp(0, last_x);
+                sum += k * reference.get(x as usize, y as usize) as i32;
+            }
+
+            intermediate[r * w + c] = round2(sum, round.round0);
+        }
+    }
+
+    let filter = &SUBPEL_FILTERS[filter_idx(interp_filter[0], h)];
+    let mut pred = vec![0; w * h];
+    for r in 0..h {
+        let p = (pos.start_y & scale_mask) + pos.y_step * r as i32;
+        let kernel = &filter[((p >> filter_shift) & SUBPEL_MASK as i32) as usize];
+        let row = (p >> SCALE_SUBPEL_BITS) as usize;
+        for c in 0..w {
+            let sum: i32 = kernel
+                .iter()
+                .enumerate()
+                .map(|(t, &k)| k * intermediate[(row + t) * w + c])
+                .sum();
+            pred[r * w + c] = round2(sum, round.round1);
+        }
+    }
+
+    pred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants::MV_BORDER, util::clip1};
+
+    fn reference(bit_depth: u8) -> Plane {
+        let mut plane = Plane::new(32, 16);
+        let max = (1 << bit_depth) - 1;
+        for y in 0..16 {
+            for x in 0..32 {
+                plane.set(x, y, ((x * 37 + y * 101) % (max + 1)) as u16);
+            }
+        }
+
+        plane
+    }
+
+    fn predict(
+        plane: &Plane,
+        x: i32,
+        y: i32,
+        mv: Mv,
+        filter: InterpolationFilter,
+        bd: u8,
+    ) -> Vec<u16> {
+        let scale = ScaleFactors::new(32, 16, 32, 16);
+        let pos = scale.scale(x, y, mv, 0, 0);
+        let round = InterRound::new(bd, false);
+        block_inter_prediction(plane, &pos, 4, 4, [filter; 2], &round)
+            .into_iter()
+            .map(|p| clip1(round2(p, round.post_round), bd))
+            .collect()
+    }
+
+    #[test]
+    fn every_kernel_sums_to_128() {
+        for filter in SUBPEL_FILTERS.iter() {
+            for kernel in filter {
+                assert_eq!(kernel.iter().sum::<i32>(), 1 << FILTER_BITS);
+            }
+        }
+    }
+
+    #[test]
+    fn whole_sample_motion_vectors_copy_the_reference() {
+        for bit_depth in [8, 10, 12] {
+            let plane = reference(bit_depth);
+            for filter in [
+                InterpolationFilter::Eighttap,
+                InterpolationFilter::EighttapSmooth,
+                InterpolationFilter::EighttapSharp,
+                InterpolationFilter::Bilinear,
+            ] {
+                let pred = predict(&plane, 8, 4, [-16, 24], filter, bit_depth);
+                for i in 0..4 {
+                    for j in 0..4 {
+                        assert_eq!(pred[i * 4 + j], plane.get(11 + j, 2 + i));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn half_sample_bilinear_averages_the_neighbours() {
+        let plane = reference(8);
+        let pred = predict(&plane, 8, 4, [4, 4], InterpolationFilter::Bilinear, 8);
+        for i in 0..4 {
+            for j in 0..4 {
+                let sum = plane.get(8 + j, 4 + i) as i32
+                    + plane.get(9 + j, 4 + i) as i32
+                    + plane.get(8 + j, 5 + i) as i32
+                    + plane.get(9 + j, 5 + i) as i32;
+                assert_eq!(pred[i * 4 + j] as i32, round2(sum, 2));
+            }
+        }
+    }
+
+    #[test]
+    fn positions_outside_of_the_frame_replicate_the_edges() {
+        let plane = reference(10);
+        let mv = [-(MV_BORDER as i32) - 4 * 8, -(MV_BORDER as i32) - 4 * 8];
+        let pred = predict(&plane, 0, 0, mv, InterpolationFilter::EighttapSharp, 10);
+        assert!(pred.iter().all(|&p| p == plane.get(0, 0)));
+    }
+
+    #[test]
+    fn references_of_twice_the_size_step_two_samples() {
+        let scale = ScaleFactors::new(64, 32, 32, 16);
+        assert!(scale.is_scaled());
+        assert!(!ScaleFactors::new(32, 16, 32, 16).is_scaled());
+
+        let pos = scale.scale(4, 4, [0, 0], 0, 0);
+        assert_eq!((pos.x_step, pos.y_step), (2048, 2048));
+        // the centre of sample 4 is at 8.5 in the reference.
+        let filter_shift = SCALE_SUBPEL_BITS - SUBPEL_BITS;
+        assert_eq!(pos.start_x >> SCALE_SUBPEL_BITS, 8);
+        assert_eq!((pos.start_x >> filter_shift) & SUBPEL_MASK as i32, 8);
+    }
+}
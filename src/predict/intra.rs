@@ -0,0 +1,1079 @@
+//! The intra prediction processes, which predict a transform block from the
+//! reconstructed samples above and to the left of it.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#intra-prediction-process
+
+use std::ops::{Index, IndexMut};
+
+use crate::{
+    constants::{
+        INTRA_EDGE_KERNELS, INTRA_EDGE_TAPS, INTRA_FILTER_MODES, INTRA_FILTER_SCALE_BITS,
+        INTRA_MODES, SMOOTH_H_PRED, SMOOTH_V_PRED,
+    },
+    picture::Plane,
+    util::{clip1, clip3, round2, round2signed},
+};
+
+/// Mode_To_Angle
+pub const MODE_TO_ANGLE: [i32; INTRA_MODES as usize] =
+    [0, 90, 180, 45, 135, 113, 157, 203, 67, 0, 0, 0, 0];
+
+/// Dr_Intra_Derivative
+pub const DR_INTRA_DERIVATIVE: [i32; 90] = [
+    0, 0, 0, 1023, 0, 0, 547, 0, 0, 372, 0, 0, 0, 0, 273, 0, 0, 215, 0, 0, 178, 0, 0, 151, 0, 0,
+    132, 0, 0, 116, 0, 0, 102, 0, 0, 0, 90, 0, 0, 80, 0, 0, 71, 0, 0, 64, 0, 0, 57, 0, 0, 51, 0, 0,
+    45, 0, 0, 0, 40, 0, 0, 35, 0, 0, 31, 0, 0, 27, 0, 0, 23, 0, 0, 19, 0, 0, 15, 0, 0, 0, 0, 11, 0,
+    0, 7, 0, 0, 3, 0, 0,
+];
+
+/// Sm_Weights_Tx_4x4
+pub const SM_WEIGHTS_TX_4X4: [i32; 4] = [255, 149, 85, 64];
+
+/// Sm_Weights_Tx_8x8
+pub const SM_WEIGHTS_TX_8X8: [i32; 8] = [255, 197, 146, 105, 73, 50, 37, 32];
+
+/// Sm_Weights_Tx_16x16
+pub const SM_WEIGHTS_TX_16X16: [i32; 16] = [
+    255, 225, 196, 170, 145, 123, 102, 84, 68, 54, 43, 33, 26, 20, 17, 16,
+];
+
+/// Sm_Weights_Tx_32x32
+pub const SM_WEIGHTS_TX_32X32: [i32; 32] = [
+    255, 240, 225, 210, 196, 182, 169, 157, 145, 133, 122, 111, 101, 92, 83, 74, 66, 59, 52, 45,
+    39, 34, 29, 25, 21, 17, 14, 12, 10, 9, 8, 8,
+];
+
+/// Sm_Weights_Tx_64x64
+pub const SM_WEIGHTS_TX_64X64: [i32; 64] = [
+    255, 248, 240, 233, 225, 218, 210, 203, 196, 189, 182, 176, 169, 163, 156, 150, 144, 138, 133,
+    127, 121, 116, 111, 106, 101, 96, 91, 86, 82, 77, 73, 69, 65, 61, 57, 54, 50, 47, 44, 41, 38,
+    35, 32, 29, 27, 25, 22, 20, 18, 16, 15, 13, 12, 10, 9, 8, 7, 6, 6, 5, 5, 4, 4, 4,
+];
+
+/// Intra_Edge_Kernel
+pub const INTRA_EDGE_KERNEL: [[i32; INTRA_EDGE_TAPS as usize]; INTRA_EDGE_KERNELS as usize] =
+    [[0, 4, 8, 4, 0], [0, 5, 6, 5, 0], [2, 4, 4, 4, 2]];
+
+/// Intra_Filter_Taps
+pub const INTRA_FILTER_TAPS: [[[i32; 7]; 8]; INTRA_FILTER_MODES as usize] = [
+    [
+        [-6, 10, 0, 0, 0, 12, 0],
+        [-5, 2, 10, 0, 0, 9, 0],
+        [-3, 1, 1, 10, 0, 7, 0],
+        [-3, 1, 1, 2, 10, 5, 0],
+        [-4, 6, 0, 0, 0, 2, 12],
+        [-3, 2, 6, 0, 0, 2, 9],
+        [-3, 2, 2, 6, 0, 2, 7],
+        [-3, 1, 2, 2, 6, 3, 5],
+    ],
+    [
+        [-10, 16, 0, 0, 0, 10, 0],
+        [-6, 0, 16, 0, 0, 6, 0],
+        [-4, 0, 0, 16, 0, 4, 0],
+        [-2, 0, 0, 0, 16, 2, 0],
+        [-10, 16, 0, 0, 0, 0, 10],
+        [-6, 0, 16, 0, 0, 0, 6],
+        [-4, 0, 0, 16, 0, 0, 4],
+        [-2, 0, 0, 0, 16, 0, 2],
+    ],
+    [
+        [-8, 8, 0, 0, 0, 16, 0],
+        [-8, 0, 8, 0, 0, 16, 0],
+        [-8, 0, 0, 8, 0, 16, 0],
+        [-8, 0, 0, 0, 8, 16, 0],
+        [-4, 4, 0, 0, 0, 0, 16],
+        [-4, 0, 4, 0, 0, 0, 16],
+        [-4, 0, 0, 4, 0, 0, 16],
+        [-4, 0, 0, 0, 4, 0, 16],
+    ],
+    [
+        [-2, 8, 0, 0, 0, 10, 0],
+        [-1, 3, 8, 0, 0, 6, 0],
+        [-1, 2, 3, 8, 0, 4, 0],
+        [0, 1, 2, 3, 8, 2, 0],
+        [-1, 4, 0, 0, 0, 3, 10],
+        [-1, 3, 4, 0, 0, 4, 6],
+        [-1, 2, 3, 4, 0, 4, 4],
+        [-1, 2, 2, 3, 4, 3, 3],
+    ],
+    [
+        [-12, 14, 0, 0, 0, 14, 0],
+        [-10, 0, 14, 0, 0, 12, 0],
+        [-9, 0, 0, 14, 0, 11, 0],
+        [-8, 0, 0, 0, 14, 10, 0],
+        [-10, 12, 0, 0, 0, 0, 14],
+        [-9, 1, 12, 0, 0, 0, 12],
+        [-8, 0, 0, 12, 0, 1, 11],
+        [-7, 0, 0, 1, 12, 1, 9],
+    ],
+];
+
+/// The number of entries of an edge before index 0, the upsampling process
+/// writes down to index -2.
+const EDGE_OFFSET: usize = 16;
+
+/// AboveRow or LeftCol, the samples along the top or the left edge of a
+/// block. An edge is indexed like in the specification, the index can be
+/// negative down to -16 and goes up to 2 * 64.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge([u16; EDGE_OFFSET + 2 * 64 + 1]);
+
+impl Default for Edge {
+    fn default() -> Self {
+        Self([0; EDGE_OFFSET + 2 * 64 + 1])
+    }
+}
+
+impl Index<i32> for Edge {
+    type Output = u16;
+
+    #[inline]
+    fn index(&self, i: i32) -> &u16 {
+        &self.0[(EDGE_OFFSET as i32 + i) as usize]
+    }
+}
+
+impl IndexMut<i32> for Edge {
+    #[inline]
+    fn index_mut(&mut self, i: i32) -> &mut u16 {
+        &mut self.0[(EDGE_OFFSET as i32 + i) as usize]
+    }
+}
+
+/// The position of a block that is intra predicted and the availability of
+/// its neighbours, the inputs of the intra prediction process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IntraBlock {
+    /// x, the position of the block in samples of the plane.
+    pub x: usize,
+    /// y
+    pub y: usize,
+    /// log2W
+    pub log2w: u32,
+    /// log2H
+    pub log2h: u32,
+    /// haveLeft
+    pub have_left: bool,
+    /// haveAbove
+    pub have_above: bool,
+    /// haveAboveRt
+    pub have_above_rt: bool,
+    /// haveBelowLft
+    pub have_below_lft: bool,
+    /// maxX, the last column of the plane that is covered by the mode info
+    /// of the frame.
+    pub max_x: usize,
+    /// maxY
+    pub max_y: usize,
+    pub bit_depth: u8,
+}
+
+impl IntraBlock {
+    #[inline]
+    fn w(&self) -> i32 {
+        1 << self.log2w
+    }
+
+    #[inline]
+    fn h(&self) -> i32 {
+        1 << self.log2h
+    }
+}
+
+/// The edges of a block that is intra predicted.
+#[derive(Debug, Clone, Default)]
+pub struct IntraEdges {
+    /// AboveRow
+    pub above_row: Edge,
+    /// LeftCol
+    pub left_col: Edge,
+}
+
+impl IntraEdges {
+    /// Reads the w + h samples above and to the left of the block from
+    /// CurrFrame, and the sample at the top left corner into AboveRow[ -1 ]
+    /// and LeftCol[ -1 ]. An edge that is not available is copied from the
+    /// other edge, or set close to the middle of the sample range when
+    /// neither is available.
+    pub fn new(frame: &Plane, block: &IntraBlock) -> Self {
+        let (x, y) = (block.x, block.y);
+        let (w, h) = (block.w(), block.h());
+        let mid = 1 << (block.bit_depth - 1);
+        let above_limit = block
+            .max_x
+            .min(x + if block.have_above_rt { 2 * w } else { w } as usize - 1);
+        let left_limit = block
+            .max_y
+            .min(y + if block.have_below_lft { 2 * h } else { h } as usize - 1);
+
+        let mut edges = Self::default();
+        for i in 0..w + h {
+            edges.above_row[i] = match (block.have_above, block.have_left) {
+                (false, true) => frame.get(x - 1, y),
+                (false, false) => mid - 1,
+                _ => frame.get(above_limit.min(x + i as usize), y - 1),
+            };
+
+            edges.left_col[i] = match (block.have_left, block.have_above) {
+                (false, true) => frame.get(x, y - 1),
+                (false, false) => mid + 1,
+                _ => frame.get(x - 1, left_limit.min(y + i as usize)),
+            };
+        }
+
+        let corner = match (block.have_above, block.have_left) {
+            (true, true) => frame.get(x - 1, y - 1),
+            (true, false) => frame.get(x, y - 1),
+            (false, true) => frame.get(x - 1, y),
+            _ => mid,
+        };
+
+        edges.above_row[-1] = corner;
+        edges.left_col[-1] = corner;
+        edges
+    }
+}
+
+/// The w x h prediction of a block in raster order, pred( i, j ) is the
+/// sample of row i and column j.
+fn predict(block: &IntraBlock, pred: impl Fn(i32, i32) -> i32) -> Vec<u16> {
+    let (w, h) = (block.w(), block.h());
+    (0..h)
+        .flat_map(|i| (0..w).map(move |j| (i, j)))
+        .map(|(i, j)| pred(i, j) as u16)
+        .collect()
+}
+
+/// The basic intra prediction process, which is the Paeth predictor. Each
+/// sample is predicted from the above, left or top left sample, whichever
+/// is closest to the gradient above + left - top left.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#basic-intra-prediction-process
+pub fn paeth_pred(block: &IntraBlock, edges: &IntraEdges) -> Vec<u16> {
+    let top_left = edges.above_row[-1] as i32;
+    predict(block, |i, j| {
+        let left = edges.left_col[i] as i32;
+        let top = edges.above_row[j] as i32;
+        let base = top + left - top_left;
+        let p_left = (base - left).abs();
+        let p_top = (base - top).abs();
+        let p_top_left = (base - top_left).abs();
+        if p_left <= p_top && p_left <= p_top_left {
+            left
+        } else if p_top <= p_top_left {
+            top
+        } else {
+            top_left
+        }
+    })
+}
+
+/// The recursive intra prediction process of blocks that use filter intra.
+/// The block is predicted in 4x2 sub-blocks, each one from the 7 samples
+/// above and to the left of it, which are predicted samples of the previous
+/// sub-blocks inside of the block.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#recursive-intra-prediction-process
+pub fn recursive_pred(block: &IntraBlock, edges: &IntraEdges, filter_intra_mode: u8) -> Vec<u16> {
+    let (w, h) = (block.w() as usize, block.h() as usize);
+    let mut pred = vec![0; w * h];
+    for i2 in 0..h >> 1 {
+        for j4 in 0..w >> 2 {
+            let p: [i32; 7] = std::array::from_fn(|i| {
+                (if i < 5 && i2 == 0 {
+                    edges.above_row[((j4 << 2) + i) as i32 - 1]
+                } else if i < 5 && j4 == 0 && i == 0 {
+                    edges.left_col[(i2 << 1) as i32 - 1]
+                } else if i < 5 {
+                    pred[((i2 << 1) - 1) * w + (j4 << 2) + i - 1]
+                } else if j4 == 0 {
+                    edges.left_col[((i2 << 1) + i - 5) as i32]
+                } else {
+                    pred[((i2 << 1) + i - 5) * w + (j4 << 2) - 1]
+                }) as i32
+            });
+
+            for (i, taps) in INTRA_FILTER_TAPS[filter_intra_mode as usize]
+                .iter()
+                .enumerate()
+            {
+                let pr: i32 = taps.iter().zip(p).map(|(&t, p)| t * p).sum();
+                pred[((i2 << 1) + (i >> 2)) * w + (j4 << 2) + (i & 3)] = clip1(
+                    round2signed(pr, INTRA_FILTER_SCALE_BITS as u32),
+                    block.bit_depth,
+                );
+            }
+        }
+    }
+
+    pred
+}
+
+/// Sm_Weights_Tx_WxW for a block dimension of 2^log2 samples.
+fn sm_weights(log2: u32) -> &'static [i32] {
+    match log2 {
+        2 => &SM_WEIGHTS_TX_4X4,
+        3 => &SM_WEIGHTS_TX_8X8,
+        4 => &SM_WEIGHTS_TX_16X16,
+        5 => &SM_WEIGHTS_TX_32X32,
+        _ => &SM_WEIGHTS_TX_64X64,
+    }
+}
+
+/// The smooth intra prediction process, interpolates between the above row
+/// and the bottom left sample (SMOOTH_V_PRED), between the left column and
+/// the top right sample (SMOOTH_H_PRED), or averages both (SMOOTH_PRED).
+///
+/// see: https://aomediacodec.github.io/av1-spec/#smooth-intra-prediction-process
+pub fn smooth_pred(block: &IntraBlock, edges: &IntraEdges, mode: u8) -> Vec<u16> {
+    let (w, h) = (block.w(), block.h());
+    let weights_x = sm_weights(block.log2w);
+    let weights_y = sm_weights(block.log2h);
+    let bottom_left = edges.left_col[h - 1] as i32;
+    let top_right = edges.above_row[w - 1] as i32;
+    predict(block, |i, j| {
+        let weight_y = weights_y[i as usize];
+        let weight_x = weights_x[j as usize];
+        let vertical = weight_y * edges.above_row[j] as i32 + (256 - weight_y) * bottom_left;
+        let horizontal = weight_x * edges.left_col[i] as i32 + (256 - weight_x) * top_right;
+        match mode {
+            SMOOTH_V_PRED => round2(vertical, 8),
+            SMOOTH_H_PRED => round2(horizontal, 8),
+            _ => round2(vertical + horizontal, 9),
+        }
+    })
+}
+
+/// The DC intra prediction process, the block is filled with the average of
+/// the available edges.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#dc-intra-prediction-process
+pub fn dc_pred(block: &IntraBlock, edges: &IntraEdges) -> Vec<u16> {
+    let (w, h) = (block.w(), block.h());
+    let sum_above: i32 = (0..w).map(|k| edges.above_row[k] as i32).sum();
+    let sum_left: i32 = (0..h).map(|k| edges.left_col[k] as i32).sum();
+    let avg = match (block.have_left, block.have_above) {
+        (true, true) => (sum_above + sum_left + ((w + h) >> 1)) / (w + h),
+        (true, false) => (sum_left + (h >> 1)) >> block.log2h,
+        (false, true) => (sum_above + (w >> 1)) >> block.log2w,
+        _ => 1 << (block.bit_depth - 1),
+    };
+
+    predict(block, |_, _| avg)
+}
+
+/// The filter corner process, the new value of the top left sample of a
+/// block before its edges are filtered.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#intra-filter-corner-process
+pub fn filter_corner(edges: &IntraEdges) -> u16 {
+    let s = edges.left_col[0] as i32 * 5
+        + edges.above_row[-1] as i32 * 6
+        + edges.above_row[0] as i32 * 5;
+    round2(s, 4) as u16
+}
+
+/// The intra edge filter strength selection process, the strength of the
+/// filter applied to an edge of a w x h block predicted at an angle of
+/// `delta` degrees from the edge. `filter_type` is set when a neighbour of
+/// the block uses a smooth prediction.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#intra-edge-filter-strength-selection-process
+pub fn intra_edge_filter_strength_selection(w: i32, h: i32, filter_type: bool, delta: i32) -> u8 {
+    let d = delta.abs();
+    let blk_wh = w + h;
+    if !filter_type {
+        match blk_wh {
+            ..=8 => (d >= 56) as u8,
+            9..=16 => (d >= 40) as u8,
+            17..=24 => match d {
+                32.. => 3,
+                16..=31 => 2,
+                8..=15 => 1,
+                _ => 0,
+            },
+            25..=32 => match d {
+                32.. => 3,
+                4..=31 => 2,
+                1..=3 => 1,
+                _ => 0,
+            },
+            _ => 3 * (d >= 1) as u8,
+        }
+    } else {
+        match blk_wh {
+            ..=8 => match d {
+                64.. => 2,
+                40..=63 => 1,
+                _ => 0,
+            },
+            9..=16 => match d {
+                48.. => 2,
+                20..=47 => 1,
+                _ => 0,
+            },
+            17..=24 => 3 * (d >= 4) as u8,
+            _ => 3 * (d >= 1) as u8,
+        }
+    }
+}
+
+/// The intra edge upsample selection process, only small blocks predicted
+/// at an angle close to their edge use an upsampled edge.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#intra-edge-upsample-selection-process
+pub fn intra_edge_upsample_selection(w: i32, h: i32, filter_type: bool, delta: i32) -> bool {
+    let d = delta.abs();
+    let blk_wh = w + h;
+    if d <= 0 || d >= 40 {
+        false
+    } else if filter_type {
+        blk_wh <= 8
+    } else {
+        blk_wh <= 16
+    }
+}
+
+/// The intra edge filter process, smooths the entries -1 to sz - 2 of an
+/// edge with the kernel of the strength.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#intra-edge-filter-process
+pub fn intra_edge_filter(buf: &mut Edge, sz: i32, strength: u8) {
+    if strength == 0 {
+        return;
+    }
+
+    let mut edge = [0; 2 * 64 + 1];
+    for i in 0..sz {
+        edge[i as usize] = buf[i - 1] as i32;
+    }
+
+    let kernel = &INTRA_EDGE_KERNEL[strength as usize - 1];
+    for i in 1..sz {
+        let s: i32 = (0..INTRA_EDGE_TAPS as i32)
+            .map(|j| kernel[j as usize] * edge[clip3(0, sz - 1, i - 2 + j) as usize])
+            .sum();
+        buf[i - 1] = ((s + 8) >> 4) as u16;
+    }
+}
+
+/// The intra edge upsample process, doubles the resolution of the entries
+/// -1 to numPx - 1 of an edge, the upsampled edge goes from -2 to
+/// 2 * numPx - 2.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#intra-edge-upsample-process
+pub fn intra_edge_upsample(buf: &mut Edge, num_px: i32, bit_depth: u8) {
+    let mut dup = [0; 16 + 3];
+    dup[0] = buf[-1] as i32;
+    for i in -1..num_px {
+        dup[(i + 2) as usize] = buf[i] as i32;
+    }
+    dup[(num_px + 2) as usize] = buf[num_px - 1] as i32;
+
+    buf[-2] = dup[0] as u16;
+    for i in 0..num_px {
+        let k = i as usize;
+        let s = -dup[k] + 9 * dup[k + 1] + 9 * dup[k + 2] - dup[k + 3];
+        buf[2 * i - 1] = clip1(round2(s, 4), bit_depth);
+        buf[2 * i] = dup[k + 2] as u16;
+    }
+}
+
+/// The directional intra prediction process, each sample is interpolated
+/// from the edges along the prediction angle `p_angle` in degrees. When
+/// `enable_intra_edge_filter` is set the edges are filtered or upsampled
+/// first, `filter_type` is get_filter_type( plane ).
+///
+/// see: https://aomediacodec.github.io/av1-spec/#directional-intra-prediction-process
+pub fn directional_pred(
+    block: &IntraBlock,
+    edges: &mut IntraEdges,
+    p_angle: i32,
+    enable_intra_edge_filter: bool,
+    filter_type: bool,
+) -> Vec<u16> {
+    let (w, h) = (block.w(), block.h());
+    let mut upsample_above = 0;
+    let mut upsample_left = 0;
+    if enable_intra_edge_filter {
+        if p_angle != 90 && p_angle != 180 {
+            if p_angle > 90 && p_angle < 180 && (w + h) >= 24 {
+                let corner = filter_corner(edges);
+                edges.left_col[-1] = corner;
+                edges.above_row[-1] = corner;
+            }
+
+            if block.have_above {
+                let strength =
+                    intra_edge_filter_strength_selection(w, h, filter_type, p_angle - 90);
+                let num_px = w.min((block.max_x - block.x) as i32 + 1)
+                    + if p_angle < 90 { h } else { 0 }
+                    + 1;
+                intra_edge_filter(&mut edges.above_row, num_px, strength);
+            }
+
+            if block.have_left {
+                let strength =
+                    intra_edge_filter_strength_selection(w, h, filter_type, p_angle - 180);
+                let num_px = h.min((block.max_y - block.y) as i32 + 1)
+                    + if p_angle > 180 { w } else { 0 }
+                    + 1;
+                intra_edge_filter(&mut edges.left_col, num_px, strength);
+            }
+        }
+
+        if intra_edge_upsample_selection(w, h, filter_type, p_angle - 90) {
+            upsample_above = 1;
+            let num_px = w + if p_angle < 90 { h } else { 0 };
+            intra_edge_upsample(&mut edges.above_row, num_px, block.bit_depth);
+        }
+
+        if intra_edge_upsample_selection(w, h, filter_type, p_angle - 180) {
+            upsample_left = 1;
+            let num_px = h + if p_angle > 180 { w } else { 0 };
+            intra_edge_upsample(&mut edges.left_col, num_px, block.bit_depth);
+        }
+    }
+
+    let dx = if p_angle < 90 {
+        DR_INTRA_DERIVATIVE[p_angle as usize]
+    } else if p_angle > 90 && p_angle < 180 {
+        DR_INTRA_DERIVATIVE[(180 - p_angle) as usize]
+    } else {
+        0
+    };
+
+    let dy = if p_angle > 90 && p_angle < 180 {
+        DR_INTRA_DERIVATIVE[(p_angle - 90) as usize]
+    } else if p_angle > 180 {
+        DR_INTRA_DERIVATIVE[(270 - p_angle) as usize]
+    } else {
+        0
+    };
+
+    let (above, left) = (&edges.above_row, &edges.left_col);
+    let interpolate = |edge: &Edge, base: i32, shift: i32| {
+        round2(
+            edge[base] as i32 * (32 - shift) + edge[base + 1] as i32 * shift,
+            5,
+        )
+    };
+
+    predict(block, |i, j| {
+        if p_angle < 90 {
+            let idx = (i + 1) * dx;
+            let base = (idx >> (6 - upsample_above)) + (j << upsample_above);
+            let shift = ((idx << upsample_above) >> 1) & 0x1f;
+            let max_base_x = (w + h - 1) << upsample_above;
+            if base < max_base_x {
+                interpolate(above, base, shift)
+            } else {
+                above[max_base_x] as i32
+            }
+        } else if p_angle > 90 && p_angle < 180 {
+            let idx = (j << 6) - (i + 1) * dx;
+            let base = idx >> (6 - upsample_above);
+            if base >= -(1 << upsample_above) {
+                let shift = ((idx << upsample_above) >> 1) & 0x1f;
+                interpolate(above, base, shift)
+            } else {
+                let idx = (i << 6) - (j + 1) * dy;
+                let base = idx >> (6 - upsample_left);
+                let shift = ((idx << upsample_left) >> 1) & 0x1f;
+                interpolate(left, base, shift)
+            }
+        } else if p_angle > 180 {
+            let idx = (j + 1) * dy;
+            let base = (idx >> (6 - upsample_left)) + (i << upsample_left);
+            let shift = ((idx << upsample_left) >> 1) & 0x1f;
+            interpolate(left, base, shift)
+        } else if p_angle == 90 {
+            above[j] as i32
+        } else {
+            left[i] as i32
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{
+        ANGLE_STEP, D45_PRED, H_PRED, MAX_ANGLE_DELTA, PAETH_PRED, SMOOTH_PRED, TX_SIZES_ALL,
+        V_PRED,
+    };
+    use crate::tables::{TX_HEIGHT_LOG2, TX_WIDTH_LOG2};
+
+    fn intra_block(log2w: u32, log2h: u32, have_left: bool, have_above: bool) -> IntraBlock {
+        IntraBlock {
+            x: 16,
+            y: 16,
+            log2w,
+            log2h,
+            have_left,
+            have_above,
+            max_x: 255,
+            max_y: 255,
+            bit_depth: 8,
+            ..Default::default()
+        }
+    }
+
+    // edges with the above row set to `above` and the left column to `left`,
+    // the top left sample is `corner`.
+    fn edges(above: &[u16], left: &[u16], corner: u16) -> IntraEdges {
+        let mut edges = IntraEdges::default();
+        for (i, &v) in above.iter().enumerate() {
+            edges.above_row[i as i32] = v;
+        }
+        for (i, &v) in left.iter().enumerate() {
+            edges.left_col[i as i32] = v;
+        }
+        edges.above_row[-1] = corner;
+        edges.left_col[-1] = corner;
+        edges
+    }
+
+    fn frame() -> Plane {
+        let mut plane = Plane::new(64, 64);
+        for y in 0..64 {
+            for x in 0..64 {
+                plane.set(x, y, (x + 64 * y) as u16 % 1000);
+            }
+        }
+        plane
+    }
+
+    #[test]
+    fn edges_of_unavailable_neighbours() {
+        for bit_depth in [8, 10, 12] {
+            let block = IntraBlock {
+                bit_depth,
+                ..intra_block(2, 3, false, false)
+            };
+            let edges = IntraEdges::new(&frame(), &block);
+            let mid = 1 << (bit_depth - 1);
+            assert!((0..12).all(|i| edges.above_row[i] == mid - 1));
+            assert!((0..12).all(|i| edges.left_col[i] == mid + 1));
+            assert_eq!(edges.above_row[-1], mid);
+            assert_eq!(edges.left_col[-1], mid);
+        }
+    }
+
+    #[test]
+    fn edges_of_one_neighbour() {
+        let frame = frame();
+        let edges = IntraEdges::new(&frame, &intra_block(2, 2, false, true));
+        assert!((0..8).all(|i| edges.above_row[i] == frame.get(16 + i.min(3) as usize, 15)));
+        assert!((0..8).all(|i| edges.left_col[i] == frame.get(16, 15)));
+        assert_eq!(edges.above_row[-1], frame.get(16, 15));
+
+        let edges = IntraEdges::new(&frame, &intra_block(2, 2, true, false));
+        assert!((0..8).all(|i| edges.left_col[i] == frame.get(15, 16 + i.min(3) as usize)));
+        assert!((0..8).all(|i| edges.above_row[i] == frame.get(15, 16)));
+        assert_eq!(edges.left_col[-1], frame.get(15, 16));
+    }
+
+    #[test]
+    fn edges_replicate_past_the_limits() {
+        let frame = frame();
+        let edges = IntraEdges::new(&frame, &intra_block(2, 2, true, true));
+        let above: Vec<u16> = (0..8).map(|i| edges.above_row[i]).collect();
+        assert_eq!(
+            above,
+            [16, 17, 18, 19, 19, 19, 19, 19].map(|x| frame.get(x, 15))
+        );
+        let left: Vec<u16> = (0..8).map(|i| edges.left_col[i]).collect();
+        assert_eq!(
+            left,
+            [16, 17, 18, 19, 19, 19, 19, 19].map(|y| frame.get(15, y))
+        );
+        assert_eq!(edges.above_row[-1], frame.get(15, 15));
+
+        let block = IntraBlock {
+            have_above_rt: true,
+            have_below_lft: true,
+            max_x: 21,
+            ..intra_block(2, 2, true, true)
+        };
+        let edges = IntraEdges::new(&frame, &block);
+        let above: Vec<u16> = (0..8).map(|i| edges.above_row[i]).collect();
+        assert_eq!(
+            above,
+            [16, 17, 18, 19, 20, 21, 21, 21].map(|x| frame.get(x, 15))
+        );
+        let left: Vec<u16> = (0..8).map(|i| edges.left_col[i]).collect();
+        assert_eq!(
+            left,
+            [16, 17, 18, 19, 20, 21, 22, 23].map(|y| frame.get(15, y))
+        );
+    }
+
+    #[test]
+    fn dc_averages_the_available_edges() {
+        let edges = edges(&[10, 20, 30, 40], &[1, 2, 3, 4, 5, 6, 7, 8], 0);
+        let pred = dc_pred(&intra_block(2, 3, true, true), &edges);
+        assert_eq!(pred.len(), 32);
+        assert!(pred.iter().all(|&v| v == (136 + 6) / 12));
+        assert!(dc_pred(&intra_block(2, 3, true, false), &edges)
+            .iter()
+            .all(|&v| v == (36 + 4) >> 3));
+        assert!(dc_pred(&intra_block(2, 3, false, true), &edges)
+            .iter()
+            .all(|&v| v == (100 + 2) >> 2));
+
+        for bit_depth in [8, 10, 12] {
+            let block = IntraBlock {
+                bit_depth,
+                ..intra_block(4, 2, false, false)
+            };
+            assert!(dc_pred(&block, &edges)
+                .iter()
+                .all(|&v| v == 1 << (bit_depth - 1)));
+        }
+    }
+
+    #[test]
+    fn paeth_picks_the_closest_neighbour() {
+        let edges = edges(&[100, 50, 200, 120], &[100, 60, 10, 255], 100);
+        let pred = paeth_pred(&intra_block(2, 2, true, true), &edges);
+        // base = top + left - 100, ties go to left then top.
+        assert_eq!(&pred[..4], [100, 50, 200, 120]);
+        assert_eq!(&pred[4..8], [60, 50, 200, 60]);
+        assert_eq!(&pred[8..12], [10, 10, 100, 10]);
+        assert_eq!(&pred[12..], [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn smooth_interpolates_towards_the_far_corners() {
+        let edges = edges(&[200; 8], &[40; 8], 0);
+        let block = intra_block(2, 3, true, true);
+        let pred = smooth_pred(&block, &edges, SMOOTH_V_PRED);
+        for (i, &weight) in SM_WEIGHTS_TX_8X8.iter().enumerate() {
+            let expected = round2(weight * 200 + (256 - weight) * 40, 8) as u16;
+            assert!(pred[i * 4..(i + 1) * 4].iter().all(|&v| v == expected));
+        }
+
+        let pred = smooth_pred(&block, &edges, SMOOTH_H_PRED);
+        for (j, &weight) in SM_WEIGHTS_TX_4X4.iter().enumerate() {
+            let expected = round2(weight * 40 + (256 - weight) * 200, 8) as u16;
+            assert!(pred.iter().skip(j).step_by(4).all(|&v| v == expected));
+        }
+
+        let pred = smooth_pred(&block, &edges, SMOOTH_PRED);
+        let v = round2(255 * 200 + 40 + 255 * 40 + 200, 9) as u16;
+        assert_eq!(pred[0], v);
+    }
+
+    #[test]
+    fn vertical_horizontal_and_diagonals() {
+        let above: Vec<u16> = (0..16).map(|i| 100 + i).collect();
+        let left: Vec<u16> = (0..16).map(|i| 200 + i).collect();
+        let block = intra_block(3, 3, true, true);
+        let edges = edges(&above, &left, 50);
+
+        let pred = directional_pred(&block, &mut edges.clone(), 90, false, false);
+        let pred_h = directional_pred(&block, &mut edges.clone(), 180, false, false);
+        let pred_45 = directional_pred(&block, &mut edges.clone(), 45, false, false);
+        let pred_135 = directional_pred(&block, &mut edges.clone(), 135, false, false);
+        for i in 0..8 {
+            for j in 0..8 {
+                let k = (i * 8 + j) as usize;
+                assert_eq!(pred[k], above[j as usize]);
+                assert_eq!(pred_h[k], left[i as usize]);
+                assert_eq!(pred_45[k], above[(i + j + 1) as usize]);
+                let diagonal = match i - j {
+                    0 => 50,
+                    d if d < 0 => above[(-d - 1) as usize],
+                    d => left[(d - 1) as usize],
+                };
+                assert_eq!(pred_135[k], diagonal, "{i} {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn interpolates_between_edge_samples() {
+        let above: Vec<u16> = (0..16).map(|i| 32 * i).collect();
+        let left: Vec<u16> = (0..16).map(|i| 64 * i).collect();
+        let block = intra_block(2, 2, true, true);
+        let edges = edges(&above, &left, 0);
+
+        // dx is 102, (i + 1) * dx / 64 + j samples along the above row.
+        let pred = directional_pred(&block, &mut edges.clone(), 32, false, false);
+        for i in 0..4 {
+            for j in 0..4 {
+                let pos = (i + 1) * 102 + (j << 6);
+                let expected = (pos >> 1).min(32 * 7);
+                assert_eq!(pred[(i * 4 + j) as usize] as i32, expected, "{i} {j}");
+            }
+        }
+
+        // dy is 27, (j + 1) * dy / 64 + i samples down the left column.
+        let pred = directional_pred(&block, &mut edges.clone(), 203, false, false);
+        for i in 0..4 {
+            for j in 0..4 {
+                let pos = (j + 1) * 27 + (i << 6);
+                assert_eq!(pred[(i * 4 + j) as usize] as i32, pos & !1, "{i} {j}");
+            }
+        }
+    }
+
+    #[test]
+    fn flat_edges_give_flat_predictions() {
+        for bit_depth in [8, 10, 12] {
+            let value = (1 << bit_depth) - 1 - 5;
+            for tx_size in 0..TX_SIZES_ALL as usize {
+                let block = IntraBlock {
+                    bit_depth,
+                    have_above_rt: true,
+                    have_below_lft: true,
+                    ..intra_block(
+                        TX_WIDTH_LOG2[tx_size] as u32,
+                        TX_HEIGHT_LOG2[tx_size] as u32,
+                        true,
+                        true,
+                    )
+                };
+                let len = 1 << (block.log2w + block.log2h);
+                let edges = edges(&[value; 128], &[value; 128], value);
+                let flat = |pred: Vec<u16>| pred.len() == len && pred.iter().all(|&v| v == value);
+                assert!(flat(dc_pred(&block, &edges)));
+                assert!(flat(paeth_pred(&block, &edges)));
+                for mode in [SMOOTH_PRED, SMOOTH_V_PRED, SMOOTH_H_PRED] {
+                    assert!(flat(smooth_pred(&block, &edges, mode)));
+                }
+
+                for mode in V_PRED..PAETH_PRED {
+                    if mode >= SMOOTH_PRED {
+                        continue;
+                    }
+
+                    for delta in -(MAX_ANGLE_DELTA as i32)..=MAX_ANGLE_DELTA as i32 {
+                        let p_angle = MODE_TO_ANGLE[mode as usize] + delta * ANGLE_STEP as i32;
+                        for (enable, filter_type) in [(false, false), (true, false), (true, true)] {
+                            let mut edges = edges.clone();
+                            let pred =
+                                directional_pred(&block, &mut edges, p_angle, enable, filter_type);
+                            assert!(flat(pred), "tx {tx_size} angle {p_angle}");
+                        }
+                    }
+                }
+            }
+        }
+
+        assert_eq!(MODE_TO_ANGLE[D45_PRED as usize], 45);
+        assert_eq!(MODE_TO_ANGLE[H_PRED as usize], 180);
+    }
+
+    #[test]
+    fn edge_filter_selection() {
+        assert_eq!(intra_edge_filter_strength_selection(4, 4, false, 55), 0);
+        assert_eq!(intra_edge_filter_strength_selection(4, 4, false, -56), 1);
+        assert_eq!(intra_edge_filter_strength_selection(8, 8, false, 39), 0);
+        assert_eq!(intra_edge_filter_strength_selection(8, 8, false, 40), 1);
+        assert_eq!(intra_edge_filter_strength_selection(8, 16, false, 15), 1);
+        assert_eq!(intra_edge_filter_strength_selection(8, 16, false, 16), 2);
+        assert_eq!(intra_edge_filter_strength_selection(16, 16, false, 3), 1);
+        assert_eq!(intra_edge_filter_strength_selection(16, 16, false, 4), 2);
+        assert_eq!(intra_edge_filter_strength_selection(16, 16, false, -87), 3);
+        assert_eq!(intra_edge_filter_strength_selection(32, 4, false, 3), 3);
+        assert_eq!(intra_edge_filter_strength_selection(4, 4, true, 64), 2);
+        assert_eq!(intra_edge_filter_strength_selection(8, 8, true, 19), 0);
+        assert_eq!(intra_edge_filter_strength_selection(8, 8, true, 20), 1);
+        assert_eq!(intra_edge_filter_strength_selection(16, 8, true, 3), 0);
+        assert_eq!(intra_edge_filter_strength_selection(16, 8, true, 4), 3);
+
+        assert!(!intra_edge_upsample_selection(4, 4, false, 0));
+        assert!(!intra_edge_upsample_selection(4, 4, false, 40));
+        assert!(intra_edge_upsample_selection(8, 8, false, -39));
+        assert!(!intra_edge_upsample_selection(8, 16, false, 3));
+        assert!(intra_edge_upsample_selection(4, 4, true, 3));
+        assert!(!intra_edge_upsample_selection(8, 4, true, 3));
+    }
+
+    #[test]
+    fn edge_filter_kernels() {
+        for (strength, kernel) in INTRA_EDGE_KERNEL.iter().enumerate() {
+            let mut edge = Edge::default();
+            edge[4] = 160;
+            intra_edge_filter(&mut edge, 12, strength as u8 + 1);
+            // edge[ i - 1 ] is filtered around the original edge[ i - 1 ].
+            for i in -1..10 {
+                let k = 4 - i + 2;
+                let expected = if (0..5).contains(&k) {
+                    (kernel[k as usize] * 160 + 8) >> 4
+                } else {
+                    0
+                };
+                assert_eq!(edge[i] as i32, expected, "strength {strength} {i}");
+            }
+        }
+
+        let mut edge = Edge::default();
+        edge[-1] = 160;
+        intra_edge_filter(&mut edge, 6, 3);
+        // the first sample is not filtered, the taps before it repeat it.
+        assert_eq!(edge[-1], 160);
+        assert_eq!(edge[0], ((2 + 4) * 160 + 8) >> 4);
+    }
+
+    #[test]
+    fn edge_upsampling() {
+        let mut edge = Edge::default();
+        for i in -1..8 {
+            edge[i] = (64 + 32 * i) as u16;
+        }
+
+        intra_edge_upsample(&mut edge, 8, 8);
+        // a ramp is upsampled to a ramp at twice the resolution, except at
+        // the ends where the edge is repeated.
+        assert_eq!(edge[-2], 32);
+        assert_eq!(edge[-1], round2(-32 + 9 * 32 + 9 * 64 - 96, 4) as u16);
+        for i in 0..8 {
+            assert_eq!(edge[2 * i], (64 + 32 * i) as u16);
+        }
+        for i in 1..7 {
+            assert_eq!(edge[2 * i - 1], (48 + 32 * i) as u16);
+        }
+
+        for bit_depth in [8, 10, 12] {
+            let max = (1 << bit_depth) - 1;
+            let mut edge = Edge::default();
+            edge[0] = max;
+            edge[1] = max;
+            intra_edge_upsample(&mut edge, 4, bit_depth);
+            assert_eq!(edge[1], max);
+            assert_eq!(edge[-1], round2(9 * max as i32 - max as i32, 4) as u16);
+            assert_eq!(edge[3], round2(9 * max as i32 - max as i32, 4) as u16);
+        }
+    }
+
+    #[test]
+    fn filtered_and_upsampled_edges() {
+        let above: Vec<u16> = (0..16).map(|i| 400 + 40 * (i % 3)).collect();
+        let left: Vec<u16> = (0..16).map(|i| 800 - 30 * (i % 4)).collect();
+        let block = IntraBlock {
+            bit_depth: 10,
+            ..intra_block(2, 2, true, true)
+        };
+
+        // upsampling doubles the resolution of the above row for a 4x4 block
+        // close to 90 degrees.
+        let mut filtered = edges(&above, &left, 500);
+        let pred = directional_pred(&block, &mut filtered, 87, true, false);
+        let mut upsampled = edges(&above, &left, 500);
+        intra_edge_upsample(&mut upsampled.above_row, 8, 10);
+        assert_eq!(filtered.above_row, upsampled.above_row);
+        for i in 0..4 {
+            for j in 0..4 {
+                let idx = (i + 1) * 3;
+                let base = (idx >> 5) + (j << 1);
+                let shift = (idx << 1 >> 1) & 0x1f;
+                let expected = round2(
+                    upsampled.above_row[base] as i32 * (32 - shift)
+                        + upsampled.above_row[base + 1] as i32 * shift,
+                    5,
+                );
+                assert_eq!(pred[(i * 4 + j) as usize] as i32, expected);
+            }
+        }
+
+        // larger blocks filter the corner and both edges.
+        let block = IntraBlock {
+            bit_depth: 10,
+            ..intra_block(4, 3, true, true)
+        };
+        let mut filtered = edges(&above, &left, 500);
+        directional_pred(&block, &mut filtered, 135, true, false);
+        let corner = round2(800 * 5 + 500 * 6 + 400 * 5, 4) as u16;
+        let mut expected = edges(&above, &left, corner);
+        intra_edge_filter(&mut expected.above_row, 17, 3);
+        intra_edge_filter(&mut expected.left_col, 9, 3);
+        assert_eq!(filtered.above_row, expected.above_row);
+        assert_eq!(filtered.left_col, expected.left_col);
+    }
+
+    #[test]
+    fn filter_intra_taps_keep_flat_edges_flat() {
+        for taps in INTRA_FILTER_TAPS.iter().flatten() {
+            assert_eq!(taps.iter().sum::<i32>(), 1 << INTRA_FILTER_SCALE_BITS);
+        }
+
+        for bit_depth in [8, 10, 12] {
+            let value = (1 << bit_depth) - 3;
+            let edges = edges(&[value; 64], &[value; 64], value);
+            for (log2w, log2h) in [(2, 2), (3, 2), (2, 4), (4, 4), (5, 3), (5, 5)] {
+                let block = IntraBlock {
+                    bit_depth,
+                    ..intra_block(log2w, log2h, true, true)
+                };
+                for mode in 0..INTRA_FILTER_MODES {
+                    let pred = recursive_pred(&block, &edges, mode);
+                    assert_eq!(pred.len(), 1 << (log2w + log2h));
+                    assert!(pred.iter().all(|&v| v == value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn filter_intra_predicts_4x2_sub_blocks() {
+        let mut rng = 0x2545f4914f6cdd1d_u64;
+        let mut next = || {
+            rng ^= rng << 13;
+            rng ^= rng >> 7;
+            rng ^= rng << 17;
+            (rng % 1024) as u16
+        };
+
+        let block = IntraBlock {
+            bit_depth: 10,
+            ..intra_block(4, 3, true, true)
+        };
+        let above: Vec<u16> = (0..24).map(|_| next()).collect();
+        let left: Vec<u16> = (0..24).map(|_| next()).collect();
+        let edges = edges(&above, &left, next());
+        for mode in 0..INTRA_FILTER_MODES {
+            // the edges and the prediction in a single grid, the sample of row
+            // i and column j of the prediction is grid[ i + 1 ][ j + 1 ].
+            let mut grid = [[0; 17]; 9];
+            for (j, v) in grid[0].iter_mut().enumerate() {
+                *v = edges.above_row[j as i32 - 1] as i32;
+            }
+            for (i, row) in grid.iter_mut().enumerate() {
+                row[0] = edges.left_col[i as i32 - 1] as i32;
+            }
+
+            for r in (0..8).step_by(2) {
+                for c in (0..16).step_by(4) {
+                    let p = [
+                        grid[r][c],
+                        grid[r][c + 1],
+                        grid[r][c + 2],
+                        grid[r][c + 3],
+                        grid[r][c + 4],
+                        grid[r + 1][c],
+                        grid[r + 2][c],
+                    ];
+                    for (k, taps) in INTRA_FILTER_TAPS[mode as usize].iter().enumerate() {
+                        let pr: i32 = taps.iter().zip(p).map(|(&t, p)| t * p).sum();
+                        grid[r + 1 + k / 4][c + 1 + k % 4] = round2signed(pr, 4).clamp(0, 1023);
+                    }
+                }
+            }
+
+            let pred = recursive_pred(&block, &edges, mode);
+            for i in 0..8 {
+                for j in 0..16 {
+                    assert_eq!(pred[i * 16 + j] as i32, grid[i + 1][j + 1]);
+                }
+            }
+        }
+
+        // FILTER_V_PRED, the first sample mostly follows the sample above it.
+        let edges = self::edges(&[100; 8], &[50; 8], 100);
+        let pred = recursive_pred(&intra_block(2, 2, true, true), &edges, 1);
+        assert_eq!(pred[0], ((-10 * 100 + 16 * 100 + 10 * 50 + 8) >> 4) as u16);
+    }
+}
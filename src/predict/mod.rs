@@ -0,0 +1,9 @@
+//! The prediction processes, which predict the samples of a block before the
+//! residual is added to them.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#prediction-processes
+
+pub mod cfl;
+pub mod compound;
+pub mod inter;
+pub mod intra;
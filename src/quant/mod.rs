@@ -0,0 +1,344 @@
+pub mod qm;
+pub mod tables;
+
+use qm::QUANTIZER_MATRIX;
+use tables::{AC_QLOOKUP, DC_QLOOKUP};
+
+use crate::{
+    constants::{AOM_QM_BITS, IDTX, NUM_QM_LEVELS, SEG_LVL_ALT_Q, TX_SIZES_ALL},
+    obu::frame_header::{QuantizationParams, SegmentationParams, UncompressedHeader},
+    tables::{TX_HEIGHT, TX_WIDTH},
+    tile::coeffs::Coefficients,
+    util::{clip3, round2},
+};
+
+/// The offset of the matrix of each transform size in Quantizer_Matrix, the
+/// transform sizes with a 64 sample side use the matrix of their coded
+/// area.
+pub const QM_OFFSET: [u16; TX_SIZES_ALL as usize] = [
+    0, 16, 80, 336, 336, 1360, 1392, 1424, 1552, 1680, 2192, 336, 336, 2704, 2768, 2832, 3088,
+    1680, 2192,
+];
+
+/// dc_q( b )
+pub fn dc_q(bit_depth: u8, b: i32) -> i32 {
+    DC_QLOOKUP[((bit_depth - 8) >> 1) as usize][clip3(0, 255, b) as usize] as i32
+}
+
+/// ac_q( b )
+pub fn ac_q(bit_depth: u8, b: i32) -> i32 {
+    AC_QLOOKUP[((bit_depth - 8) >> 1) as usize][clip3(0, 255, b) as usize] as i32
+}
+
+/// get_qidx( ignoreDeltaQ, segmentId ), the quantizer index of a segment.
+///
+/// `current_q_index` is the CurrentQIndex of the block when the block level
+/// delta is used, None for get_qidx( 1, segmentId ) or when delta_q_present
+/// is equal to 0.
+pub fn get_qidx(
+    quantization_params: &QuantizationParams,
+    segmentation_params: &SegmentationParams,
+    current_q_index: Option<i32>,
+    segment_id: u8,
+) -> i32 {
+    let qindex = current_q_index.unwrap_or(quantization_params.base_q_idx as i32);
+    if segmentation_params.feature_active(segment_id, SEG_LVL_ALT_Q) {
+        let data = segmentation_params.feature_data[segment_id as usize][SEG_LVL_ALT_Q as usize];
+        clip3(0, 255, qindex + data as i32)
+    } else {
+        qindex
+    }
+}
+
+/// LosslessArray[ segmentId ], the segment is coded losslessly when its
+/// quantizer index and all of the DC and chroma quantizer deltas are zero.
+pub fn is_lossless(
+    quantization_params: &QuantizationParams,
+    segmentation_params: &SegmentationParams,
+    segment_id: u8,
+) -> bool {
+    let qp = quantization_params;
+    get_qidx(qp, segmentation_params, None, segment_id) == 0
+        && qp.delta_q_y_dc == 0
+        && qp.delta_q_u_ac == 0
+        && qp.delta_q_u_dc == 0
+        && qp.delta_q_v_ac == 0
+        && qp.delta_q_v_dc == 0
+}
+
+/// The dequantization of the coefficients of one plane of a segment.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#dequantization-functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dequantizer {
+    /// get_dc_quant( plane )
+    pub dc_quant: i32,
+    /// get_ac_quant( plane )
+    pub ac_quant: i32,
+    /// SegQMLevel of the plane, NUM_QM_LEVELS - 1 when the quantizer matrix
+    /// is not used.
+    pub qm_level: u8,
+    pub plane: usize,
+    pub bit_depth: u8,
+}
+
+impl Dequantizer {
+    /// The quantizers of the plane for the quantizer index `qindex` of the
+    /// segment, which is get_qidx( 0, segmentId ).
+    pub fn new(
+        frame_header: &UncompressedHeader,
+        plane: usize,
+        segment_id: u8,
+        qindex: i32,
+        bit_depth: u8,
+    ) -> Self {
+        let qp = &frame_header.quantization_params;
+        let (dc_delta, ac_delta) = match plane {
+            0 => (qp.delta_q_y_dc, 0),
+            1 => (qp.delta_q_u_dc, qp.delta_q_u_ac),
+            _ => (qp.delta_q_v_dc, qp.delta_q_v_ac),
+        };
+
+        let qm_level = if qp.using_qmatrix {
+            frame_header.seg_qm_level[plane][segment_id as usize]
+        } else {
+            NUM_QM_LEVELS - 1
+        };
+
+        Self {
+            dc_quant: dc_q(bit_depth, qindex + dc_delta as i32),
+            ac_quant: ac_q(bit_depth, qindex + ac_delta as i32),
+            qm_level,
+            plane,
+            bit_depth,
+        }
+    }
+
+    /// Dequantizes the coefficients of a transform block into Dequant, in
+    /// the raster order of the coded area of the transform block.
+    ///
+    /// The quantizer matrix only applies to transform types with a non
+    /// identity transform in both directions, and the result is clamped to
+    /// the range of a signed 8 + BitDepth bit integer.
+    pub fn dequantize(&self, tx_size: u8, coefficients: &Coefficients) -> Vec<i32> {
+        let area = TX_WIDTH[tx_size as usize] as u32 * TX_HEIGHT[tx_size as usize] as u32;
+        let dq_denom = (area > 256) as u32 + (area > 1024) as u32;
+        let qm = (self.qm_level < NUM_QM_LEVELS - 1 && coefficients.tx_type < IDTX).then(|| {
+            let offset = QM_OFFSET[tx_size as usize] as usize;
+            &QUANTIZER_MATRIX[self.qm_level as usize][(self.plane > 0) as usize][offset..]
+        });
+
+        let max = (1 << (7 + self.bit_depth)) - 1;
+        let min = -(1 << (7 + self.bit_depth));
+        coefficients
+            .quant
+            .iter()
+            .enumerate()
+            .map(|(pos, &quant)| {
+                if quant == 0 {
+                    return 0;
+                }
+
+                let mut q = if pos == 0 {
+                    self.dc_quant
+                } else {
+                    self.ac_quant
+                };
+
+                if let Some(qm) = qm {
+                    q = round2(q * qm[pos] as i32, AOM_QM_BITS as u32);
+                }
+
+                let dq = ((quant.unsigned_abs() as i64 * q as i64) & 0xFFFFFF) >> dq_denom;
+                let dq = if quant < 0 { -dq } else { dq } as i32;
+                clip3(min, max, dq)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{DCT_DCT, QM_TOTAL_SIZE, TX_16X16, TX_32X32, TX_4X4, TX_64X64};
+
+    #[test]
+    fn lossless_segments() {
+        let mut qp = QuantizationParams::default();
+        let mut sp = SegmentationParams::default();
+        assert!(is_lossless(&qp, &sp, 0));
+
+        qp.base_q_idx = 10;
+        sp.segmentation_enabled = true;
+        sp.feature_enabled[2][SEG_LVL_ALT_Q as usize] = true;
+        sp.feature_data[2][SEG_LVL_ALT_Q as usize] = -20;
+        sp.feature_enabled[3][SEG_LVL_ALT_Q as usize] = true;
+        sp.feature_data[3][SEG_LVL_ALT_Q as usize] = -5;
+        assert!(!is_lossless(&qp, &sp, 0));
+        assert!(is_lossless(&qp, &sp, 2));
+        assert!(!is_lossless(&qp, &sp, 3));
+
+        qp.delta_q_v_dc = 1;
+        assert!(!is_lossless(&qp, &sp, 2));
+    }
+
+    #[test]
+    fn lossless_quantizers() {
+        for bit_depth in [8, 10, 12] {
+            assert_eq!(dc_q(bit_depth, 0), 4);
+            assert_eq!(ac_q(bit_depth, 0), 4);
+        }
+    }
+
+    #[test]
+    fn qidx_of_segments_and_delta_q() {
+        let qp = QuantizationParams {
+            base_q_idx: 100,
+            ..Default::default()
+        };
+
+        let mut sp = SegmentationParams::default();
+        sp.feature_enabled[1][SEG_LVL_ALT_Q as usize] = true;
+        sp.feature_data[1][SEG_LVL_ALT_Q as usize] = 50;
+        sp.feature_enabled[2][SEG_LVL_ALT_Q as usize] = true;
+        sp.feature_data[2][SEG_LVL_ALT_Q as usize] = -255;
+
+        // the features are ignored while segmentation is disabled.
+        assert_eq!(get_qidx(&qp, &sp, None, 1), 100);
+        assert_eq!(get_qidx(&qp, &sp, Some(120), 1), 120);
+
+        // the segment delta is added to CurrentQIndex, or to base_q_idx
+        // when the block level delta is ignored, and clipped to 0..=255.
+        sp.segmentation_enabled = true;
+        assert_eq!(get_qidx(&qp, &sp, None, 0), 100);
+        assert_eq!(get_qidx(&qp, &sp, None, 1), 150);
+        assert_eq!(get_qidx(&qp, &sp, Some(120), 1), 170);
+        assert_eq!(get_qidx(&qp, &sp, Some(230), 1), 255);
+        assert_eq!(get_qidx(&qp, &sp, Some(120), 2), 0);
+    }
+
+    #[test]
+    fn quantizers_of_each_bit_depth() {
+        let dc: Vec<_> = [8, 10, 12].map(|b| [100, 255].map(|q| dc_q(b, q))).into();
+        assert_eq!(dc, [[93, 1336], [369, 5347], [1469, 21387]]);
+        let ac: Vec<_> = [8, 10, 12].map(|b| [100, 255].map(|q| ac_q(b, q))).into();
+        assert_eq!(ac, [[112, 1828], [441, 7312], [1758, 29247]]);
+
+        // the index is clipped to the table.
+        assert_eq!(dc_q(10, -20), dc_q(10, 0));
+        assert_eq!(ac_q(12, 300), ac_q(12, 255));
+    }
+
+    #[test]
+    fn qm_levels_of_the_planes_and_segments() {
+        let mut frame_header = UncompressedHeader::default();
+        let qp = &mut frame_header.quantization_params;
+        qp.delta_q_u_dc = -10;
+        qp.delta_q_v_ac = 5;
+        frame_header.seg_qm_level[0][1] = 5;
+        frame_header.seg_qm_level[1][1] = 7;
+        frame_header.seg_qm_level[2][1] = 9;
+
+        // without quantizer matrices every plane has the last level.
+        for plane in 0..3 {
+            let dequantizer = Dequantizer::new(&frame_header, plane, 1, 100, 8);
+            assert_eq!(dequantizer.qm_level, NUM_QM_LEVELS - 1);
+        }
+
+        frame_header.quantization_params.using_qmatrix = true;
+        let dequantizers = [0, 1, 2].map(|plane| Dequantizer::new(&frame_header, plane, 1, 100, 8));
+        assert_eq!(dequantizers.map(|d| d.qm_level), [5, 7, 9]);
+        assert_eq!(
+            dequantizers.map(|d| (d.dc_quant, d.ac_quant)),
+            [(93, 112), (dc_q(8, 90), 112), (93, ac_q(8, 105))]
+        );
+
+        let dequantizer = Dequantizer::new(&frame_header, 2, 0, 100, 8);
+        assert_eq!(dequantizer.qm_level, 0);
+    }
+
+    #[test]
+    fn quantizer_matrices_do_not_overlap() {
+        // the distinct offsets followed by the coded area of their size
+        // cover Quantizer_Matrix.
+        let mut matrices: Vec<_> = (0..TX_SIZES_ALL as usize)
+            .map(|tx_size| {
+                let w = TX_WIDTH[tx_size].min(32) as u16;
+                let h = TX_HEIGHT[tx_size].min(32) as u16;
+                (QM_OFFSET[tx_size], w * h)
+            })
+            .collect();
+        matrices.sort_unstable();
+        matrices.dedup();
+
+        let mut end = 0;
+        for (offset, area) in matrices {
+            assert_eq!(offset, end);
+            end += area;
+        }
+
+        assert_eq!(end, QM_TOTAL_SIZE);
+    }
+
+    #[test]
+    fn dequantize_with_quantizer_matrices() {
+        let coefficients = |tx_type| Coefficients {
+            tx_type,
+            eob: 16,
+            quant: vec![1; 16],
+        };
+
+        // level 0 weights the 4x4 luma DC by 32 and the last position by 200,
+        // with AOM_QM_BITS fractional bits.
+        let mut dequantizer = Dequantizer {
+            dc_quant: 93,
+            ac_quant: 112,
+            qm_level: 0,
+            plane: 0,
+            bit_depth: 8,
+        };
+        let dequant = dequantizer.dequantize(TX_4X4, &coefficients(DCT_DCT));
+        assert_eq!((dequant[0], dequant[15]), (93, 700));
+
+        // chroma has its own matrices, identity transforms do not use them.
+        dequantizer.plane = 1;
+        let dequant = dequantizer.dequantize(TX_4X4, &coefficients(DCT_DCT));
+        assert_eq!(dequant[15], 382);
+        let dequant = dequantizer.dequantize(TX_4X4, &coefficients(IDTX));
+        assert_eq!(dequant[15], 112);
+    }
+
+    #[test]
+    fn dequantize_large_transforms_and_clamp() {
+        let mut dequantizer = Dequantizer {
+            dc_quant: 93,
+            ac_quant: 112,
+            qm_level: NUM_QM_LEVELS - 1,
+            plane: 0,
+            bit_depth: 8,
+        };
+
+        // dqDenom is 2 above 256 coefficients and 4 above 1024.
+        let quant = |quant: Vec<i32>| Coefficients {
+            tx_type: DCT_DCT,
+            eob: 1,
+            quant,
+        };
+
+        for (tx_size, dequant) in [(TX_16X16, 336), (TX_32X32, 168), (TX_64X64, 84)] {
+            let dq = dequantizer.dequantize(tx_size, &quant(vec![0, -3]));
+            assert_eq!(dq, [0, -dequant], "{tx_size}");
+        }
+
+        // the result is clamped to 8 + BitDepth bits.
+        dequantizer.ac_quant = 1828;
+        let dq = dequantizer.dequantize(TX_4X4, &quant(vec![0, 1000, -1000]));
+        assert_eq!(dq, [0, 32767, -32768]);
+
+        // and the product is masked to 24 bits before the sign is applied.
+        dequantizer.ac_quant = 7312;
+        dequantizer.bit_depth = 10;
+        let dq = dequantizer.dequantize(TX_4X4, &quant(vec![0, 2295, -2295]));
+        assert_eq!(dq, [0, 3824, -3824]);
+    }
+}
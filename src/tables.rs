@@ -0,0 +1,887 @@
+//! Lookup tables from the additional tables section of the specification
+//! that are shared by the tile decoding processes.
+
+use crate::constants::*;
+
+/// Num_4x4_Blocks_Wide
+pub const NUM_4X4_BLOCKS_WIDE: [u8; BLOCK_SIZES as usize] = [
+    1, 1, 2, 2, 2, 4, 4, 4, 8, 8, 8, 16, 16, 16, 32, 32, 1, 4, 2, 8, 4, 16,
+];
+
+/// Num_4x4_Blocks_High
+pub const NUM_4X4_BLOCKS_HIGH: [u8; BLOCK_SIZES as usize] = [
+    1, 2, 1, 2, 4, 2, 4, 8, 4, 8, 16, 8, 16, 32, 16, 32, 4, 1, 8, 2, 16, 4,
+];
+
+/// Mi_Width_Log2
+pub const MI_WIDTH_LOG2: [u8; BLOCK_SIZES as usize] = [
+    0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4, 5, 5, 0, 2, 1, 3, 2, 4,
+];
+
+/// Mi_Height_Log2
+pub const MI_HEIGHT_LOG2: [u8; BLOCK_SIZES as usize] = [
+    0, 1, 0, 1, 2, 1, 2, 3, 2, 3, 4, 3, 4, 5, 4, 5, 2, 0, 3, 1, 4, 2,
+];
+
+/// Partition_Subsize, indexed by the partition and then the block size.
+pub const PARTITION_SUBSIZE: [[u8; BLOCK_SIZES as usize]; 10] = [
+    [
+        BLOCK_4X4,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_8X8,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_128X128,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_8X4,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X8,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_128X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_4X8,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_8X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X128,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_4X4,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_8X8,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X8,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_128X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X8,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_128X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_8X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X128,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_8X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X128,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X4,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_32X8,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_64X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+    [
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_4X16,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_8X32,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_16X64,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+        BLOCK_INVALID,
+    ],
+];
+
+/// Wiener_Taps_Mid
+pub const WIENER_TAPS_MID: [i32; WIENER_COEFFS as usize] = [3, -7, 15];
+
+/// Wiener_Taps_Min
+pub const WIENER_TAPS_MIN: [i32; WIENER_COEFFS as usize] = [-5, -23, -17];
+
+/// Wiener_Taps_Max
+pub const WIENER_TAPS_MAX: [i32; WIENER_COEFFS as usize] = [10, 8, 46];
+
+/// Wiener_Taps_K
+pub const WIENER_TAPS_K: [u32; WIENER_COEFFS as usize] = [1, 2, 3];
+
+/// Sgrproj_Xqd_Mid
+pub const SGRPROJ_XQD_MID: [i32; 2] = [-32, 31];
+
+/// Sgrproj_Xqd_Min
+pub const SGRPROJ_XQD_MIN: [i32; 2] = [-96, -32];
+
+/// Sgrproj_Xqd_Max
+pub const SGRPROJ_XQD_MAX: [i32; 2] = [31, 95];
+
+/// Sgr_Params, the radius and noise parameter of the two filter passes of
+/// each self guided filter set.
+pub const SGR_PARAMS: [[i32; 4]; 1 << SGRPROJ_PARAMS_BITS] = [
+    [2, 140, 1, 3236],
+    [2, 112, 1, 2158],
+    [2, 93, 1, 1618],
+    [2, 80, 1, 1438],
+    [2, 70, 1, 1295],
+    [2, 58, 1, 1177],
+    [2, 47, 1, 1079],
+    [2, 37, 1, 996],
+    [2, 30, 1, 925],
+    [2, 25, 1, 863],
+    [0, -1, 2, 2589],
+    [0, -1, 2, 1618],
+    [0, -1, 2, 1177],
+    [0, -1, 2, 925],
+    [2, 56, 0, -1],
+    [2, 22, 0, -1],
+];
+
+/// Block_Width, Num_4x4_Blocks_Wide in luma samples.
+pub const BLOCK_WIDTH: [u8; BLOCK_SIZES as usize] = [
+    4, 4, 8, 8, 8, 16, 16, 16, 32, 32, 32, 64, 64, 64, 128, 128, 4, 16, 8, 32, 16, 64,
+];
+
+/// Block_Height, Num_4x4_Blocks_High in luma samples.
+pub const BLOCK_HEIGHT: [u8; BLOCK_SIZES as usize] = [
+    4, 8, 4, 8, 16, 8, 16, 32, 16, 32, 64, 32, 64, 128, 64, 128, 16, 4, 32, 8, 64, 16,
+];
+
+/// Subsampled_Size, the size of the chroma block for the block size, indexed
+/// by the block size, subsampling_x and subsampling_y.
+pub const SUBSAMPLED_SIZE: [[[u8; 2]; 2]; BLOCK_SIZES as usize] = [
+    [[BLOCK_4X4, BLOCK_4X4], [BLOCK_4X4, BLOCK_4X4]],
+    [[BLOCK_4X8, BLOCK_4X4], [BLOCK_INVALID, BLOCK_4X4]],
+    [[BLOCK_8X4, BLOCK_INVALID], [BLOCK_4X4, BLOCK_4X4]],
+    [[BLOCK_8X8, BLOCK_8X4], [BLOCK_4X8, BLOCK_4X4]],
+    [[BLOCK_8X16, BLOCK_8X8], [BLOCK_INVALID, BLOCK_4X8]],
+    [[BLOCK_16X8, BLOCK_INVALID], [BLOCK_8X8, BLOCK_8X4]],
+    [[BLOCK_16X16, BLOCK_16X8], [BLOCK_8X16, BLOCK_8X8]],
+    [[BLOCK_16X32, BLOCK_16X16], [BLOCK_INVALID, BLOCK_8X16]],
+    [[BLOCK_32X16, BLOCK_INVALID], [BLOCK_16X16, BLOCK_16X8]],
+    [[BLOCK_32X32, BLOCK_32X16], [BLOCK_16X32, BLOCK_16X16]],
+    [[BLOCK_32X64, BLOCK_32X32], [BLOCK_INVALID, BLOCK_16X32]],
+    [[BLOCK_64X32, BLOCK_INVALID], [BLOCK_32X32, BLOCK_32X16]],
+    [[BLOCK_64X64, BLOCK_64X32], [BLOCK_32X64, BLOCK_32X32]],
+    [[BLOCK_64X128, BLOCK_64X64], [BLOCK_INVALID, BLOCK_32X64]],
+    [[BLOCK_128X64, BLOCK_INVALID], [BLOCK_64X64, BLOCK_64X32]],
+    [[BLOCK_128X128, BLOCK_128X64], [BLOCK_64X128, BLOCK_64X64]],
+    [[BLOCK_4X16, BLOCK_4X8], [BLOCK_INVALID, BLOCK_4X8]],
+    [[BLOCK_16X4, BLOCK_INVALID], [BLOCK_8X4, BLOCK_8X4]],
+    [[BLOCK_8X32, BLOCK_8X16], [BLOCK_INVALID, BLOCK_4X16]],
+    [[BLOCK_32X8, BLOCK_INVALID], [BLOCK_16X8, BLOCK_16X4]],
+    [[BLOCK_16X64, BLOCK_16X32], [BLOCK_INVALID, BLOCK_8X32]],
+    [[BLOCK_64X16, BLOCK_INVALID], [BLOCK_32X16, BLOCK_32X8]],
+];
+
+/// Intra_Mode_Context, the context of an intra mode when it is used by the
+/// intra_frame_y_mode of a neighbouring block.
+pub const INTRA_MODE_CONTEXT: [usize; INTRA_MODES as usize] =
+    [0, 1, 2, 3, 4, 4, 4, 4, 3, 0, 1, 2, 0];
+
+/// Size_Group, the context of y_mode and of the inter intra syntax elements.
+pub const SIZE_GROUP: [u8; BLOCK_SIZES as usize] = [
+    0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 0, 0, 1, 1, 2, 2,
+];
+
+/// Wedge_Bits, the number of bits of wedge_index, 0 for the block sizes that
+/// cannot use wedge masks.
+pub const WEDGE_BITS: [u8; BLOCK_SIZES as usize] = [
+    0, 0, 0, 4, 4, 4, 4, 4, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 4, 0, 0,
+];
+
+/// Compound_Mode_Ctx_Map
+pub const COMPOUND_MODE_CTX_MAP: [[u8; COMP_NEWMV_CTXS as usize]; 3] =
+    [[0, 1, 1, 1, 1], [1, 2, 3, 4, 4], [4, 4, 5, 6, 7]];
+
+/// Subpel_Filters, the interpolation kernels of the block inter prediction
+/// process: EIGHTTAP, EIGHTTAP_SMOOTH, EIGHTTAP_SHARP, BILINEAR and the 4-tap
+/// variants of EIGHTTAP and EIGHTTAP_SMOOTH for the blocks of 4 samples or
+/// less.
+pub const SUBPEL_FILTERS: [[[i32; 8]; 16]; 6] = [
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 2, -6, 126, 8, -2, 0, 0],
+        [0, 2, -10, 122, 18, -4, 0, 0],
+        [0, 2, -12, 116, 28, -8, 2, 0],
+        [0, 2, -14, 110, 38, -10, 2, 0],
+        [0, 2, -14, 102, 48, -12, 2, 0],
+        [0, 2, -16, 94, 58, -12, 2, 0],
+        [0, 2, -14, 84, 66, -12, 2, 0],
+        [0, 2, -14, 76, 76, -14, 2, 0],
+        [0, 2, -12, 66, 84, -14, 2, 0],
+        [0, 2, -12, 58, 94, -16, 2, 0],
+        [0, 2, -12, 48, 102, -14, 2, 0],
+        [0, 2, -10, 38, 110, -14, 2, 0],
+        [0, 2, -8, 28, 116, -12, 2, 0],
+        [0, 0, -4, 18, 122, -10, 2, 0],
+        [0, 0, -2, 8, 126, -6, 2, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 2, 28, 62, 34, 2, 0, 0],
+        [0, 0, 26, 62, 36, 4, 0, 0],
+        [0, 0, 22, 62, 40, 4, 0, 0],
+        [0, 0, 20, 60, 42, 6, 0, 0],
+        [0, 0, 18, 58, 44, 8, 0, 0],
+        [0, 0, 16, 56, 46, 10, 0, 0],
+        [0, -2, 16, 54, 48, 12, 0, 0],
+        [0, -2, 14, 52, 52, 14, -2, 0],
+        [0, 0, 12, 48, 54, 16, -2, 0],
+        [0, 0, 10, 46, 56, 16, 0, 0],
+        [0, 0, 8, 44, 58, 18, 0, 0],
+        [0, 0, 6, 42, 60, 20, 0, 0],
+        [0, 0, 4, 40, 62, 22, 0, 0],
+        [0, 0, 4, 36, 62, 26, 0, 0],
+        [0, 0, 2, 34, 62, 28, 2, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [-2, 2, -6, 126, 8, -2, 2, 0],
+        [-2, 6, -12, 124, 16, -6, 4, -2],
+        [-2, 8, -18, 120, 26, -10, 6, -2],
+        [-4, 10, -22, 116, 38, -14, 6, -2],
+        [-4, 10, -22, 108, 48, -18, 8, -2],
+        [-4, 10, -24, 100, 60, -20, 8, -2],
+        [-4, 10, -24, 90, 70, -22, 10, -2],
+        [-4, 12, -24, 80, 80, -24, 12, -4],
+        [-2, 10, -22, 70, 90, -24, 10, -4],
+        [-2, 8, -20, 60, 100, -24, 10, -4],
+        [-2, 8, -18, 48, 108, -22, 10, -4],
+        [-2, 6, -14, 38, 116, -22, 10, -4],
+        [-2, 6, -10, 26, 120, -18, 8, -2],
+        [-2, 4, -6, 16, 124, -12, 6, -2],
+        [0, 2, -2, 8, 126, -6, 2, -2],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 0, 0, 120, 8, 0, 0, 0],
+        [0, 0, 0, 112, 16, 0, 0, 0],
+        [0, 0, 0, 104, 24, 0, 0, 0],
+        [0, 0, 0, 96, 32, 0, 0, 0],
+        [0, 0, 0, 88, 40, 0, 0, 0],
+        [0, 0, 0, 80, 48, 0, 0, 0],
+        [0, 0, 0, 72, 56, 0, 0, 0],
+        [0, 0, 0, 64, 64, 0, 0, 0],
+        [0, 0, 0, 56, 72, 0, 0, 0],
+        [0, 0, 0, 48, 80, 0, 0, 0],
+        [0, 0, 0, 40, 88, 0, 0, 0],
+        [0, 0, 0, 32, 96, 0, 0, 0],
+        [0, 0, 0, 24, 104, 0, 0, 0],
+        [0, 0, 0, 16, 112, 0, 0, 0],
+        [0, 0, 0, 8, 120, 0, 0, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 0, -4, 126, 8, -2, 0, 0],
+        [0, 0, -8, 122, 18, -4, 0, 0],
+        [0, 0, -10, 116, 28, -6, 0, 0],
+        [0, 0, -12, 110, 38, -8, 0, 0],
+        [0, 0, -12, 102, 48, -10, 0, 0],
+        [0, 0, -14, 94, 58, -10, 0, 0],
+        [0, 0, -12, 84, 66, -10, 0, 0],
+        [0, 0, -12, 76, 76, -12, 0, 0],
+        [0, 0, -10, 66, 84, -12, 0, 0],
+        [0, 0, -10, 58, 94, -14, 0, 0],
+        [0, 0, -10, 48, 102, -12, 0, 0],
+        [0, 0, -8, 38, 110, -12, 0, 0],
+        [0, 0, -6, 28, 116, -10, 0, 0],
+        [0, 0, -4, 18, 122, -8, 0, 0],
+        [0, 0, -2, 8, 126, -4, 0, 0],
+    ],
+    [
+        [0, 0, 0, 128, 0, 0, 0, 0],
+        [0, 0, 30, 62, 34, 2, 0, 0],
+        [0, 0, 26, 62, 36, 4, 0, 0],
+        [0, 0, 22, 62, 40, 4, 0, 0],
+        [0, 0, 20, 60, 42, 6, 0, 0],
+        [0, 0, 18, 58, 44, 8, 0, 0],
+        [0, 0, 16, 56, 46, 10, 0, 0],
+        [0, 0, 14, 54, 48, 12, 0, 0],
+        [0, 0, 12, 52, 52, 12, 0, 0],
+        [0, 0, 12, 48, 54, 14, 0, 0],
+        [0, 0, 10, 46, 56, 16, 0, 0],
+        [0, 0, 8, 44, 58, 18, 0, 0],
+        [0, 0, 6, 42, 60, 20, 0, 0],
+        [0, 0, 4, 40, 62, 22, 0, 0],
+        [0, 0, 4, 36, 62, 26, 0, 0],
+        [0, 0, 2, 34, 62, 30, 0, 0],
+    ],
+];
+
+/// Wedge_Master_Oblique_Odd, the odd rows of the 63 degree wedge master
+/// mask.
+pub const WEDGE_MASTER_OBLIQUE_ODD: [u8; MASK_MASTER_SIZE as usize] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 6,
+    18, 37, 53, 60, 63, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
+    64, 64, 64, 64, 64, 64, 64, 64, 64,
+];
+
+/// Wedge_Master_Oblique_Even
+pub const WEDGE_MASTER_OBLIQUE_EVEN: [u8; MASK_MASTER_SIZE as usize] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 4, 11,
+    27, 46, 58, 62, 63, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
+    64, 64, 64, 64, 64, 64, 64, 64, 64,
+];
+
+/// Wedge_Master_Vertical
+pub const WEDGE_MASTER_VERTICAL: [u8; MASK_MASTER_SIZE as usize] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 7,
+    21, 43, 57, 62, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64, 64,
+    64, 64, 64, 64, 64, 64, 64, 64, 64,
+];
+
+/// Wedge_Codebook, the direction and the x and y offsets in eighths of the
+/// block of each wedge_index, for the square blocks, the blocks that are
+/// taller than wide and the blocks that are wider than tall.
+pub const WEDGE_CODEBOOK: [[[u8; 3]; WEDGE_TYPES as usize]; 3] = [
+    [
+        [WEDGE_OBLIQUE27, 4, 4],
+        [WEDGE_OBLIQUE63, 4, 4],
+        [WEDGE_OBLIQUE117, 4, 4],
+        [WEDGE_OBLIQUE153, 4, 4],
+        [WEDGE_HORIZONTAL, 4, 2],
+        [WEDGE_HORIZONTAL, 4, 6],
+        [WEDGE_VERTICAL, 2, 4],
+        [WEDGE_VERTICAL, 6, 4],
+        [WEDGE_OBLIQUE27, 4, 2],
+        [WEDGE_OBLIQUE27, 4, 6],
+        [WEDGE_OBLIQUE153, 4, 2],
+        [WEDGE_OBLIQUE153, 4, 6],
+        [WEDGE_OBLIQUE63, 2, 4],
+        [WEDGE_OBLIQUE63, 6, 4],
+        [WEDGE_OBLIQUE117, 2, 4],
+        [WEDGE_OBLIQUE117, 6, 4],
+    ],
+    [
+        [WEDGE_OBLIQUE27, 4, 4],
+        [WEDGE_OBLIQUE63, 4, 4],
+        [WEDGE_OBLIQUE117, 4, 4],
+        [WEDGE_OBLIQUE153, 4, 4],
+        [WEDGE_HORIZONTAL, 4, 2],
+        [WEDGE_HORIZONTAL, 4, 4],
+        [WEDGE_HORIZONTAL, 4, 6],
+        [WEDGE_VERTICAL, 4, 4],
+        [WEDGE_OBLIQUE27, 4, 2],
+        [WEDGE_OBLIQUE27, 4, 6],
+        [WEDGE_OBLIQUE153, 4, 2],
+        [WEDGE_OBLIQUE153, 4, 6],
+        [WEDGE_OBLIQUE63, 2, 4],
+        [WEDGE_OBLIQUE63, 6, 4],
+        [WEDGE_OBLIQUE117, 2, 4],
+        [WEDGE_OBLIQUE117, 6, 4],
+    ],
+    [
+        [WEDGE_OBLIQUE27, 4, 4],
+        [WEDGE_OBLIQUE63, 4, 4],
+        [WEDGE_OBLIQUE117, 4, 4],
+        [WEDGE_OBLIQUE153, 4, 4],
+        [WEDGE_VERTICAL, 2, 4],
+        [WEDGE_VERTICAL, 4, 4],
+        [WEDGE_VERTICAL, 6, 4],
+        [WEDGE_HORIZONTAL, 4, 4],
+        [WEDGE_OBLIQUE27, 4, 2],
+        [WEDGE_OBLIQUE27, 4, 6],
+        [WEDGE_OBLIQUE153, 4, 2],
+        [WEDGE_OBLIQUE153, 4, 6],
+        [WEDGE_OBLIQUE63, 2, 4],
+        [WEDGE_OBLIQUE63, 6, 4],
+        [WEDGE_OBLIQUE117, 2, 4],
+        [WEDGE_OBLIQUE117, 6, 4],
+    ],
+];
+
+/// Ii_Weights_1d, the weight of the intra prediction of the smooth inter
+/// intra masks by the distance to the edge.
+pub const II_WEIGHTS_1D: [u8; MAX_SB_SIZE as usize] = [
+    60, 58, 56, 54, 52, 50, 48, 47, 45, 44, 42, 41, 39, 38, 37, 35, 34, 33, 32, 31, 30, 29, 28, 27,
+    26, 25, 24, 23, 22, 22, 21, 20, 19, 19, 18, 18, 17, 16, 16, 15, 15, 14, 14, 13, 13, 12, 12, 12,
+    11, 11, 10, 10, 10, 9, 9, 9, 8, 8, 8, 8, 7, 7, 7, 7, 6, 6, 6, 6, 6, 5, 5, 5, 5, 5, 4, 4, 4, 4,
+    4, 4, 4, 4, 3, 3, 3, 3, 3, 3, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+];
+
+/// Quant_Dist_Weight
+pub const QUANT_DIST_WEIGHT: [[i32; 2]; 4] =
+    [[2, 3], [2, 5], [2, 7], [1, MAX_FRAME_DISTANCE as i32]];
+
+/// Quant_Dist_Lookup, the weights of distance weighted compound prediction.
+pub const QUANT_DIST_LOOKUP: [[i32; 2]; 4] = [[9, 7], [11, 5], [12, 4], [13, 3]];
+
+/// Tx_Width
+pub const TX_WIDTH: [u8; TX_SIZES_ALL as usize] = [
+    4, 8, 16, 32, 64, 4, 8, 8, 16, 16, 32, 32, 64, 4, 16, 8, 32, 16, 64,
+];
+
+/// Tx_Height
+pub const TX_HEIGHT: [u8; TX_SIZES_ALL as usize] = [
+    4, 8, 16, 32, 64, 8, 4, 16, 8, 32, 16, 64, 32, 16, 4, 32, 8, 64, 16,
+];
+
+/// Tx_Width_Log2
+pub const TX_WIDTH_LOG2: [u8; TX_SIZES_ALL as usize] =
+    [2, 3, 4, 5, 6, 2, 3, 3, 4, 4, 5, 5, 6, 2, 4, 3, 5, 4, 6];
+
+/// Tx_Height_Log2
+pub const TX_HEIGHT_LOG2: [u8; TX_SIZES_ALL as usize] =
+    [2, 3, 4, 5, 6, 3, 2, 4, 3, 5, 4, 6, 5, 4, 2, 5, 3, 6, 4];
+
+/// Palette_Color_Hash_Multipliers
+pub const PALETTE_COLOR_HASH_MULTIPLIERS: [u8; PALETTE_NUM_NEIGHBORS as usize] = [1, 2, 2];
+
+/// Palette_Color_Context, the ctx of palette_color_idx_y and
+/// palette_color_idx_uv for each ColorContextHash, -1 for hashes that cannot
+/// occur.
+pub const PALETTE_COLOR_CONTEXT: [i8; PALETTE_MAX_COLOR_CONTEXT_HASH as usize + 1] =
+    [-1, -1, 0, -1, -1, 4, 3, 2, 1];
+
+/// Max_Tx_Size_Rect, the largest transform size of each block size.
+pub const MAX_TX_SIZE_RECT: [u8; BLOCK_SIZES as usize] = [
+    TX_4X4, TX_4X8, TX_8X4, TX_8X8, TX_8X16, TX_16X8, TX_16X16, TX_16X32, TX_32X16, TX_32X32,
+    TX_32X64, TX_64X32, TX_64X64, TX_64X64, TX_64X64, TX_64X64, TX_4X16, TX_16X4, TX_8X32, TX_32X8,
+    TX_16X64, TX_64X16,
+];
+
+/// Max_Tx_Depth, selects the CDF of tx_depth for each block size.
+pub const MAX_TX_DEPTHS: [u8; BLOCK_SIZES as usize] = [
+    0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4, 4, 4, 4, 2, 2, 3, 3, 4, 4,
+];
+
+/// Split_Tx_Size, the transform size after one split.
+pub const SPLIT_TX_SIZE: [u8; TX_SIZES_ALL as usize] = [
+    TX_4X4, TX_4X4, TX_8X8, TX_16X16, TX_32X32, TX_4X4, TX_4X4, TX_8X8, TX_8X8, TX_16X16, TX_16X16,
+    TX_32X32, TX_32X32, TX_4X8, TX_8X4, TX_8X16, TX_16X8, TX_16X32, TX_32X16,
+];
+
+/// Tx_Size_Sqr, the largest square transform size that fits inside of
+/// each transform size.
+pub const TX_SIZE_SQR: [u8; TX_SIZES_ALL as usize] = [
+    TX_4X4, TX_8X8, TX_16X16, TX_32X32, TX_64X64, TX_4X4, TX_4X4, TX_8X8, TX_8X8, TX_16X16,
+    TX_16X16, TX_32X32, TX_32X32, TX_4X4, TX_4X4, TX_8X8, TX_8X8, TX_16X16, TX_16X16,
+];
+
+/// Tx_Size_Sqr_Up, the smallest square transform size that contains each
+/// transform size.
+pub const TX_SIZE_SQR_UP: [u8; TX_SIZES_ALL as usize] = [
+    TX_4X4, TX_8X8, TX_16X16, TX_32X32, TX_64X64, TX_8X8, TX_8X8, TX_16X16, TX_16X16, TX_32X32,
+    TX_32X32, TX_64X64, TX_64X64, TX_16X16, TX_16X16, TX_32X32, TX_32X32, TX_64X64, TX_64X64,
+];
+
+/// Mode_To_Txfm, the transform type implied by the intra mode of a chroma
+/// block.
+pub const MODE_TO_TXFM: [u8; UV_INTRA_MODES_CFL_ALLOWED as usize] = [
+    DCT_DCT, ADST_DCT, DCT_ADST, DCT_DCT, ADST_ADST, ADST_DCT, DCT_ADST, DCT_ADST, ADST_DCT,
+    ADST_ADST, ADST_DCT, DCT_ADST, ADST_ADST, DCT_DCT,
+];
+
+/// Filter_Intra_Mode_To_Intra_Dir
+pub const FILTER_INTRA_MODE_TO_INTRA_DIR: [u8; 5] = [DC_PRED, V_PRED, H_PRED, D157_PRED, DC_PRED];
+
+/// Tx_Type_Intra_Inv_Set1
+pub const TX_TYPE_INTRA_INV_SET1: [u8; 7] =
+    [IDTX, DCT_DCT, V_DCT, H_DCT, ADST_ADST, ADST_DCT, DCT_ADST];
+
+/// Tx_Type_Intra_Inv_Set2
+pub const TX_TYPE_INTRA_INV_SET2: [u8; 5] = [IDTX, DCT_DCT, ADST_ADST, ADST_DCT, DCT_ADST];
+
+/// Tx_Type_Inter_Inv_Set1
+pub const TX_TYPE_INTER_INV_SET1: [u8; 16] = [
+    IDTX,
+    V_DCT,
+    H_DCT,
+    V_ADST,
+    H_ADST,
+    V_FLIPADST,
+    H_FLIPADST,
+    DCT_DCT,
+    ADST_DCT,
+    DCT_ADST,
+    FLIPADST_DCT,
+    DCT_FLIPADST,
+    ADST_ADST,
+    FLIPADST_FLIPADST,
+    ADST_FLIPADST,
+    FLIPADST_ADST,
+];
+
+/// Tx_Type_Inter_Inv_Set2
+pub const TX_TYPE_INTER_INV_SET2: [u8; 12] = [
+    IDTX,
+    V_DCT,
+    H_DCT,
+    DCT_DCT,
+    ADST_DCT,
+    DCT_ADST,
+    FLIPADST_DCT,
+    DCT_FLIPADST,
+    ADST_ADST,
+    FLIPADST_FLIPADST,
+    ADST_FLIPADST,
+    FLIPADST_ADST,
+];
+
+/// Tx_Type_Inter_Inv_Set3
+pub const TX_TYPE_INTER_INV_SET3: [u8; 2] = [IDTX, DCT_DCT];
+
+/// Tx_Type_In_Set_Intra, indexed by the transform set and then the
+/// transform type.
+pub const TX_TYPE_IN_SET_INTRA: [[bool; TX_TYPES as usize]; TX_SET_TYPES_INTRA as usize] = {
+    let (o, x) = (true, false);
+    [
+        [o, x, x, x, x, x, x, x, x, x, x, x, x, x, x, x],
+        [o, o, o, o, x, x, x, x, x, o, o, o, x, x, x, x],
+        [o, o, o, o, x, x, x, x, x, o, x, x, x, x, x, x],
+    ]
+};
+
+/// Tx_Type_In_Set_Inter, indexed by the transform set and then the
+/// transform type.
+pub const TX_TYPE_IN_SET_INTER: [[bool; TX_TYPES as usize]; TX_SET_TYPES_INTER as usize] = {
+    let (o, x) = (true, false);
+    [
+        [o, x, x, x, x, x, x, x, x, x, x, x, x, x, x, x],
+        [o, o, o, o, o, o, o, o, o, o, o, o, o, o, o, o],
+        [o, o, o, o, o, o, o, o, o, o, o, o, x, x, x, x],
+        [o, x, x, x, x, x, x, x, x, o, x, x, x, x, x, x],
+    ]
+};
+
+/// Adjusted_Tx_Size, the size of the area of a transform block that can
+/// contain non zero coefficients.
+pub const ADJUSTED_TX_SIZE: [u8; TX_SIZES_ALL as usize] = [
+    TX_4X4, TX_8X8, TX_16X16, TX_32X32, TX_32X32, TX_4X8, TX_8X4, TX_8X16, TX_16X8, TX_16X32,
+    TX_32X16, TX_32X32, TX_32X32, TX_4X16, TX_16X4, TX_8X32, TX_32X8, TX_16X32, TX_32X16,
+];
+
+/// Sig_Ref_Diff_Offset, the row and column offsets of the coefficients
+/// used by the context of coeff_base, for each transform class.
+pub const SIG_REF_DIFF_OFFSET: [[[u8; 2]; SIG_REF_DIFF_OFFSET_NUM as usize]; 3] = [
+    [[0, 1], [1, 0], [1, 1], [0, 2], [2, 0]],
+    [[0, 1], [1, 0], [0, 2], [0, 3], [0, 4]],
+    [[0, 1], [1, 0], [2, 0], [3, 0], [4, 0]],
+];
+
+/// Mag_Ref_Offset_With_Tx_Class, the row and column offsets of the
+/// coefficients used by the context of coeff_br.
+pub const MAG_REF_OFFSET_WITH_TX_CLASS: [[[u8; 2]; 3]; 3] = [
+    [[0, 1], [1, 0], [1, 1]],
+    [[0, 1], [1, 0], [0, 2]],
+    [[0, 1], [1, 0], [2, 0]],
+];
+
+/// Coeff_Base_Pos_Ctx_Offset
+pub const COEFF_BASE_POS_CTX_OFFSET: [u8; 3] = [
+    SIG_COEF_CONTEXTS_2D,
+    SIG_COEF_CONTEXTS_2D + 5,
+    SIG_COEF_CONTEXTS_2D + 10,
+];
+
+/// Coeff_Base_Ctx_Offset, indexed by the transform size, and then the row
+/// and column of the coefficient (limited to 4).
+pub const COEFF_BASE_CTX_OFFSET: [[[u8; 5]; 5]; TX_SIZES_ALL as usize] = [
+    [
+        [0, 1, 6, 6, 0],
+        [1, 6, 6, 21, 0],
+        [6, 6, 21, 21, 0],
+        [6, 21, 21, 21, 0],
+        [0, 0, 0, 0, 0],
+    ],
+    [
+        [0, 1, 6, 6, 21],
+        [1, 6, 6, 21, 21],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 1, 6, 6, 21],
+        [1, 6, 6, 21, 21],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 1, 6, 6, 21],
+        [1, 6, 6, 21, 21],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 1, 6, 6, 21],
+        [1, 6, 6, 21, 21],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 11, 11, 11, 0],
+        [11, 11, 11, 11, 0],
+        [6, 6, 21, 21, 0],
+        [6, 21, 21, 21, 0],
+        [21, 21, 21, 21, 0],
+    ],
+    [
+        [0, 16, 6, 6, 21],
+        [16, 16, 6, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+        [0, 0, 0, 0, 0],
+    ],
+    [
+        [0, 11, 11, 11, 11],
+        [11, 11, 11, 11, 11],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 16, 6, 6, 21],
+        [16, 16, 6, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+    ],
+    [
+        [0, 11, 11, 11, 11],
+        [11, 11, 11, 11, 11],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 16, 6, 6, 21],
+        [16, 16, 6, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+    ],
+    [
+        [0, 11, 11, 11, 11],
+        [11, 11, 11, 11, 11],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 16, 6, 6, 21],
+        [16, 16, 6, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+    ],
+    [
+        [0, 11, 11, 11, 0],
+        [11, 11, 11, 11, 0],
+        [6, 6, 21, 21, 0],
+        [6, 21, 21, 21, 0],
+        [21, 21, 21, 21, 0],
+    ],
+    [
+        [0, 16, 6, 6, 21],
+        [16, 16, 6, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+        [0, 0, 0, 0, 0],
+    ],
+    [
+        [0, 11, 11, 11, 11],
+        [11, 11, 11, 11, 11],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 16, 6, 6, 21],
+        [16, 16, 6, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+    ],
+    [
+        [0, 11, 11, 11, 11],
+        [11, 11, 11, 11, 11],
+        [6, 6, 21, 21, 21],
+        [6, 21, 21, 21, 21],
+        [21, 21, 21, 21, 21],
+    ],
+    [
+        [0, 16, 6, 6, 21],
+        [16, 16, 6, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+        [16, 16, 21, 21, 21],
+    ],
+];
@@ -0,0 +1,118 @@
+use crate::{
+    tables::{NUM_4X4_BLOCKS_HIGH, NUM_4X4_BLOCKS_WIDE},
+    Av1DecodeError,
+};
+
+use super::{mode_info::ModeInfo, tx::TransformBlock, TileDecoder};
+
+/// A block of the partition tree, and the position dependent variables of
+/// decode_block( ).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Block {
+    /// MiRow
+    pub mi_row: u32,
+    /// MiCol
+    pub mi_col: u32,
+    /// MiSize
+    pub mi_size: u8,
+    /// HasChroma, the block contains chroma samples (a chroma block can cover
+    /// more than one luma block when the chroma planes are subsampled).
+    pub has_chroma: bool,
+    /// AvailU
+    pub avail_u: bool,
+    /// AvailL
+    pub avail_l: bool,
+    /// AvailUChroma
+    pub avail_u_chroma: bool,
+    /// AvailLChroma
+    pub avail_l_chroma: bool,
+    /// The transform blocks of all planes of the block, in decoding order.
+    pub transform_blocks: Vec<TransformBlock>,
+}
+
+impl TileDecoder<'_> {
+    /// decode_block( r, c, subSize )
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#decode-block-syntax
+    pub fn decode_block(&mut self, r: u32, c: u32, sub_size: u8) -> Result<Block, Av1DecodeError> {
+        let color_config = &self.sequence_header.color_config;
+        let bw4 = NUM_4X4_BLOCKS_WIDE[sub_size as usize];
+        let bh4 = NUM_4X4_BLOCKS_HIGH[sub_size as usize];
+        let has_chroma = !(bh4 == 1 && color_config.subsampling_y && (r & 1) == 0
+            || bw4 == 1 && color_config.subsampling_x && (c & 1) == 0)
+            && self.ctx.num_planes > 1;
+
+        let (r, c) = (r as i32, c as i32);
+        let avail_u = self.is_inside(r - 1, c);
+        let avail_l = self.is_inside(r, c - 1);
+        let (avail_u_chroma, avail_l_chroma) = if has_chroma {
+            (
+                if color_config.subsampling_y && bh4 == 1 {
+                    self.is_inside(r - 2, c)
+                } else {
+                    avail_u
+                },
+                if color_config.subsampling_x && bw4 == 1 {
+                    self.is_inside(r, c - 2)
+                } else {
+                    avail_l
+                },
+            )
+        } else {
+            (false, false)
+        };
+
+        self.block = Block {
+            mi_row: r as u32,
+            mi_col: c as u32,
+            mi_size: sub_size,
+            has_chroma,
+            avail_u,
+            avail_l,
+            avail_u_chroma,
+            avail_l_chroma,
+            transform_blocks: Vec::new(),
+        };
+
+        self.mi = ModeInfo {
+            mi_size: sub_size,
+            ..Default::default()
+        };
+
+        self.mode_info()?;
+        self.palette_tokens();
+        self.read_block_tx_size();
+        if self.mi.skip {
+            self.reset_block_context();
+        }
+
+        // compute_prediction( )
+        if self.mi.use_intrabc {
+            self.predict_intrabc();
+        } else if self.mi.is_inter {
+            self.predict_inter_block();
+        }
+
+        // residual( )
+        let mut transform_blocks = self.transform_blocks();
+        for block in &mut transform_blocks {
+            self.predict_transform_block(block);
+            if !self.mi.skip {
+                let plane = block.plane as usize;
+                block.coeffs = self.coeffs(plane, block.start_x, block.start_y, block.tx_size)?;
+                self.reconstruct(block);
+            }
+
+            self.set_block_decoded(block);
+        }
+
+        self.block.transform_blocks = transform_blocks;
+        for (delta_lf, value) in self.mi.delta_lf.iter_mut().zip(self.delta_lf) {
+            *delta_lf = value as i8;
+        }
+
+        self.ctx.mode_info.fill(&self.block, &self.mi);
+
+        Ok(self.block.clone())
+    }
+}
@@ -0,0 +1,693 @@
+pub mod scan;
+
+use std::{cmp::Ordering, ops::Range};
+
+use scan::*;
+
+use crate::{
+    cdf::CdfContext,
+    constants::{
+        BR_CDF_SIZE, COEFF_BASE_RANGE, DCT_DCT, H_ADST, H_DCT, H_FLIPADST, IDTX, NUM_BASE_LEVELS,
+        SIG_COEF_CONTEXTS, SIG_COEF_CONTEXTS_EOB, TX_16X16, TX_16X32, TX_16X4, TX_16X64, TX_16X8,
+        TX_32X16, TX_32X32, TX_32X8, TX_4X16, TX_4X4, TX_4X8, TX_64X16, TX_64X64, TX_8X16, TX_8X32,
+        TX_8X4, TX_8X8, TX_CLASS_2D, TX_CLASS_HORIZ, TX_CLASS_VERT, V_ADST, V_DCT, V_FLIPADST,
+    },
+    entropy::SymbolDecoder,
+    tables::{
+        ADJUSTED_TX_SIZE, BLOCK_HEIGHT, BLOCK_WIDTH, COEFF_BASE_CTX_OFFSET,
+        COEFF_BASE_POS_CTX_OFFSET, MAG_REF_OFFSET_WITH_TX_CLASS, NUM_4X4_BLOCKS_HIGH,
+        NUM_4X4_BLOCKS_WIDE, SIG_REF_DIFF_OFFSET, TX_HEIGHT, TX_HEIGHT_LOG2, TX_SIZE_SQR,
+        TX_SIZE_SQR_UP, TX_WIDTH, TX_WIDTH_LOG2,
+    },
+    Av1DecodeError, Av1DecodeInvalidError,
+};
+
+use super::TileDecoder;
+
+/// The quantized coefficients of a transform block with at least one non
+/// zero coefficient, ready to be dequantized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coefficients {
+    /// PlaneTxType
+    pub tx_type: u8,
+    /// eob, the number of coefficients up to and including the last non zero
+    /// coefficient in scan order.
+    pub eob: u32,
+    /// Quant, in raster order of the coded area of the transform block. The
+    /// coded area is at most 32x32, the coefficients outside of it are zero.
+    pub quant: Vec<i32>,
+}
+
+/// get_tx_class( txType )
+pub fn get_tx_class(tx_type: u8) -> u8 {
+    match tx_type {
+        V_DCT | V_ADST | V_FLIPADST => TX_CLASS_VERT,
+        H_DCT | H_ADST | H_FLIPADST => TX_CLASS_HORIZ,
+        _ => TX_CLASS_2D,
+    }
+}
+
+/// get_scan( txSz ), the scan order of the coefficients of a transform
+/// block, transforms with a single 1D transform scan rows or columns first.
+pub fn get_scan(tx_size: u8, tx_type: u8) -> &'static [u16] {
+    if tx_size == TX_16X64 {
+        return &DEFAULT_SCAN_16X32;
+    }
+
+    if tx_size == TX_64X16 {
+        return &DEFAULT_SCAN_32X16;
+    }
+
+    if TX_SIZE_SQR_UP[tx_size as usize] == TX_64X64 {
+        return &DEFAULT_SCAN_32X32;
+    }
+
+    if tx_type != IDTX {
+        match get_tx_class(tx_type) {
+            TX_CLASS_VERT => {
+                let area =
+                    TX_WIDTH[tx_size as usize] as usize * TX_HEIGHT[tx_size as usize] as usize;
+                return &MROW_SCAN[..area];
+            }
+            TX_CLASS_HORIZ => return get_mcol_scan(tx_size),
+            _ => (),
+        }
+    }
+
+    get_default_scan(tx_size)
+}
+
+/// get_mcol_scan( txSz ), 1D transforms are only used up to 16x16.
+fn get_mcol_scan(tx_size: u8) -> &'static [u16] {
+    match tx_size {
+        TX_4X4 => &MCOL_SCAN_4X4,
+        TX_4X8 => &MCOL_SCAN_4X8,
+        TX_8X4 => &MCOL_SCAN_8X4,
+        TX_8X8 => &MCOL_SCAN_8X8,
+        TX_8X16 => &MCOL_SCAN_8X16,
+        TX_16X8 => &MCOL_SCAN_16X8,
+        TX_4X16 => &MCOL_SCAN_4X16,
+        TX_16X4 => &MCOL_SCAN_16X4,
+        _ => &MCOL_SCAN_16X16,
+    }
+}
+
+/// get_default_scan( txSz )
+fn get_default_scan(tx_size: u8) -> &'static [u16] {
+    match tx_size {
+        TX_4X4 => &DEFAULT_SCAN_4X4,
+        TX_4X8 => &DEFAULT_SCAN_4X8,
+        TX_8X4 => &DEFAULT_SCAN_8X4,
+        TX_8X8 => &DEFAULT_SCAN_8X8,
+        TX_8X16 => &DEFAULT_SCAN_8X16,
+        TX_16X8 => &DEFAULT_SCAN_16X8,
+        TX_16X16 => &DEFAULT_SCAN_16X16,
+        TX_16X32 => &DEFAULT_SCAN_16X32,
+        TX_32X16 => &DEFAULT_SCAN_32X16,
+        TX_4X16 => &DEFAULT_SCAN_4X16,
+        TX_16X4 => &DEFAULT_SCAN_16X4,
+        TX_8X32 => &DEFAULT_SCAN_8X32,
+        TX_32X8 => &DEFAULT_SCAN_32X8,
+        _ => &DEFAULT_SCAN_32X32,
+    }
+}
+
+/// The ctx of coeff_base_eob, from the position of the last non zero
+/// coefficient in scan order.
+fn coeff_base_eob_ctx(tx_size: u8, c: usize) -> usize {
+    let adj_tx_size = ADJUSTED_TX_SIZE[tx_size as usize] as usize;
+    let area = (TX_WIDTH[adj_tx_size] as usize) * (TX_HEIGHT[adj_tx_size] as usize);
+    let ctx = if c == 0 {
+        SIG_COEF_CONTEXTS - 4
+    } else if c <= area / 8 {
+        SIG_COEF_CONTEXTS - 3
+    } else if c <= area / 4 {
+        SIG_COEF_CONTEXTS - 2
+    } else {
+        SIG_COEF_CONTEXTS - 1
+    };
+
+    (ctx + SIG_COEF_CONTEXTS_EOB - SIG_COEF_CONTEXTS) as usize
+}
+
+/// The ctx of coeff_base, from the magnitudes of the already decoded
+/// coefficients below and to the right of the position.
+fn coeff_base_ctx(quant: &[i32], tx_size: u8, tx_class: u8, pos: usize) -> usize {
+    let adj_tx_size = ADJUSTED_TX_SIZE[tx_size as usize] as usize;
+    let bwl = TX_WIDTH_LOG2[adj_tx_size] as usize;
+    let txh = TX_HEIGHT[adj_tx_size] as usize;
+    let row = pos >> bwl;
+    let col = pos - (row << bwl);
+
+    let mut mag = 0;
+    for [dr, dc] in SIG_REF_DIFF_OFFSET[tx_class as usize] {
+        let ref_row = row + dr as usize;
+        let ref_col = col + dc as usize;
+        if ref_row < txh && ref_col < (1 << bwl) {
+            mag += quant[(ref_row << bwl) + ref_col].abs().min(3) as usize;
+        }
+    }
+
+    let ctx = ((mag + 1) >> 1).min(4);
+    if tx_class == TX_CLASS_2D {
+        if row == 0 && col == 0 {
+            return 0;
+        }
+
+        return ctx + COEFF_BASE_CTX_OFFSET[tx_size as usize][row.min(4)][col.min(4)] as usize;
+    }
+
+    let idx = if tx_class == TX_CLASS_VERT { row } else { col };
+    ctx + COEFF_BASE_POS_CTX_OFFSET[idx.min(2)] as usize
+}
+
+/// The ctx of coeff_br.
+fn coeff_br_ctx(quant: &[i32], tx_size: u8, tx_class: u8, pos: usize) -> usize {
+    let adj_tx_size = ADJUSTED_TX_SIZE[tx_size as usize] as usize;
+    let bwl = TX_WIDTH_LOG2[adj_tx_size] as usize;
+    let txh = TX_HEIGHT[adj_tx_size] as usize;
+    let row = pos >> bwl;
+    let col = pos - (row << bwl);
+
+    let mut mag = 0;
+    for [dr, dc] in MAG_REF_OFFSET_WITH_TX_CLASS[tx_class as usize] {
+        let ref_row = row + dr as usize;
+        let ref_col = col + dc as usize;
+        if ref_row < txh && ref_col < (1 << bwl) {
+            let max = (COEFF_BASE_RANGE + NUM_BASE_LEVELS + 1) as i32;
+            mag += quant[(ref_row << bwl) + ref_col].min(max) as usize;
+        }
+    }
+
+    let mag = ((mag + 1) >> 1).min(6);
+    let near = match tx_class {
+        TX_CLASS_2D => row < 2 && col < 2,
+        TX_CLASS_HORIZ => col == 0,
+        _ => row == 0,
+    };
+
+    if pos == 0 {
+        mag
+    } else if near {
+        mag + 7
+    } else {
+        mag + 14
+    }
+}
+
+/// The part of coeffs( ) after all_zero, reads the end of block position,
+/// the levels in reverse scan order and then the signs and the Exp-Golomb
+/// coded remainders in scan order.
+///
+/// Returns the coefficients and the culLevel and dcCategory for the above
+/// and left contexts.
+pub fn read_coeffs(
+    sd: &mut SymbolDecoder,
+    cdf: &mut CdfContext,
+    tx_size: u8,
+    tx_type: u8,
+    plane_type: usize,
+    dc_sign_ctx: usize,
+) -> Result<(Coefficients, u8, u8), Av1DecodeError> {
+    let tx_sz_ctx =
+        ((TX_SIZE_SQR[tx_size as usize] + TX_SIZE_SQR_UP[tx_size as usize] + 1) >> 1) as usize;
+    let tx_class = get_tx_class(tx_type);
+    let scan = get_scan(tx_size, tx_type);
+    let adj_tx_size = ADJUSTED_TX_SIZE[tx_size as usize] as usize;
+    let mut quant = vec![0; TX_WIDTH[adj_tx_size] as usize * TX_HEIGHT[adj_tx_size] as usize];
+
+    let eob_multisize =
+        TX_WIDTH_LOG2[tx_size as usize].min(5) + TX_HEIGHT_LOG2[tx_size as usize].min(5) - 4;
+    let ctx = (tx_class != TX_CLASS_2D) as usize;
+    let eob_pt = match eob_multisize {
+        // eob_pt_16	S()
+        0 => sd.read_symbol(&mut cdf.eob_pt_16_cdf[plane_type][ctx]),
+        // eob_pt_32	S()
+        1 => sd.read_symbol(&mut cdf.eob_pt_32_cdf[plane_type][ctx]),
+        // eob_pt_64	S()
+        2 => sd.read_symbol(&mut cdf.eob_pt_64_cdf[plane_type][ctx]),
+        // eob_pt_128	S()
+        3 => sd.read_symbol(&mut cdf.eob_pt_128_cdf[plane_type][ctx]),
+        // eob_pt_256	S()
+        4 => sd.read_symbol(&mut cdf.eob_pt_256_cdf[plane_type][ctx]),
+        // eob_pt_512	S()
+        5 => sd.read_symbol(&mut cdf.eob_pt_512_cdf[plane_type]),
+        // eob_pt_1024	S()
+        _ => sd.read_symbol(&mut cdf.eob_pt_1024_cdf[plane_type]),
+    } + 1;
+
+    let mut eob = if eob_pt < 2 {
+        eob_pt
+    } else {
+        (1 << (eob_pt - 2)) + 1
+    };
+
+    if eob_pt >= 3 {
+        // eob_extra	S()
+        let cdf = &mut cdf.eob_extra_cdf[tx_sz_ctx][plane_type][eob_pt - 3];
+        if sd.read_symbol(cdf) == 1 {
+            eob += 1 << (eob_pt - 3);
+        }
+
+        for i in 1..eob_pt - 2 {
+            // eob_extra_bit	L(1)
+            if sd.read_literal(1) == 1 {
+                eob += 1 << (eob_pt - 3 - i);
+            }
+        }
+    }
+
+    for c in (0..eob).rev() {
+        let pos = scan[c] as usize;
+        let mut level = if c == eob - 1 {
+            // coeff_base_eob	S()
+            let ctx = coeff_base_eob_ctx(tx_size, c);
+            sd.read_symbol(&mut cdf.coeff_base_eob_cdf[tx_sz_ctx][plane_type][ctx]) + 1
+        } else {
+            // coeff_base	S()
+            let ctx = coeff_base_ctx(&quant, tx_size, tx_class, pos);
+            sd.read_symbol(&mut cdf.coeff_base_cdf[tx_sz_ctx][plane_type][ctx])
+        };
+
+        if level > NUM_BASE_LEVELS as usize {
+            let ctx = coeff_br_ctx(&quant, tx_size, tx_class, pos);
+            let cdf = &mut cdf.coeff_br_cdf[tx_sz_ctx.min(TX_32X32 as usize)][plane_type][ctx];
+            for _ in 0..COEFF_BASE_RANGE / (BR_CDF_SIZE - 1) {
+                // coeff_br	S()
+                let coeff_br = sd.read_symbol(cdf);
+                level += coeff_br;
+                if coeff_br < (BR_CDF_SIZE - 1) as usize {
+                    break;
+                }
+            }
+        }
+
+        quant[pos] = level as i32;
+    }
+
+    let mut cul_level = 0u32;
+    let mut dc_category = 0;
+    for (c, &pos) in scan[..eob].iter().enumerate() {
+        let pos = pos as usize;
+        let sign = if quant[pos] == 0 {
+            false
+        } else if c == 0 {
+            // dc_sign	S()
+            sd.read_symbol(&mut cdf.dc_sign_cdf[plane_type][dc_sign_ctx]) == 1
+        } else {
+            // sign_bit	L(1)
+            sd.read_literal(1) == 1
+        };
+
+        if quant[pos] > (NUM_BASE_LEVELS + COEFF_BASE_RANGE) as i32 {
+            quant[pos] = read_golomb(sd)? + (COEFF_BASE_RANGE + NUM_BASE_LEVELS) as i32;
+        }
+
+        if pos == 0 && quant[pos] > 0 {
+            dc_category = if sign { 1 } else { 2 };
+        }
+
+        quant[pos] &= 0xFFFFF;
+        cul_level += quant[pos] as u32;
+        if sign {
+            quant[pos] = -quant[pos];
+        }
+    }
+
+    let coefficients = Coefficients {
+        tx_type,
+        eob: eob as u32,
+        quant,
+    };
+
+    Ok((coefficients, cul_level.min(63) as u8, dc_category))
+}
+
+/// read_golomb( )
+fn read_golomb(sd: &mut SymbolDecoder) -> Result<i32, Av1DecodeError> {
+    let mut length = 0;
+    loop {
+        length += 1;
+        if length > 20 {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::GolombLength));
+        }
+
+        // golomb_length_bit	L(1)
+        if sd.read_literal(1) == 1 {
+            break;
+        }
+    }
+
+    let mut x = 1;
+    for _ in 1..length {
+        // golomb_data_bit	L(1)
+        x = (x << 1) | sd.read_literal(1) as i32;
+    }
+
+    Ok(x)
+}
+
+impl TileDecoder<'_> {
+    /// clear_above_context( ), at the start of each tile.
+    pub fn clear_above_context(&mut self) {
+        // blocks and transform blocks can extend past the end of the frame by
+        // up to 16 4x4 units.
+        let len = self.ctx.mi_cols as usize + 16;
+        self.above_level_context = [vec![0; len], vec![0; len], vec![0; len]];
+        self.above_dc_context = [vec![0; len], vec![0; len], vec![0; len]];
+        self.above_seg_pred_context = vec![0; self.ctx.mi_cols as usize];
+    }
+
+    /// clear_left_context( ), at the start of each superblock row.
+    pub fn clear_left_context(&mut self) {
+        let len = self.ctx.mi_rows as usize + 16;
+        self.left_level_context = [vec![0; len], vec![0; len], vec![0; len]];
+        self.left_dc_context = [vec![0; len], vec![0; len], vec![0; len]];
+        self.left_seg_pred_context = vec![0; self.ctx.mi_rows as usize];
+    }
+
+    /// reset_block_context( bw4, bh4 ), a skipped block has no coefficients
+    /// in any of its planes.
+    pub fn reset_block_context(&mut self) {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize] as usize;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize] as usize;
+        let (mi_row, mi_col) = (self.block.mi_row as usize, self.block.mi_col as usize);
+        let num_planes = if self.block.has_chroma { 3 } else { 1 };
+        for plane in 0..num_planes {
+            let (sub_x, sub_y) = self.plane_subsampling(plane);
+            let cols = (mi_col >> sub_x)..((mi_col + bw4) >> sub_x);
+            self.above_level_context[plane][cols.clone()].fill(0);
+            self.above_dc_context[plane][cols].fill(0);
+
+            let rows = (mi_row >> sub_y)..((mi_row + bh4) >> sub_y);
+            self.left_level_context[plane][rows.clone()].fill(0);
+            self.left_dc_context[plane][rows].fill(0);
+        }
+    }
+
+    /// coeffs( plane, startX, startY, txSz ), reads the coefficients of a
+    /// transform block, None when all of them are zero.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#coefficients-syntax
+    pub fn coeffs(
+        &mut self,
+        plane: usize,
+        start_x: u32,
+        start_y: u32,
+        tx_size: u8,
+    ) -> Result<Option<Coefficients>, Av1DecodeError> {
+        let x4 = start_x >> 2;
+        let y4 = start_y >> 2;
+        let w4 = TX_WIDTH[tx_size as usize] as u32 >> 2;
+        let h4 = TX_HEIGHT[tx_size as usize] as u32 >> 2;
+        let tx_sz_ctx =
+            ((TX_SIZE_SQR[tx_size as usize] + TX_SIZE_SQR_UP[tx_size as usize] + 1) >> 1) as usize;
+
+        // all_zero	S()
+        let ctx = self.all_zero_ctx(plane, x4, y4, tx_size);
+        if self
+            .sd
+            .read_symbol(&mut self.cdf.txb_skip_cdf[tx_sz_ctx][ctx])
+            == 1
+        {
+            if plane == 0 {
+                self.ctx.mode_info.set_tx_type(y4, x4, w4, h4, DCT_DCT);
+            }
+
+            self.set_coeff_context(plane, x4, y4, tx_size, 0, 0);
+            return Ok(None);
+        }
+
+        if plane == 0 {
+            self.transform_type(x4, y4, tx_size);
+        }
+
+        let tx_type = self.compute_tx_type(plane, tx_size, x4, y4);
+        let dc_sign_ctx = self.dc_sign_ctx(plane, x4, y4, tx_size);
+        let (coefficients, cul_level, dc_category) = read_coeffs(
+            &mut self.sd,
+            &mut self.cdf,
+            tx_size,
+            tx_type,
+            (plane > 0) as usize,
+            dc_sign_ctx,
+        )?;
+
+        self.set_coeff_context(plane, x4, y4, tx_size, cul_level, dc_category);
+        Ok(Some(coefficients))
+    }
+
+    /// Stores the culLevel and dcCategory of a transform block in the above
+    /// and left contexts.
+    fn set_coeff_context(
+        &mut self,
+        plane: usize,
+        x4: u32,
+        y4: u32,
+        tx_size: u8,
+        cul_level: u8,
+        dc_category: u8,
+    ) {
+        let w4 = TX_WIDTH[tx_size as usize] as u32 >> 2;
+        let h4 = TX_HEIGHT[tx_size as usize] as u32 >> 2;
+        let cols = x4 as usize..(x4 + w4) as usize;
+        self.above_level_context[plane][cols.clone()].fill(cul_level);
+        self.above_dc_context[plane][cols].fill(dc_category);
+
+        let rows = y4 as usize..(y4 + h4) as usize;
+        self.left_level_context[plane][rows.clone()].fill(cul_level);
+        self.left_dc_context[plane][rows].fill(dc_category);
+    }
+
+    /// The positions of the above and left contexts of a transform block
+    /// that are inside of the frame.
+    fn coeff_context_range(
+        &self,
+        plane: usize,
+        x4: u32,
+        y4: u32,
+        tx_size: u8,
+    ) -> (Range<usize>, Range<usize>) {
+        let w4 = TX_WIDTH[tx_size as usize] as u32 >> 2;
+        let h4 = TX_HEIGHT[tx_size as usize] as u32 >> 2;
+        let (sub_x, sub_y) = self.plane_subsampling(plane);
+        let max_x4 = self.ctx.mi_cols >> sub_x;
+        let max_y4 = self.ctx.mi_rows >> sub_y;
+        (
+            x4 as usize..(x4 + w4).min(max_x4) as usize,
+            y4 as usize..(y4 + h4).min(max_y4) as usize,
+        )
+    }
+
+    /// The ctx of all_zero.
+    fn all_zero_ctx(&self, plane: usize, x4: u32, y4: u32, tx_size: u8) -> usize {
+        let (cols, rows) = self.coeff_context_range(plane, x4, y4, tx_size);
+        let w = TX_WIDTH[tx_size as usize] as u32;
+        let h = TX_HEIGHT[tx_size as usize] as u32;
+        let bsize = self.plane_residual_size(plane) as usize;
+        let bw = BLOCK_WIDTH[bsize] as u32;
+        let bh = BLOCK_HEIGHT[bsize] as u32;
+
+        if plane == 0 {
+            let top = self.above_level_context[plane][cols]
+                .iter()
+                .copied()
+                .max()
+                .unwrap_or(0);
+            let left = self.left_level_context[plane][rows]
+                .iter()
+                .copied()
+                .max()
+                .unwrap_or(0);
+            let (max, min) = (top.max(left), top.min(left));
+            if bw == w && bh == h {
+                0
+            } else if top == 0 && left == 0 {
+                1
+            } else if top == 0 || left == 0 {
+                2 + (max > 3) as usize
+            } else if max <= 3 {
+                4
+            } else if min <= 3 {
+                5
+            } else {
+                6
+            }
+        } else {
+            let above = self.above_level_context[plane][cols.clone()]
+                .iter()
+                .chain(&self.above_dc_context[plane][cols])
+                .any(|&v| v != 0);
+            let left = self.left_level_context[plane][rows.clone()]
+                .iter()
+                .chain(&self.left_dc_context[plane][rows])
+                .any(|&v| v != 0);
+
+            let ctx = 7 + above as usize + left as usize;
+            if bw * bh > w * h {
+                ctx + 3
+            } else {
+                ctx
+            }
+        }
+    }
+
+    /// The ctx of dc_sign, from the signs of the dc coefficients of the above
+    /// and left transform blocks.
+    fn dc_sign_ctx(&self, plane: usize, x4: u32, y4: u32, tx_size: u8) -> usize {
+        let (cols, rows) = self.coeff_context_range(plane, x4, y4, tx_size);
+        let dc_sign: i32 = self.above_dc_context[plane][cols]
+            .iter()
+            .chain(&self.left_dc_context[plane][rows])
+            .map(|&dc_category| match dc_category {
+                1 => -1,
+                2 => 1,
+                _ => 0,
+            })
+            .sum();
+
+        match dc_sign.cmp(&0) {
+            Ordering::Less => 1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::TX_SIZES_ALL;
+
+    #[test]
+    fn scans_are_permutations() {
+        for tx_size in 0..TX_SIZES_ALL {
+            for tx_type in [DCT_DCT, IDTX, V_DCT, H_DCT] {
+                if TX_SIZE_SQR_UP[tx_size as usize] >= TX_32X32 && tx_type != DCT_DCT {
+                    continue;
+                }
+
+                let mut scan = get_scan(tx_size, tx_type).to_vec();
+                let adj_tx_size = ADJUSTED_TX_SIZE[tx_size as usize] as usize;
+                let area = TX_WIDTH[adj_tx_size] as usize * TX_HEIGHT[adj_tx_size] as usize;
+                scan.sort_unstable();
+                assert!(
+                    scan.iter().copied().eq(0..area as u16),
+                    "{tx_size} {tx_type}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scan_orders() {
+        assert_eq!(
+            get_scan(TX_4X4, DCT_DCT),
+            [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15]
+        );
+        assert_eq!(get_scan(TX_4X4, IDTX), get_scan(TX_4X4, DCT_DCT));
+        assert_eq!(
+            &get_scan(TX_8X4, V_ADST)[..10],
+            [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
+        );
+        assert_eq!(&get_scan(TX_8X4, H_ADST)[..6], [0, 8, 16, 24, 1, 9]);
+        assert_eq!(
+            &get_scan(TX_4X8, H_FLIPADST)[..10],
+            [0, 4, 8, 12, 16, 20, 24, 28, 1, 5]
+        );
+        assert_eq!(get_scan(TX_64X64, DCT_DCT), DEFAULT_SCAN_32X32);
+        assert_eq!(get_scan(TX_16X64, DCT_DCT), DEFAULT_SCAN_16X32);
+    }
+
+    #[test]
+    fn coeff_base_contexts() {
+        // the eob contexts split the scan positions at 1/8 and 1/4 of the
+        // area.
+        assert_eq!(coeff_base_eob_ctx(TX_8X8, 0), 0);
+        assert_eq!(coeff_base_eob_ctx(TX_8X8, 8), 1);
+        assert_eq!(coeff_base_eob_ctx(TX_8X8, 16), 2);
+        assert_eq!(coeff_base_eob_ctx(TX_8X8, 17), 3);
+        assert_eq!(coeff_base_eob_ctx(TX_64X64, 128), 1);
+
+        // 4x4 at row 1, col 1: right 2, below 3 and below right 1 give
+        // mag = 6 and ctx = Min(3, 4) + Coeff_Base_Ctx_Offset 6.
+        let mut quant = [0; 16];
+        quant[6] = 2;
+        quant[9] = -5;
+        quant[10] = 1;
+        assert_eq!(coeff_base_ctx(&quant, TX_4X4, TX_CLASS_2D, 5), 9);
+        assert_eq!(coeff_base_ctx(&quant, TX_4X4, TX_CLASS_2D, 0), 0);
+
+        // the 1D classes look further along their direction, with a context
+        // offset from the row or column.
+        assert_eq!(coeff_base_ctx(&quant, TX_4X4, TX_CLASS_VERT, 1), 26 + 2);
+        assert_eq!(coeff_base_ctx(&quant, TX_4X4, TX_CLASS_HORIZ, 8), 26 + 2);
+        assert_eq!(coeff_base_ctx(&quant, TX_4X4, TX_CLASS_HORIZ, 15), 26 + 10);
+    }
+
+    #[test]
+    fn coeff_br_contexts() {
+        let mut quant = [0; 64];
+        quant[1] = 20;
+        quant[8] = 3;
+        quant[9] = 4;
+        // Min(20, 15) + 3 + 4 = 22, Min((22 + 1) >> 1, 6) = 6
+        assert_eq!(coeff_br_ctx(&quant, TX_8X8, TX_CLASS_2D, 0), 6);
+        assert_eq!(coeff_br_ctx(&quant, TX_8X8, TX_CLASS_2D, 1), 2 + 7);
+        assert_eq!(coeff_br_ctx(&quant, TX_8X8, TX_CLASS_2D, 2), 14);
+        assert_eq!(coeff_br_ctx(&quant, TX_8X8, TX_CLASS_VERT, 1), 2 + 7);
+        assert_eq!(coeff_br_ctx(&quant, TX_8X8, TX_CLASS_HORIZ, 1), 2 + 14);
+    }
+
+    #[test]
+    fn read_coeffs_from_zeros() {
+        // all zeros decode the first symbol of every cdf, eob_pt_16 = 0 so
+        // there is a single coefficient, coeff_base_eob = 0 gives level 1
+        // and dc_sign = 0 makes it positive.
+        let data = [0x00; 16];
+        let mut sd = SymbolDecoder::new(&data, false);
+        let mut cdf = CdfContext::new(0);
+        let (coefficients, cul_level, dc_category) =
+            read_coeffs(&mut sd, &mut cdf, TX_4X4, DCT_DCT, 0, 0).unwrap();
+
+        assert_eq!(coefficients.eob, 1);
+        assert_eq!(coefficients.quant[0], 1);
+        assert!(coefficients.quant[1..].iter().all(|&v| v == 0));
+        assert_eq!((cul_level, dc_category), (1, 2));
+    }
+
+    #[test]
+    fn read_coeffs_from_ones() {
+        // all ones decode the last symbol of every cdf, eob is the whole
+        // block, every level goes through the coeff_br range to the golomb
+        // remainder 1 and is negative.
+        let data = [0xFF; 256];
+        for tx_size in [TX_4X4, TX_8X8, TX_16X8] {
+            let mut sd = SymbolDecoder::new(&data, false);
+            let mut cdf = CdfContext::new(0);
+            let (coefficients, cul_level, dc_category) =
+                read_coeffs(&mut sd, &mut cdf, tx_size, DCT_DCT, 1, 2).unwrap();
+
+            let area = TX_WIDTH[tx_size as usize] as usize * TX_HEIGHT[tx_size as usize] as usize;
+            assert_eq!(coefficients.eob as usize, area);
+            assert_eq!(coefficients.quant.len(), area);
+            assert!(coefficients.quant.iter().all(|&v| v == -15));
+            assert_eq!((cul_level, dc_category), (63, 1));
+        }
+    }
+
+    #[test]
+    fn golomb_length_is_limited() {
+        let data = [0x00; 16];
+        let mut sd = SymbolDecoder::new(&data, false);
+        assert_eq!(
+            read_golomb(&mut sd),
+            Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::GolombLength))
+        );
+
+        let data = [0x5F, 0xFF];
+        let mut sd = SymbolDecoder::new(&data, false);
+        // length bits 0 1, then data bit 0 gives 0b10.
+        assert_eq!(read_golomb(&mut sd), Ok(2));
+    }
+}
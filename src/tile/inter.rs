@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use crate::{
+    constants::{
+        COMPOUND_AVERAGE, COMPOUND_DIFFWTD, COMPOUND_DISTANCE, COMPOUND_INTRA, COMPOUND_WEDGE,
+        DC_PRED, H_PRED, II_DC_PRED, II_H_PRED, II_V_PRED, INTRA_FRAME, MAX_FRAME_DISTANCE,
+        MI_SIZE, MI_SIZE_LOG2, SMOOTH_PRED, V_PRED,
+    },
+    obu::frame_header::get_relative_dist,
+    picture::Picture,
+    predict::{
+        compound::{
+            blend_mask, difference_weight_mask, distance_weights, intra_mode_variant_mask,
+            wedge_mask,
+        },
+        inter::{block_inter_prediction, InterRound, ScaleFactors},
+        intra::IntraBlock,
+    },
+    tables::{
+        BLOCK_HEIGHT, BLOCK_WIDTH, MI_HEIGHT_LOG2, MI_WIDTH_LOG2, NUM_4X4_BLOCKS_HIGH,
+        NUM_4X4_BLOCKS_WIDE, SUBSAMPLED_SIZE,
+    },
+    util::{clip1, round2},
+};
+
+use super::{mode_info::ModeInfo, TileDecoder};
+
+impl TileDecoder<'_> {
+    /// The inter part of compute_prediction( ), the planes of an inter block
+    /// are predicted as a whole before the residual is decoded.
+    ///
+    /// A chroma block of a block smaller than 8x8 covers the luma of its
+    /// neighbours as well, each part of it is predicted with the motion
+    /// vectors of the luma block it is collocated with, unless one of those
+    /// blocks is intra.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#compute-prediction-process
+    pub fn predict_inter_block(&mut self) {
+        let num_planes = if self.block.has_chroma { 3 } else { 1 };
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        for plane in 0..num_planes {
+            let (sub_x, sub_y) = self.plane_subsampling(plane);
+            let plane_size =
+                SUBSAMPLED_SIZE[self.mi.mi_size as usize][sub_x as usize][sub_y as usize];
+            let num4x4_w = NUM_4X4_BLOCKS_WIDE[plane_size as usize] as u32;
+            let num4x4_h = NUM_4X4_BLOCKS_HIGH[plane_size as usize] as u32;
+            let base_x = (mi_col >> sub_x) * MI_SIZE as u32;
+            let base_y = (mi_row >> sub_y) * MI_SIZE as u32;
+            let mut cand_row = (mi_row >> sub_y) << sub_y;
+            let mut cand_col = (mi_col >> sub_x) << sub_x;
+            if self.mi.ref_frame[1] == INTRA_FRAME as i8 {
+                self.predict_inter_intra(plane, base_x, base_y, plane_size);
+            }
+
+            let mut pred_w = (BLOCK_WIDTH[self.mi.mi_size as usize] as u32) >> sub_x;
+            let mut pred_h = (BLOCK_HEIGHT[self.mi.mi_size as usize] as u32) >> sub_y;
+            let some_use_intra = (0..num4x4_h << sub_y).any(|r| {
+                (0..num4x4_w << sub_x).any(|c| {
+                    self.cand_mode_info(cand_row + r, cand_col + c).ref_frame[0]
+                        == INTRA_FRAME as i8
+                })
+            });
+
+            if some_use_intra {
+                pred_w = num4x4_w * 4;
+                pred_h = num4x4_h * 4;
+                cand_row = mi_row;
+                cand_col = mi_col;
+            }
+
+            for (r, y) in (0..num4x4_h * 4).step_by(pred_h as usize).enumerate() {
+                for (c, x) in (0..num4x4_w * 4).step_by(pred_w as usize).enumerate() {
+                    let cand = *self.cand_mode_info(cand_row + r as u32, cand_col + c as u32);
+                    self.predict_inter(
+                        plane,
+                        base_x + x,
+                        base_y + y,
+                        pred_w as usize,
+                        pred_h as usize,
+                        &cand,
+                    );
+                }
+            }
+        }
+    }
+
+    /// The mode info at a position of the frame, the block that is being
+    /// decoded is only written to the frame once it is reconstructed.
+    fn cand_mode_info(&self, row: u32, col: u32) -> &ModeInfo {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize] as u32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize] as u32;
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        if (mi_row..mi_row + bh4).contains(&row) && (mi_col..mi_col + bw4).contains(&col) {
+            &self.mi
+        } else {
+            self.ctx.mode_info.get(row, col)
+        }
+    }
+
+    /// The picture of the reference frame refFrame.
+    fn reference_picture(&self, ref_frame: i8) -> Option<(Arc<Picture>, ScaleFactors)> {
+        let ref_idx = self.frame_header.ref_frame_idx[ref_frame as usize - 1] as usize;
+        let r = self.ctx.refs[ref_idx].as_ref()?;
+        let scale = ScaleFactors::new(
+            r.upscaled_width as u32,
+            r.frame_height as u32,
+            self.ctx.frame_width as u32,
+            self.ctx.frame_height as u32,
+        );
+
+        Some((r.picture.clone(), scale))
+    }
+
+    /// predict_inter( plane, baseX, baseY, w, h, candRow, candCol ), predicts
+    /// a w x h part of a plane with the reference frames, motion vectors and
+    /// interpolation filters of cand, the mode info at candRow, candCol.
+    ///
+    /// The predictions of a compound block are averaged, weighted by the
+    /// distances to the reference frames or blended with a mask, and the
+    /// inter prediction of an inter intra block is blended with the intra
+    /// prediction that is already in CurrFrame.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#inter-prediction-process
+    fn predict_inter(&mut self, plane: usize, x: u32, y: u32, w: usize, h: usize, cand: &ModeInfo) {
+        let is_compound = cand.ref_frame[1] > INTRA_FRAME as i8;
+        let is_inter_intra = self.mi.is_inter && self.mi.ref_frame[1] == INTRA_FRAME as i8;
+        let bit_depth = self.ctx.bit_depth;
+        let round = InterRound::new(bit_depth, is_compound);
+        let (sub_x, sub_y) = self.plane_subsampling(plane);
+
+        let mut preds = Vec::with_capacity(2);
+        for ref_list in 0..1 + is_compound as usize {
+            let Some((reference, scale)) = self.reference_picture(cand.ref_frame[ref_list]) else {
+                return;
+            };
+
+            let pos = scale.scale(x as i32, y as i32, cand.mv[ref_list], sub_x, sub_y);
+            preds.push(block_inter_prediction(
+                &reference.planes[plane],
+                &pos,
+                w,
+                h,
+                cand.interp_filter,
+                &round,
+            ));
+        }
+
+        match self.mi.compound_type {
+            COMPOUND_WEDGE if plane == 0 => wedge_mask(
+                &mut self.mask,
+                self.mi.mi_size,
+                self.mi.wedge_sign,
+                self.mi.wedge_index,
+            ),
+            COMPOUND_INTRA => {
+                intra_mode_variant_mask(&mut self.mask, w, h, self.mi.interintra_mode);
+            }
+            COMPOUND_DIFFWTD if plane == 0 => difference_weight_mask(
+                &mut self.mask,
+                [&preds[0], &preds[1]],
+                w,
+                self.mi.mask_type,
+                bit_depth,
+                round.post_round,
+            ),
+            _ => {}
+        }
+
+        let weights = if is_compound && self.mi.compound_type == COMPOUND_DISTANCE {
+            self.distance_weights(cand)
+        } else {
+            [1, 1]
+        };
+
+        let Some(picture) = self.ctx.current_frame.as_mut() else {
+            return;
+        };
+
+        // the smooth inter intra masks are built at the resolution of the
+        // plane, all other masks at the resolution of luma.
+        let (mask_sub_x, mask_sub_y) = if is_inter_intra && !self.mi.wedge_interintra {
+            (0, 0)
+        } else {
+            (sub_x, sub_y)
+        };
+
+        let frame = &mut picture.planes[plane];
+        let post_round = round.post_round;
+        for i in 0..h {
+            for j in 0..w {
+                let (fx, fy) = (x as usize + j, y as usize + i);
+                let p0 = preds[0][i * w + j];
+                let value = if !is_compound && !is_inter_intra {
+                    clip1(round2(p0, post_round), bit_depth)
+                } else if is_inter_intra {
+                    let m = blend_mask(&self.mask, j, i, mask_sub_x, mask_sub_y);
+                    let inter = clip1(round2(p0, post_round), bit_depth) as i32;
+                    let intra = frame.get(fx, fy) as i32;
+                    round2(m * intra + (64 - m) * inter, 6) as u16
+                } else {
+                    let p1 = preds[1][i * w + j];
+                    match self.mi.compound_type {
+                        COMPOUND_AVERAGE => clip1(round2(p0 + p1, 1 + post_round), bit_depth),
+                        COMPOUND_DISTANCE => clip1(
+                            round2(weights[0] * p0 + weights[1] * p1, 4 + post_round),
+                            bit_depth,
+                        ),
+                        _ => {
+                            let m = blend_mask(&self.mask, j, i, mask_sub_x, mask_sub_y);
+                            clip1(round2(m * p0 + (64 - m) * p1, 6 + post_round), bit_depth)
+                        }
+                    }
+                };
+
+                frame.set(fx, fy, value);
+            }
+        }
+    }
+
+    /// The distance weights process, FwdWeight and BckWeight of the two
+    /// reference frames of cand.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#distance-weights-process
+    fn distance_weights(&self, cand: &ModeInfo) -> [i32; 2] {
+        let dist = cand.ref_frame.map(|ref_frame| {
+            let hint = self.frame_header.order_hints[ref_frame as usize];
+            let dist = get_relative_dist(self.ctx, hint, self.frame_header.order_hint);
+            dist.abs().clamp(0, MAX_FRAME_DISTANCE as i32)
+        });
+
+        distance_weights(dist)
+    }
+
+    /// The inter intra part of compute_prediction( ), the intra prediction
+    /// of an inter intra block is written to CurrFrame before the inter
+    /// prediction is blended with it.
+    fn predict_inter_intra(&mut self, plane: usize, x: u32, y: u32, plane_size: u8) {
+        let mode = match self.mi.interintra_mode {
+            II_DC_PRED => DC_PRED,
+            II_V_PRED => V_PRED,
+            II_H_PRED => H_PRED,
+            _ => SMOOTH_PRED,
+        };
+
+        let (sub_x, sub_y) = self.plane_subsampling(plane);
+        let sb_mask = if self.sequence_header.use_128x128_superblock {
+            31
+        } else {
+            15
+        };
+
+        let row = ((self.block.mi_row & sb_mask) >> sub_y) as i32;
+        let col = ((self.block.mi_col & sb_mask) >> sub_x) as i32;
+        let num4x4_w = NUM_4X4_BLOCKS_WIDE[plane_size as usize] as i32;
+        let num4x4_h = NUM_4X4_BLOCKS_HIGH[plane_size as usize] as i32;
+        let (have_left, have_above) = if plane == 0 {
+            (self.block.avail_l, self.block.avail_u)
+        } else {
+            (self.block.avail_l_chroma, self.block.avail_u_chroma)
+        };
+
+        let intra_block = IntraBlock {
+            x: x as usize,
+            y: y as usize,
+            log2w: (MI_SIZE_LOG2 + MI_WIDTH_LOG2[plane_size as usize]) as u32,
+            log2h: (MI_SIZE_LOG2 + MI_HEIGHT_LOG2[plane_size as usize]) as u32,
+            have_left,
+            have_above,
+            have_above_rt: self.block_decoded(plane, row - 1, col + num4x4_w),
+            have_below_lft: self.block_decoded(plane, row + num4x4_h, col - 1),
+            max_x: ((self.ctx.mi_cols * MI_SIZE as u32) >> sub_x) as usize - 1,
+            max_y: ((self.ctx.mi_rows * MI_SIZE as u32) >> sub_y) as usize - 1,
+            bit_depth: self.ctx.bit_depth,
+        };
+
+        self.predict_intra(plane, &intra_block, mode);
+    }
+}
@@ -0,0 +1,904 @@
+use crate::{
+    constants::{
+        ALTREF2_FRAME, ALTREF_FRAME, BLOCK_32X32, BLOCK_8X8, BWDREF_FRAME, COMPOUND_AVERAGE,
+        COMPOUND_DIFFWTD, COMPOUND_DISTANCE, COMPOUND_INTRA, COMPOUND_REFERENCE, COMPOUND_WEDGE,
+        COMP_NEWMV_CTXS, GLOBALMV, GLOBAL_GLOBALMV, GOLDEN_FRAME, INTRA_FRAME, LAST2_FRAME,
+        LAST3_FRAME, LAST_FRAME, LOCALWARP, NEARESTMV, NEAREST_NEARESTMV, NEAREST_NEWMV, NEARMV,
+        NEAR_NEARMV, NEAR_NEWMV, NEWMV, NEW_NEARESTMV, NEW_NEARMV, NEW_NEWMV, NONE, OBMC,
+        SEG_LVL_GLOBALMV, SEG_LVL_REF_FRAME, SEG_LVL_SKIP, SIMPLE, SINGLE_REFERENCE, TRANSLATION,
+        UNIDIR_COMP_REFERENCE,
+    },
+    obu::frame_header::{get_relative_dist, InterpolationFilter},
+    predict::inter::ScaleFactors,
+    tables::{
+        BLOCK_HEIGHT, BLOCK_WIDTH, COMPOUND_MODE_CTX_MAP, NUM_4X4_BLOCKS_HIGH, NUM_4X4_BLOCKS_WIDE,
+        SIZE_GROUP, WEDGE_BITS,
+    },
+    Av1DecodeError, Av1DecodeInvalidError,
+};
+
+use super::{mode_info::ModeInfo, TileDecoder};
+
+/// The reference frames of the above and left neighbours of a block, which
+/// the contexts of is_inter and of the reference frame syntax elements are
+/// computed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeighbourRefs {
+    /// AvailU
+    pub avail_u: bool,
+    /// AvailL
+    pub avail_l: bool,
+    /// AboveRefFrame
+    pub above: [i8; 2],
+    /// LeftRefFrame
+    pub left: [i8; 2],
+}
+
+/// check_backward( refFrame )
+#[inline]
+fn check_backward(ref_frame: i8) -> bool {
+    (BWDREF_FRAME as i8..=ALTREF_FRAME as i8).contains(&ref_frame)
+}
+
+/// is_samedir_ref_pair( ref0, ref1 )
+#[inline]
+fn is_samedir_ref_pair(ref0: i8, ref1: i8) -> bool {
+    (ref0 >= BWDREF_FRAME as i8) == (ref1 >= BWDREF_FRAME as i8)
+}
+
+/// ref_count_ctx( counts0, counts1 )
+#[inline]
+fn ref_count_ctx(counts0: u32, counts1: u32) -> usize {
+    if counts0 < counts1 {
+        0
+    } else if counts0 == counts1 {
+        1
+    } else {
+        2
+    }
+}
+
+/// has_nearmv( )
+#[inline]
+fn has_nearmv(mode: u8) -> bool {
+    matches!(mode, NEARMV | NEAR_NEARMV | NEAR_NEWMV | NEW_NEARMV)
+}
+
+/// get_mode( refList ), the single reference mode that a compound mode
+/// uses for one of its reference frames.
+fn get_mode(y_mode: u8, ref_list: usize) -> u8 {
+    if ref_list == 0 {
+        match y_mode {
+            _ if y_mode < NEAREST_NEARESTMV => y_mode,
+            NEW_NEWMV | NEW_NEARESTMV | NEW_NEARMV => NEWMV,
+            NEAREST_NEARESTMV | NEAREST_NEWMV => NEARESTMV,
+            NEAR_NEARMV | NEAR_NEWMV => NEARMV,
+            _ => GLOBALMV,
+        }
+    } else {
+        match y_mode {
+            NEW_NEWMV | NEAREST_NEWMV | NEAR_NEWMV => NEWMV,
+            NEAREST_NEARESTMV | NEW_NEARESTMV => NEARESTMV,
+            NEAR_NEARMV | NEW_NEARMV => NEARMV,
+            _ => GLOBALMV,
+        }
+    }
+}
+
+impl NeighbourRefs {
+    /// AboveIntra
+    fn above_intra(&self) -> bool {
+        self.above[0] <= INTRA_FRAME as i8
+    }
+
+    /// LeftIntra
+    fn left_intra(&self) -> bool {
+        self.left[0] <= INTRA_FRAME as i8
+    }
+
+    /// AboveSingle
+    fn above_single(&self) -> bool {
+        self.above[1] <= INTRA_FRAME as i8
+    }
+
+    /// LeftSingle
+    fn left_single(&self) -> bool {
+        self.left[1] <= INTRA_FRAME as i8
+    }
+
+    /// The context of is_inter.
+    pub fn is_inter_ctx(&self) -> usize {
+        let (above_intra, left_intra) = (self.above_intra(), self.left_intra());
+        match (self.avail_u, self.avail_l) {
+            (true, true) if above_intra && left_intra => 3,
+            (true, true) => (above_intra || left_intra) as usize,
+            (true, false) => 2 * above_intra as usize,
+            (false, true) => 2 * left_intra as usize,
+            _ => 0,
+        }
+    }
+
+    /// The context of comp_mode.
+    pub fn comp_mode_ctx(&self) -> usize {
+        let (above0, left0) = (self.above[0], self.left[0]);
+        match (self.avail_u, self.avail_l) {
+            (true, true) => match (self.above_single(), self.left_single()) {
+                (true, true) => (check_backward(above0) ^ check_backward(left0)) as usize,
+                (true, false) => 2 + (check_backward(above0) || self.above_intra()) as usize,
+                (false, true) => 2 + (check_backward(left0) || self.left_intra()) as usize,
+                _ => 4,
+            },
+            (true, false) if self.above_single() => check_backward(above0) as usize,
+            (false, true) if self.left_single() => check_backward(left0) as usize,
+            (true, false) | (false, true) => 3,
+            _ => 1,
+        }
+    }
+
+    /// The context of comp_ref_type.
+    pub fn comp_ref_type_ctx(&self) -> usize {
+        let [above0, above1] = self.above;
+        let [left0, left1] = self.left;
+        let above_comp_inter = self.avail_u && !self.above_intra() && !self.above_single();
+        let left_comp_inter = self.avail_l && !self.left_intra() && !self.left_single();
+        let above_uni_comp = above_comp_inter && is_samedir_ref_pair(above0, above1);
+        let left_uni_comp = left_comp_inter && is_samedir_ref_pair(left0, left1);
+
+        if self.avail_u && !self.above_intra() && self.avail_l && !self.left_intra() {
+            let samedir = is_samedir_ref_pair(above0, left0) as usize;
+            if !above_comp_inter && !left_comp_inter {
+                1 + 2 * samedir
+            } else if !above_comp_inter {
+                if !left_uni_comp {
+                    1
+                } else {
+                    3 + samedir
+                }
+            } else if !left_comp_inter {
+                if !above_uni_comp {
+                    1
+                } else {
+                    3 + samedir
+                }
+            } else if !above_uni_comp && !left_uni_comp {
+                0
+            } else if !above_uni_comp || !left_uni_comp {
+                2
+            } else {
+                3 + ((above0 == BWDREF_FRAME as i8) == (left0 == BWDREF_FRAME as i8)) as usize
+            }
+        } else if self.avail_u && self.avail_l {
+            if above_comp_inter {
+                1 + 2 * above_uni_comp as usize
+            } else if left_comp_inter {
+                1 + 2 * left_uni_comp as usize
+            } else {
+                2
+            }
+        } else if above_comp_inter {
+            4 * above_uni_comp as usize
+        } else if left_comp_inter {
+            4 * left_uni_comp as usize
+        } else {
+            2
+        }
+    }
+
+    /// count_refs( frameType )
+    fn count_refs(&self, frame_type: u8) -> u32 {
+        let mut c = 0;
+        if self.avail_u {
+            c += self
+                .above
+                .iter()
+                .filter(|&&r| r == frame_type as i8)
+                .count();
+        }
+
+        if self.avail_l {
+            c += self.left.iter().filter(|&&r| r == frame_type as i8).count();
+        }
+
+        c as u32
+    }
+
+    /// The context of the reference frame syntax elements that choose
+    /// between the frame types of `refs0` and the frame types of `refs1`,
+    /// from the number of times the neighbours use each group.
+    pub fn ref_ctx(&self, refs0: &[u8], refs1: &[u8]) -> usize {
+        let counts0 = refs0.iter().map(|&r| self.count_refs(r)).sum();
+        let counts1 = refs1.iter().map(|&r| self.count_refs(r)).sum();
+        ref_count_ctx(counts0, counts1)
+    }
+}
+
+impl TileDecoder<'_> {
+    /// LeftRefFrame and AboveRefFrame of the block that is being decoded.
+    pub fn neighbour_refs(&self) -> NeighbourRefs {
+        let mode_info = &self.ctx.mode_info;
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        let (avail_u, avail_l) = (self.block.avail_u, self.block.avail_l);
+        let none = [INTRA_FRAME as i8, NONE];
+        NeighbourRefs {
+            avail_u,
+            avail_l,
+            above: if avail_u {
+                mode_info.get(mi_row - 1, mi_col).ref_frame
+            } else {
+                none
+            },
+            left: if avail_l {
+                mode_info.get(mi_row, mi_col - 1).ref_frame
+            } else {
+                none
+            },
+        }
+    }
+
+    /// inter_block_mode_info( )
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#inter-block-mode-info-syntax
+    pub fn inter_block_mode_info(&mut self, refs: &NeighbourRefs) -> Result<(), Av1DecodeError> {
+        self.mi.palette_size = [0; 2];
+        self.read_ref_frames(refs);
+        let is_compound = self.mi.ref_frame[1] > INTRA_FRAME as i8;
+        self.mv_stack = self.find_mv_stack(is_compound);
+
+        let segment_id = self.mi.segment_id;
+        self.mi.y_mode = if self.mi.skip_mode {
+            NEAREST_NEARESTMV
+        } else if self.seg_feature_active(segment_id, SEG_LVL_SKIP)
+            || self.seg_feature_active(segment_id, SEG_LVL_GLOBALMV)
+        {
+            GLOBALMV
+        } else if is_compound {
+            // compound_mode	S()
+            let ctx = COMPOUND_MODE_CTX_MAP[(self.mv_stack.ref_mv_context >> 1) as usize]
+                [self.mv_stack.new_mv_context.min(COMP_NEWMV_CTXS - 1) as usize];
+            let cdf = &mut self.cdf.compound_mode_cdf[ctx as usize];
+            NEAREST_NEARESTMV + self.sd.read_symbol(cdf) as u8
+        } else {
+            self.read_single_mode()
+        };
+
+        let ref_mv_idx = self.read_ref_mv_idx();
+        self.assign_mv(is_compound, ref_mv_idx)?;
+        self.read_interintra_mode(is_compound);
+        self.read_motion_mode(is_compound);
+        self.read_compound_type(is_compound, refs);
+        self.read_interp_filter()
+    }
+
+    /// read_ref_frames( )
+    fn read_ref_frames(&mut self, refs: &NeighbourRefs) {
+        let segment_id = self.mi.segment_id;
+        self.mi.ref_frame = if self.mi.skip_mode {
+            self.frame_header.skip_mode_frame.map(|r| r as i8)
+        } else if self.seg_feature_active(segment_id, SEG_LVL_REF_FRAME) {
+            let params = &self.frame_header.segmentation_params;
+            let ref_frame = params.feature_data[segment_id as usize][SEG_LVL_REF_FRAME as usize];
+            [ref_frame as i8, NONE]
+        } else if self.seg_feature_active(segment_id, SEG_LVL_SKIP)
+            || self.seg_feature_active(segment_id, SEG_LVL_GLOBALMV)
+        {
+            [LAST_FRAME as i8, NONE]
+        } else {
+            let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize];
+            let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize];
+            let comp_mode = if self.frame_header.reference_select && bw4.min(bh4) >= 2 {
+                // comp_mode	S()
+                let cdf = &mut self.cdf.comp_mode_cdf[refs.comp_mode_ctx()];
+                self.sd.read_symbol(cdf) as u8
+            } else {
+                SINGLE_REFERENCE
+            };
+
+            if comp_mode == COMPOUND_REFERENCE {
+                self.read_compound_ref_frames(refs).map(|r| r as i8)
+            } else {
+                [self.read_single_ref_frame(refs) as i8, NONE]
+            }
+        };
+    }
+
+    /// The compound reference frames of read_ref_frames( ).
+    fn read_compound_ref_frames(&mut self, refs: &NeighbourRefs) -> [u8; 2] {
+        // comp_ref_type	S()
+        let cdf = &mut self.cdf.comp_ref_type_cdf[refs.comp_ref_type_ctx()];
+        let comp_ref_type = self.sd.read_symbol(cdf) as u8;
+        if comp_ref_type == UNIDIR_COMP_REFERENCE {
+            // uni_comp_ref	S()
+            let ctx = refs.ref_ctx(
+                &[LAST_FRAME, LAST2_FRAME, LAST3_FRAME, GOLDEN_FRAME],
+                &[BWDREF_FRAME, ALTREF2_FRAME, ALTREF_FRAME],
+            );
+            if self.sd.read_symbol(&mut self.cdf.uni_comp_ref_cdf[ctx][0]) == 1 {
+                return [BWDREF_FRAME, ALTREF_FRAME];
+            }
+
+            // uni_comp_ref_p1	S()
+            let ctx = refs.ref_ctx(&[LAST2_FRAME], &[LAST3_FRAME, GOLDEN_FRAME]);
+            if self.sd.read_symbol(&mut self.cdf.uni_comp_ref_cdf[ctx][1]) == 0 {
+                return [LAST_FRAME, LAST2_FRAME];
+            }
+
+            // uni_comp_ref_p2	S()
+            let ctx = refs.ref_ctx(&[LAST3_FRAME], &[GOLDEN_FRAME]);
+            return if self.sd.read_symbol(&mut self.cdf.uni_comp_ref_cdf[ctx][2]) == 1 {
+                [LAST_FRAME, GOLDEN_FRAME]
+            } else {
+                [LAST_FRAME, LAST3_FRAME]
+            };
+        }
+
+        // comp_ref	S()
+        let ctx = refs.ref_ctx(&[LAST_FRAME, LAST2_FRAME], &[LAST3_FRAME, GOLDEN_FRAME]);
+        let ref0 = if self.sd.read_symbol(&mut self.cdf.comp_ref_cdf[ctx][0]) == 0 {
+            // comp_ref_p1	S()
+            let ctx = refs.ref_ctx(&[LAST_FRAME], &[LAST2_FRAME]);
+            if self.sd.read_symbol(&mut self.cdf.comp_ref_cdf[ctx][1]) == 1 {
+                LAST2_FRAME
+            } else {
+                LAST_FRAME
+            }
+        } else {
+            // comp_ref_p2	S()
+            let ctx = refs.ref_ctx(&[LAST3_FRAME], &[GOLDEN_FRAME]);
+            if self.sd.read_symbol(&mut self.cdf.comp_ref_cdf[ctx][2]) == 1 {
+                GOLDEN_FRAME
+            } else {
+                LAST3_FRAME
+            }
+        };
+
+        // comp_bwdref	S()
+        let ctx = refs.ref_ctx(&[BWDREF_FRAME, ALTREF2_FRAME], &[ALTREF_FRAME]);
+        let ref1 = if self.sd.read_symbol(&mut self.cdf.comp_bwd_ref_cdf[ctx][0]) == 0 {
+            // comp_bwdref_p1	S()
+            let ctx = refs.ref_ctx(&[BWDREF_FRAME], &[ALTREF2_FRAME]);
+            if self.sd.read_symbol(&mut self.cdf.comp_bwd_ref_cdf[ctx][1]) == 1 {
+                ALTREF2_FRAME
+            } else {
+                BWDREF_FRAME
+            }
+        } else {
+            ALTREF_FRAME
+        };
+
+        [ref0, ref1]
+    }
+
+    /// The single reference frame of read_ref_frames( ).
+    fn read_single_ref_frame(&mut self, refs: &NeighbourRefs) -> u8 {
+        // single_ref_p1	S()
+        let ctx = refs.ref_ctx(
+            &[LAST_FRAME, LAST2_FRAME, LAST3_FRAME, GOLDEN_FRAME],
+            &[BWDREF_FRAME, ALTREF2_FRAME, ALTREF_FRAME],
+        );
+        if self.sd.read_symbol(&mut self.cdf.single_ref_cdf[ctx][0]) == 1 {
+            // single_ref_p2	S()
+            let ctx = refs.ref_ctx(&[BWDREF_FRAME, ALTREF2_FRAME], &[ALTREF_FRAME]);
+            if self.sd.read_symbol(&mut self.cdf.single_ref_cdf[ctx][1]) == 1 {
+                return ALTREF_FRAME;
+            }
+
+            // single_ref_p6	S()
+            let ctx = refs.ref_ctx(&[BWDREF_FRAME], &[ALTREF2_FRAME]);
+            if self.sd.read_symbol(&mut self.cdf.single_ref_cdf[ctx][5]) == 1 {
+                ALTREF2_FRAME
+            } else {
+                BWDREF_FRAME
+            }
+        } else {
+            // single_ref_p3	S()
+            let ctx = refs.ref_ctx(&[LAST_FRAME, LAST2_FRAME], &[LAST3_FRAME, GOLDEN_FRAME]);
+            if self.sd.read_symbol(&mut self.cdf.single_ref_cdf[ctx][2]) == 1 {
+                // single_ref_p5	S()
+                let ctx = refs.ref_ctx(&[LAST3_FRAME], &[GOLDEN_FRAME]);
+                if self.sd.read_symbol(&mut self.cdf.single_ref_cdf[ctx][4]) == 1 {
+                    GOLDEN_FRAME
+                } else {
+                    LAST3_FRAME
+                }
+            } else {
+                // single_ref_p4	S()
+                let ctx = refs.ref_ctx(&[LAST_FRAME], &[LAST2_FRAME]);
+                if self.sd.read_symbol(&mut self.cdf.single_ref_cdf[ctx][3]) == 1 {
+                    LAST2_FRAME
+                } else {
+                    LAST_FRAME
+                }
+            }
+        }
+    }
+
+    /// The YMode of a single reference block, from new_mv, zero_mv and
+    /// ref_mv.
+    fn read_single_mode(&mut self) -> u8 {
+        // new_mv	S()
+        let ctx = self.mv_stack.new_mv_context as usize;
+        if self.sd.read_symbol(&mut self.cdf.new_mv_cdf[ctx]) == 0 {
+            return NEWMV;
+        }
+
+        // zero_mv	S()
+        let ctx = self.mv_stack.zero_mv_context as usize;
+        if self.sd.read_symbol(&mut self.cdf.zero_mv_cdf[ctx]) == 0 {
+            return GLOBALMV;
+        }
+
+        // ref_mv	S()
+        let ctx = self.mv_stack.ref_mv_context as usize;
+        if self.sd.read_symbol(&mut self.cdf.ref_mv_cdf[ctx]) == 0 {
+            NEARESTMV
+        } else {
+            NEARMV
+        }
+    }
+
+    /// RefMvIdx, the candidate of the stack that is used by the NEWMV and
+    /// NEARMV modes, coded with the drl_mode syntax elements.
+    fn read_ref_mv_idx(&mut self) -> usize {
+        let y_mode = self.mi.y_mode;
+        let start = if y_mode == NEWMV || y_mode == NEW_NEWMV {
+            0
+        } else if has_nearmv(y_mode) {
+            1
+        } else {
+            return 0;
+        };
+
+        let mut ref_mv_idx = start;
+        for idx in start..start + 2 {
+            if self.mv_stack.num_mv_found > idx + 1 {
+                // drl_mode	S()
+                let ctx = self.mv_stack.drl_ctx_stack[idx] as usize;
+                if self.sd.read_symbol(&mut self.cdf.drl_mode_cdf[ctx]) == 0 {
+                    return idx;
+                }
+
+                ref_mv_idx = idx + 1;
+            }
+        }
+
+        ref_mv_idx
+    }
+
+    /// assign_mv( isCompound ) for an inter block, the motion vector of each
+    /// reference frame is either a candidate of the stack, the global motion
+    /// vector, or a candidate plus a coded difference.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#assign-mv-semantics
+    fn assign_mv(&mut self, is_compound: bool, ref_mv_idx: usize) -> Result<(), Av1DecodeError> {
+        for i in 0..1 + is_compound as usize {
+            let comp_mode = get_mode(self.mi.y_mode, i);
+            let pred_mv = if comp_mode == GLOBALMV {
+                self.mv_stack.global_mvs[i]
+            } else {
+                let pos = if comp_mode == NEARESTMV
+                    || comp_mode == NEWMV && self.mv_stack.num_mv_found <= 1
+                {
+                    0
+                } else {
+                    ref_mv_idx
+                };
+
+                self.mv_stack.ref_stack_mv[pos][i]
+            };
+
+            let mv = if comp_mode == NEWMV {
+                self.read_mv(pred_mv)
+            } else {
+                pred_mv
+            };
+
+            if !self.is_mv_valid(mv) {
+                return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::MotionVector));
+            }
+
+            self.mi.mv[i] = mv;
+        }
+
+        Ok(())
+    }
+
+    /// read_interintra_mode( isCompound )
+    fn read_interintra_mode(&mut self, is_compound: bool) {
+        let mi_size = self.mi.mi_size;
+        self.mi.interintra = false;
+        if self.mi.skip_mode
+            || !self.sequence_header.enable_interintra_compound
+            || is_compound
+            || !(BLOCK_8X8..=BLOCK_32X32).contains(&mi_size)
+        {
+            return;
+        }
+
+        // interintra	S()
+        let ctx = SIZE_GROUP[mi_size as usize] as usize - 1;
+        self.mi.interintra = self.sd.read_symbol(&mut self.cdf.inter_intra_cdf[ctx]) == 1;
+        if self.mi.interintra {
+            // interintra_mode	S()
+            let cdf = &mut self.cdf.inter_intra_mode_cdf[ctx];
+            self.mi.interintra_mode = self.sd.read_symbol(cdf) as u8;
+            self.mi.ref_frame[1] = INTRA_FRAME as i8;
+            self.mi.angle_delta_y = 0;
+            self.mi.angle_delta_uv = 0;
+            self.mi.use_filter_intra = false;
+
+            // wedge_interintra	S()
+            let cdf = &mut self.cdf.wedge_inter_intra_cdf[mi_size as usize];
+            self.mi.wedge_interintra = self.sd.read_symbol(cdf) == 1;
+            if self.mi.wedge_interintra {
+                // wedge_index	S()
+                let cdf = &mut self.cdf.wedge_index_cdf[mi_size as usize];
+                self.mi.wedge_index = self.sd.read_symbol(cdf) as u8;
+                self.mi.wedge_sign = 0;
+            }
+        }
+    }
+
+    /// read_motion_mode( isCompound ), OBMC and local warp are only coded
+    /// for single reference blocks of at least 8x8 that have an inter
+    /// neighbour.
+    fn read_motion_mode(&mut self, is_compound: bool) {
+        self.mi.motion_mode = SIMPLE;
+        let mi_size = self.mi.mi_size as usize;
+        if self.mi.skip_mode
+            || !self.frame_header.is_motion_mode_switchable
+            || BLOCK_WIDTH[mi_size].min(BLOCK_HEIGHT[mi_size]) < 8
+        {
+            return;
+        }
+
+        let force_integer_mv = self.frame_header.force_integer_mv;
+        let gm_type = &self.frame_header.global_motion_params.gm_type;
+        let y_mode = self.mi.y_mode;
+        if !force_integer_mv
+            && (y_mode == GLOBALMV || y_mode == GLOBAL_GLOBALMV)
+            && gm_type[self.mi.ref_frame[0] as usize] > TRANSLATION
+        {
+            return;
+        }
+
+        if is_compound
+            || self.mi.ref_frame[1] == INTRA_FRAME as i8
+            || !self.mv_predictor().has_overlappable_candidates()
+        {
+            return;
+        }
+
+        self.warp_samples = self.mv_predictor().find_warp_samples(self.mi.mv[0]);
+        if force_integer_mv
+            || self.warp_samples.num_samples == 0
+            || !self.frame_header.allow_warped_motion
+            || self.is_scaled(self.mi.ref_frame[0])
+        {
+            // use_obmc	S()
+            if self.sd.read_symbol(&mut self.cdf.use_obmc_cdf[mi_size]) == 1 {
+                self.mi.motion_mode = OBMC;
+            }
+        } else {
+            // motion_mode	S()
+            let cdf = &mut self.cdf.motion_mode_cdf[mi_size];
+            self.mi.motion_mode = self.sd.read_symbol(cdf) as u8;
+        }
+    }
+
+    /// is_scaled( refFrame ), the reference frame has another size than the
+    /// current frame.
+    pub fn is_scaled(&self, ref_frame: i8) -> bool {
+        let ref_idx = self.frame_header.ref_frame_idx[ref_frame as usize - 1] as usize;
+        let Some(r) = self.ctx.refs[ref_idx].as_ref() else {
+            return false;
+        };
+
+        ScaleFactors::new(
+            r.upscaled_width as u32,
+            r.frame_height as u32,
+            self.ctx.frame_width as u32,
+            self.ctx.frame_height as u32,
+        )
+        .is_scaled()
+    }
+
+    /// read_compound_type( isCompound )
+    fn read_compound_type(&mut self, is_compound: bool, refs: &NeighbourRefs) {
+        self.mi.comp_group_idx = 0;
+        self.mi.compound_idx = 1;
+        if self.mi.skip_mode {
+            self.mi.compound_type = COMPOUND_AVERAGE;
+            return;
+        }
+
+        if !is_compound {
+            self.mi.compound_type = if !self.mi.interintra {
+                COMPOUND_AVERAGE
+            } else if self.mi.wedge_interintra {
+                COMPOUND_WEDGE
+            } else {
+                COMPOUND_INTRA
+            };
+
+            return;
+        }
+
+        let mi_size = self.mi.mi_size as usize;
+        if self.sequence_header.enable_masked_compound {
+            // comp_group_idx	S()
+            let ctx = self.comp_group_idx_ctx(refs);
+            let cdf = &mut self.cdf.comp_group_idx_cdf[ctx];
+            self.mi.comp_group_idx = self.sd.read_symbol(cdf) as u8;
+        }
+
+        self.mi.compound_type = if self.mi.comp_group_idx == 0 {
+            if self.sequence_header.enable_jnt_comp {
+                // compound_idx	S()
+                let ctx = self.compound_idx_ctx(refs);
+                let cdf = &mut self.cdf.compound_idx_cdf[ctx];
+                self.mi.compound_idx = self.sd.read_symbol(cdf) as u8;
+                if self.mi.compound_idx == 1 {
+                    COMPOUND_AVERAGE
+                } else {
+                    COMPOUND_DISTANCE
+                }
+            } else {
+                COMPOUND_AVERAGE
+            }
+        } else if WEDGE_BITS[mi_size] == 0 {
+            COMPOUND_DIFFWTD
+        } else {
+            // compound_type	S()
+            let cdf = &mut self.cdf.compound_type_cdf[mi_size];
+            self.sd.read_symbol(cdf) as u8
+        };
+
+        if self.mi.compound_type == COMPOUND_WEDGE {
+            // wedge_index	S()
+            let cdf = &mut self.cdf.wedge_index_cdf[mi_size];
+            self.mi.wedge_index = self.sd.read_symbol(cdf) as u8;
+
+            // wedge_sign	L(1)
+            self.mi.wedge_sign = self.sd.read_literal(1) as u8;
+        } else if self.mi.compound_type == COMPOUND_DIFFWTD {
+            // mask_type	L(1)
+            self.mi.mask_type = self.sd.read_literal(1) as u8;
+        }
+    }
+
+    /// The part of the comp_group_idx and compound_idx contexts that comes
+    /// from the neighbours: `value` of a compound neighbour, or
+    /// `altref_weight` for a single reference neighbour that uses
+    /// ALTREF_FRAME.
+    fn compound_neighbour_ctx(
+        &self,
+        refs: &NeighbourRefs,
+        value: fn(&ModeInfo) -> u8,
+        altref_weight: usize,
+    ) -> usize {
+        let mode_info = &self.ctx.mode_info;
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        let mut ctx = 0;
+        let neighbours = [
+            (refs.avail_u, refs.above_single(), refs.above[0]),
+            (refs.avail_l, refs.left_single(), refs.left[0]),
+        ];
+
+        for (i, (avail, single, ref0)) in neighbours.into_iter().enumerate() {
+            if !avail {
+                continue;
+            }
+
+            if !single {
+                let mi = if i == 0 {
+                    mode_info.get(mi_row - 1, mi_col)
+                } else {
+                    mode_info.get(mi_row, mi_col - 1)
+                };
+
+                ctx += value(mi) as usize;
+            } else if ref0 == ALTREF_FRAME as i8 {
+                ctx += altref_weight;
+            }
+        }
+
+        ctx
+    }
+
+    /// The context of comp_group_idx.
+    fn comp_group_idx_ctx(&self, refs: &NeighbourRefs) -> usize {
+        self.compound_neighbour_ctx(refs, |mi| mi.comp_group_idx, 3)
+            .min(5)
+    }
+
+    /// The context of compound_idx, which also depends on whether the two
+    /// reference frames are at the same distance from the current frame.
+    fn compound_idx_ctx(&self, refs: &NeighbourRefs) -> usize {
+        let order_hints = &self.frame_header.order_hints;
+        let order_hint = self.frame_header.order_hint;
+        let [fwd, bck] = self
+            .mi
+            .ref_frame
+            .map(|r| get_relative_dist(self.ctx, order_hints[r as usize], order_hint).abs());
+
+        let ctx = if fwd == bck { 3 } else { 0 };
+        ctx + self.compound_neighbour_ctx(refs, |mi| mi.compound_idx, 1)
+    }
+
+    /// The interp_filter syntax elements, one filter for each direction
+    /// when enable_dual_filter is equal to 1.
+    fn read_interp_filter(&mut self) -> Result<(), Av1DecodeError> {
+        let interpolation_filter = self.frame_header.interpolation_filter;
+        if interpolation_filter != InterpolationFilter::Switchable {
+            self.mi.interp_filter = [interpolation_filter; 2];
+            return Ok(());
+        }
+
+        let enable_dual_filter = self.sequence_header.enable_dual_filter;
+        for dir in 0..1 + enable_dual_filter as usize {
+            self.mi.interp_filter[dir] = if self.needs_interp_filter() {
+                // interp_filter	S()
+                let ctx = self.interp_filter_ctx(dir);
+                let cdf = &mut self.cdf.interp_filter_cdf[ctx];
+                InterpolationFilter::try_from(self.sd.read_symbol(cdf) as u8)?
+            } else {
+                InterpolationFilter::Eighttap
+            };
+        }
+
+        if !enable_dual_filter {
+            self.mi.interp_filter[1] = self.mi.interp_filter[0];
+        }
+
+        Ok(())
+    }
+
+    /// needs_interp_filter( ), the filter is not coded for blocks that use
+    /// skip mode, local warp, or a global motion that is not a translation.
+    fn needs_interp_filter(&self) -> bool {
+        let mi_size = self.mi.mi_size as usize;
+        let large = BLOCK_WIDTH[mi_size].min(BLOCK_HEIGHT[mi_size]) >= 8;
+        let gm_type = |ref_list: usize| {
+            self.frame_header.global_motion_params.gm_type[self.mi.ref_frame[ref_list] as usize]
+        };
+
+        if self.mi.skip_mode || self.mi.motion_mode == LOCALWARP {
+            false
+        } else if large && self.mi.y_mode == GLOBALMV {
+            gm_type(0) == TRANSLATION
+        } else if large && self.mi.y_mode == GLOBAL_GLOBALMV {
+            gm_type(0) == TRANSLATION || gm_type(1) == TRANSLATION
+        } else {
+            true
+        }
+    }
+
+    /// The context of interp_filter, from the filters of the neighbours that
+    /// use the same first reference frame.
+    fn interp_filter_ctx(&self, dir: usize) -> usize {
+        let ref_frame = self.mi.ref_frame;
+        let ctx = ((dir & 1) * 2 + (ref_frame[1] > INTRA_FRAME as i8) as usize) * 4;
+        let filter_type = |avail: bool, mi_row: u32, mi_col: u32| {
+            if avail {
+                let mi = self.ctx.mode_info.get(mi_row, mi_col);
+                if mi.ref_frame[0] == ref_frame[0] || mi.ref_frame[1] == ref_frame[0] {
+                    return mi.interp_filter[dir] as usize;
+                }
+            }
+
+            3
+        };
+
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        let left_type = filter_type(self.block.avail_l, mi_row, mi_col.wrapping_sub(1));
+        let above_type = filter_type(self.block.avail_u, mi_row.wrapping_sub(1), mi_col);
+        ctx + if left_type == above_type {
+            left_type
+        } else if left_type == 3 {
+            above_type
+        } else if above_type == 3 {
+            left_type
+        } else {
+            3
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONE_REFS: [i8; 2] = [INTRA_FRAME as i8, NONE];
+
+    fn neighbour_refs(above: Option<[u8; 2]>, left: Option<[u8; 2]>) -> NeighbourRefs {
+        let refs = |r: Option<[u8; 2]>| {
+            r.map(|[r0, r1]| [r0 as i8, if r1 == 0 { NONE } else { r1 as i8 }])
+                .unwrap_or(NONE_REFS)
+        };
+
+        NeighbourRefs {
+            avail_u: above.is_some(),
+            avail_l: left.is_some(),
+            above: refs(above),
+            left: refs(left),
+        }
+    }
+
+    #[test]
+    fn compound_modes_of_each_list() {
+        assert_eq!(get_mode(NEARMV, 0), NEARMV);
+        assert_eq!(get_mode(NEW_NEARESTMV, 0), NEWMV);
+        assert_eq!(get_mode(NEW_NEARESTMV, 1), NEARESTMV);
+        assert_eq!(get_mode(NEAR_NEWMV, 0), NEARMV);
+        assert_eq!(get_mode(NEAR_NEWMV, 1), NEWMV);
+        assert_eq!(get_mode(GLOBAL_GLOBALMV, 0), GLOBALMV);
+        assert_eq!(get_mode(GLOBAL_GLOBALMV, 1), GLOBALMV);
+    }
+
+    #[test]
+    fn is_inter_contexts() {
+        let intra = Some([INTRA_FRAME, 0]);
+        let inter = Some([LAST_FRAME, 0]);
+        assert_eq!(neighbour_refs(None, None).is_inter_ctx(), 0);
+        assert_eq!(neighbour_refs(inter, inter).is_inter_ctx(), 0);
+        assert_eq!(neighbour_refs(intra, inter).is_inter_ctx(), 1);
+        assert_eq!(neighbour_refs(intra, None).is_inter_ctx(), 2);
+        assert_eq!(neighbour_refs(intra, intra).is_inter_ctx(), 3);
+    }
+
+    #[test]
+    fn comp_mode_contexts() {
+        let last = Some([LAST_FRAME, 0]);
+        let altref = Some([ALTREF_FRAME, 0]);
+        let compound = Some([LAST_FRAME, ALTREF_FRAME]);
+        assert_eq!(neighbour_refs(None, None).comp_mode_ctx(), 1);
+        assert_eq!(neighbour_refs(last, None).comp_mode_ctx(), 0);
+        assert_eq!(neighbour_refs(None, altref).comp_mode_ctx(), 1);
+        assert_eq!(neighbour_refs(compound, None).comp_mode_ctx(), 3);
+        assert_eq!(neighbour_refs(last, altref).comp_mode_ctx(), 1);
+        assert_eq!(neighbour_refs(last, compound).comp_mode_ctx(), 2);
+        assert_eq!(neighbour_refs(compound, altref).comp_mode_ctx(), 3);
+        assert_eq!(neighbour_refs(compound, compound).comp_mode_ctx(), 4);
+    }
+
+    #[test]
+    fn comp_ref_type_contexts() {
+        let bidir = Some([LAST_FRAME, ALTREF_FRAME]);
+        let unidir = Some([LAST_FRAME, LAST2_FRAME]);
+        let single = Some([LAST_FRAME, 0]);
+        let intra = Some([INTRA_FRAME, 0]);
+        assert_eq!(neighbour_refs(None, None).comp_ref_type_ctx(), 2);
+        assert_eq!(neighbour_refs(unidir, None).comp_ref_type_ctx(), 4);
+        assert_eq!(neighbour_refs(None, bidir).comp_ref_type_ctx(), 0);
+        assert_eq!(neighbour_refs(unidir, intra).comp_ref_type_ctx(), 3);
+        assert_eq!(neighbour_refs(single, single).comp_ref_type_ctx(), 3);
+        assert_eq!(neighbour_refs(single, unidir).comp_ref_type_ctx(), 4);
+        assert_eq!(neighbour_refs(bidir, bidir).comp_ref_type_ctx(), 0);
+        assert_eq!(neighbour_refs(bidir, unidir).comp_ref_type_ctx(), 2);
+        assert_eq!(neighbour_refs(unidir, unidir).comp_ref_type_ctx(), 4);
+    }
+
+    #[test]
+    fn ref_count_contexts() {
+        let refs = neighbour_refs(Some([LAST_FRAME, ALTREF_FRAME]), Some([GOLDEN_FRAME, 0]));
+
+        let fwd = [LAST_FRAME, LAST2_FRAME, LAST3_FRAME, GOLDEN_FRAME];
+        let bwd = [BWDREF_FRAME, ALTREF2_FRAME, ALTREF_FRAME];
+        assert_eq!(refs.ref_ctx(&fwd, &bwd), 2);
+        assert_eq!(refs.ref_ctx(&[LAST_FRAME], &[LAST2_FRAME]), 2);
+        assert_eq!(refs.ref_ctx(&[LAST3_FRAME], &[GOLDEN_FRAME]), 0);
+        assert_eq!(
+            refs.ref_ctx(&[BWDREF_FRAME, ALTREF2_FRAME], &[ALTREF_FRAME]),
+            0
+        );
+        assert_eq!(refs.ref_ctx(&[LAST_FRAME], &[ALTREF_FRAME]), 1);
+
+        // the neighbours are not counted when they are not available.
+        let refs = NeighbourRefs {
+            avail_u: false,
+            ..refs
+        };
+
+        assert_eq!(refs.ref_ctx(&[LAST_FRAME], &[GOLDEN_FRAME]), 0);
+    }
+}
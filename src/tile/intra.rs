@@ -0,0 +1,279 @@
+use crate::{
+    constants::{
+        ANGLE_STEP, DC_PRED, INTRA_FRAME, MI_SIZE, MI_SIZE_LOG2, SMOOTH_H_PRED, SMOOTH_PRED,
+        SMOOTH_V_PRED, UV_CFL_PRED,
+    },
+    predict::{
+        cfl::{predict_chroma_from_luma, CflBlock},
+        intra::{
+            dc_pred, directional_pred, paeth_pred, recursive_pred, smooth_pred, IntraBlock,
+            IntraEdges, MODE_TO_ANGLE,
+        },
+    },
+    tables::{TX_HEIGHT, TX_HEIGHT_LOG2, TX_WIDTH, TX_WIDTH_LOG2},
+};
+
+use super::{mode_info::is_directional_mode, tx::TransformBlock, TileDecoder};
+
+impl TileDecoder<'_> {
+    /// clear_block_decoded_flags( r, c, sbSize4 ), only the positions above
+    /// and to the left of the superblock that are inside of the tile are
+    /// marked as decoded at the start of a superblock.
+    pub fn clear_block_decoded_flags(&mut self, r: u32, c: u32, sb_size4: u32) {
+        for plane in 0..self.ctx.num_planes as usize {
+            let (sub_x, sub_y) = self.plane_subsampling(plane);
+            let sb_width4 = ((self.mi_col_end - c) >> sub_x) as i32;
+            let sb_height4 = ((self.mi_row_end - r) >> sub_y) as i32;
+            let block_decoded = &mut self.block_decoded[plane];
+            for y in -1..=(sb_size4 >> sub_y) as i32 {
+                for x in -1..=(sb_size4 >> sub_x) as i32 {
+                    block_decoded[(y + 1) as usize][(x + 1) as usize] =
+                        y < 0 && x < sb_width4 || x < 0 && y < sb_height4;
+                }
+            }
+
+            block_decoded[(sb_size4 >> sub_y) as usize + 1][0] = false;
+        }
+    }
+
+    /// BlockDecoded[ plane ][ row ][ col ], the position in units of 4
+    /// samples of the plane relative to the superblock has been decoded.
+    pub fn block_decoded(&self, plane: usize, row: i32, col: i32) -> bool {
+        self.block_decoded[plane][(row + 1) as usize][(col + 1) as usize]
+    }
+
+    /// Marks the positions covered by a transform block as decoded.
+    pub fn set_block_decoded(&mut self, block: &TransformBlock) {
+        let plane = block.plane as usize;
+        let (row, col) = self.sub_block_position(block);
+        let step_x = (TX_WIDTH[block.tx_size as usize] >> MI_SIZE_LOG2) as usize;
+        let step_y = (TX_HEIGHT[block.tx_size as usize] >> MI_SIZE_LOG2) as usize;
+        for flags in &mut self.block_decoded[plane][row as usize + 1..][..step_y] {
+            flags[col as usize + 1..][..step_x].fill(true);
+        }
+    }
+
+    /// subBlockMiRow >> subY and subBlockMiCol >> subX, the position of a
+    /// transform block in units of 4 samples of the plane relative to the
+    /// superblock.
+    fn sub_block_position(&self, block: &TransformBlock) -> (i32, i32) {
+        let (sub_x, sub_y) = self.plane_subsampling(block.plane as usize);
+        let sb_mask = if self.sequence_header.use_128x128_superblock {
+            31
+        } else {
+            15
+        };
+
+        let row = (block.start_y << sub_y) >> MI_SIZE_LOG2;
+        let col = (block.start_x << sub_x) >> MI_SIZE_LOG2;
+        (
+            ((row & sb_mask) >> sub_y) as i32,
+            ((col & sb_mask) >> sub_x) as i32,
+        )
+    }
+
+    /// The prediction part of transform_block( ), the transform blocks of
+    /// intra blocks are predicted one at a time from the samples of the
+    /// previously reconstructed transform blocks. The luma transform blocks
+    /// also set the extent of the decoded luma that is used by CFL.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#transform-block-semantics
+    pub fn predict_transform_block(&mut self, block: &TransformBlock) {
+        if self.mi.is_inter {
+            return;
+        }
+
+        let plane = block.plane as usize;
+        let (start_x, start_y) = (block.start_x as usize, block.start_y as usize);
+        let step_x = (TX_WIDTH[block.tx_size as usize] >> MI_SIZE_LOG2) as u32;
+        let step_y = (TX_HEIGHT[block.tx_size as usize] >> MI_SIZE_LOG2) as u32;
+        if self.mi.palette_size[plane.min(1)] > 0 {
+            self.predict_palette(
+                plane,
+                start_x,
+                start_y,
+                block.x as usize,
+                block.y as usize,
+                block.tx_size,
+            );
+        } else {
+            let is_cfl = plane > 0 && self.mi.uv_mode == UV_CFL_PRED;
+            let mode = if plane == 0 {
+                self.mi.y_mode
+            } else if is_cfl {
+                DC_PRED
+            } else {
+                self.mi.uv_mode
+            };
+
+            let (sub_x, sub_y) = self.plane_subsampling(plane);
+            let (row, col) = self.sub_block_position(block);
+            let (avail_l, avail_u) = if plane == 0 {
+                (self.block.avail_l, self.block.avail_u)
+            } else {
+                (self.block.avail_l_chroma, self.block.avail_u_chroma)
+            };
+
+            let intra_block = IntraBlock {
+                x: start_x,
+                y: start_y,
+                log2w: TX_WIDTH_LOG2[block.tx_size as usize] as u32,
+                log2h: TX_HEIGHT_LOG2[block.tx_size as usize] as u32,
+                have_left: avail_l || block.x > 0,
+                have_above: avail_u || block.y > 0,
+                have_above_rt: self.block_decoded(plane, row - 1, col + step_x as i32),
+                have_below_lft: self.block_decoded(plane, row + step_y as i32, col - 1),
+                max_x: ((self.ctx.mi_cols * MI_SIZE as u32) >> sub_x) as usize - 1,
+                max_y: ((self.ctx.mi_rows * MI_SIZE as u32) >> sub_y) as usize - 1,
+                bit_depth: self.ctx.bit_depth,
+            };
+
+            self.predict_intra(plane, &intra_block, mode);
+            if is_cfl {
+                self.predict_cfl(block);
+            }
+        }
+
+        if plane == 0 {
+            self.max_luma_w = block.start_x + step_x * 4;
+            self.max_luma_h = block.start_y + step_y * 4;
+        }
+    }
+
+    /// predict_chroma_from_luma( plane, startX, startY, txSz ), adds the
+    /// scaled luma of the block to the DC prediction of a chroma transform
+    /// block.
+    fn predict_cfl(&mut self, block: &TransformBlock) {
+        let plane = block.plane as usize;
+        let (sub_x, sub_y) = self.plane_subsampling(plane);
+        let cfl_block = CflBlock {
+            start_x: block.start_x as usize,
+            start_y: block.start_y as usize,
+            tx_size: block.tx_size,
+            sub_x,
+            sub_y,
+            max_luma_w: self.max_luma_w as usize,
+            max_luma_h: self.max_luma_h as usize,
+            alpha: if plane == 1 {
+                self.mi.cfl_alpha_u
+            } else {
+                self.mi.cfl_alpha_v
+            } as i32,
+            bit_depth: self.ctx.bit_depth,
+        };
+
+        let Some(picture) = self.ctx.current_frame.as_mut() else {
+            return;
+        };
+
+        let (luma, chroma) = picture.planes.split_at_mut(1);
+        predict_chroma_from_luma(&luma[0], &mut chroma[plane - 1], &cfl_block);
+    }
+
+    /// predict_intra( plane, x, y, haveLeft, haveAbove, haveAboveRt,
+    /// haveBelowLft, mode, log2W, log2H ), predicts a block from its edges
+    /// in CurrFrame and writes the prediction into CurrFrame. The luma
+    /// blocks that use filter intra ignore the mode and are predicted with
+    /// the recursive intra prediction process.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#intra-prediction-process
+    pub fn predict_intra(&mut self, plane: usize, block: &IntraBlock, mode: u8) {
+        let enable_intra_edge_filter = self.sequence_header.enable_intra_edge_filter;
+        let filter_type = self.get_filter_type(plane);
+        let use_filter_intra = plane == 0 && self.mi.use_filter_intra;
+        let filter_intra_mode = self.mi.filter_intra_mode;
+        let angle_delta = if plane == 0 {
+            self.mi.angle_delta_y
+        } else {
+            self.mi.angle_delta_uv
+        };
+
+        let Some(picture) = self.ctx.current_frame.as_mut() else {
+            return;
+        };
+
+        let frame = &mut picture.planes[plane];
+        let mut edges = IntraEdges::new(frame, block);
+        let pred = if use_filter_intra {
+            recursive_pred(block, &edges, filter_intra_mode)
+        } else if is_directional_mode(mode) {
+            let p_angle = MODE_TO_ANGLE[mode as usize] + angle_delta as i32 * ANGLE_STEP as i32;
+            directional_pred(
+                block,
+                &mut edges,
+                p_angle,
+                enable_intra_edge_filter,
+                filter_type,
+            )
+        } else {
+            match mode {
+                SMOOTH_PRED | SMOOTH_V_PRED | SMOOTH_H_PRED => smooth_pred(block, &edges, mode),
+                DC_PRED => dc_pred(block, &edges),
+                // PAETH_PRED
+                _ => paeth_pred(block, &edges),
+            }
+        };
+
+        for (i, row) in pred.chunks_exact(1 << block.log2w).enumerate() {
+            for (j, &v) in row.iter().enumerate() {
+                frame.set(block.x + j, block.y + i, v);
+            }
+        }
+    }
+
+    /// get_filter_type( plane ), the above or left neighbour of the block
+    /// uses a smooth prediction.
+    fn get_filter_type(&self, plane: usize) -> bool {
+        let color_config = &self.sequence_header.color_config;
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        let mut above_smooth = false;
+        let mut left_smooth = false;
+        if plane == 0 && self.block.avail_u || plane > 0 && self.block.avail_u_chroma {
+            let mut r = mi_row - 1;
+            let mut c = mi_col;
+            if plane > 0 {
+                if color_config.subsampling_x && mi_col & 1 == 0 {
+                    c += 1;
+                }
+                if color_config.subsampling_y && mi_row & 1 == 1 {
+                    r -= 1;
+                }
+            }
+
+            above_smooth = self.is_smooth(r, c, plane);
+        }
+
+        if plane == 0 && self.block.avail_l || plane > 0 && self.block.avail_l_chroma {
+            let mut r = mi_row;
+            let mut c = mi_col - 1;
+            if plane > 0 {
+                if color_config.subsampling_x && mi_col & 1 == 1 {
+                    c -= 1;
+                }
+                if color_config.subsampling_y && mi_row & 1 == 0 {
+                    r += 1;
+                }
+            }
+
+            left_smooth = self.is_smooth(r, c, plane);
+        }
+
+        above_smooth || left_smooth
+    }
+
+    /// is_smooth( row, col, plane )
+    fn is_smooth(&self, row: u32, col: u32, plane: usize) -> bool {
+        let mi = self.ctx.mode_info.get(row, col);
+        let mode = if plane == 0 {
+            mi.y_mode
+        } else {
+            if mi.ref_frame[0] > INTRA_FRAME as i8 {
+                return false;
+            }
+
+            mi.uv_mode
+        };
+
+        mode == SMOOTH_PRED || mode == SMOOTH_V_PRED || mode == SMOOTH_H_PRED
+    }
+}
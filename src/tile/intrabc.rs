@@ -0,0 +1,121 @@
+use crate::{
+    constants::{DC_PRED, INTRABC_DELAY_PIXELS, MI_SIZE},
+    obu::frame_header::InterpolationFilter,
+    tables::{NUM_4X4_BLOCKS_HIGH, NUM_4X4_BLOCKS_WIDE, SUBSAMPLED_SIZE},
+    util::round2,
+    Av1DecodeError, Av1DecodeInvalidError,
+};
+
+use super::TileDecoder;
+
+impl TileDecoder<'_> {
+    /// The use_intrabc branch of intra_frame_mode_info( ), the block is
+    /// predicted from an already decoded area of the current frame that is
+    /// pointed to by a whole sample motion vector.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#intra-frame-mode-info-syntax
+    pub fn intrabc_mode_info(&mut self) -> Result<(), Av1DecodeError> {
+        self.mi.is_inter = true;
+        self.mi.y_mode = DC_PRED;
+        self.mi.uv_mode = DC_PRED;
+        self.mi.palette_size = [0; 2];
+        self.mi.interp_filter = [InterpolationFilter::Bilinear; 2];
+
+        self.mv_stack = self.find_mv_stack(false);
+        self.assign_intrabc_mv()
+    }
+
+    /// assign_mv( 0 ) for a block that uses intra block copy. When the
+    /// stack has no usable candidate the prediction points one superblock
+    /// up, or to the left by a superblock and the INTRABC_DELAY_PIXELS in
+    /// the first superblock row of the tile.
+    fn assign_intrabc_mv(&mut self) -> Result<(), Av1DecodeError> {
+        let mut pred_mv = self.mv_stack.ref_stack_mv[0][0];
+        if pred_mv == [0, 0] {
+            pred_mv = self.mv_stack.ref_stack_mv[1][0];
+        }
+
+        if pred_mv == [0, 0] {
+            let sb_size4 = NUM_4X4_BLOCKS_HIGH[self.sb_size() as usize] as i32;
+            let mi_size = MI_SIZE as i32;
+            pred_mv = if (self.block.mi_row as i32) - sb_size4 < self.mi_row_start as i32 {
+                [0, -(sb_size4 * mi_size + INTRABC_DELAY_PIXELS as i32) * 8]
+            } else {
+                [-(sb_size4 * mi_size * 8), 0]
+            };
+        }
+
+        let mv = self.read_mv(pred_mv);
+        if !self.is_mv_valid(mv) {
+            return Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::MotionVector));
+        }
+
+        self.mi.mv[0] = mv;
+        Ok(())
+    }
+
+    /// Predicts the samples of an intra block copy block from the current
+    /// frame before any of the loop filters are applied.
+    ///
+    /// The motion vector is a whole number of luma samples, so the bilinear
+    /// filter of the block inter prediction process only ever samples whole
+    /// or half sample positions of the (subsampled) plane, and reduces to a
+    /// rounded average of the two or four nearest samples.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#block-inter-prediction-process
+    pub fn predict_intrabc(&mut self) {
+        let Some(picture) = self.ctx.current_frame.as_mut() else {
+            return;
+        };
+
+        let mv = self.mi.mv[0];
+        for plane in 0..self.ctx.num_planes as usize {
+            if plane > 0 && !self.block.has_chroma {
+                continue;
+            }
+
+            let (sub_x, sub_y) = if plane == 0 {
+                (0, 0)
+            } else {
+                (
+                    picture.subsampling_x as usize,
+                    picture.subsampling_y as usize,
+                )
+            };
+
+            // A chroma block of a block smaller than 8x8 covers the luma of
+            // its neighbours as well, and is predicted as a whole with the
+            // motion vector of this block.
+            let plane_size = SUBSAMPLED_SIZE[self.mi.mi_size as usize][sub_x][sub_y];
+            let w = NUM_4X4_BLOCKS_WIDE[plane_size as usize] as usize * MI_SIZE as usize;
+            let h = NUM_4X4_BLOCKS_HIGH[plane_size as usize] as usize * MI_SIZE as usize;
+            let base_x = (self.block.mi_col as usize >> sub_x) * MI_SIZE as usize;
+            let base_y = (self.block.mi_row as usize >> sub_y) * MI_SIZE as usize;
+
+            // positions in 1/16 sample units of the plane.
+            let start_x = ((base_x as i32) << 4) + ((2 * mv[1]) >> sub_x);
+            let start_y = ((base_y as i32) << 4) + ((2 * mv[0]) >> sub_y);
+            let taps_x = if start_x & 15 != 0 { 2 } else { 1 };
+            let taps_y = if start_y & 15 != 0 { 2 } else { 1 };
+            let shift = (taps_x == 2) as u32 + (taps_y == 2) as u32;
+
+            let plane = &mut picture.planes[plane];
+            let last_x = plane.width as i32 - 1;
+            let last_y = plane.height as i32 - 1;
+            for i in 0..h {
+                for j in 0..w {
+                    let mut sum = 0;
+                    for t in 0..taps_y {
+                        for s in 0..taps_x {
+                            let y = ((start_y >> 4) + (i + t) as i32).clamp(0, last_y);
+                            let x = ((start_x >> 4) + (j + s) as i32).clamp(0, last_x);
+                            sum += plane.get(x as usize, y as usize) as i32;
+                        }
+                    }
+
+                    plane.set(base_x + j, base_y + i, round2(sum, shift) as u16);
+                }
+            }
+        }
+    }
+}
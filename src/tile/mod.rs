@@ -0,0 +1,311 @@
+pub mod block;
+pub mod coeffs;
+pub mod inter;
+pub mod inter_mode_info;
+pub mod intra;
+pub mod intrabc;
+pub mod mode_info;
+pub mod mv;
+pub mod mvpred;
+pub mod palette;
+pub mod partition;
+pub mod recon;
+pub mod restoration;
+pub mod tx;
+
+use block::Block;
+use mode_info::ModeInfo;
+use mvpred::{MvStack, WarpSamples};
+
+use crate::{
+    cdf::CdfContext,
+    constants::{BLOCK_64X64, FRAME_LF_COUNT, MAX_SB_SIZE},
+    entropy::SymbolDecoder,
+    obu::{frame_header::UncompressedHeader, sequence_header::SequenceHeader, tile_group::Tile},
+    predict::compound::Mask,
+    tables::{NUM_4X4_BLOCKS_WIDE, SGRPROJ_XQD_MID, WIENER_TAPS_MID},
+    Av1DecodeError, Av1DecodeInvalidError, Av1DecoderContext,
+};
+
+/// Decodes the symbols of a single tile into the frame state of the decoder
+/// context.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#decode-tile-syntax
+pub struct TileDecoder<'a> {
+    pub ctx: &'a mut Av1DecoderContext,
+    pub sequence_header: SequenceHeader,
+    pub frame_header: UncompressedHeader,
+    pub sd: SymbolDecoder<'a>,
+    /// The CDF arrays of this tile, a copy of the CDF arrays of the frame.
+    pub cdf: CdfContext,
+    pub mi_row_start: u32,
+    pub mi_row_end: u32,
+    pub mi_col_start: u32,
+    pub mi_col_end: u32,
+    /// The block that is being decoded.
+    pub block: Block,
+    /// The mode info of the block that is being decoded.
+    pub mi: ModeInfo,
+    /// The reference motion vector candidates of the block that is being
+    /// decoded.
+    pub mv_stack: MvStack,
+    /// The local warp samples of the block that is being decoded.
+    pub warp_samples: WarpSamples,
+    /// RefLrWiener
+    pub ref_lr_wiener: [[[i32; 3]; 2]; 3],
+    /// RefSgrXqd
+    pub ref_sgr_xqd: [[i32; 2]; 3],
+    /// CurrentQIndex
+    pub current_q_index: i32,
+    /// DeltaLF
+    pub delta_lf: [i32; FRAME_LF_COUNT as usize],
+    /// ReadDeltas, the delta q and delta lf values are read by the first
+    /// block of each superblock.
+    pub read_deltas: bool,
+    /// AboveSegPredContext
+    pub above_seg_pred_context: Vec<u8>,
+    /// LeftSegPredContext
+    pub left_seg_pred_context: Vec<u8>,
+    /// ColorMapY
+    pub color_map_y: [[u8; 64]; 64],
+    /// ColorMapUV
+    pub color_map_uv: [[u8; 64]; 64],
+    /// Mask, the blending mask of the block that is being predicted, the
+    /// chroma planes reuse the wedge and difference weight masks of luma.
+    pub mask: Box<Mask>,
+    /// AboveLevelContext
+    pub above_level_context: [Vec<u8>; 3],
+    /// AboveDcContext
+    pub above_dc_context: [Vec<u8>; 3],
+    /// LeftLevelContext
+    pub left_level_context: [Vec<u8>; 3],
+    /// LeftDcContext
+    pub left_dc_context: [Vec<u8>; 3],
+    /// BlockDecoded, offset by one so that the row and the column above and
+    /// to the left of the superblock can be indexed.
+    pub block_decoded: [[[bool; 34]; 34]; 3],
+    /// MaxLumaW, the right edge of the luma transform blocks of the block
+    /// that are decoded.
+    pub max_luma_w: u32,
+    /// MaxLumaH
+    pub max_luma_h: u32,
+}
+
+impl<'a> TileDecoder<'a> {
+    pub fn new(ctx: &'a mut Av1DecoderContext, tile: &Tile<'a>) -> Result<Self, Av1DecodeError> {
+        let (Some(sequence_header), Some(frame_header)) = (&ctx.sequence_header, &ctx.frame_header)
+        else {
+            return Err(Av1DecodeError::Invalid(
+                Av1DecodeInvalidError::MissingFrameHeader,
+            ));
+        };
+
+        let sequence_header = sequence_header.clone();
+        let frame_header = frame_header.uncompressed_header.clone();
+        let sd = SymbolDecoder::new(tile.data, frame_header.disable_cdf_update);
+        let cdf = ctx.cdf.clone();
+        let current_q_index = frame_header.quantization_params.base_q_idx as i32;
+        let mut tile_decoder = Self {
+            ctx,
+            sequence_header,
+            frame_header,
+            sd,
+            cdf,
+            mi_row_start: tile.mi_row_start,
+            mi_row_end: tile.mi_row_end,
+            mi_col_start: tile.mi_col_start,
+            mi_col_end: tile.mi_col_end,
+            block: Block::default(),
+            mi: ModeInfo::default(),
+            mv_stack: MvStack::default(),
+            warp_samples: WarpSamples::default(),
+            ref_lr_wiener: [[WIENER_TAPS_MID; 2]; 3],
+            ref_sgr_xqd: [SGRPROJ_XQD_MID; 3],
+            current_q_index,
+            delta_lf: [0; FRAME_LF_COUNT as usize],
+            read_deltas: false,
+            above_seg_pred_context: Vec::new(),
+            left_seg_pred_context: Vec::new(),
+            color_map_y: [[0; 64]; 64],
+            color_map_uv: [[0; 64]; 64],
+            mask: Box::new([[0; MAX_SB_SIZE as usize]; MAX_SB_SIZE as usize]),
+            above_level_context: Default::default(),
+            above_dc_context: Default::default(),
+            left_level_context: Default::default(),
+            left_dc_context: Default::default(),
+            block_decoded: [[[false; 34]; 34]; 3],
+            max_luma_w: 0,
+            max_luma_h: 0,
+        };
+
+        tile_decoder.clear_above_context();
+        Ok(tile_decoder)
+    }
+
+    /// decode_tile( ), the superblocks of the tile are decoded in raster
+    /// order and the partition tree of each one is appended to the
+    /// superblocks of the frame.
+    pub fn decode(&mut self) -> Result<(), Av1DecodeError> {
+        let sb_size = self.sb_size();
+        let sb_size4 = NUM_4X4_BLOCKS_WIDE[sb_size as usize] as usize;
+        for r in (self.mi_row_start..self.mi_row_end).step_by(sb_size4) {
+            self.clear_left_context();
+            for c in (self.mi_col_start..self.mi_col_end).step_by(sb_size4) {
+                self.clear_block_decoded_flags(r, c, sb_size4 as u32);
+                self.read_deltas = self.frame_header.delta_q_params.delta_q_present;
+                self.clear_cdef(r, c);
+                self.read_lr(r, c, sb_size);
+                if let Some(node) = self.decode_partition(r, c, sb_size)? {
+                    self.ctx.superblocks.push(node);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the padding at the end of the tile data, and returns the CDF
+    /// arrays as they are at the end of the tile.
+    pub fn finish(self) -> Result<CdfContext, Av1DecodeError> {
+        self.sd.exit()?;
+        Ok(self.cdf)
+    }
+
+    /// The cdef_idx of the 64x64 block containing the position, None when
+    /// the position is outside of the frame.
+    pub fn cdef_idx(&self, r: u32, c: u32) -> Option<i8> {
+        let cdef_size4 = NUM_4X4_BLOCKS_WIDE[BLOCK_64X64 as usize] as u32;
+        if r >= self.ctx.mi_rows || c >= self.ctx.mi_cols {
+            return None;
+        }
+
+        let cols = self.ctx.mi_cols.div_ceil(cdef_size4);
+        Some(self.ctx.cdef_idx[((r / cdef_size4) * cols + c / cdef_size4) as usize])
+    }
+
+    pub fn set_cdef_idx(&mut self, r: u32, c: u32, cdef_idx: i8) {
+        let cdef_size4 = NUM_4X4_BLOCKS_WIDE[BLOCK_64X64 as usize] as u32;
+        if r >= self.ctx.mi_rows || c >= self.ctx.mi_cols {
+            return;
+        }
+
+        let cols = self.ctx.mi_cols.div_ceil(cdef_size4);
+        self.ctx.cdef_idx[((r / cdef_size4) * cols + c / cdef_size4) as usize] = cdef_idx;
+    }
+
+    /// clear_cdef( r, c ), marks the cdef_idx of the 64x64 blocks of the
+    /// superblock as not read yet.
+    fn clear_cdef(&mut self, r: u32, c: u32) {
+        self.set_cdef_idx(r, c, -1);
+        if self.sequence_header.use_128x128_superblock {
+            let cdef_size4 = NUM_4X4_BLOCKS_WIDE[BLOCK_64X64 as usize] as u32;
+            self.set_cdef_idx(r, c + cdef_size4, -1);
+            self.set_cdef_idx(r + cdef_size4, c, -1);
+            self.set_cdef_idx(r + cdef_size4, c + cdef_size4, -1);
+        }
+    }
+
+    /// is_inside( candR, candC ), the position is inside of the current
+    /// tile.
+    pub fn is_inside(&self, cand_r: i32, cand_c: i32) -> bool {
+        cand_c >= self.mi_col_start as i32
+            && cand_c < self.mi_col_end as i32
+            && cand_r >= self.mi_row_start as i32
+            && cand_r < self.mi_row_end as i32
+    }
+}
+
+/// Key frames and tiles for the tests of the tile syntax, the symbols are
+/// read from synthetic tile data.
+#[cfg(test)]
+pub(crate) mod test_tiles {
+    use crate::{
+        buffer::BitWriter,
+        obu::{
+            frame_header::FrameHeader,
+            test_streams::{context, key_frame_header},
+            tile_group::Tile,
+        },
+        Av1DecoderContext, Buffer,
+    };
+
+    use super::{block::Block, mode_info::ModeInfo, TileDecoder};
+
+    /// A context with CurrFrame allocated for a shown lossless key frame of
+    /// width x height, the headers can be changed before a tile decoder is
+    /// created.
+    pub fn key_frame_context(
+        width: u16,
+        height: u16,
+        use_128x128_superblock: bool,
+    ) -> Av1DecoderContext {
+        let mut ctx = context(width, height);
+        if let Some(sequence_header) = ctx.sequence_header.as_mut() {
+            sequence_header.use_128x128_superblock = use_128x128_superblock;
+        }
+
+        let mut w = BitWriter::default();
+        key_frame_header(&mut w, true, 0xff);
+        // the tile info of wider frames has more increment flags, they are
+        // read as 0 from the padding.
+        w.put_bits(0, 8);
+        let payload = w.into_bytes();
+        FrameHeader::decode(&mut ctx, &mut Buffer::new(&payload)).unwrap();
+        ctx
+    }
+
+    /// A tile decoder for a single tile covering the whole frame, with the
+    /// left context of the first superblock row cleared.
+    pub fn tile_decoder<'a>(ctx: &'a mut Av1DecoderContext, data: &'a [u8]) -> TileDecoder<'a> {
+        let tile = frame_tile(ctx, data);
+        let mut decoder = TileDecoder::new(ctx, &tile).unwrap();
+        decoder.clear_left_context();
+        decoder
+    }
+
+    /// Moves all of the probability of a cdf to one symbol, the other symbols
+    /// keep the minimum probability at the ends of the range. The symbol is
+    /// decoded from tile data in the middle of the range, bytes of 0x80.
+    pub fn force_symbol(cdf: &mut [u16], symbol: usize) {
+        let n = cdf.len() - 1;
+        for (i, value) in cdf[..n].iter_mut().enumerate() {
+            *value = if i < symbol { 0 } else { 1 << 15 };
+        }
+
+        cdf[n] = 0;
+    }
+
+    /// Sets up the block at r, c as decode_block( ) does.
+    pub fn set_block(d: &mut TileDecoder, r: u32, c: u32, mi_size: u8) {
+        d.block = Block {
+            mi_row: r,
+            mi_col: c,
+            mi_size,
+            has_chroma: true,
+            avail_u: r > 0,
+            avail_l: c > 0,
+            avail_u_chroma: r > 0,
+            avail_l_chroma: c > 0,
+            transform_blocks: Vec::new(),
+        };
+
+        d.mi = ModeInfo {
+            mi_size,
+            ..Default::default()
+        };
+    }
+
+    /// A single tile covering the whole frame.
+    pub fn frame_tile<'a>(ctx: &Av1DecoderContext, data: &'a [u8]) -> Tile<'a> {
+        Tile {
+            tile_num: 0,
+            tile_row: 0,
+            tile_col: 0,
+            mi_row_start: 0,
+            mi_row_end: ctx.mi_rows,
+            mi_col_start: 0,
+            mi_col_end: ctx.mi_cols,
+            data,
+        }
+    }
+}
@@ -0,0 +1,1040 @@
+use crate::{
+    constants::{
+        BLOCK_128X128, BLOCK_4X4, BLOCK_64X64, BLOCK_8X8, CFL_SIGN_NEG, CFL_SIGN_ZERO,
+        COMPOUND_AVERAGE, D67_PRED, DCT_DCT, DC_PRED, DELTA_LF_SMALL, DELTA_Q_SMALL,
+        FRAME_LF_COUNT, INTRA_FRAME, MAX_ANGLE_DELTA, MAX_LOOP_FILTER, MAX_SEGMENTS, NONE,
+        PALETTE_COLORS, SEG_LVL_GLOBALMV, SEG_LVL_REF_FRAME, SEG_LVL_SKIP, SIMPLE, TX_4X4,
+        UV_CFL_PRED, V_PRED,
+    },
+    obu::frame_header::InterpolationFilter,
+    quant::{self, Dequantizer},
+    tables::{
+        BLOCK_HEIGHT, BLOCK_WIDTH, INTRA_MODE_CONTEXT, NUM_4X4_BLOCKS_HIGH, NUM_4X4_BLOCKS_WIDE,
+        SIZE_GROUP, SUBSAMPLED_SIZE,
+    },
+    util::clip3,
+    Av1DecodeError,
+};
+
+use super::{block::Block, inter_mode_info::NeighbourRefs, mvpred::Mv, TileDecoder};
+
+/// The mode info that is stored for each 4x4 luma block of the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModeInfo {
+    /// MiSizes
+    pub mi_size: u8,
+    /// SegmentIds
+    pub segment_id: u8,
+    /// Skips
+    pub skip: bool,
+    /// SkipModes
+    pub skip_mode: bool,
+    /// IsInters
+    pub is_inter: bool,
+    /// RefFrames
+    pub ref_frame: [i8; 2],
+    pub use_intrabc: bool,
+    /// Mvs
+    pub mv: [Mv; 2],
+    /// InterpFilters
+    pub interp_filter: [InterpolationFilter; 2],
+    /// CompGroupIdxs
+    pub comp_group_idx: u8,
+    /// CompoundIdxs
+    pub compound_idx: u8,
+    pub motion_mode: u8,
+    pub compound_type: u8,
+    pub interintra: bool,
+    pub interintra_mode: u8,
+    pub wedge_interintra: bool,
+    pub wedge_index: u8,
+    pub wedge_sign: u8,
+    pub mask_type: u8,
+    /// YModes
+    pub y_mode: u8,
+    /// UVMode
+    pub uv_mode: u8,
+    /// AngleDeltaY
+    pub angle_delta_y: i8,
+    /// AngleDeltaUV
+    pub angle_delta_uv: i8,
+    /// CflAlphaU
+    pub cfl_alpha_u: i8,
+    /// CflAlphaV
+    pub cfl_alpha_v: i8,
+    pub use_filter_intra: bool,
+    pub filter_intra_mode: u8,
+    /// PaletteSizes, PaletteSizeY and PaletteSizeUV.
+    pub palette_size: [u8; 2],
+    /// PaletteColors, palette_colors_y, palette_colors_u and
+    /// palette_colors_v.
+    pub palette_colors: [[u16; PALETTE_COLORS as usize]; 3],
+    /// DeltaLFs
+    pub delta_lf: [i8; FRAME_LF_COUNT as usize],
+    /// TxSizes
+    pub tx_size: u8,
+}
+
+impl Default for ModeInfo {
+    fn default() -> Self {
+        Self {
+            mi_size: BLOCK_4X4,
+            segment_id: 0,
+            skip: false,
+            skip_mode: false,
+            is_inter: false,
+            ref_frame: [INTRA_FRAME as i8, NONE],
+            use_intrabc: false,
+            mv: [[0; 2]; 2],
+            interp_filter: [InterpolationFilter::default(); 2],
+            comp_group_idx: 0,
+            compound_idx: 0,
+            motion_mode: SIMPLE,
+            compound_type: COMPOUND_AVERAGE,
+            interintra: false,
+            interintra_mode: 0,
+            wedge_interintra: false,
+            wedge_index: 0,
+            wedge_sign: 0,
+            mask_type: 0,
+            y_mode: DC_PRED,
+            uv_mode: DC_PRED,
+            angle_delta_y: 0,
+            angle_delta_uv: 0,
+            cfl_alpha_u: 0,
+            cfl_alpha_v: 0,
+            use_filter_intra: false,
+            filter_intra_mode: 0,
+            palette_size: [0; 2],
+            palette_colors: [[0; PALETTE_COLORS as usize]; 3],
+            delta_lf: [0; FRAME_LF_COUNT as usize],
+            tx_size: TX_4X4,
+        }
+    }
+}
+
+/// The mode info of the current frame, one entry for each 4x4 luma block.
+#[derive(Debug, Clone, Default)]
+pub struct ModeInfoGrid {
+    pub mi_rows: u32,
+    pub mi_cols: u32,
+    data: Vec<ModeInfo>,
+    /// Positions that have been written by a decoded block of the current
+    /// frame.
+    written: Vec<bool>,
+    /// InterTxSizes, the transform size at each position, which can vary
+    /// inside of an inter block.
+    inter_tx_sizes: Vec<u8>,
+    /// TxTypes, the transform type of the luma transform block at each
+    /// position.
+    tx_types: Vec<u8>,
+}
+
+impl ModeInfoGrid {
+    pub fn new(mi_rows: u32, mi_cols: u32) -> Self {
+        Self {
+            mi_rows,
+            mi_cols,
+            data: vec![ModeInfo::default(); (mi_rows * mi_cols) as usize],
+            written: vec![false; (mi_rows * mi_cols) as usize],
+            inter_tx_sizes: vec![TX_4X4; (mi_rows * mi_cols) as usize],
+            tx_types: vec![DCT_DCT; (mi_rows * mi_cols) as usize],
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, row: u32, col: u32) -> &ModeInfo {
+        &self.data[(row * self.mi_cols + col) as usize]
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, row: u32, col: u32) -> &mut ModeInfo {
+        &mut self.data[(row * self.mi_cols + col) as usize]
+    }
+
+    /// The position has been written by a block of the current frame that is
+    /// already decoded.
+    #[inline]
+    pub fn is_written(&self, row: u32, col: u32) -> bool {
+        self.written[(row * self.mi_cols + col) as usize]
+    }
+
+    #[inline]
+    pub fn inter_tx_size(&self, row: u32, col: u32) -> u8 {
+        self.inter_tx_sizes[(row * self.mi_cols + col) as usize]
+    }
+
+    /// Stores the transform size of a w4 x h4 area, the parts outside of the
+    /// frame are not stored.
+    pub fn set_inter_tx_size(&mut self, row: u32, col: u32, w4: u32, h4: u32, tx_size: u8) {
+        for r in row..(row + h4).min(self.mi_rows) {
+            for c in col..(col + w4).min(self.mi_cols) {
+                self.inter_tx_sizes[(r * self.mi_cols + c) as usize] = tx_size;
+            }
+        }
+    }
+
+    #[inline]
+    pub fn tx_type(&self, row: u32, col: u32) -> u8 {
+        self.tx_types[(row * self.mi_cols + col) as usize]
+    }
+
+    /// Stores the transform type of a w4 x h4 area, the parts outside of the
+    /// frame are not stored.
+    pub fn set_tx_type(&mut self, row: u32, col: u32, w4: u32, h4: u32, tx_type: u8) {
+        for r in row..(row + h4).min(self.mi_rows) {
+            for c in col..(col + w4).min(self.mi_cols) {
+                self.tx_types[(r * self.mi_cols + c) as usize] = tx_type;
+            }
+        }
+    }
+
+    /// SegmentIds, the segment id at each position in raster order.
+    pub fn segment_ids(&self) -> Vec<u8> {
+        self.data.iter().map(|mi| mi.segment_id).collect()
+    }
+
+    /// Stores the mode info of a block at every position it covers, the
+    /// parts of the block outside of the frame are not stored.
+    pub fn fill(&mut self, block: &Block, mode_info: &ModeInfo) {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[block.mi_size as usize] as u32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[block.mi_size as usize] as u32;
+        let row_end = (block.mi_row + bh4).min(self.mi_rows);
+        let col_end = (block.mi_col + bw4).min(self.mi_cols);
+        for row in block.mi_row..row_end {
+            let start = (row * self.mi_cols) as usize;
+            let range = start + block.mi_col as usize..start + col_end as usize;
+            self.data[range.clone()].fill(*mode_info);
+            self.written[range].fill(true);
+        }
+    }
+}
+
+/// is_directional_mode( mode )
+#[inline]
+pub fn is_directional_mode(mode: u8) -> bool {
+    (V_PRED..=D67_PRED).contains(&mode)
+}
+
+/// neg_deinterleave( diff, ref, max )
+fn neg_deinterleave(diff: i32, r: i32, max: i32) -> i32 {
+    if r == 0 {
+        return diff;
+    }
+
+    if r >= max - 1 {
+        return max - diff - 1;
+    }
+
+    if 2 * r < max {
+        if diff <= 2 * r {
+            if diff & 1 == 1 {
+                return r + ((diff + 1) >> 1);
+            } else {
+                return r - (diff >> 1);
+            }
+        }
+
+        diff
+    } else {
+        if diff <= 2 * (max - r - 1) {
+            if diff & 1 == 1 {
+                return r + ((diff + 1) >> 1);
+            } else {
+                return r - (diff >> 1);
+            }
+        }
+
+        max - (diff + 1)
+    }
+}
+
+impl TileDecoder<'_> {
+    /// mode_info( )
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#mode-info-syntax
+    pub fn mode_info(&mut self) -> Result<(), Av1DecodeError> {
+        if self.frame_header.frame_is_intra {
+            self.intra_frame_mode_info()
+        } else {
+            self.inter_frame_mode_info()
+        }
+    }
+
+    /// see: https://aomediacodec.github.io/av1-spec/#intra-frame-mode-info-syntax
+    fn intra_frame_mode_info(&mut self) -> Result<(), Av1DecodeError> {
+        let seg_id_pre_skip = self.frame_header.segmentation_params.seg_id_pre_skip;
+        if seg_id_pre_skip {
+            self.intra_segment_id();
+        }
+
+        self.mi.skip_mode = false;
+        self.read_skip();
+        if !seg_id_pre_skip {
+            self.intra_segment_id();
+        }
+
+        self.read_cdef();
+        self.read_delta_qindex();
+        self.read_delta_lf();
+        self.read_deltas = false;
+
+        self.mi.ref_frame = [INTRA_FRAME as i8, NONE];
+        if self.frame_header.allow_intrabc {
+            // use_intrabc	S()
+            self.mi.use_intrabc = self.sd.read_symbol(&mut self.cdf.intrabc_cdf) == 1;
+        }
+
+        if self.mi.use_intrabc {
+            return self.intrabc_mode_info();
+        }
+
+        self.mi.is_inter = false;
+
+        // intra_frame_y_mode	S()
+        let mode_info = &self.ctx.mode_info;
+        let above_mode = if self.block.avail_u {
+            mode_info
+                .get(self.block.mi_row - 1, self.block.mi_col)
+                .y_mode
+        } else {
+            DC_PRED
+        };
+
+        let left_mode = if self.block.avail_l {
+            mode_info
+                .get(self.block.mi_row, self.block.mi_col - 1)
+                .y_mode
+        } else {
+            DC_PRED
+        };
+
+        let cdf = &mut self.cdf.intra_frame_y_mode_cdf[INTRA_MODE_CONTEXT[above_mode as usize]]
+            [INTRA_MODE_CONTEXT[left_mode as usize]];
+        self.mi.y_mode = self.sd.read_symbol(cdf) as u8;
+        self.intra_modes_after_y_mode();
+        Ok(())
+    }
+
+    /// see: https://aomediacodec.github.io/av1-spec/#inter-frame-mode-info-syntax
+    fn inter_frame_mode_info(&mut self) -> Result<(), Av1DecodeError> {
+        self.mi.use_intrabc = false;
+        let refs = self.neighbour_refs();
+
+        self.mi.skip = false;
+        self.inter_segment_id(true);
+        self.read_skip_mode();
+        if self.mi.skip_mode {
+            self.mi.skip = true;
+        } else {
+            self.read_skip();
+        }
+
+        if !self.frame_header.segmentation_params.seg_id_pre_skip {
+            self.inter_segment_id(false);
+        }
+
+        self.read_cdef();
+        self.read_delta_qindex();
+        self.read_delta_lf();
+        self.read_deltas = false;
+
+        self.read_is_inter(&refs);
+        if self.mi.is_inter {
+            self.inter_block_mode_info(&refs)
+        } else {
+            self.intra_block_mode_info();
+            Ok(())
+        }
+    }
+
+    /// intra_block_mode_info( ), an intra block of an inter frame.
+    fn intra_block_mode_info(&mut self) {
+        self.mi.ref_frame = [INTRA_FRAME as i8, NONE];
+
+        // y_mode	S()
+        let cdf = &mut self.cdf.y_mode_cdf[SIZE_GROUP[self.mi.mi_size as usize] as usize];
+        self.mi.y_mode = self.sd.read_symbol(cdf) as u8;
+        self.intra_modes_after_y_mode();
+    }
+
+    /// The part of intra_frame_mode_info( ) and intra_block_mode_info( ) that
+    /// follows the luma mode: the angle deltas, the chroma mode, the palette
+    /// and filter intra.
+    fn intra_modes_after_y_mode(&mut self) {
+        self.mi.angle_delta_y = self.intra_angle_info(self.mi.mi_size, self.mi.y_mode);
+
+        if self.block.has_chroma {
+            self.mi.uv_mode = self.read_uv_mode();
+            if self.mi.uv_mode == UV_CFL_PRED {
+                self.read_cfl_alphas();
+            }
+
+            self.mi.angle_delta_uv = self.intra_angle_info(self.mi.mi_size, self.mi.uv_mode);
+        }
+
+        self.mi.palette_size = [0; 2];
+        if self.mi.mi_size >= BLOCK_8X8
+            && BLOCK_WIDTH[self.mi.mi_size as usize] <= 64
+            && BLOCK_HEIGHT[self.mi.mi_size as usize] <= 64
+            && self.frame_header.allow_screen_content_tools
+        {
+            self.palette_mode_info();
+        }
+
+        self.filter_intra_mode_info();
+    }
+
+    /// intra_segment_id( )
+    fn intra_segment_id(&mut self) {
+        self.mi.segment_id = if self.frame_header.segmentation_params.segmentation_enabled {
+            self.read_segment_id()
+        } else {
+            0
+        };
+    }
+
+    /// inter_segment_id( preSkip ), the segment id is either read, or
+    /// predicted from the segmentation map of the previous frame.
+    fn inter_segment_id(&mut self, pre_skip: bool) {
+        let params = &self.frame_header.segmentation_params;
+        if !params.segmentation_enabled {
+            self.mi.segment_id = 0;
+            return;
+        }
+
+        let predicted_segment_id = self.get_segment_id();
+        if !params.segmentation_update_map {
+            self.mi.segment_id = predicted_segment_id;
+            return;
+        }
+
+        if pre_skip && !params.seg_id_pre_skip {
+            self.mi.segment_id = 0;
+            return;
+        }
+
+        if !pre_skip && self.mi.skip {
+            self.set_seg_id_predicted(false);
+            self.mi.segment_id = self.read_segment_id();
+            return;
+        }
+
+        if params.segmentation_temporal_update {
+            // seg_id_predicted	S()
+            let ctx = self.left_seg_pred_context[self.block.mi_row as usize]
+                + self.above_seg_pred_context[self.block.mi_col as usize];
+            let cdf = &mut self.cdf.segment_id_predicted_cdf[ctx as usize];
+            let seg_id_predicted = self.sd.read_symbol(cdf) == 1;
+            self.mi.segment_id = if seg_id_predicted {
+                predicted_segment_id
+            } else {
+                self.read_segment_id()
+            };
+
+            self.set_seg_id_predicted(seg_id_predicted);
+        } else {
+            self.mi.segment_id = self.read_segment_id();
+        }
+    }
+
+    /// Stores seg_id_predicted in AboveSegPredContext and LeftSegPredContext
+    /// for the positions of the block inside of the frame.
+    fn set_seg_id_predicted(&mut self, seg_id_predicted: bool) {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize] as u32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize] as u32;
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        let cols = mi_col as usize..(mi_col + bw4).min(self.ctx.mi_cols) as usize;
+        self.above_seg_pred_context[cols].fill(seg_id_predicted as u8);
+        let rows = mi_row as usize..(mi_row + bh4).min(self.ctx.mi_rows) as usize;
+        self.left_seg_pred_context[rows].fill(seg_id_predicted as u8);
+    }
+
+    /// get_segment_id( ), the smallest segment id of the previous
+    /// segmentation map in the area of the block.
+    fn get_segment_id(&self) -> u8 {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize] as u32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize] as u32;
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        let x_mis = (self.ctx.mi_cols - mi_col).min(bw4);
+        let y_mis = (self.ctx.mi_rows - mi_row).min(bh4);
+        let mut seg = MAX_SEGMENTS - 1;
+        for y in mi_row..mi_row + y_mis {
+            let row = (y * self.ctx.mi_cols) as usize;
+            for &segment_id in
+                &self.ctx.prev_segment_ids[row..][mi_col as usize..][..x_mis as usize]
+            {
+                seg = seg.min(segment_id);
+            }
+        }
+
+        seg
+    }
+
+    /// read_segment_id( ), the segment id is coded as the difference to a
+    /// prediction from the above and left segment ids.
+    fn read_segment_id(&mut self) -> u8 {
+        let mode_info = &self.ctx.mode_info;
+        let (r, c) = (self.block.mi_row, self.block.mi_col);
+        let prev_ul = if self.block.avail_u && self.block.avail_l {
+            mode_info.get(r - 1, c - 1).segment_id as i32
+        } else {
+            -1
+        };
+
+        let prev_u = if self.block.avail_u {
+            mode_info.get(r - 1, c).segment_id as i32
+        } else {
+            -1
+        };
+
+        let prev_l = if self.block.avail_l {
+            mode_info.get(r, c - 1).segment_id as i32
+        } else {
+            -1
+        };
+
+        let pred = if prev_u == -1 {
+            if prev_l == -1 {
+                0
+            } else {
+                prev_l
+            }
+        } else if prev_l == -1 || prev_ul == prev_u {
+            prev_u
+        } else {
+            prev_l
+        };
+
+        if self.mi.skip {
+            return pred as u8;
+        }
+
+        let ctx = if prev_ul < 0 {
+            0
+        } else if prev_ul == prev_u && prev_ul == prev_l {
+            2
+        } else if prev_ul == prev_u || prev_ul == prev_l || prev_u == prev_l {
+            1
+        } else {
+            0
+        };
+
+        // segment_id	S()
+        let segment_id = self.sd.read_symbol(&mut self.cdf.segment_id_cdf[ctx]) as i32;
+        let last_active_seg_id = self.frame_header.segmentation_params.last_active_seg_id as i32;
+        let segment_id = neg_deinterleave(segment_id, pred, last_active_seg_id + 1);
+        clip3(0, last_active_seg_id, segment_id) as u8
+    }
+
+    /// seg_feature_active( feature ) for the segment of the block.
+    pub fn seg_feature_active(&self, segment_id: u8, feature: u8) -> bool {
+        let params = &self.frame_header.segmentation_params;
+        params.segmentation_enabled && params.feature_enabled[segment_id as usize][feature as usize]
+    }
+
+    /// get_qidx( ignoreDeltaQ, segmentId ), the quantizer index of a segment.
+    pub fn get_qidx(&self, ignore_delta_q: bool, segment_id: u8) -> i32 {
+        let use_delta_q = !ignore_delta_q && self.frame_header.delta_q_params.delta_q_present;
+        quant::get_qidx(
+            &self.frame_header.quantization_params,
+            &self.frame_header.segmentation_params,
+            use_delta_q.then_some(self.current_q_index),
+            segment_id,
+        )
+    }
+
+    /// The dequantizer of a plane of the block.
+    pub fn dequantizer(&self, plane: usize) -> Dequantizer {
+        let qindex = self.get_qidx(false, self.mi.segment_id);
+        Dequantizer::new(
+            &self.frame_header,
+            plane,
+            self.mi.segment_id,
+            qindex,
+            self.ctx.bit_depth,
+        )
+    }
+
+    /// read_skip( )
+    fn read_skip(&mut self) {
+        if self.frame_header.segmentation_params.seg_id_pre_skip
+            && self.seg_feature_active(self.mi.segment_id, SEG_LVL_SKIP)
+        {
+            self.mi.skip = true;
+            return;
+        }
+
+        let mode_info = &self.ctx.mode_info;
+        let mut ctx = 0;
+        if self.block.avail_u {
+            ctx += mode_info.get(self.block.mi_row - 1, self.block.mi_col).skip as usize;
+        }
+
+        if self.block.avail_l {
+            ctx += mode_info.get(self.block.mi_row, self.block.mi_col - 1).skip as usize;
+        }
+
+        // skip	S()
+        self.mi.skip = self.sd.read_symbol(&mut self.cdf.skip_cdf[ctx]) == 1;
+    }
+
+    /// read_skip_mode( )
+    fn read_skip_mode(&mut self) {
+        let segment_id = self.mi.segment_id;
+        let mi_size = self.mi.mi_size as usize;
+        if self.seg_feature_active(segment_id, SEG_LVL_SKIP)
+            || self.seg_feature_active(segment_id, SEG_LVL_REF_FRAME)
+            || self.seg_feature_active(segment_id, SEG_LVL_GLOBALMV)
+            || !self.frame_header.skip_mode_present
+            || BLOCK_WIDTH[mi_size] < 8
+            || BLOCK_HEIGHT[mi_size] < 8
+        {
+            self.mi.skip_mode = false;
+            return;
+        }
+
+        let mode_info = &self.ctx.mode_info;
+        let mut ctx = 0;
+        if self.block.avail_u {
+            ctx += mode_info
+                .get(self.block.mi_row - 1, self.block.mi_col)
+                .skip_mode as usize;
+        }
+
+        if self.block.avail_l {
+            ctx += mode_info
+                .get(self.block.mi_row, self.block.mi_col - 1)
+                .skip_mode as usize;
+        }
+
+        // skip_mode	S()
+        self.mi.skip_mode = self.sd.read_symbol(&mut self.cdf.skip_mode_cdf[ctx]) == 1;
+    }
+
+    /// read_is_inter( ), blocks that use skip mode, or a segment with a
+    /// reference frame or global motion feature, do not code is_inter.
+    fn read_is_inter(&mut self, refs: &NeighbourRefs) {
+        let segment_id = self.mi.segment_id;
+        self.mi.is_inter = if self.mi.skip_mode {
+            true
+        } else if self.seg_feature_active(segment_id, SEG_LVL_REF_FRAME) {
+            let params = &self.frame_header.segmentation_params;
+            params.feature_data[segment_id as usize][SEG_LVL_REF_FRAME as usize]
+                != INTRA_FRAME as i16
+        } else if self.seg_feature_active(segment_id, SEG_LVL_GLOBALMV) {
+            true
+        } else {
+            // is_inter	S()
+            let cdf = &mut self.cdf.is_inter_cdf[refs.is_inter_ctx()];
+            self.sd.read_symbol(cdf) == 1
+        };
+    }
+
+    /// read_cdef( ), cdef_idx is read by the first block in each 64x64 area
+    /// that is not skipped.
+    fn read_cdef(&mut self) {
+        if self.mi.skip
+            || self.frame_header.coded_lossless
+            || !self.sequence_header.enable_cdef
+            || self.frame_header.allow_intrabc
+        {
+            return;
+        }
+
+        let cdef_size4 = NUM_4X4_BLOCKS_WIDE[BLOCK_64X64 as usize] as u32;
+        let cdef_mask4 = !(cdef_size4 - 1);
+        let r = self.block.mi_row & cdef_mask4;
+        let c = self.block.mi_col & cdef_mask4;
+        if self.cdef_idx(r, c) == Some(-1) {
+            // cdef_idx	L(cdef_bits)
+            let cdef_bits = self.frame_header.cdef_params.cdef_bits as usize;
+            let cdef_idx = self.sd.read_literal(cdef_bits) as i8;
+
+            let w4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize] as u32;
+            let h4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize] as u32;
+            for y in (r..r + h4).step_by(cdef_size4 as usize) {
+                for x in (c..c + w4).step_by(cdef_size4 as usize) {
+                    self.set_cdef_idx(y, x, cdef_idx);
+                }
+            }
+        }
+    }
+
+    /// read_delta_qindex( )
+    fn read_delta_qindex(&mut self) {
+        if self.mi.mi_size == self.sb_size() && self.mi.skip {
+            return;
+        }
+
+        if self.read_deltas {
+            // delta_q_abs	S()
+            let mut delta_q_abs = self.sd.read_symbol(&mut self.cdf.delta_q_cdf) as i32;
+            if delta_q_abs == DELTA_Q_SMALL as i32 {
+                // delta_q_rem_bits	L(3)
+                let delta_q_rem_bits = self.sd.read_literal(3) as usize + 1;
+
+                // delta_q_abs_bits	L(delta_q_rem_bits)
+                let delta_q_abs_bits = self.sd.read_literal(delta_q_rem_bits) as i32;
+                delta_q_abs = delta_q_abs_bits + (1 << delta_q_rem_bits) + 1;
+            }
+
+            if delta_q_abs != 0 {
+                // delta_q_sign_bit	L(1)
+                let delta_q_sign_bit = self.sd.read_literal(1) == 1;
+                let reduced_delta_q_index = if delta_q_sign_bit {
+                    -delta_q_abs
+                } else {
+                    delta_q_abs
+                };
+
+                let delta_q_res = self.frame_header.delta_q_params.delta_q_res;
+                self.current_q_index = clip3(
+                    1,
+                    255,
+                    self.current_q_index + (reduced_delta_q_index << delta_q_res),
+                );
+            }
+        }
+    }
+
+    /// read_delta_lf( )
+    fn read_delta_lf(&mut self) {
+        if self.mi.mi_size == self.sb_size() && self.mi.skip {
+            return;
+        }
+
+        let params = self.frame_header.delta_lf_params.clone();
+        if self.read_deltas && params.delta_lf_present {
+            let frame_lf_count = if params.delta_lf_multi {
+                if self.ctx.num_planes > 1 {
+                    FRAME_LF_COUNT
+                } else {
+                    FRAME_LF_COUNT - 2
+                }
+            } else {
+                1
+            };
+
+            for i in 0..frame_lf_count as usize {
+                // delta_lf_abs	S()
+                let cdf = if params.delta_lf_multi {
+                    &mut self.cdf.delta_lf_multi_cdf[i]
+                } else {
+                    &mut self.cdf.delta_lf_cdf
+                };
+
+                let delta_lf_abs = self.sd.read_symbol(cdf) as i32;
+                let delta_lf_abs = if delta_lf_abs == DELTA_LF_SMALL as i32 {
+                    // delta_lf_rem_bits	L(3)
+                    let n = self.sd.read_literal(3) as usize + 1;
+
+                    // delta_lf_abs_bits	L(n)
+                    let delta_lf_abs_bits = self.sd.read_literal(n) as i32;
+                    delta_lf_abs_bits + (1 << n) + 1
+                } else {
+                    delta_lf_abs
+                };
+
+                if delta_lf_abs != 0 {
+                    // delta_lf_sign_bit	L(1)
+                    let delta_lf_sign_bit = self.sd.read_literal(1) == 1;
+                    let reduced_delta_lf_level = if delta_lf_sign_bit {
+                        -delta_lf_abs
+                    } else {
+                        delta_lf_abs
+                    };
+
+                    self.delta_lf[i] = clip3(
+                        -(MAX_LOOP_FILTER as i32),
+                        MAX_LOOP_FILTER as i32,
+                        self.delta_lf[i] + (reduced_delta_lf_level << params.delta_lf_res),
+                    );
+                }
+            }
+        }
+    }
+
+    /// intra_angle_info_y( ) and intra_angle_info_uv( ), returns AngleDeltaY
+    /// or AngleDeltaUV for the mode.
+    fn intra_angle_info(&mut self, mi_size: u8, mode: u8) -> i8 {
+        let use_angle_delta = mi_size >= BLOCK_8X8;
+        if use_angle_delta && is_directional_mode(mode) {
+            // angle_delta_y	S() or angle_delta_uv	S()
+            let cdf = &mut self.cdf.angle_delta_cdf[(mode - V_PRED) as usize];
+            self.sd.read_symbol(cdf) as i8 - MAX_ANGLE_DELTA as i8
+        } else {
+            0
+        }
+    }
+
+    /// Reads uv_mode, the chroma from luma mode is only allowed for small
+    /// blocks.
+    fn read_uv_mode(&mut self) -> u8 {
+        let lossless = self.frame_header.lossless_array[self.mi.segment_id as usize];
+        let color_config = &self.sequence_header.color_config;
+        let cfl_allowed = if lossless
+            && SUBSAMPLED_SIZE[self.mi.mi_size as usize][color_config.subsampling_x as usize]
+                [color_config.subsampling_y as usize]
+                == BLOCK_4X4
+        {
+            true
+        } else {
+            !lossless
+                && BLOCK_WIDTH[self.mi.mi_size as usize].max(BLOCK_HEIGHT[self.mi.mi_size as usize])
+                    <= 32
+        };
+
+        let cdf = if cfl_allowed {
+            &mut self.cdf.uv_mode_cfl_allowed_cdf[self.mi.y_mode as usize][..]
+        } else {
+            &mut self.cdf.uv_mode_cfl_not_allowed_cdf[self.mi.y_mode as usize][..]
+        };
+
+        self.sd.read_symbol(cdf) as u8
+    }
+
+    /// read_cfl_alphas( )
+    fn read_cfl_alphas(&mut self) {
+        // cfl_alpha_signs	S()
+        let cfl_alpha_signs = self.sd.read_symbol(&mut self.cdf.cfl_sign_cdf) as u8;
+        let sign_u = (cfl_alpha_signs + 1) / 3;
+        let sign_v = (cfl_alpha_signs + 1) % 3;
+
+        self.mi.cfl_alpha_u = if sign_u != CFL_SIGN_ZERO {
+            // cfl_alpha_u	S()
+            let ctx = ((sign_u - 1) * 3 + sign_v) as usize;
+            let cfl_alpha_u = 1 + self.sd.read_symbol(&mut self.cdf.cfl_alpha_cdf[ctx]) as i8;
+            if sign_u == CFL_SIGN_NEG {
+                -cfl_alpha_u
+            } else {
+                cfl_alpha_u
+            }
+        } else {
+            0
+        };
+
+        self.mi.cfl_alpha_v = if sign_v != CFL_SIGN_ZERO {
+            // cfl_alpha_v	S()
+            let ctx = ((sign_v - 1) * 3 + sign_u) as usize;
+            let cfl_alpha_v = 1 + self.sd.read_symbol(&mut self.cdf.cfl_alpha_cdf[ctx]) as i8;
+            if sign_v == CFL_SIGN_NEG {
+                -cfl_alpha_v
+            } else {
+                cfl_alpha_v
+            }
+        } else {
+            0
+        };
+    }
+
+    /// filter_intra_mode_info( )
+    fn filter_intra_mode_info(&mut self) {
+        self.mi.use_filter_intra = false;
+        if self.sequence_header.enable_filter_intra
+            && self.mi.y_mode == DC_PRED
+            && self.mi.palette_size[0] == 0
+            && BLOCK_WIDTH[self.mi.mi_size as usize].max(BLOCK_HEIGHT[self.mi.mi_size as usize])
+                <= 32
+        {
+            // use_filter_intra	S()
+            let cdf = &mut self.cdf.filter_intra_cdf[self.mi.mi_size as usize];
+            self.mi.use_filter_intra = self.sd.read_symbol(cdf) == 1;
+            if self.mi.use_filter_intra {
+                // filter_intra_mode	S()
+                self.mi.filter_intra_mode =
+                    self.sd.read_symbol(&mut self.cdf.filter_intra_mode_cdf) as u8;
+            }
+        }
+    }
+
+    /// The superblock size of the sequence, BLOCK_128X128 or BLOCK_64X64.
+    pub fn sb_size(&self) -> u8 {
+        if self.sequence_header.use_128x128_superblock {
+            BLOCK_128X128
+        } else {
+            BLOCK_64X64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{BLOCK_4X8, MAX_SEGMENTS, MV_INTRABC_CONTEXT, SMOOTH_PRED},
+        tile::test_tiles::{force_symbol, key_frame_context, set_block, tile_decoder},
+        Av1DecodeInvalidError,
+    };
+
+    #[test]
+    fn segment_ids_are_predicted_from_the_neighbours() {
+        let mut ctx = key_frame_context(64, 64, false);
+        ctx.mode_info.get_mut(0, 0).segment_id = 3;
+        ctx.mode_info.get_mut(0, 1).segment_id = 3;
+        ctx.mode_info.get_mut(1, 0).segment_id = 5;
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+        d.frame_header.segmentation_params.segmentation_enabled = true;
+        d.frame_header.segmentation_params.last_active_seg_id = 7;
+        set_block(&mut d, 1, 1, BLOCK_4X4);
+
+        // above left equal to above, the prediction is the above segment id
+        // and only the left one differs: ctx 1.
+        for (symbol, segment_id) in [(0, 3), (1, 4), (2, 2), (5, 6), (7, 7)] {
+            force_symbol(&mut d.cdf.segment_id_cdf[1], symbol);
+            assert_eq!(d.read_segment_id(), segment_id, "{symbol}");
+            assert_eq!(d.cdf.segment_id_cdf[1][MAX_SEGMENTS as usize], 1);
+        }
+
+        // the prediction is used as is for a skipped block.
+        d.mi.skip = true;
+        force_symbol(&mut d.cdf.segment_id_cdf[1], 5);
+        assert_eq!(d.read_segment_id(), 3);
+        assert_eq!(d.cdf.segment_id_cdf[1][MAX_SEGMENTS as usize], 0);
+
+        // the prediction is the last active segment id, the differences
+        // count down from it.
+        d.mi.skip = false;
+        d.frame_header.segmentation_params.last_active_seg_id = 3;
+        force_symbol(&mut d.cdf.segment_id_cdf[1], 1);
+        assert_eq!(d.read_segment_id(), 2);
+    }
+
+    #[test]
+    fn segment_ids_are_predicted_from_the_previous_frame() {
+        let mut ctx = key_frame_context(64, 64, false);
+        ctx.prev_segment_ids = vec![7; 16 * 16];
+        for (i, segment_id) in [
+            (2 * 16 + 2, 6),
+            (2 * 16 + 3, 2),
+            (3 * 16 + 2, 5),
+            (3 * 16 + 3, 4),
+        ] {
+            ctx.prev_segment_ids[i] = segment_id;
+        }
+
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+        let params = &mut d.frame_header.segmentation_params;
+        params.segmentation_enabled = true;
+        params.segmentation_update_map = true;
+        params.segmentation_temporal_update = true;
+        params.last_active_seg_id = 7;
+        set_block(&mut d, 2, 2, BLOCK_8X8);
+
+        // seg_id_predicted 1, the smallest segment id in the area of the
+        // block.
+        force_symbol(&mut d.cdf.segment_id_predicted_cdf[0], 1);
+        d.inter_segment_id(false);
+        assert_eq!(d.mi.segment_id, 2);
+        assert_eq!(d.above_seg_pred_context[..5], [0, 0, 1, 1, 0]);
+        assert_eq!(d.left_seg_pred_context[..5], [0, 0, 1, 1, 0]);
+
+        // the block to the right has a predicted left neighbour: ctx 1,
+        // seg_id_predicted 0 reads the segment id (the neighbours in the
+        // frame all have segment id 0: ctx 2).
+        set_block(&mut d, 2, 4, BLOCK_8X8);
+        force_symbol(&mut d.cdf.segment_id_predicted_cdf[1], 0);
+        force_symbol(&mut d.cdf.segment_id_cdf[2], 6);
+        d.inter_segment_id(false);
+        assert_eq!(d.mi.segment_id, 6);
+        assert_eq!(d.cdf.segment_id_predicted_cdf[1][2], 1);
+        assert_eq!(d.above_seg_pred_context[4..6], [0, 0]);
+        assert_eq!(d.left_seg_pred_context[2..4], [0, 0]);
+
+        // without an update of the map the prediction is used without
+        // reading a symbol.
+        d.frame_header.segmentation_params.segmentation_update_map = false;
+        set_block(&mut d, 2, 2, BLOCK_8X8);
+        d.inter_segment_id(false);
+        assert_eq!(d.mi.segment_id, 2);
+        assert_eq!(d.cdf.segment_id_predicted_cdf[0][2], 1);
+    }
+
+    #[test]
+    fn angle_deltas_of_directional_modes() {
+        let mut ctx = key_frame_context(64, 64, false);
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+
+        // each directional mode has its own cdf, the symbol is the delta
+        // plus MAX_ANGLE_DELTA.
+        for mode in V_PRED..=D67_PRED {
+            let symbol = (mode - V_PRED) as usize % 7;
+            force_symbol(&mut d.cdf.angle_delta_cdf[(mode - V_PRED) as usize], symbol);
+        }
+
+        for mode in V_PRED..=D67_PRED {
+            let delta = (mode - V_PRED) as i8 % 7 - MAX_ANGLE_DELTA as i8;
+            assert_eq!(d.intra_angle_info(BLOCK_8X8, mode), delta, "{mode}");
+        }
+
+        // blocks smaller than 8x8 and the other modes have no angle delta.
+        let cdf = d.cdf.angle_delta_cdf;
+        assert_eq!(d.intra_angle_info(BLOCK_4X8, V_PRED), 0);
+        assert_eq!(d.intra_angle_info(BLOCK_8X8, DC_PRED), 0);
+        assert_eq!(d.intra_angle_info(BLOCK_8X8, SMOOTH_PRED), 0);
+        assert_eq!(d.cdf.angle_delta_cdf, cdf);
+    }
+
+    #[test]
+    fn cfl_alpha_signs_select_the_alpha_contexts() {
+        let mut ctx = key_frame_context(64, 64, false);
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+
+        // the alpha cdf of ctx decodes ctx, a magnitude of ctx + 1.
+        let expected = [
+            (0, -1),
+            (0, 4),
+            (-1, 0),
+            (-2, -2),
+            (-3, 5),
+            (4, 0),
+            (5, -3),
+            (6, 6),
+        ];
+
+        for (signs, alphas) in expected.into_iter().enumerate() {
+            force_symbol(&mut d.cdf.cfl_sign_cdf, signs);
+            for (ctx, cdf) in d.cdf.cfl_alpha_cdf.iter_mut().enumerate() {
+                force_symbol(cdf, ctx);
+            }
+
+            d.read_cfl_alphas();
+            assert_eq!((d.mi.cfl_alpha_u, d.mi.cfl_alpha_v), alphas, "{signs}");
+        }
+    }
+
+    #[test]
+    fn use_intrabc_is_only_read_when_allowed() {
+        let mut ctx = key_frame_context(64, 64, false);
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+        set_block(&mut d, 0, 0, BLOCK_8X8);
+        force_symbol(&mut d.cdf.intrabc_cdf, 1);
+        d.intra_frame_mode_info().unwrap();
+        assert!(!d.mi.use_intrabc && !d.mi.is_inter);
+        assert_eq!(d.cdf.intrabc_cdf[2], 0);
+
+        d.frame_header.allow_intrabc = true;
+        set_block(&mut d, 0, 0, BLOCK_8X8);
+        force_symbol(&mut d.cdf.intrabc_cdf, 0);
+        d.intra_frame_mode_info().unwrap();
+        assert!(!d.mi.use_intrabc && !d.mi.is_inter);
+        assert_eq!(d.cdf.intrabc_cdf[2], 1);
+
+        // the default vector of the first block of the tile points to the
+        // left of the tile.
+        set_block(&mut d, 0, 0, BLOCK_8X8);
+        force_symbol(&mut d.cdf.intrabc_cdf, 1);
+        force_symbol(&mut d.cdf.mv_joint_cdf[MV_INTRABC_CONTEXT as usize], 0);
+        assert!(matches!(
+            d.intra_frame_mode_info(),
+            Err(Av1DecodeError::Invalid(Av1DecodeInvalidError::MotionVector))
+        ));
+        assert!(d.mi.use_intrabc && d.mi.is_inter);
+        assert_eq!(d.mi.y_mode, DC_PRED);
+    }
+}
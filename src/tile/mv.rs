@@ -0,0 +1,284 @@
+use crate::{
+    constants::{
+        CLASS0_SIZE, INTRABC_DELAY_SB64, MI_SIZE, MV_CLASS_0, MV_INTRABC_CONTEXT, MV_JOINT_HNZVNZ,
+        MV_JOINT_HNZVZ, MV_JOINT_HZVNZ,
+    },
+    tables::{BLOCK_HEIGHT, BLOCK_WIDTH},
+};
+
+use super::{mvpred::Mv, TileDecoder};
+
+impl TileDecoder<'_> {
+    /// read_mv( ref ), reads the difference to the predicted motion vector
+    /// and returns the motion vector.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#mv-syntax
+    pub fn read_mv(&mut self, pred_mv: Mv) -> Mv {
+        let mv_ctx = if self.mi.use_intrabc {
+            MV_INTRABC_CONTEXT as usize
+        } else {
+            0
+        };
+
+        let mut diff_mv = [0; 2];
+
+        // mv_joint	S()
+        let mv_joint = self.sd.read_symbol(&mut self.cdf.mv_joint_cdf[mv_ctx]) as u8;
+        if mv_joint == MV_JOINT_HZVNZ || mv_joint == MV_JOINT_HNZVNZ {
+            diff_mv[0] = self.read_mv_component(mv_ctx, 0);
+        }
+
+        if mv_joint == MV_JOINT_HNZVZ || mv_joint == MV_JOINT_HNZVNZ {
+            diff_mv[1] = self.read_mv_component(mv_ctx, 1);
+        }
+
+        [pred_mv[0] + diff_mv[0], pred_mv[1] + diff_mv[1]]
+    }
+
+    /// read_mv_component( comp )
+    fn read_mv_component(&mut self, mv_ctx: usize, comp: usize) -> i32 {
+        let force_integer_mv = self.frame_header.force_integer_mv;
+        let allow_high_precision_mv = self.frame_header.allow_high_precision_mv;
+        let cdf = &mut self.cdf;
+
+        // mv_sign	S()
+        let mv_sign = self.sd.read_symbol(&mut cdf.mv_sign_cdf[mv_ctx][comp]) == 1;
+
+        // mv_class	S()
+        let mv_class = self.sd.read_symbol(&mut cdf.mv_class_cdf[mv_ctx][comp]) as u8;
+        let mag = if mv_class == MV_CLASS_0 {
+            // mv_class0_bit	S()
+            let mv_class0_bit = self
+                .sd
+                .read_symbol(&mut cdf.mv_class0_bit_cdf[mv_ctx][comp]);
+            let mv_class0_fr = if force_integer_mv {
+                3
+            } else {
+                // mv_class0_fr	S()
+                let cdf = &mut cdf.mv_class0_fr_cdf[mv_ctx][comp][mv_class0_bit];
+                self.sd.read_symbol(cdf)
+            };
+
+            let mv_class0_hp = if allow_high_precision_mv {
+                // mv_class0_hp	S()
+                self.sd.read_symbol(&mut cdf.mv_class0_hp_cdf[mv_ctx][comp])
+            } else {
+                1
+            };
+
+            ((mv_class0_bit << 3) | (mv_class0_fr << 1) | mv_class0_hp) as i32 + 1
+        } else {
+            let mut d = 0;
+            for i in 0..mv_class as usize {
+                // mv_bit	S()
+                let mv_bit = self.sd.read_symbol(&mut cdf.mv_bit_cdf[mv_ctx][comp][i]);
+                d |= mv_bit << i;
+            }
+
+            let mv_fr = if force_integer_mv {
+                3
+            } else {
+                // mv_fr	S()
+                self.sd.read_symbol(&mut cdf.mv_fr_cdf[mv_ctx][comp])
+            };
+
+            let mv_hp = if allow_high_precision_mv {
+                // mv_hp	S()
+                self.sd.read_symbol(&mut cdf.mv_hp_cdf[mv_ctx][comp])
+            } else {
+                1
+            };
+
+            let mag = (CLASS0_SIZE as i32) << (mv_class + 2);
+            mag + ((d << 3) | (mv_fr << 1) | mv_hp) as i32 + 1
+        };
+
+        if mv_sign {
+            -mag
+        } else {
+            mag
+        }
+    }
+
+    /// is_mv_valid( 0 ), the motion vector is in range, and an intra block
+    /// copy motion vector only refers to samples of the current tile that
+    /// are decoded at least INTRABC_DELAY_SB64 64x64 blocks earlier.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#assign-mv-semantics
+    pub fn is_mv_valid(&self, mv: Mv) -> bool {
+        if mv.iter().any(|v| v.abs() >= 1 << 14) {
+            return false;
+        }
+
+        if !self.mi.use_intrabc {
+            return true;
+        }
+
+        if mv[0] & 7 != 0 || mv[1] & 7 != 0 {
+            return false;
+        }
+
+        let bw = BLOCK_WIDTH[self.mi.mi_size as usize] as i32;
+        let bh = BLOCK_HEIGHT[self.mi.mi_size as usize] as i32;
+        let mi_size = MI_SIZE as i32;
+        let (mi_row, mi_col) = (self.block.mi_row as i32, self.block.mi_col as i32);
+        let delta_row = mv[0] >> 3;
+        let delta_col = mv[1] >> 3;
+        let mut src_top_edge = mi_row * mi_size + delta_row;
+        let mut src_left_edge = mi_col * mi_size + delta_col;
+        let src_bottom_edge = src_top_edge + bh;
+        let src_right_edge = src_left_edge + bw;
+        if self.block.has_chroma {
+            let color_config = &self.sequence_header.color_config;
+            if bw < 8 && color_config.subsampling_x {
+                src_left_edge -= 4;
+            }
+
+            if bh < 8 && color_config.subsampling_y {
+                src_top_edge -= 4;
+            }
+        }
+
+        if src_top_edge < self.mi_row_start as i32 * mi_size
+            || src_left_edge < self.mi_col_start as i32 * mi_size
+            || src_bottom_edge > self.mi_row_end as i32 * mi_size
+            || src_right_edge > self.mi_col_end as i32 * mi_size
+        {
+            return false;
+        }
+
+        let sb_h = BLOCK_HEIGHT[self.sb_size() as usize] as i32;
+        let active_sb_row = (mi_row * mi_size) / sb_h;
+        let active_sb64_col = (mi_col * mi_size) >> 6;
+        let src_sb_row = (src_bottom_edge - 1) / sb_h;
+        let src_sb64_col = (src_right_edge - 1) >> 6;
+        let total_sb64_per_row = ((self.mi_col_end - self.mi_col_start - 1) >> 4) as i32 + 1;
+        let active_sb64 = active_sb_row * total_sb64_per_row + active_sb64_col;
+        let src_sb64 = src_sb_row * total_sb64_per_row + src_sb64_col;
+        if src_sb64 >= active_sb64 - INTRABC_DELAY_SB64 as i32 {
+            return false;
+        }
+
+        let gradient = 1 + INTRABC_DELAY_SB64 as i32 + (sb_h > 64) as i32;
+        let wf_offset = gradient * (active_sb_row - src_sb_row);
+        !(src_sb_row > active_sb_row
+            || src_sb64_col >= active_sb64_col - INTRABC_DELAY_SB64 as i32 + wf_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{BLOCK_4X4, BLOCK_8X8, INTRABC_DELAY_PIXELS},
+        tile::test_tiles::{key_frame_context, set_block, tile_decoder},
+    };
+
+    /// An intra block copy motion vector of whole samples.
+    fn dv(delta_row: i32, delta_col: i32) -> Mv {
+        [delta_row * 8, delta_col * 8]
+    }
+
+    #[test]
+    fn intrabc_vectors_are_delayed_in_the_same_superblock_row() {
+        // 8 64x64 superblocks per row.
+        let mut ctx = key_frame_context(512, 256, false);
+        let data = [0];
+        let mut d = tile_decoder(&mut ctx, &data);
+
+        // the 6th superblock of the first row can only refer to the first
+        // one, INTRABC_DELAY_PIXELS and the width of the block to the left.
+        set_block(&mut d, 0, 80, BLOCK_8X8);
+        d.mi.use_intrabc = true;
+        d.block.has_chroma = false;
+        let delay = INTRABC_DELAY_PIXELS as i32;
+        assert!(d.is_mv_valid(dv(0, -delay - 8)));
+        assert!(!d.is_mv_valid(dv(0, -delay - 7)));
+        assert!(!d.is_mv_valid(dv(0, -8)));
+
+        // vectors of fractional samples and out of range vectors.
+        assert!(!d.is_mv_valid([0, (-delay - 8) * 8 + 4]));
+        assert!(!d.is_mv_valid([-(1 << 14), 0]));
+
+        // only intra block copy vectors are restricted.
+        d.mi.use_intrabc = false;
+        assert!(d.is_mv_valid(dv(0, -8)));
+        assert!(!d.is_mv_valid([1 << 14, 0]));
+    }
+
+    #[test]
+    fn intrabc_vectors_follow_the_wavefront() {
+        let mut ctx = key_frame_context(512, 256, false);
+        let data = [0];
+        let mut d = tile_decoder(&mut ctx, &data);
+
+        // the first superblock of the second row can refer to the first
+        // superblock above it, not to the one above right.
+        set_block(&mut d, 16, 0, BLOCK_8X8);
+        d.mi.use_intrabc = true;
+        d.block.has_chroma = false;
+        assert!(d.is_mv_valid(dv(-64, 0)));
+        assert!(d.is_mv_valid(dv(-64, 56)));
+        assert!(!d.is_mv_valid(dv(-64, 57)));
+        assert!(d.is_mv_valid(dv(-8, 0)));
+        assert!(!d.is_mv_valid(dv(-7, 0)));
+
+        // further down the wavefront allows more superblocks to the right.
+        set_block(&mut d, 32, 0, BLOCK_8X8);
+        d.mi.use_intrabc = true;
+        d.block.has_chroma = false;
+        assert!(d.is_mv_valid(dv(-128, 6 * 64 - 8)));
+        assert!(!d.is_mv_valid(dv(-128, 6 * 64 - 7)));
+    }
+
+    #[test]
+    fn intrabc_vectors_stay_inside_of_the_tile() {
+        let mut ctx = key_frame_context(512, 256, false);
+        let data = [0];
+        let mut d = tile_decoder(&mut ctx, &data);
+        d.mi_col_start = 32;
+        d.mi_row_end = 32;
+
+        set_block(&mut d, 16, 32, BLOCK_8X8);
+        d.mi.use_intrabc = true;
+        d.block.has_chroma = false;
+        assert!(d.is_mv_valid(dv(-64, 0)));
+        assert!(!d.is_mv_valid(dv(-64, -8)));
+        assert!(!d.is_mv_valid(dv(-72, 0)));
+
+        // the superblocks per row are counted in the tile: the 6th
+        // superblock of the first row of the tile.
+        set_block(&mut d, 0, 32 + 80, BLOCK_8X8);
+        d.mi.use_intrabc = true;
+        d.block.has_chroma = false;
+        assert!(d.is_mv_valid(dv(0, -264)));
+        assert!(!d.is_mv_valid(dv(0, -263)));
+
+        // the chroma of a 4x4 block extends 4 samples to the left.
+        set_block(&mut d, 16, 33, BLOCK_4X4);
+        d.mi.use_intrabc = true;
+        assert!(!d.is_mv_valid(dv(-64, -4)));
+        d.block.has_chroma = false;
+        assert!(d.is_mv_valid(dv(-64, -4)));
+    }
+
+    #[test]
+    fn intrabc_vectors_with_128x128_superblocks() {
+        // 8 64x64 columns in 4 128x128 superblocks per row.
+        let mut ctx = key_frame_context(512, 256, true);
+        let data = [0];
+        let mut d = tile_decoder(&mut ctx, &data);
+
+        // the wavefront gradient is one 64x64 column larger, the first two
+        // columns above are available.
+        set_block(&mut d, 32, 0, BLOCK_8X8);
+        d.mi.use_intrabc = true;
+        d.block.has_chroma = false;
+        assert!(d.is_mv_valid(dv(-128, 120)));
+        assert!(!d.is_mv_valid(dv(-128, 121)));
+
+        // the source ends in the 128 samples high superblock row above.
+        assert!(d.is_mv_valid(dv(-8, 0)));
+        assert!(!d.is_mv_valid(dv(-7, 0)));
+    }
+}
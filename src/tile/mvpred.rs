@@ -0,0 +1,1260 @@
+use crate::{
+    constants::{
+        BLOCK_64X64, BLOCK_8X8, GLOBALMV, GLOBAL_GLOBALMV, IDENTITY, INTRA_FRAME,
+        LEAST_SQUARES_SAMPLES_MAX, MAX_REF_MV_STACK_SIZE, MI_SIZE, MV_BORDER, NEAREST_NEWMV,
+        NEAR_NEWMV, NEWMV, NEW_NEARESTMV, NEW_NEARMV, NEW_NEWMV, NONE, REF_CAT_LEVEL, TRANSLATION,
+        WARPEDMODEL_PREC_BITS,
+    },
+    motion_field::{MotionFieldMvs, INVALID_MV},
+    obu::frame_header::UncompressedHeader,
+    tables::{BLOCK_HEIGHT, BLOCK_WIDTH, NUM_4X4_BLOCKS_HIGH, NUM_4X4_BLOCKS_WIDE},
+    util::clip3,
+};
+
+use super::{mode_info::ModeInfoGrid, TileDecoder};
+
+/// A motion vector, the row component followed by the column component, in
+/// units of 1/8 luma sample.
+pub type Mv = [i32; 2];
+
+/// The reference motion vector candidates of the current block.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#motion-vector-prediction-processes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MvStack {
+    /// NumMvFound
+    pub num_mv_found: usize,
+    /// NewMvCount
+    pub new_mv_count: usize,
+    /// RefStackMv
+    pub ref_stack_mv: [[Mv; 2]; MAX_REF_MV_STACK_SIZE as usize],
+    /// WeightStack
+    pub weight_stack: [u32; MAX_REF_MV_STACK_SIZE as usize],
+    /// GlobalMvs
+    pub global_mvs: [Mv; 2],
+    /// DrlCtxStack
+    pub drl_ctx_stack: [u8; MAX_REF_MV_STACK_SIZE as usize],
+    /// NewMvContext
+    pub new_mv_context: u8,
+    /// RefMvContext
+    pub ref_mv_context: u8,
+    /// ZeroMvContext
+    pub zero_mv_context: u8,
+    /// FoundMatch
+    found_match: bool,
+}
+
+impl MvStack {
+    /// swap_stack( i, j )
+    fn swap(&mut self, i: usize, j: usize) {
+        self.weight_stack.swap(i, j);
+        self.ref_stack_mv.swap(i, j);
+    }
+
+    /// sorting( start, end ), a stable bubble sort of the candidates by
+    /// descending weight.
+    fn sort(&mut self, start: usize, mut end: usize) {
+        while end > start {
+            let mut new_end = start;
+            for idx in start + 1..end {
+                if self.weight_stack[idx - 1] < self.weight_stack[idx] {
+                    self.swap(idx - 1, idx);
+                    new_end = idx;
+                }
+            }
+
+            end = new_end;
+        }
+    }
+
+    /// Adds the weight to the candidate with the same motion vectors, or
+    /// pushes a new candidate while the stack is not full.
+    fn add(&mut self, mvs: [Mv; 2], num_lists: usize, weight: u32) {
+        let num_mv_found = self.num_mv_found;
+        if let Some(idx) =
+            (0..num_mv_found).find(|&idx| self.ref_stack_mv[idx][..num_lists] == mvs[..num_lists])
+        {
+            self.weight_stack[idx] += weight;
+        } else if num_mv_found < MAX_REF_MV_STACK_SIZE as usize {
+            self.ref_stack_mv[num_mv_found] = mvs;
+            self.weight_stack[num_mv_found] = weight;
+            self.num_mv_found += 1;
+        }
+    }
+}
+
+/// has_newmv( mode )
+#[inline]
+pub fn has_newmv(mode: u8) -> bool {
+    matches!(
+        mode,
+        NEWMV | NEW_NEWMV | NEAR_NEWMV | NEW_NEARMV | NEAREST_NEWMV | NEW_NEARESTMV
+    )
+}
+
+/// The samples that a local warp of the block is estimated from.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#find-warp-samples-process
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WarpSamples {
+    /// NumSamples
+    pub num_samples: usize,
+    /// NumSamplesScanned
+    pub num_samples_scanned: usize,
+    /// CandList, the position of the centre of a neighbour followed by the
+    /// position it is moved to by its motion vector, in units of 1/8
+    /// sample.
+    pub cand_list: [[i32; 4]; LEAST_SQUARES_SAMPLES_MAX as usize],
+}
+
+/// The state that is read by the motion vector prediction processes of a
+/// block: the mode info of the already decoded neighbours, the motion field
+/// of the frame and the position, size and reference frames of the block.
+#[derive(Debug, Clone, Copy)]
+pub struct MvPredictor<'a> {
+    pub frame_header: &'a UncompressedHeader,
+    /// The mode info of the frame, which also gives MiRows and MiCols.
+    pub mode_info: &'a ModeInfoGrid,
+    pub motion_field_mvs: &'a MotionFieldMvs,
+    /// MiRowStart
+    pub mi_row_start: u32,
+    /// MiRowEnd
+    pub mi_row_end: u32,
+    /// MiColStart
+    pub mi_col_start: u32,
+    /// MiColEnd
+    pub mi_col_end: u32,
+    /// MiRow
+    pub mi_row: u32,
+    /// MiCol
+    pub mi_col: u32,
+    /// MiSize
+    pub mi_size: u8,
+    /// RefFrame
+    pub ref_frame: [i8; 2],
+}
+
+impl TileDecoder<'_> {
+    /// The motion vector prediction state of the block that is being
+    /// decoded.
+    pub fn mv_predictor(&self) -> MvPredictor<'_> {
+        MvPredictor {
+            frame_header: &self.frame_header,
+            mode_info: &self.ctx.mode_info,
+            motion_field_mvs: &self.ctx.motion_field_mvs,
+            mi_row_start: self.mi_row_start,
+            mi_row_end: self.mi_row_end,
+            mi_col_start: self.mi_col_start,
+            mi_col_end: self.mi_col_end,
+            mi_row: self.block.mi_row,
+            mi_col: self.block.mi_col,
+            mi_size: self.mi.mi_size,
+            ref_frame: self.mi.ref_frame,
+        }
+    }
+
+    /// find_mv_stack( isCompound ) for the block that is being decoded.
+    pub fn find_mv_stack(&self, is_compound: bool) -> MvStack {
+        self.mv_predictor().find_mv_stack(is_compound)
+    }
+}
+
+impl MvPredictor<'_> {
+    /// find_mv_stack( isCompound ), builds the stack of candidate motion
+    /// vectors (pairs of motion vectors for compound blocks) from the
+    /// spatial neighbours, the motion field and the global motion of the
+    /// reference frames.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#find-mv-stack-process
+    pub fn find_mv_stack(&self, is_compound: bool) -> MvStack {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi_size as usize];
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi_size as usize];
+
+        let mut stack = MvStack::default();
+        stack.global_mvs[0] = self.setup_global_mv(0);
+        if is_compound {
+            stack.global_mvs[1] = self.setup_global_mv(1);
+        }
+
+        self.scan_row(&mut stack, -1, is_compound);
+        let mut found_above_match = stack.found_match;
+        stack.found_match = false;
+
+        self.scan_col(&mut stack, -1, is_compound);
+        let mut found_left_match = stack.found_match;
+        stack.found_match = false;
+
+        if bw4.max(bh4) <= 16 {
+            self.scan_point(&mut stack, -1, bw4 as i32, is_compound);
+        }
+
+        found_above_match |= stack.found_match;
+        let close_matches = found_above_match as u32 + found_left_match as u32;
+        let num_nearest = stack.num_mv_found;
+        let num_new = stack.new_mv_count;
+        for weight in &mut stack.weight_stack[..num_nearest] {
+            *weight += REF_CAT_LEVEL as u32;
+        }
+
+        stack.zero_mv_context = 0;
+        if self.frame_header.use_ref_frame_mvs {
+            self.temporal_scan(&mut stack, is_compound);
+        }
+
+        stack.found_match = false;
+        self.scan_point(&mut stack, -1, -1, is_compound);
+        found_above_match |= stack.found_match;
+        stack.found_match = false;
+
+        self.scan_row(&mut stack, -3, is_compound);
+        found_above_match |= stack.found_match;
+        stack.found_match = false;
+
+        self.scan_col(&mut stack, -3, is_compound);
+        found_left_match |= stack.found_match;
+        stack.found_match = false;
+
+        if bh4 > 1 {
+            self.scan_row(&mut stack, -5, is_compound);
+        }
+
+        found_above_match |= stack.found_match;
+        stack.found_match = false;
+
+        if bw4 > 1 {
+            self.scan_col(&mut stack, -5, is_compound);
+        }
+
+        found_left_match |= stack.found_match;
+        let total_matches = found_above_match as u32 + found_left_match as u32;
+
+        let num_mv_found = stack.num_mv_found;
+        stack.sort(0, num_nearest);
+        stack.sort(num_nearest, num_mv_found);
+        if stack.num_mv_found < 2 {
+            self.extra_search(&mut stack, is_compound);
+        }
+
+        self.context_and_clamping(
+            &mut stack,
+            is_compound,
+            close_matches,
+            total_matches,
+            num_new,
+        );
+        stack
+    }
+
+    /// is_inside( candR, candC ), the position is inside of the tile.
+    fn is_inside(&self, cand_r: i32, cand_c: i32) -> bool {
+        cand_c >= self.mi_col_start as i32
+            && cand_c < self.mi_col_end as i32
+            && cand_r >= self.mi_row_start as i32
+            && cand_r < self.mi_row_end as i32
+    }
+
+    /// GmType[ RefFrame[ refList ] ], IDENTITY for intra blocks.
+    fn gm_type(&self, ref_list: usize) -> u8 {
+        let ref_frame = self.ref_frame[ref_list];
+        if ref_frame > INTRA_FRAME as i8 {
+            self.frame_header.global_motion_params.gm_type[ref_frame as usize]
+        } else {
+            IDENTITY
+        }
+    }
+
+    /// setup_global_mv( refList )
+    fn setup_global_mv(&self, ref_list: usize) -> Mv {
+        let ref_frame = self.ref_frame[ref_list];
+        let gm_type = self.gm_type(ref_list);
+        let mut mv = if gm_type == IDENTITY {
+            [0, 0]
+        } else {
+            let gm_params = &self.frame_header.global_motion_params.gm_params[ref_frame as usize];
+            if gm_type == TRANSLATION {
+                [
+                    gm_params[0] >> (WARPEDMODEL_PREC_BITS - 3),
+                    gm_params[1] >> (WARPEDMODEL_PREC_BITS - 3),
+                ]
+            } else {
+                let bw = BLOCK_WIDTH[self.mi_size as usize] as i64;
+                let bh = BLOCK_HEIGHT[self.mi_size as usize] as i64;
+                let x = self.mi_col as i64 * MI_SIZE as i64 + bw / 2 - 1;
+                let y = self.mi_row as i64 * MI_SIZE as i64 + bh / 2 - 1;
+                let gm = gm_params.map(|v| v as i64);
+                let xc = (gm[2] - (1 << WARPEDMODEL_PREC_BITS)) * x + gm[3] * y + gm[0];
+                let yc = gm[4] * x + (gm[5] - (1 << WARPEDMODEL_PREC_BITS)) * y + gm[1];
+
+                if self.frame_header.allow_high_precision_mv {
+                    [
+                        round2signed_i64(yc, WARPEDMODEL_PREC_BITS - 3),
+                        round2signed_i64(xc, WARPEDMODEL_PREC_BITS - 3),
+                    ]
+                } else {
+                    [
+                        round2signed_i64(yc, WARPEDMODEL_PREC_BITS - 2) * 2,
+                        round2signed_i64(xc, WARPEDMODEL_PREC_BITS - 2) * 2,
+                    ]
+                }
+            }
+        };
+
+        self.lower_mv_precision(&mut mv);
+        mv
+    }
+
+    /// lower_mv_precision( candMv )
+    pub fn lower_mv_precision(&self, mv: &mut Mv) {
+        if self.frame_header.allow_high_precision_mv {
+            return;
+        }
+
+        for v in mv.iter_mut() {
+            if self.frame_header.force_integer_mv {
+                let a = (v.abs() + 3) >> 3;
+                *v = if *v > 0 { a << 3 } else { -(a << 3) };
+            } else if *v & 1 == 1 {
+                *v += if *v > 0 { -1 } else { 1 };
+            }
+        }
+    }
+
+    /// scan_row( deltaRow, isCompound )
+    fn scan_row(&self, stack: &mut MvStack, mut delta_row: i32, is_compound: bool) {
+        let (mi_row, mi_col) = (self.mi_row as i32, self.mi_col as i32);
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi_size as usize] as i32;
+        let end4 = bw4.min(self.mode_info.mi_cols as i32 - mi_col).min(16);
+        let use_step16 = bw4 >= 16;
+        let mut delta_col = 0;
+        if delta_row.abs() > 1 {
+            delta_row += mi_row & 1;
+            delta_col = 1 - (mi_col & 1);
+        }
+
+        let mut i = 0;
+        while i < end4 {
+            let mv_row = mi_row + delta_row;
+            let mv_col = mi_col + delta_col + i;
+            if !self.is_inside(mv_row, mv_col) {
+                break;
+            }
+
+            let cand_size = self.mode_info.get(mv_row as u32, mv_col as u32).mi_size;
+            let mut len = bw4.min(NUM_4X4_BLOCKS_WIDE[cand_size as usize] as i32);
+            if delta_row.abs() > 1 {
+                len = len.max(2);
+            }
+
+            if use_step16 {
+                len = len.max(4);
+            }
+
+            let weight = len as u32 * 2;
+            self.add_ref_mv_candidate(stack, mv_row as u32, mv_col as u32, is_compound, weight);
+            i += len;
+        }
+    }
+
+    /// scan_col( deltaCol, isCompound )
+    fn scan_col(&self, stack: &mut MvStack, mut delta_col: i32, is_compound: bool) {
+        let (mi_row, mi_col) = (self.mi_row as i32, self.mi_col as i32);
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi_size as usize] as i32;
+        let end4 = bh4.min(self.mode_info.mi_rows as i32 - mi_row).min(16);
+        let use_step16 = bh4 >= 16;
+        let mut delta_row = 0;
+        if delta_col.abs() > 1 {
+            delta_row = 1 - (mi_row & 1);
+            delta_col += mi_col & 1;
+        }
+
+        let mut i = 0;
+        while i < end4 {
+            let mv_row = mi_row + delta_row + i;
+            let mv_col = mi_col + delta_col;
+            if !self.is_inside(mv_row, mv_col) {
+                break;
+            }
+
+            let cand_size = self.mode_info.get(mv_row as u32, mv_col as u32).mi_size;
+            let mut len = bh4.min(NUM_4X4_BLOCKS_HIGH[cand_size as usize] as i32);
+            if delta_col.abs() > 1 {
+                len = len.max(2);
+            }
+
+            if use_step16 {
+                len = len.max(4);
+            }
+
+            let weight = len as u32 * 2;
+            self.add_ref_mv_candidate(stack, mv_row as u32, mv_col as u32, is_compound, weight);
+            i += len;
+        }
+    }
+
+    /// scan_point( deltaRow, deltaCol, isCompound ), the position is only
+    /// used once it has been decoded.
+    fn scan_point(&self, stack: &mut MvStack, delta_row: i32, delta_col: i32, is_compound: bool) {
+        let mv_row = self.mi_row as i32 + delta_row;
+        let mv_col = self.mi_col as i32 + delta_col;
+        if self.is_inside(mv_row, mv_col)
+            && (mv_row as u32) < self.mode_info.mi_rows
+            && (mv_col as u32) < self.mode_info.mi_cols
+            && self.mode_info.is_written(mv_row as u32, mv_col as u32)
+        {
+            self.add_ref_mv_candidate(stack, mv_row as u32, mv_col as u32, is_compound, 4);
+        }
+    }
+
+    /// temporal_scan( isCompound ), adds the candidates of the motion field
+    /// at every other 8x8 block inside of the block (capped to 64x64), and
+    /// at three positions below and to the right of medium sized blocks.
+    fn temporal_scan(&self, stack: &mut MvStack, is_compound: bool) {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi_size as usize] as i32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi_size as usize] as i32;
+        let step_w4 = if bw4 >= 16 { 4 } else { 2 };
+        let step_h4 = if bh4 >= 16 { 4 } else { 2 };
+        for delta_row in (0..bh4.min(16)).step_by(step_h4) {
+            for delta_col in (0..bw4.min(16)).step_by(step_w4) {
+                self.add_tpl_ref_mv(stack, delta_row, delta_col, is_compound);
+            }
+        }
+
+        let allow_extension = bh4 >= NUM_4X4_BLOCKS_HIGH[BLOCK_8X8 as usize] as i32
+            && bh4 < NUM_4X4_BLOCKS_HIGH[BLOCK_64X64 as usize] as i32
+            && bw4 >= NUM_4X4_BLOCKS_WIDE[BLOCK_8X8 as usize] as i32
+            && bw4 < NUM_4X4_BLOCKS_WIDE[BLOCK_64X64 as usize] as i32;
+
+        if allow_extension {
+            // tplSamplePos
+            for (delta_row, delta_col) in [(bh4, -2), (bh4, bw4), (bh4 - 2, bw4)] {
+                if self.check_sb_border(delta_row, delta_col) {
+                    self.add_tpl_ref_mv(stack, delta_row, delta_col, is_compound);
+                }
+            }
+        }
+    }
+
+    /// check_sb_border( deltaRow, deltaCol ), the position is inside of the
+    /// 64x64 block containing the block.
+    fn check_sb_border(&self, delta_row: i32, delta_col: i32) -> bool {
+        let row = (self.mi_row as i32 & 15) + delta_row;
+        let col = (self.mi_col as i32 & 15) + delta_col;
+        (0..16).contains(&row) && (0..16).contains(&col)
+    }
+
+    /// add_tpl_ref_mv( deltaRow, deltaCol, isCompound )
+    fn add_tpl_ref_mv(
+        &self,
+        stack: &mut MvStack,
+        delta_row: i32,
+        delta_col: i32,
+        is_compound: bool,
+    ) {
+        let mv_row = (self.mi_row as i32 + delta_row) | 1;
+        let mv_col = (self.mi_col as i32 + delta_col) | 1;
+        if !self.is_inside(mv_row, mv_col) {
+            return;
+        }
+
+        let (x8, y8) = ((mv_col >> 1) as u32, (mv_row >> 1) as u32);
+        let at_origin = delta_row == 0 && delta_col == 0;
+        if at_origin {
+            stack.zero_mv_context = 1;
+        }
+
+        let num_lists = 1 + is_compound as usize;
+        let mut cand_mvs = [[0; 2]; 2];
+        for (list, cand_mv) in cand_mvs.iter_mut().enumerate().take(num_lists) {
+            *cand_mv = self
+                .motion_field_mvs
+                .get(self.ref_frame[list] as u8, y8, x8);
+            if cand_mv[0] == INVALID_MV {
+                return;
+            }
+
+            self.lower_mv_precision(cand_mv);
+        }
+
+        if at_origin {
+            let far = (0..num_lists).any(|list| {
+                let (mv, global_mv) = (cand_mvs[list], stack.global_mvs[list]);
+                (mv[0] - global_mv[0]).abs() >= 16 || (mv[1] - global_mv[1]).abs() >= 16
+            });
+
+            stack.zero_mv_context = far as u8;
+        }
+
+        stack.add(cand_mvs, num_lists, 2);
+    }
+
+    /// add_ref_mv_candidate( mvRow, mvCol, isCompound, weight ), single
+    /// reference blocks take the motion vectors of the neighbour that use
+    /// the same reference frame, compound blocks only take neighbours that
+    /// use the same pair of reference frames.
+    fn add_ref_mv_candidate(
+        &self,
+        stack: &mut MvStack,
+        mv_row: u32,
+        mv_col: u32,
+        is_compound: bool,
+        weight: u32,
+    ) {
+        let candidate = self.mode_info.get(mv_row, mv_col);
+        if !candidate.is_inter {
+            return;
+        }
+
+        if !is_compound {
+            for cand_list in 0..2 {
+                if candidate.ref_frame[cand_list] == self.ref_frame[0] {
+                    self.search_stack(stack, mv_row, mv_col, cand_list, weight);
+                }
+            }
+        } else if candidate.ref_frame == self.ref_frame {
+            self.compound_search_stack(stack, mv_row, mv_col, weight);
+        }
+    }
+
+    /// search_stack( mvRow, mvCol, candList, weight )
+    fn search_stack(
+        &self,
+        stack: &mut MvStack,
+        mv_row: u32,
+        mv_col: u32,
+        cand_list: usize,
+        weight: u32,
+    ) {
+        let candidate = self.mode_info.get(mv_row, mv_col);
+        let cand_mode = candidate.y_mode;
+        let cand_size = candidate.mi_size as usize;
+        let large = BLOCK_WIDTH[cand_size].min(BLOCK_HEIGHT[cand_size]) >= 8;
+        let mut cand_mv = if (cand_mode == GLOBALMV || cand_mode == GLOBAL_GLOBALMV)
+            && self.gm_type(0) > TRANSLATION
+            && large
+        {
+            stack.global_mvs[0]
+        } else {
+            candidate.mv[cand_list]
+        };
+
+        self.lower_mv_precision(&mut cand_mv);
+        if has_newmv(cand_mode) {
+            stack.new_mv_count += 1;
+        }
+
+        stack.found_match = true;
+        stack.add([cand_mv, [0, 0]], 1, weight);
+    }
+
+    /// compound_search_stack( mvRow, mvCol, weight )
+    fn compound_search_stack(&self, stack: &mut MvStack, mv_row: u32, mv_col: u32, weight: u32) {
+        let candidate = self.mode_info.get(mv_row, mv_col);
+        let cand_mode = candidate.y_mode;
+        let mut cand_mvs = candidate.mv;
+        if cand_mode == GLOBAL_GLOBALMV {
+            for (ref_list, cand_mv) in cand_mvs.iter_mut().enumerate() {
+                if self.gm_type(ref_list) > TRANSLATION {
+                    *cand_mv = stack.global_mvs[ref_list];
+                }
+            }
+        }
+
+        for cand_mv in &mut cand_mvs {
+            self.lower_mv_precision(cand_mv);
+        }
+
+        stack.found_match = true;
+        stack.add(cand_mvs, 2, weight);
+        if has_newmv(cand_mode) {
+            stack.new_mv_count += 1;
+        }
+    }
+
+    /// extra_search( isCompound ), fills the stack up to two candidates
+    /// from neighbours that use other reference frames, and then from the
+    /// global motion vectors.
+    fn extra_search(&self, stack: &mut MvStack, is_compound: bool) {
+        let (mi_row, mi_col) = (self.mi_row, self.mi_col);
+        let w4 = (NUM_4X4_BLOCKS_WIDE[self.mi_size as usize] as u32)
+            .min(16)
+            .min(self.mode_info.mi_cols - mi_col);
+        let h4 = (NUM_4X4_BLOCKS_HIGH[self.mi_size as usize] as u32)
+            .min(16)
+            .min(self.mode_info.mi_rows - mi_row);
+        let num4x4 = w4.min(h4);
+
+        let mut extra = ExtraMvs::default();
+        for pass in 0..2 {
+            let mut idx = 0;
+            while idx < num4x4 && stack.num_mv_found < 2 {
+                let (mv_row, mv_col) = if pass == 0 {
+                    (mi_row as i32 - 1, (mi_col + idx) as i32)
+                } else {
+                    ((mi_row + idx) as i32, mi_col as i32 - 1)
+                };
+
+                if !self.is_inside(mv_row, mv_col) {
+                    break;
+                }
+
+                let (mv_row, mv_col) = (mv_row as u32, mv_col as u32);
+                if is_compound {
+                    self.add_extra_compound_mv_candidate(&mut extra, mv_row, mv_col);
+                } else {
+                    self.add_extra_mv_candidate(stack, mv_row, mv_col);
+                }
+
+                let cand_size = self.mode_info.get(mv_row, mv_col).mi_size;
+                idx += if pass == 0 {
+                    NUM_4X4_BLOCKS_WIDE[cand_size as usize]
+                } else {
+                    NUM_4X4_BLOCKS_HIGH[cand_size as usize]
+                } as u32;
+            }
+        }
+
+        if is_compound {
+            let mut combined_mvs = [[[0; 2]; 2]; 2];
+            for list in 0..2 {
+                let mut comp_count = 0;
+                let ref_id = &extra.ref_id_mvs[list][..extra.ref_id_count[list]];
+                let ref_diff = &extra.ref_diff_mvs[list][..extra.ref_diff_count[list]];
+                for &mv in ref_id.iter().chain(ref_diff).take(2) {
+                    combined_mvs[comp_count][list] = mv;
+                    comp_count += 1;
+                }
+
+                for mvs in &mut combined_mvs[comp_count..] {
+                    mvs[list] = stack.global_mvs[list];
+                }
+            }
+
+            if stack.num_mv_found == 1 {
+                let mvs = if combined_mvs[0] == stack.ref_stack_mv[0] {
+                    combined_mvs[1]
+                } else {
+                    combined_mvs[0]
+                };
+
+                stack.ref_stack_mv[1] = mvs;
+                stack.weight_stack[1] = 2;
+                stack.num_mv_found = 2;
+            } else {
+                for (idx, mvs) in combined_mvs.into_iter().enumerate() {
+                    stack.ref_stack_mv[idx] = mvs;
+                    stack.weight_stack[idx] = 2;
+                }
+
+                stack.num_mv_found = 2;
+            }
+        } else {
+            for idx in stack.num_mv_found..2 {
+                stack.ref_stack_mv[idx][0] = stack.global_mvs[0];
+            }
+        }
+    }
+
+    /// The motion vector of a neighbour, negated when the reference frame of
+    /// the neighbour is on the other side of the current frame than the
+    /// reference frame of refList.
+    fn sign_corrected_mv(&self, mv: Mv, cand_ref: i8, ref_list: usize) -> Mv {
+        let sign_bias = &self.frame_header.ref_frame_sign_bias;
+        let ref_frame = self.ref_frame[ref_list].max(INTRA_FRAME as i8);
+        if sign_bias[cand_ref as usize] != sign_bias[ref_frame as usize] {
+            [-mv[0], -mv[1]]
+        } else {
+            mv
+        }
+    }
+
+    /// add_extra_mv_candidate( mvRow, mvCol, 0 )
+    fn add_extra_mv_candidate(&self, stack: &mut MvStack, mv_row: u32, mv_col: u32) {
+        let candidate = self.mode_info.get(mv_row, mv_col);
+        for cand_list in 0..2 {
+            let cand_ref = candidate.ref_frame[cand_list];
+            if cand_ref <= INTRA_FRAME as i8 {
+                continue;
+            }
+
+            let cand_mv = self.sign_corrected_mv(candidate.mv[cand_list], cand_ref, 0);
+            let num_mv_found = stack.num_mv_found;
+            if !(0..num_mv_found).any(|idx| stack.ref_stack_mv[idx][0] == cand_mv) {
+                stack.ref_stack_mv[num_mv_found][0] = cand_mv;
+                stack.weight_stack[num_mv_found] = 2;
+                stack.num_mv_found += 1;
+            }
+        }
+    }
+
+    /// add_extra_mv_candidate( mvRow, mvCol, 1 ), collects the motion
+    /// vectors of the neighbour that use the same reference frame as each
+    /// list, and the sign corrected motion vectors that use another one.
+    fn add_extra_compound_mv_candidate(&self, extra: &mut ExtraMvs, mv_row: u32, mv_col: u32) {
+        let candidate = self.mode_info.get(mv_row, mv_col);
+        for cand_list in 0..2 {
+            let cand_ref = candidate.ref_frame[cand_list];
+            if cand_ref <= INTRA_FRAME as i8 {
+                continue;
+            }
+
+            for list in 0..2 {
+                let cand_mv = candidate.mv[cand_list];
+                if cand_ref == self.ref_frame[list] && extra.ref_id_count[list] < 2 {
+                    extra.ref_id_mvs[list][extra.ref_id_count[list]] = cand_mv;
+                    extra.ref_id_count[list] += 1;
+                } else if extra.ref_diff_count[list] < 2 {
+                    let cand_mv = self.sign_corrected_mv(cand_mv, cand_ref, list);
+                    extra.ref_diff_mvs[list][extra.ref_diff_count[list]] = cand_mv;
+                    extra.ref_diff_count[list] += 1;
+                }
+            }
+        }
+    }
+
+    /// context_and_clamping( isCompound, numNew )
+    fn context_and_clamping(
+        &self,
+        stack: &mut MvStack,
+        is_compound: bool,
+        close_matches: u32,
+        total_matches: u32,
+        num_new: usize,
+    ) {
+        let num_mv_found = stack.num_mv_found;
+        for idx in 0..num_mv_found {
+            stack.drl_ctx_stack[idx] = if idx + 1 < num_mv_found {
+                let w0 = stack.weight_stack[idx];
+                let w1 = stack.weight_stack[idx + 1];
+                if w0 >= REF_CAT_LEVEL as u32 {
+                    (w1 < REF_CAT_LEVEL as u32) as u8
+                } else {
+                    2
+                }
+            } else {
+                0
+            };
+        }
+
+        for mvs in &mut stack.ref_stack_mv[..num_mv_found] {
+            for mv in &mut mvs[..1 + is_compound as usize] {
+                *mv = self.clamp_mv(*mv);
+            }
+        }
+
+        let num_new = num_new.min(1) as u8;
+        let total_matches = total_matches as u8;
+        (stack.new_mv_context, stack.ref_mv_context) = match close_matches {
+            0 => (total_matches.min(1), total_matches),
+            1 => (3 - num_new, 2 + total_matches),
+            _ => (5 - num_new, 5),
+        };
+    }
+
+    /// clamp_mv_row( ) and clamp_mv_col( ), limits the motion vector to
+    /// point at most MV_BORDER 1/8 samples plus the block size outside of
+    /// the frame.
+    pub fn clamp_mv(&self, mv: Mv) -> Mv {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi_size as usize] as i32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi_size as usize] as i32;
+        let (mi_row, mi_col) = (self.mi_row as i32, self.mi_col as i32);
+        let (mi_rows, mi_cols) = (self.mode_info.mi_rows as i32, self.mode_info.mi_cols as i32);
+
+        let border = MV_BORDER as i32 + bh4 * 4 * 8;
+        let mb_to_top_edge = -(mi_row * MI_SIZE as i32 * 8);
+        let mb_to_bottom_edge = (mi_rows - bh4 - mi_row) * MI_SIZE as i32 * 8;
+        let row = clip3(mb_to_top_edge - border, mb_to_bottom_edge + border, mv[0]);
+
+        let border = MV_BORDER as i32 + bw4 * 4 * 8;
+        let mb_to_left_edge = -(mi_col * MI_SIZE as i32 * 8);
+        let mb_to_right_edge = (mi_cols - bw4 - mi_col) * MI_SIZE as i32 * 8;
+        let col = clip3(mb_to_left_edge - border, mb_to_right_edge + border, mv[1]);
+
+        [row, col]
+    }
+
+    /// has_overlappable_candidates( ), an above or left neighbour of the
+    /// block is an inter block.
+    pub fn has_overlappable_candidates(&self) -> bool {
+        let (mi_row, mi_col) = (self.mi_row, self.mi_col);
+        let (mi_rows, mi_cols) = (self.mode_info.mi_rows, self.mode_info.mi_cols);
+        if self.is_inside(mi_row as i32 - 1, mi_col as i32) {
+            let w4 = NUM_4X4_BLOCKS_WIDE[self.mi_size as usize] as u32;
+            for x4 in (mi_col..mi_cols.min(mi_col + w4)).step_by(2) {
+                let x5 = (x4 | 1).min(mi_cols - 1);
+                if self.mode_info.get(mi_row - 1, x5).ref_frame[0] > INTRA_FRAME as i8 {
+                    return true;
+                }
+            }
+        }
+
+        if self.is_inside(mi_row as i32, mi_col as i32 - 1) {
+            let h4 = NUM_4X4_BLOCKS_HIGH[self.mi_size as usize] as u32;
+            for y4 in (mi_row..mi_rows.min(mi_row + h4)).step_by(2) {
+                let y5 = (y4 | 1).min(mi_rows - 1);
+                if self.mode_info.get(y5, mi_col - 1).ref_frame[0] > INTRA_FRAME as i8 {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// find_warp_samples( ), collects the positions and motion vectors of
+    /// the neighbours that use the same single reference frame as the
+    /// block, `mv` is Mv[ 0 ] of the block.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#find-warp-samples-process
+    pub fn find_warp_samples(&self, mv: Mv) -> WarpSamples {
+        let (mi_row, mi_col) = (self.mi_row, self.mi_col);
+        let w4 = NUM_4X4_BLOCKS_WIDE[self.mi_size as usize] as u32;
+        let h4 = NUM_4X4_BLOCKS_HIGH[self.mi_size as usize] as u32;
+        let mut samples = WarpSamples::default();
+        let mut do_top_left = true;
+        let mut do_top_right = true;
+
+        if self.is_inside(mi_row as i32 - 1, mi_col as i32) {
+            let src_size = self.mode_info.get(mi_row - 1, mi_col).mi_size;
+            let src_w = NUM_4X4_BLOCKS_WIDE[src_size as usize] as u32;
+            if w4 <= src_w {
+                let col_offset = -((mi_col & (src_w - 1)) as i32);
+                if col_offset < 0 {
+                    do_top_left = false;
+                }
+
+                if col_offset + src_w as i32 > w4 as i32 {
+                    do_top_right = false;
+                }
+
+                self.add_sample(&mut samples, mv, -1, 0);
+            } else {
+                let mut i = 0;
+                while i < w4.min(self.mode_info.mi_cols - mi_col) {
+                    let src_size = self.mode_info.get(mi_row - 1, mi_col + i).mi_size;
+                    let src_w = NUM_4X4_BLOCKS_WIDE[src_size as usize];
+                    let step = src_w.max(NUM_4X4_BLOCKS_WIDE[BLOCK_8X8 as usize]) as u32;
+                    self.add_sample(&mut samples, mv, -1, i as i32);
+                    i += step;
+                }
+            }
+        }
+
+        if self.is_inside(mi_row as i32, mi_col as i32 - 1) {
+            let src_size = self.mode_info.get(mi_row, mi_col - 1).mi_size;
+            let src_h = NUM_4X4_BLOCKS_HIGH[src_size as usize] as u32;
+            if h4 <= src_h {
+                if mi_row & (src_h - 1) != 0 {
+                    do_top_left = false;
+                }
+
+                self.add_sample(&mut samples, mv, 0, -1);
+            } else {
+                let mut i = 0;
+                while i < h4.min(self.mode_info.mi_rows - mi_row) {
+                    let src_size = self.mode_info.get(mi_row + i, mi_col - 1).mi_size;
+                    let src_h = NUM_4X4_BLOCKS_HIGH[src_size as usize];
+                    let step = src_h.max(NUM_4X4_BLOCKS_HIGH[BLOCK_8X8 as usize]) as u32;
+                    self.add_sample(&mut samples, mv, i as i32, -1);
+                    i += step;
+                }
+            }
+        }
+
+        if do_top_left {
+            self.add_sample(&mut samples, mv, -1, -1);
+        }
+
+        if do_top_right && w4.max(h4) <= 16 {
+            self.add_sample(&mut samples, mv, -1, w4 as i32);
+        }
+
+        if samples.num_samples == 0 && samples.num_samples_scanned > 0 {
+            samples.num_samples = 1;
+        }
+
+        samples
+    }
+
+    /// add_sample( deltaRow, deltaCol ), a neighbour whose motion vector is
+    /// far from the motion vector of the block is only kept when it is the
+    /// first one that is scanned.
+    fn add_sample(&self, samples: &mut WarpSamples, mv: Mv, delta_row: i32, delta_col: i32) {
+        if samples.num_samples_scanned >= LEAST_SQUARES_SAMPLES_MAX as usize {
+            return;
+        }
+
+        let mv_row = self.mi_row as i32 + delta_row;
+        let mv_col = self.mi_col as i32 + delta_col;
+        if !self.is_inside(mv_row, mv_col)
+            || mv_row as u32 >= self.mode_info.mi_rows
+            || mv_col as u32 >= self.mode_info.mi_cols
+            || !self.mode_info.is_written(mv_row as u32, mv_col as u32)
+        {
+            return;
+        }
+
+        let candidate = self.mode_info.get(mv_row as u32, mv_col as u32);
+        if candidate.ref_frame[0] != self.ref_frame[0] || candidate.ref_frame[1] != NONE {
+            return;
+        }
+
+        let cand_w4 = NUM_4X4_BLOCKS_WIDE[candidate.mi_size as usize] as i32;
+        let cand_h4 = NUM_4X4_BLOCKS_HIGH[candidate.mi_size as usize] as i32;
+        let cand_row = mv_row & !(cand_h4 - 1);
+        let cand_col = mv_col & !(cand_w4 - 1);
+        let mid_y = cand_row * 4 + cand_h4 * 4 / 2 - 1;
+        let mid_x = cand_col * 4 + cand_w4 * 4 / 2 - 1;
+        let bw = BLOCK_WIDTH[self.mi_size as usize] as i32;
+        let bh = BLOCK_HEIGHT[self.mi_size as usize] as i32;
+        let threshold = clip3(16, 112, bw.max(bh));
+        let cand_mv = self.mode_info.get(cand_row as u32, cand_col as u32).mv[0];
+        let mv_diff_row = (cand_mv[0] - mv[0]).abs();
+        let mv_diff_col = (cand_mv[1] - mv[1]).abs();
+        let valid = mv_diff_row + mv_diff_col <= threshold;
+
+        let cand = [
+            mid_y * 8,
+            mid_x * 8,
+            mid_y * 8 + cand_mv[0],
+            mid_x * 8 + cand_mv[1],
+        ];
+
+        samples.num_samples_scanned += 1;
+        if !valid && samples.num_samples_scanned > 1 {
+            return;
+        }
+
+        samples.cand_list[samples.num_samples] = cand;
+        if valid {
+            samples.num_samples += 1;
+        }
+    }
+}
+
+/// RefIdCount, RefIdMvs, RefDiffCount and RefDiffMvs of the compound extra
+/// search.
+#[derive(Debug, Default)]
+struct ExtraMvs {
+    ref_id_count: [usize; 2],
+    ref_id_mvs: [[Mv; 2]; 2],
+    ref_diff_count: [usize; 2],
+    ref_diff_mvs: [[Mv; 2]; 2],
+}
+
+/// Round2Signed( x, n ) for the 64 bit intermediate values of the global
+/// motion projection.
+fn round2signed_i64(x: i64, n: u8) -> i32 {
+    let v = (x.abs() + (1 << (n - 1))) >> n;
+    (if x < 0 { -v } else { v }) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{BLOCK_16X16, BWDREF_FRAME, LAST_FRAME, NEARESTMV, NEAREST_NEARESTMV, NONE},
+        tile::{block::Block, mode_info::ModeInfo},
+    };
+
+    const LAST: i8 = LAST_FRAME as i8;
+    const BWDREF: i8 = BWDREF_FRAME as i8;
+
+    fn frame_header() -> UncompressedHeader {
+        let mut frame_header = UncompressedHeader {
+            allow_high_precision_mv: true,
+            ..Default::default()
+        };
+
+        frame_header.ref_frame_sign_bias[BWDREF_FRAME as usize] = true;
+        frame_header
+    }
+
+    fn mv_predictor<'a>(
+        frame_header: &'a UncompressedHeader,
+        mode_info: &'a ModeInfoGrid,
+        motion_field_mvs: &'a MotionFieldMvs,
+        mi_size: u8,
+        ref_frame: [i8; 2],
+    ) -> MvPredictor<'a> {
+        MvPredictor {
+            frame_header,
+            mode_info,
+            motion_field_mvs,
+            mi_row_start: 0,
+            mi_row_end: mode_info.mi_rows,
+            mi_col_start: 0,
+            mi_col_end: mode_info.mi_cols,
+            mi_row: 4,
+            mi_col: 4,
+            mi_size,
+            ref_frame,
+        }
+    }
+
+    /// Writes an 8x8 inter neighbour into the grid.
+    fn neighbour(grid: &mut ModeInfoGrid, mi_row: u32, mi_col: u32, mode: u8, refs: [(i8, Mv); 2]) {
+        let block = Block {
+            mi_row,
+            mi_col,
+            mi_size: BLOCK_8X8,
+            ..Default::default()
+        };
+
+        let mi = ModeInfo {
+            mi_size: BLOCK_8X8,
+            is_inter: true,
+            y_mode: mode,
+            ref_frame: [refs[0].0, refs[1].0],
+            mv: [refs[0].1, refs[1].1],
+            ..Default::default()
+        };
+
+        grid.fill(&block, &mi);
+    }
+
+    #[test]
+    fn sorting_is_stable() {
+        let mut stack = MvStack::default();
+        for (idx, weight) in [2, 8, 2, 8, 4].into_iter().enumerate() {
+            stack.ref_stack_mv[idx][0] = [idx as i32, 0];
+            stack.weight_stack[idx] = weight;
+        }
+
+        stack.sort(0, 5);
+        assert_eq!(stack.weight_stack[..5], [8, 8, 4, 2, 2]);
+        let order: Vec<i32> = stack.ref_stack_mv[..5]
+            .iter()
+            .map(|mvs| mvs[0][0])
+            .collect();
+        assert_eq!(order, [1, 3, 4, 0, 2]);
+    }
+
+    #[test]
+    fn spatial_candidates() {
+        let frame_header = frame_header();
+        let motion_field_mvs = MotionFieldMvs::default();
+        let mut grid = ModeInfoGrid::new(16, 16);
+        let none = (NONE, [0, 0]);
+        neighbour(&mut grid, 2, 4, NEWMV, [(LAST, [8, 16]), none]);
+        // the second reference frame of the left neighbour is only used by
+        // the extra search.
+        neighbour(
+            &mut grid,
+            4,
+            2,
+            NEAREST_NEARESTMV,
+            [(LAST, [8, 16]), (BWDREF, [-4, 2])],
+        );
+
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_8X8,
+            [LAST, NONE],
+        );
+        let stack = predictor.find_mv_stack(false);
+
+        // the above and left neighbours merge into one nearest candidate,
+        // and the extra search adds the negated motion vector of the
+        // BWDREF_FRAME neighbour.
+        assert_eq!(stack.num_mv_found, 2);
+        assert_eq!(stack.ref_stack_mv[0][0], [8, 16]);
+        assert_eq!(stack.weight_stack[0], REF_CAT_LEVEL as u32 + 8);
+        assert_eq!(stack.ref_stack_mv[1][0], [4, -2]);
+        assert_eq!(stack.weight_stack[1], 2);
+        assert_eq!(stack.new_mv_count, 1);
+        assert_eq!(stack.drl_ctx_stack[..2], [1, 0]);
+        assert_eq!((stack.new_mv_context, stack.ref_mv_context), (4, 5));
+    }
+
+    #[test]
+    fn global_motion_fills_the_stack() {
+        let mut frame_header = frame_header();
+        let params = &mut frame_header.global_motion_params;
+        params.gm_type[LAST_FRAME as usize] = TRANSLATION;
+        params.gm_params[LAST_FRAME as usize][0] = 4 << (WARPEDMODEL_PREC_BITS - 3);
+        params.gm_params[LAST_FRAME as usize][1] = -2 << (WARPEDMODEL_PREC_BITS - 3);
+
+        let motion_field_mvs = MotionFieldMvs::default();
+        let grid = ModeInfoGrid::new(16, 16);
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_8X8,
+            [LAST, NONE],
+        );
+        let stack = predictor.find_mv_stack(false);
+
+        assert_eq!(stack.num_mv_found, 0);
+        assert_eq!(stack.global_mvs[0], [4, -2]);
+        assert_eq!(stack.ref_stack_mv[0][0], [4, -2]);
+        assert_eq!(stack.ref_stack_mv[1][0], [4, -2]);
+        assert_eq!((stack.new_mv_context, stack.ref_mv_context), (0, 0));
+    }
+
+    #[test]
+    fn compound_candidates() {
+        let frame_header = frame_header();
+        let motion_field_mvs = MotionFieldMvs::default();
+        let mut grid = ModeInfoGrid::new(16, 16);
+        neighbour(
+            &mut grid,
+            2,
+            4,
+            NEAREST_NEARESTMV,
+            [(LAST, [4, 4]), (BWDREF, [-4, -4])],
+        );
+        neighbour(&mut grid, 4, 2, NEARESTMV, [(LAST, [2, 6]), (NONE, [0, 0])]);
+
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_8X8,
+            [LAST, BWDREF],
+        );
+        let stack = predictor.find_mv_stack(true);
+
+        // only the above neighbour uses both reference frames, the extra
+        // search combines the first list of the left neighbour with the
+        // sign corrected LAST_FRAME motion vector of the above neighbour.
+        assert_eq!(stack.num_mv_found, 2);
+        assert_eq!(stack.ref_stack_mv[0], [[4, 4], [-4, -4]]);
+        assert_eq!(stack.ref_stack_mv[1], [[2, 6], [-4, -4]]);
+        assert_eq!(stack.weight_stack[..2], [REF_CAT_LEVEL as u32 + 4, 2]);
+        assert_eq!((stack.new_mv_context, stack.ref_mv_context), (3, 3));
+    }
+
+    #[test]
+    fn temporal_candidates() {
+        let mut frame_header = frame_header();
+        frame_header.use_ref_frame_mvs = true;
+        let grid = ModeInfoGrid::new(16, 16);
+        let mut motion_field_mvs = MotionFieldMvs::new(16, 16);
+        motion_field_mvs.set(LAST_FRAME, 2, 2, [16, -8]);
+        motion_field_mvs.set(LAST_FRAME, 3, 3, [16, -8]);
+        // below the block, added by the extension of medium sized blocks.
+        motion_field_mvs.set(LAST_FRAME, 4, 4, [2, 2]);
+
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_16X16,
+            [LAST, NONE],
+        );
+        let stack = predictor.find_mv_stack(false);
+        assert_eq!(stack.num_mv_found, 2);
+        assert_eq!(stack.ref_stack_mv[0][0], [16, -8]);
+        assert_eq!(stack.ref_stack_mv[1][0], [2, 2]);
+        assert_eq!(stack.weight_stack[..2], [4, 2]);
+        assert_eq!(stack.zero_mv_context, 1);
+
+        // a candidate close to the global motion vector at the top left of
+        // the block clears ZeroMvContext.
+        motion_field_mvs.set(LAST_FRAME, 2, 2, [8, 0]);
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_16X16,
+            [LAST, NONE],
+        );
+        assert_eq!(predictor.find_mv_stack(false).zero_mv_context, 0);
+
+        // compound candidates need a valid motion vector for both
+        // reference frames.
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_16X16,
+            [LAST, BWDREF],
+        );
+        let stack = predictor.find_mv_stack(true);
+        assert_eq!(stack.weight_stack[..2], [2, 2]);
+        assert_eq!(stack.zero_mv_context, 1);
+    }
+
+    #[test]
+    fn clamped_to_the_frame() {
+        let frame_header = frame_header();
+        let motion_field_mvs = MotionFieldMvs::default();
+        let mut grid = ModeInfoGrid::new(16, 16);
+        neighbour(
+            &mut grid,
+            2,
+            4,
+            NEARESTMV,
+            [(LAST, [-4000, 4000]), (NONE, [0, 0])],
+        );
+
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_8X8,
+            [LAST, NONE],
+        );
+        let stack = predictor.find_mv_stack(false);
+        // MV_BORDER + 8 * 8 outside of the 64x64 frame.
+        assert_eq!(
+            stack.ref_stack_mv[0][0],
+            [-(4 * 4 * 8) - 192, (10 * 4 * 8) + 192]
+        );
+    }
+
+    #[test]
+    fn warp_samples_of_the_neighbours() {
+        let frame_header = frame_header();
+        let motion_field_mvs = MotionFieldMvs::default();
+        let mut grid = ModeInfoGrid::new(16, 16);
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_8X8,
+            [LAST, NONE],
+        );
+        assert!(!predictor.has_overlappable_candidates());
+
+        // the above neighbour is too far from the motion vector of the block,
+        // and is replaced by the left neighbour.
+        neighbour(
+            &mut grid,
+            2,
+            4,
+            NEARESTMV,
+            [(LAST, [400, 0]), (NONE, [0, 0])],
+        );
+        neighbour(&mut grid, 4, 2, NEARESTMV, [(LAST, [8, 8]), (NONE, [0, 0])]);
+        let predictor = mv_predictor(
+            &frame_header,
+            &grid,
+            &motion_field_mvs,
+            BLOCK_8X8,
+            [LAST, NONE],
+        );
+        assert!(predictor.has_overlappable_candidates());
+
+        let samples = predictor.find_warp_samples([0, 0]);
+        assert_eq!(samples.num_samples, 1);
+        assert_eq!(samples.num_samples_scanned, 2);
+        assert_eq!(
+            samples.cand_list[0],
+            [19 * 8, 11 * 8, 19 * 8 + 8, 11 * 8 + 8]
+        );
+
+        // a single scanned neighbour is kept even when it is far.
+        let predictor = MvPredictor {
+            mi_col_start: 4,
+            ..predictor
+        };
+        let samples = predictor.find_warp_samples([0, 0]);
+        assert_eq!(samples.num_samples, 1);
+        assert_eq!(samples.num_samples_scanned, 1);
+        assert_eq!(samples.cand_list[0], [11 * 8, 19 * 8, 11 * 8 + 400, 19 * 8]);
+    }
+}
@@ -0,0 +1,619 @@
+use crate::{
+    cdf::CdfContext,
+    constants::{DC_PRED, MI_SIZE, PALETTE_COLORS, PALETTE_NUM_NEIGHBORS},
+    tables::{
+        BLOCK_HEIGHT, BLOCK_WIDTH, MI_HEIGHT_LOG2, MI_WIDTH_LOG2, PALETTE_COLOR_CONTEXT,
+        PALETTE_COLOR_HASH_MULTIPLIERS, TX_HEIGHT, TX_WIDTH,
+    },
+    util::{ceil_log2, clip1},
+};
+
+use super::TileDecoder;
+
+/// The palette_color_idx_y or palette_color_idx_uv cdf for a palette of n
+/// colors.
+fn palette_color_cdf(cdf: &mut CdfContext, plane: usize, n: u8, ctx: usize) -> &mut [u16] {
+    match (plane > 0, n) {
+        (false, 2) => &mut cdf.palette_size_2_y_color_cdf[ctx],
+        (false, 3) => &mut cdf.palette_size_3_y_color_cdf[ctx],
+        (false, 4) => &mut cdf.palette_size_4_y_color_cdf[ctx],
+        (false, 5) => &mut cdf.palette_size_5_y_color_cdf[ctx],
+        (false, 6) => &mut cdf.palette_size_6_y_color_cdf[ctx],
+        (false, 7) => &mut cdf.palette_size_7_y_color_cdf[ctx],
+        (false, _) => &mut cdf.palette_size_8_y_color_cdf[ctx],
+        (true, 2) => &mut cdf.palette_size_2_uv_color_cdf[ctx],
+        (true, 3) => &mut cdf.palette_size_3_uv_color_cdf[ctx],
+        (true, 4) => &mut cdf.palette_size_4_uv_color_cdf[ctx],
+        (true, 5) => &mut cdf.palette_size_5_uv_color_cdf[ctx],
+        (true, 6) => &mut cdf.palette_size_6_uv_color_cdf[ctx],
+        (true, 7) => &mut cdf.palette_size_7_uv_color_cdf[ctx],
+        (true, _) => &mut cdf.palette_size_8_uv_color_cdf[ctx],
+    }
+}
+
+/// get_palette_color_context( colorMap, r, c, n ), returns the ctx of the
+/// color index and ColorOrder, the palette indices sorted by how often they
+/// are used by the left, above left and above neighbours.
+fn get_palette_color_context(
+    color_map: &[[u8; 64]; 64],
+    r: usize,
+    c: usize,
+    n: usize,
+) -> (usize, [u8; PALETTE_COLORS as usize]) {
+    let mut scores = [0u8; PALETTE_COLORS as usize];
+    let mut color_order = [0, 1, 2, 3, 4, 5, 6, 7];
+    if c > 0 {
+        scores[color_map[r][c - 1] as usize] += 2;
+    }
+
+    if r > 0 && c > 0 {
+        scores[color_map[r - 1][c - 1] as usize] += 1;
+    }
+
+    if r > 0 {
+        scores[color_map[r - 1][c] as usize] += 2;
+    }
+
+    for i in 0..PALETTE_NUM_NEIGHBORS as usize {
+        let mut max_score = scores[i];
+        let mut max_idx = i;
+        for (j, &score) in scores.iter().enumerate().take(n).skip(i + 1) {
+            if score > max_score {
+                max_score = score;
+                max_idx = j;
+            }
+        }
+
+        if max_idx != i {
+            let max_color_order = color_order[max_idx];
+            for k in (i + 1..=max_idx).rev() {
+                scores[k] = scores[k - 1];
+                color_order[k] = color_order[k - 1];
+            }
+
+            scores[i] = max_score;
+            color_order[i] = max_color_order;
+        }
+    }
+
+    let mut color_context_hash = 0;
+    for i in 0..PALETTE_NUM_NEIGHBORS as usize {
+        color_context_hash += scores[i] * PALETTE_COLOR_HASH_MULTIPLIERS[i];
+    }
+
+    let ctx = PALETTE_COLOR_CONTEXT[color_context_hash as usize];
+    debug_assert!(ctx >= 0);
+    (ctx.max(0) as usize, color_order)
+}
+
+impl TileDecoder<'_> {
+    /// palette_mode_info( )
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#palette-mode-info-syntax
+    pub fn palette_mode_info(&mut self) {
+        let bsize_ctx = (MI_WIDTH_LOG2[self.mi.mi_size as usize]
+            + MI_HEIGHT_LOG2[self.mi.mi_size as usize]
+            - 2) as usize;
+        let bit_depth = self.ctx.bit_depth as usize;
+
+        if self.mi.y_mode == DC_PRED {
+            let mode_info = &self.ctx.mode_info;
+            let mut ctx = 0;
+            if self.block.avail_u
+                && mode_info
+                    .get(self.block.mi_row - 1, self.block.mi_col)
+                    .palette_size[0]
+                    > 0
+            {
+                ctx += 1;
+            }
+
+            if self.block.avail_l
+                && mode_info
+                    .get(self.block.mi_row, self.block.mi_col - 1)
+                    .palette_size[0]
+                    > 0
+            {
+                ctx += 1;
+            }
+
+            // has_palette_y	S()
+            let cdf = &mut self.cdf.palette_y_mode_cdf[bsize_ctx][ctx];
+            if self.sd.read_symbol(cdf) == 1 {
+                // palette_size_y_minus_2	S()
+                let cdf = &mut self.cdf.palette_y_size_cdf[bsize_ctx];
+                let palette_size_y = self.sd.read_symbol(cdf) as u8 + 2;
+                self.mi.palette_size[0] = palette_size_y;
+
+                let n = palette_size_y as usize;
+                let cache = self.get_palette_cache(0);
+                let colors = &mut self.mi.palette_colors[0];
+                let mut idx = 0;
+                for &color in cache.iter() {
+                    if idx >= n {
+                        break;
+                    }
+
+                    // use_palette_color_cache_y	L(1)
+                    if self.sd.read_literal(1) == 1 {
+                        colors[idx] = color;
+                        idx += 1;
+                    }
+                }
+
+                if idx < n {
+                    // palette_colors_y[ idx ]	L(BitDepth)
+                    colors[idx] = self.sd.read_literal(bit_depth) as u16;
+                    idx += 1;
+                }
+
+                let mut palette_bits = 0;
+                if idx < n {
+                    let min_bits = bit_depth - 3;
+
+                    // palette_num_extra_bits_y	L(2)
+                    palette_bits = min_bits + self.sd.read_literal(2) as usize;
+                }
+
+                while idx < n {
+                    // palette_delta_y	L(paletteBits)
+                    let palette_delta_y = self.sd.read_literal(palette_bits) as i32 + 1;
+                    colors[idx] = clip1(colors[idx - 1] as i32 + palette_delta_y, bit_depth as u8);
+
+                    let range = (1 << bit_depth) - colors[idx] as u32 - 1;
+                    palette_bits = palette_bits.min(ceil_log2(range) as usize);
+                    idx += 1;
+                }
+
+                colors[..n].sort_unstable();
+            }
+        }
+
+        if self.block.has_chroma && self.mi.uv_mode == DC_PRED {
+            // has_palette_uv	S()
+            let ctx = (self.mi.palette_size[0] > 0) as usize;
+            if self.sd.read_symbol(&mut self.cdf.palette_uv_mode_cdf[ctx]) == 1 {
+                // palette_size_uv_minus_2	S()
+                let cdf = &mut self.cdf.palette_uv_size_cdf[bsize_ctx];
+                let palette_size_uv = self.sd.read_symbol(cdf) as u8 + 2;
+                self.mi.palette_size[1] = palette_size_uv;
+
+                let n = palette_size_uv as usize;
+                let cache = self.get_palette_cache(1);
+                let colors = &mut self.mi.palette_colors[1];
+                let mut idx = 0;
+                for &color in cache.iter() {
+                    if idx >= n {
+                        break;
+                    }
+
+                    // use_palette_color_cache_u	L(1)
+                    if self.sd.read_literal(1) == 1 {
+                        colors[idx] = color;
+                        idx += 1;
+                    }
+                }
+
+                if idx < n {
+                    // palette_colors_u[ idx ]	L(BitDepth)
+                    colors[idx] = self.sd.read_literal(bit_depth) as u16;
+                    idx += 1;
+                }
+
+                let mut palette_bits = 0;
+                if idx < n {
+                    let min_bits = bit_depth - 3;
+
+                    // palette_num_extra_bits_u	L(2)
+                    palette_bits = min_bits + self.sd.read_literal(2) as usize;
+                }
+
+                while idx < n {
+                    // palette_delta_u	L(paletteBits)
+                    let palette_delta_u = self.sd.read_literal(palette_bits) as i32;
+                    colors[idx] = clip1(colors[idx - 1] as i32 + palette_delta_u, bit_depth as u8);
+
+                    let range = (1 << bit_depth) - colors[idx] as u32;
+                    palette_bits = palette_bits.min(ceil_log2(range) as usize);
+                    idx += 1;
+                }
+
+                colors[..n].sort_unstable();
+
+                // delta_encode_palette_colors_v	L(1)
+                let colors = &mut self.mi.palette_colors[2];
+                if self.sd.read_literal(1) == 1 {
+                    let min_bits = bit_depth - 4;
+                    let max_val = 1 << bit_depth;
+
+                    // palette_num_extra_bits_v	L(2)
+                    let palette_bits = min_bits + self.sd.read_literal(2) as usize;
+
+                    // palette_colors_v[ 0 ]	L(BitDepth)
+                    colors[0] = self.sd.read_literal(bit_depth) as u16;
+                    for idx in 1..n {
+                        // palette_delta_v	L(paletteBits)
+                        let mut palette_delta_v = self.sd.read_literal(palette_bits) as i32;
+                        if palette_delta_v != 0 {
+                            // palette_delta_sign_bit_v	L(1)
+                            if self.sd.read_literal(1) == 1 {
+                                palette_delta_v = -palette_delta_v;
+                            }
+                        }
+
+                        let mut val = colors[idx - 1] as i32 + palette_delta_v;
+                        if val < 0 {
+                            val += max_val;
+                        }
+
+                        if val >= max_val {
+                            val -= max_val;
+                        }
+
+                        colors[idx] = clip1(val, bit_depth as u8);
+                    }
+                } else {
+                    for color in colors.iter_mut().take(n) {
+                        // palette_colors_v[ idx ]	L(BitDepth)
+                        *color = self.sd.read_literal(bit_depth) as u16;
+                    }
+                }
+            }
+        }
+    }
+
+    /// get_palette_cache( plane ), the sorted and deduplicated union of the
+    /// palettes of the above (in the same 64x64 row) and left blocks.
+    fn get_palette_cache(&self, plane: usize) -> Vec<u16> {
+        let mode_info = &self.ctx.mode_info;
+        let (above, above_n) =
+            if !(self.block.mi_row * MI_SIZE as u32).is_multiple_of(64) && self.block.avail_u {
+                let above = mode_info.get(self.block.mi_row - 1, self.block.mi_col);
+                (
+                    above.palette_colors[plane],
+                    above.palette_size[plane] as usize,
+                )
+            } else {
+                Default::default()
+            };
+
+        let (left, left_n) = if self.block.avail_l {
+            let left = mode_info.get(self.block.mi_row, self.block.mi_col - 1);
+            (
+                left.palette_colors[plane],
+                left.palette_size[plane] as usize,
+            )
+        } else {
+            Default::default()
+        };
+
+        let mut cache: Vec<u16> = Vec::with_capacity(above_n + left_n);
+        let mut push = |value: u16| {
+            if cache.last() != Some(&value) {
+                cache.push(value);
+            }
+        };
+
+        let (mut above_idx, mut left_idx) = (0, 0);
+        while above_idx < above_n && left_idx < left_n {
+            let above_c = above[above_idx];
+            let left_c = left[left_idx];
+            if left_c < above_c {
+                push(left_c);
+                left_idx += 1;
+            } else {
+                push(above_c);
+                above_idx += 1;
+                if left_c == above_c {
+                    left_idx += 1;
+                }
+            }
+        }
+
+        for &value in &above[above_idx..above_n] {
+            push(value);
+        }
+
+        for &value in &left[left_idx..left_n] {
+            push(value);
+        }
+
+        cache
+    }
+
+    /// palette_tokens( ), the color index maps are coded in wavefront order
+    /// (anti-diagonals from the top left corner), the parts of the block
+    /// outside of the frame are copied from the last row or column inside.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#palette-tokens-syntax
+    pub fn palette_tokens(&mut self) {
+        let mut block_height = BLOCK_HEIGHT[self.mi.mi_size as usize] as usize;
+        let mut block_width = BLOCK_WIDTH[self.mi.mi_size as usize] as usize;
+        let mut onscreen_height =
+            block_height.min(((self.ctx.mi_rows - self.block.mi_row) * MI_SIZE as u32) as usize);
+        let mut onscreen_width =
+            block_width.min(((self.ctx.mi_cols - self.block.mi_col) * MI_SIZE as u32) as usize);
+
+        if self.mi.palette_size[0] > 0 {
+            self.read_color_map(
+                0,
+                self.mi.palette_size[0],
+                block_width,
+                block_height,
+                onscreen_width,
+                onscreen_height,
+            );
+        }
+
+        if self.mi.palette_size[1] > 0 {
+            let color_config = &self.sequence_header.color_config;
+            let (sub_x, sub_y) = (
+                color_config.subsampling_x as usize,
+                color_config.subsampling_y as usize,
+            );
+
+            block_height >>= sub_y;
+            block_width >>= sub_x;
+            onscreen_height >>= sub_y;
+            onscreen_width >>= sub_x;
+            if block_width < 4 {
+                block_width += 2;
+                onscreen_width += 2;
+            }
+
+            if block_height < 4 {
+                block_height += 2;
+                onscreen_height += 2;
+            }
+
+            self.read_color_map(
+                1,
+                self.mi.palette_size[1],
+                block_width,
+                block_height,
+                onscreen_width,
+                onscreen_height,
+            );
+        }
+    }
+
+    /// Reads ColorMapY (plane 0) or ColorMapUV (plane 1).
+    fn read_color_map(
+        &mut self,
+        plane: usize,
+        n: u8,
+        block_width: usize,
+        block_height: usize,
+        onscreen_width: usize,
+        onscreen_height: usize,
+    ) {
+        let color_map = if plane == 0 {
+            &mut self.color_map_y
+        } else {
+            &mut self.color_map_uv
+        };
+
+        // color_index_map_y	NS(PaletteSizeY) or color_index_map_uv	NS(PaletteSizeUV)
+        color_map[0][0] = self.sd.read_ns(n as u32) as u8;
+        for i in 1..onscreen_height + onscreen_width - 1 {
+            let j_end = i.min(onscreen_width - 1);
+            let j_start = (i + 1).saturating_sub(onscreen_height);
+            for j in (j_start..=j_end).rev() {
+                let (ctx, color_order) = get_palette_color_context(color_map, i - j, j, n as usize);
+
+                // palette_color_idx_y	S() or palette_color_idx_uv	S()
+                let cdf = palette_color_cdf(&mut self.cdf, plane, n, ctx);
+                let palette_color_idx = self.sd.read_symbol(cdf);
+                color_map[i - j][j] = color_order[palette_color_idx];
+            }
+        }
+
+        for row in color_map.iter_mut().take(onscreen_height) {
+            let value = row[onscreen_width - 1];
+            row[onscreen_width..block_width].fill(value);
+        }
+
+        for i in onscreen_height..block_height {
+            color_map[i] = color_map[onscreen_height - 1];
+        }
+    }
+
+    /// The palette prediction process, the transform block at (x, y) in units
+    /// of 4x4 samples inside of the block is set to the palette colors of its
+    /// color indices.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#palette-prediction-process
+    pub fn predict_palette(
+        &mut self,
+        plane: usize,
+        start_x: usize,
+        start_y: usize,
+        x: usize,
+        y: usize,
+        tx_sz: u8,
+    ) {
+        let w = TX_WIDTH[tx_sz as usize] as usize;
+        let h = TX_HEIGHT[tx_sz as usize] as usize;
+        let palette = &self.mi.palette_colors[plane];
+        let color_map = if plane == 0 {
+            &self.color_map_y
+        } else {
+            &self.color_map_uv
+        };
+
+        let Some(picture) = self.ctx.current_frame.as_mut() else {
+            return;
+        };
+
+        let frame = &mut picture.planes[plane];
+        for i in 0..h {
+            for j in 0..w {
+                let index = color_map[y * 4 + i][x * 4 + j];
+                frame.set(start_x + j, start_y + i, palette[index as usize]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{BLOCK_16X16, BLOCK_8X8, V_PRED},
+        entropy::SymbolWriter,
+        tile::test_tiles::{key_frame_context, set_block, tile_decoder},
+        Av1DecoderContext,
+    };
+
+    /// Sets the luma palette of the block at r, c of the frame.
+    fn set_palette(ctx: &mut Av1DecoderContext, r: u32, c: u32, colors: &[u16]) {
+        let mode_info = ctx.mode_info.get_mut(r, c);
+        mode_info.palette_size[0] = colors.len() as u8;
+        mode_info.palette_colors[0][..colors.len()].copy_from_slice(colors);
+    }
+
+    #[test]
+    fn palette_caches_merge_the_above_and_left_palettes() {
+        let mut ctx = key_frame_context(64, 128, false);
+        set_palette(&mut ctx, 0, 1, &[10, 20, 30]);
+        set_palette(&mut ctx, 1, 0, &[5, 20, 40]);
+        set_palette(&mut ctx, 15, 1, &[10, 20, 30]);
+        set_palette(&mut ctx, 16, 0, &[5, 20, 40]);
+        let data = [0];
+        let mut d = tile_decoder(&mut ctx, &data);
+
+        set_block(&mut d, 1, 1, BLOCK_8X8);
+        assert_eq!(d.get_palette_cache(0), [5, 10, 20, 30, 40]);
+        assert!(d.get_palette_cache(1).is_empty());
+
+        // the palette above is not used across a 64x64 row.
+        set_block(&mut d, 16, 1, BLOCK_8X8);
+        assert_eq!(d.get_palette_cache(0), [5, 20, 40]);
+    }
+
+    #[test]
+    fn luma_colors_from_the_cache_and_deltas() {
+        let mut ctx = key_frame_context(64, 64, false);
+        set_palette(&mut ctx, 0, 1, &[10, 20, 30]);
+        set_palette(&mut ctx, 1, 0, &[5, 20, 40]);
+
+        // has_palette_y with both neighbours using a palette, 4 colors: 5
+        // and 20 from the cache, then 100 and a delta of 9 + 1 with 5 + 1
+        // bits.
+        let mut cdf = ctx.cdf.clone();
+        let mut w = SymbolWriter::default();
+        w.write_symbol(1, &mut cdf.palette_y_mode_cdf[0][2]);
+        w.write_symbol(2, &mut cdf.palette_y_size_cdf[0]);
+        for use_cache in [1, 0, 1, 0, 0] {
+            w.write_literal(use_cache, 1);
+        }
+
+        w.write_literal(100, 8);
+        w.write_literal(1, 2);
+        w.write_literal(9, 6);
+        let data = w.into_bytes();
+
+        let mut d = tile_decoder(&mut ctx, &data);
+        set_block(&mut d, 1, 1, BLOCK_8X8);
+        d.block.has_chroma = false;
+        d.palette_mode_info();
+        assert_eq!(d.mi.palette_size, [4, 0]);
+        assert_eq!(d.mi.palette_colors[0][..4], [5, 20, 100, 110]);
+    }
+
+    #[test]
+    fn chroma_colors_and_delta_coded_v_colors_wrap_around() {
+        let mut ctx = key_frame_context(64, 64, false);
+
+        // 3 colors, U from 200 with deltas of 20 and 0 with 5 bits, V from
+        // 250 with deltas of +10 and -5 with 4 + 2 bits.
+        let mut cdf = ctx.cdf.clone();
+        let mut w = SymbolWriter::default();
+        w.write_symbol(1, &mut cdf.palette_uv_mode_cdf[0]);
+        w.write_symbol(1, &mut cdf.palette_uv_size_cdf[0]);
+        w.write_literal(200, 8);
+        w.write_literal(0, 2);
+        w.write_literal(20, 5);
+        w.write_literal(0, 5);
+        w.write_literal(1, 1);
+        w.write_literal(2, 2);
+        w.write_literal(250, 8);
+        w.write_literal(10, 6);
+        w.write_literal(0, 1);
+        w.write_literal(5, 6);
+        w.write_literal(1, 1);
+        let data = w.into_bytes();
+
+        let mut d = tile_decoder(&mut ctx, &data);
+        set_block(&mut d, 0, 0, BLOCK_8X8);
+        d.mi.y_mode = V_PRED;
+        d.mi.uv_mode = DC_PRED;
+        d.palette_mode_info();
+        assert_eq!(d.mi.palette_size, [0, 3]);
+        assert_eq!(d.mi.palette_colors[1][..3], [200, 220, 220]);
+        assert_eq!(d.mi.palette_colors[2][..3], [250, 4, 255]);
+    }
+
+    #[test]
+    fn color_contexts_order_the_neighbour_colors() {
+        let mut color_map = [[0; 64]; 64];
+
+        // only the left neighbour in the first row.
+        color_map[0][0] = 4;
+        let (ctx, order) = get_palette_color_context(&color_map, 0, 1, 8);
+        assert_eq!((ctx, order[..3].to_vec()), (0, vec![4, 0, 1]));
+
+        // left 2, above left and above 1: scores 3 and 2.
+        color_map[0][0] = 1;
+        color_map[0][1] = 1;
+        color_map[1][0] = 2;
+        let (ctx, order) = get_palette_color_context(&color_map, 1, 1, 8);
+        assert_eq!((ctx, order[..4].to_vec()), (2, vec![1, 2, 0, 3]));
+
+        // all neighbours with the same color.
+        color_map[1][0] = 1;
+        let (ctx, order) = get_palette_color_context(&color_map, 1, 1, 8);
+        assert_eq!((ctx, order[..4].to_vec()), (4, vec![1, 0, 2, 3]));
+
+        // left 0 and above 2 tie, the lower index goes first.
+        color_map[0][0] = 1;
+        color_map[0][1] = 2;
+        color_map[1][0] = 0;
+        let (ctx, order) = get_palette_color_context(&color_map, 1, 1, 3);
+        assert_eq!((ctx, order[..3].to_vec()), (1, vec![0, 2, 1]));
+    }
+
+    #[test]
+    fn color_maps_are_read_in_wavefront_order() {
+        // a 16x16 block at the right edge of a 56 samples wide frame, the
+        // right half is outside of the frame.
+        let mut ctx = key_frame_context(56, 64, false);
+        let mut expected = [[0u8; 64]; 64];
+        for (i, row) in expected.iter_mut().enumerate().take(16) {
+            for (j, color) in row.iter_mut().enumerate().take(8) {
+                *color = ((i + j * j) % 3) as u8;
+            }
+        }
+
+        let mut cdf = ctx.cdf.clone();
+        let mut w = SymbolWriter::default();
+        w.write_ns(expected[0][0] as u32, 3);
+        for i in 1usize..16 + 8 - 1 {
+            for j in (i.saturating_sub(15)..=i.min(7)).rev() {
+                let (ctx, order) = get_palette_color_context(&expected, i - j, j, 3);
+                let symbol = order.iter().position(|&c| c == expected[i - j][j]);
+                w.write_symbol(symbol.unwrap(), palette_color_cdf(&mut cdf, 0, 3, ctx));
+            }
+        }
+
+        let data = w.into_bytes();
+        let mut d = tile_decoder(&mut ctx, &data);
+        set_block(&mut d, 0, 12, BLOCK_16X16);
+        d.mi.palette_size = [3, 0];
+        d.palette_tokens();
+        for (i, row) in d.color_map_y.iter().enumerate().take(16) {
+            assert_eq!(row[..8], expected[i][..8], "{i}");
+            assert!(row[8..16].iter().all(|&color| color == row[7]), "{i}");
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::{
+    cdf::CdfContext,
+    constants::{BLOCK_8X8, BLOCK_INVALID},
+    tables::{MI_HEIGHT_LOG2, MI_WIDTH_LOG2, NUM_4X4_BLOCKS_WIDE, PARTITION_SUBSIZE},
+    Av1DecodeError,
+};
+
+use super::{block::Block, TileDecoder};
+
+/// see: https://aomediacodec.github.io/av1-spec/#block-partition-semantics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    None,
+    Horz,
+    Vert,
+    Split,
+    HorzA,
+    HorzB,
+    VertA,
+    VertB,
+    Horz4,
+    Vert4,
+}
+
+impl Partition {
+    fn from_symbol(symbol: usize) -> Self {
+        match symbol {
+            0 => Self::None,
+            1 => Self::Horz,
+            2 => Self::Vert,
+            3 => Self::Split,
+            4 => Self::HorzA,
+            5 => Self::HorzB,
+            6 => Self::VertA,
+            7 => Self::VertB,
+            8 => Self::Horz4,
+            _ => Self::Vert4,
+        }
+    }
+}
+
+/// A node of the partition tree of a superblock.
+///
+/// A node with the split partition has up to four children (the quadrants
+/// outside of the frame are not coded), a node with any other partition has
+/// the blocks it was divided into.
+#[derive(Debug, Clone)]
+pub struct PartitionNode {
+    pub mi_row: u32,
+    pub mi_col: u32,
+    pub bsize: u8,
+    pub partition: Partition,
+    pub children: Vec<PartitionNode>,
+    pub blocks: Vec<Block>,
+}
+
+impl PartitionNode {
+    /// Visits all blocks of the tree in decoding order.
+    pub fn for_each_block<F: FnMut(&Block)>(&self, f: &mut F) {
+        for child in &self.children {
+            child.for_each_block(f);
+        }
+
+        for block in &self.blocks {
+            f(block);
+        }
+    }
+}
+
+/// The partition cdf for the block size, bsl is Mi_Width_Log2[ bSize ].
+fn partition_cdf(cdf: &mut CdfContext, bsl: u8, ctx: usize) -> &mut [u16] {
+    match bsl {
+        1 => &mut cdf.partition_w8_cdf[ctx],
+        2 => &mut cdf.partition_w16_cdf[ctx],
+        3 => &mut cdf.partition_w32_cdf[ctx],
+        4 => &mut cdf.partition_w64_cdf[ctx],
+        _ => &mut cdf.partition_w128_cdf[ctx],
+    }
+}
+
+impl TileDecoder<'_> {
+    /// decode_partition( r, c, bSize ), returns None when the position is
+    /// outside of the frame.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#decode-partition-syntax
+    pub fn decode_partition(
+        &mut self,
+        r: u32,
+        c: u32,
+        bsize: u8,
+    ) -> Result<Option<PartitionNode>, Av1DecodeError> {
+        if r >= self.ctx.mi_rows || c >= self.ctx.mi_cols {
+            return Ok(None);
+        }
+
+        let num4x4 = NUM_4X4_BLOCKS_WIDE[bsize as usize] as u32;
+        let half_block4x4 = num4x4 >> 1;
+        let quarter_block4x4 = half_block4x4 >> 1;
+        let has_rows = (r + half_block4x4) < self.ctx.mi_rows;
+        let has_cols = (c + half_block4x4) < self.ctx.mi_cols;
+
+        let partition = if bsize < BLOCK_8X8 {
+            Partition::None
+        } else if has_rows && has_cols {
+            // partition	S()
+            let ctx = self.partition_ctx(r, c, bsize);
+            let cdf = partition_cdf(&mut self.cdf, MI_WIDTH_LOG2[bsize as usize], ctx);
+            Partition::from_symbol(self.sd.read_symbol(cdf))
+        } else if has_cols {
+            // split_or_horz	S()
+            let split_or_horz = self.read_split_or(
+                r,
+                c,
+                bsize,
+                &[
+                    Partition::Horz,
+                    Partition::Split,
+                    Partition::HorzA,
+                    Partition::HorzB,
+                    Partition::VertA,
+                    Partition::Horz4,
+                ],
+            );
+
+            if split_or_horz {
+                Partition::Split
+            } else {
+                Partition::Horz
+            }
+        } else if has_rows {
+            // split_or_vert	S()
+            let split_or_vert = self.read_split_or(
+                r,
+                c,
+                bsize,
+                &[
+                    Partition::Vert,
+                    Partition::Split,
+                    Partition::HorzA,
+                    Partition::VertA,
+                    Partition::VertB,
+                    Partition::Vert4,
+                ],
+            );
+
+            if split_or_vert {
+                Partition::Split
+            } else {
+                Partition::Vert
+            }
+        } else {
+            Partition::Split
+        };
+
+        let sub_size = PARTITION_SUBSIZE[partition as usize][bsize as usize];
+        let split_size = PARTITION_SUBSIZE[Partition::Split as usize][bsize as usize];
+        debug_assert_ne!(sub_size, BLOCK_INVALID);
+
+        let mut node = PartitionNode {
+            mi_row: r,
+            mi_col: c,
+            bsize,
+            partition,
+            children: Vec::new(),
+            blocks: Vec::new(),
+        };
+
+        let (h, q) = (half_block4x4, quarter_block4x4);
+        let blocks = match partition {
+            Partition::None => vec![(r, c, sub_size)],
+            Partition::Horz => {
+                let mut blocks = vec![(r, c, sub_size)];
+                if has_rows {
+                    blocks.push((r + h, c, sub_size));
+                }
+
+                blocks
+            }
+            Partition::Vert => {
+                let mut blocks = vec![(r, c, sub_size)];
+                if has_cols {
+                    blocks.push((r, c + h, sub_size));
+                }
+
+                blocks
+            }
+            Partition::Split => {
+                for (r, c) in [(r, c), (r, c + h), (r + h, c), (r + h, c + h)] {
+                    if let Some(child) = self.decode_partition(r, c, sub_size)? {
+                        node.children.push(child);
+                    }
+                }
+
+                Vec::new()
+            }
+            Partition::HorzA => vec![
+                (r, c, split_size),
+                (r, c + h, split_size),
+                (r + h, c, sub_size),
+            ],
+            Partition::HorzB => vec![
+                (r, c, sub_size),
+                (r + h, c, split_size),
+                (r + h, c + h, split_size),
+            ],
+            Partition::VertA => vec![
+                (r, c, split_size),
+                (r + h, c, split_size),
+                (r, c + h, sub_size),
+            ],
+            Partition::VertB => vec![
+                (r, c, sub_size),
+                (r, c + h, split_size),
+                (r + h, c + h, split_size),
+            ],
+            Partition::Horz4 => (0..4)
+                .map(|i| (r + q * i, c, sub_size))
+                .filter(|&(r, _, _)| r < self.ctx.mi_rows)
+                .collect(),
+            Partition::Vert4 => (0..4)
+                .map(|i| (r, c + q * i, sub_size))
+                .filter(|&(_, c, _)| c < self.ctx.mi_cols)
+                .collect(),
+        };
+
+        for (r, c, sub_size) in blocks {
+            let block = self.decode_block(r, c, sub_size)?;
+            node.blocks.push(block);
+        }
+
+        Ok(Some(node))
+    }
+
+    /// The ctx of the partition symbol, from the sizes of the blocks above
+    /// and to the left.
+    fn partition_ctx(&self, r: u32, c: u32, bsize: u8) -> usize {
+        let bsl = MI_WIDTH_LOG2[bsize as usize];
+        let mode_info = &self.ctx.mode_info;
+        let above = self.is_inside(r as i32 - 1, c as i32)
+            && MI_WIDTH_LOG2[mode_info.get(r - 1, c).mi_size as usize] < bsl;
+        let left = self.is_inside(r as i32, c as i32 - 1)
+            && MI_HEIGHT_LOG2[mode_info.get(r, c - 1).mi_size as usize] < bsl;
+
+        left as usize * 2 + above as usize
+    }
+
+    /// Reads split_or_horz or split_or_vert, the probability of a split is
+    /// the sum of the probabilities in the partition cdf of the partitions
+    /// that split the block in the same direction. Partitions that the cdf
+    /// does not contain (the 4-way partitions of 128x128 blocks) are skipped.
+    fn read_split_or(&mut self, r: u32, c: u32, bsize: u8, partitions: &[Partition]) -> bool {
+        let ctx = self.partition_ctx(r, c, bsize);
+        let partition_cdf = partition_cdf(&mut self.cdf, MI_WIDTH_LOG2[bsize as usize], ctx);
+        let n = partition_cdf.len() - 1;
+
+        let mut psum = 0;
+        for &partition in partitions {
+            let i = partition as usize;
+            if i < n {
+                psum += (partition_cdf[i] - partition_cdf[i - 1]) as u32;
+            }
+        }
+
+        let mut cdf = [((1 << 15) - psum) as u16, 1 << 15, 0];
+        self.sd.read_symbol(&mut cdf) == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{
+            BLOCK_128X128, BLOCK_16X64, BLOCK_32X32, BLOCK_32X64, BLOCK_4X4, BLOCK_64X128,
+            BLOCK_64X32, BLOCK_64X64, BLOCK_8X32,
+        },
+        tile::test_tiles::{frame_tile, key_frame_context},
+    };
+
+    /// The partition trees of the superblocks of a frame, the tile data is
+    /// all zeros (the first symbol of each cdf) or all ones (the last one).
+    fn superblocks(width: u16, height: u16, sb128: bool, byte: u8) -> Vec<PartitionNode> {
+        let mut ctx = key_frame_context(width, height, sb128);
+        let data = vec![byte; 4096];
+        let tile = frame_tile(&ctx, &data);
+        TileDecoder::new(&mut ctx, &tile).unwrap().decode().unwrap();
+        ctx.superblocks
+    }
+
+    /// The positions and sizes of the blocks of a tree, in decoding order.
+    fn blocks(node: &PartitionNode) -> Vec<(u32, u32, u8)> {
+        let mut blocks = Vec::new();
+        node.for_each_block(&mut |b| blocks.push((b.mi_row, b.mi_col, b.mi_size)));
+        blocks
+    }
+
+    #[test]
+    fn partition_symbols_inside_of_the_frame() {
+        let sbs = superblocks(64, 64, false, 0);
+        assert_eq!(sbs.len(), 1);
+        assert_eq!(sbs[0].partition, Partition::None);
+        assert_eq!(blocks(&sbs[0]), [(0, 0, BLOCK_64X64)]);
+
+        // the last symbol of the 64x64 cdf is the 4-way vertical partition.
+        let sbs = superblocks(64, 64, false, 0xff);
+        assert_eq!(sbs[0].partition, Partition::Vert4);
+        assert_eq!(
+            blocks(&sbs[0]),
+            [
+                (0, 0, BLOCK_16X64),
+                (0, 4, BLOCK_16X64),
+                (0, 8, BLOCK_16X64),
+                (0, 12, BLOCK_16X64)
+            ]
+        );
+    }
+
+    #[test]
+    fn superblocks_128x128() {
+        let sbs = superblocks(128, 128, true, 0);
+        assert_eq!(sbs.len(), 1);
+        assert_eq!(sbs[0].bsize, BLOCK_128X128);
+        assert_eq!(blocks(&sbs[0]), [(0, 0, BLOCK_128X128)]);
+
+        // the 128x128 cdf has no 4-way partitions, its last symbol is
+        // PARTITION_VERT_B.
+        let sbs = superblocks(128, 128, true, 0xff);
+        assert_eq!(sbs[0].partition, Partition::VertB);
+        assert_eq!(
+            blocks(&sbs[0]),
+            [
+                (0, 0, BLOCK_64X128),
+                (0, 16, BLOCK_64X64),
+                (16, 16, BLOCK_64X64)
+            ]
+        );
+    }
+
+    #[test]
+    fn blocks_at_the_right_and_bottom_edges() {
+        // the second superblock of a 72x64 frame has no cols, split_or_vert
+        // 0 keeps the left half.
+        let sbs = superblocks(72, 64, false, 0);
+        assert_eq!(sbs.len(), 2);
+        assert_eq!(sbs[1].partition, Partition::Vert);
+        assert_eq!(blocks(&sbs[1]), [(0, 16, BLOCK_32X64)]);
+
+        // and the second superblock of a 64x72 frame has no rows.
+        let sbs = superblocks(64, 72, false, 0);
+        assert_eq!(sbs[1].partition, Partition::Horz);
+        assert_eq!(blocks(&sbs[1]), [(16, 0, BLOCK_64X32)]);
+
+        // split_or_vert 1 splits down to the 8x8 blocks that have cols, the
+        // quadrants outside of the frame are not coded.
+        let sbs = superblocks(72, 64, false, 0xff);
+        assert_eq!(sbs[1].partition, Partition::Split);
+        assert_eq!(sbs[1].children.len(), 2);
+        let sb = blocks(&sbs[1]);
+        assert_eq!(sb.len(), 16 * 2);
+        assert!(sb
+            .iter()
+            .all(|&(r, c, bsize)| r < 16 && (16..18).contains(&c) && bsize == BLOCK_4X4));
+    }
+
+    #[test]
+    fn blocks_at_the_corner_of_the_frame() {
+        // a 40x24 frame has cols but no rows at 64x64, split_or_horz 0 keeps
+        // the top half only.
+        let sbs = superblocks(40, 24, false, 0);
+        assert_eq!(sbs[0].partition, Partition::Horz);
+        assert_eq!(blocks(&sbs[0]), [(0, 0, BLOCK_64X32)]);
+
+        // split_or_horz 1, the top left 32x32 has rows and cols and reads a
+        // partition symbol, the top right one has rows only.
+        let sbs = superblocks(40, 24, false, 0xff);
+        let [left, right] = &sbs[0].children[..] else {
+            panic!("{:?}", sbs[0].children);
+        };
+        assert_eq!(
+            (left.bsize, left.partition),
+            (BLOCK_32X32, Partition::Vert4)
+        );
+        assert_eq!(blocks(left)[3], (0, 6, BLOCK_8X32));
+        assert_eq!(right.partition, Partition::Split);
+        assert!(blocks(right)
+            .iter()
+            .all(|&(r, c, _)| r < 6 && (8..10).contains(&c)));
+    }
+
+    #[test]
+    fn four_way_blocks_outside_of_the_frame_are_skipped() {
+        // 9 mi cols, the last quarter of the 64x64 block starts at col 12.
+        let sbs = superblocks(36, 64, false, 0xff);
+        assert_eq!(sbs[0].partition, Partition::Vert4);
+        assert_eq!(
+            blocks(&sbs[0]),
+            [
+                (0, 0, BLOCK_16X64),
+                (0, 4, BLOCK_16X64),
+                (0, 8, BLOCK_16X64)
+            ]
+        );
+    }
+}
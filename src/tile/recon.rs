@@ -0,0 +1,42 @@
+use crate::{constants::WHT_WHT, tables::TX_WIDTH, transform::inverse_transform_2d, util::clip1};
+
+use super::{tx::TransformBlock, TileDecoder};
+
+impl TileDecoder<'_> {
+    /// The reconstruction process, dequantizes the coefficients of a
+    /// transform block and adds the inverse transformed residual to the
+    /// prediction in CurrFrame. The transform blocks of lossless segments
+    /// use the inverse WHT whatever their PlaneTxType.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#reconstruction-process
+    pub fn reconstruct(&mut self, block: &TransformBlock) {
+        let Some(coeffs) = &block.coeffs else {
+            return;
+        };
+
+        let plane = block.plane as usize;
+        let tx_type = if self.lossless() {
+            WHT_WHT
+        } else {
+            coeffs.tx_type
+        };
+
+        let bit_depth = self.ctx.bit_depth;
+        let dequant = self.dequantizer(plane).dequantize(block.tx_size, coeffs);
+        let residual = inverse_transform_2d(&dequant, block.tx_size, tx_type, bit_depth);
+
+        let Some(picture) = self.ctx.current_frame.as_mut() else {
+            return;
+        };
+
+        let frame = &mut picture.planes[plane];
+        let w = TX_WIDTH[block.tx_size as usize] as usize;
+        for (i, row) in residual.chunks_exact(w).enumerate() {
+            let y = block.start_y as usize + i;
+            for (j, &r) in row.iter().enumerate() {
+                let x = block.start_x as usize + j;
+                frame.set(x, y, clip1(frame.get(x, y) as i32 + r, bit_depth));
+            }
+        }
+    }
+}
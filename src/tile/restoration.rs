@@ -0,0 +1,231 @@
+use crate::{
+    constants::{
+        MI_SIZE, SGRPROJ_PARAMS_BITS, SGRPROJ_PRJ_BITS, SGRPROJ_PRJ_SUBEXP_K, SUPERRES_NUM,
+    },
+    obu::frame_header::RestorationType,
+    tables::{
+        NUM_4X4_BLOCKS_HIGH, NUM_4X4_BLOCKS_WIDE, SGRPROJ_XQD_MAX, SGRPROJ_XQD_MIN, SGR_PARAMS,
+        WIENER_TAPS_K, WIENER_TAPS_MAX, WIENER_TAPS_MIN,
+    },
+    util::{clip3, inverse_recenter, round2},
+};
+
+use super::TileDecoder;
+
+/// The coefficients of a loop restoration unit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LrUnit {
+    /// LrType
+    pub restoration_type: RestorationType,
+    /// LrWiener, the filter taps of each pass.
+    pub wiener: [[i32; 3]; 2],
+    /// LrSgrSet
+    pub sgr_set: u8,
+    /// LrSgrXqd
+    pub sgr_xqd: [i32; 2],
+}
+
+/// The loop restoration units of one plane of the current frame.
+#[derive(Debug, Clone, Default)]
+pub struct LrUnits {
+    pub unit_rows: u32,
+    pub unit_cols: u32,
+    pub units: Vec<LrUnit>,
+}
+
+impl LrUnits {
+    /// The plane is width x height samples, before the subsampling is
+    /// applied.
+    pub fn new(unit_size: u16, width: u16, height: u16, sub_x: u32, sub_y: u32) -> Self {
+        let unit_rows = count_units_in_frame(unit_size as u32, round2(height as i32, sub_y) as u32);
+        let unit_cols = count_units_in_frame(unit_size as u32, round2(width as i32, sub_x) as u32);
+        Self {
+            unit_rows,
+            unit_cols,
+            units: vec![LrUnit::default(); (unit_rows * unit_cols) as usize],
+        }
+    }
+}
+
+/// count_units_in_frame( unitSize, frameSize )
+fn count_units_in_frame(unit_size: u32, frame_size: u32) -> u32 {
+    ((frame_size + (unit_size >> 1)) / unit_size).max(1)
+}
+
+impl TileDecoder<'_> {
+    /// read_lr( r, c, bSize ), reads the coefficients of the loop restoration
+    /// units whose top left corner is inside of the superblock.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#loop-restoration-unit-syntax
+    pub fn read_lr(&mut self, r: u32, c: u32, bsize: u8) {
+        if self.frame_header.allow_intrabc {
+            return;
+        }
+
+        let w = NUM_4X4_BLOCKS_WIDE[bsize as usize] as u32;
+        let h = NUM_4X4_BLOCKS_HIGH[bsize as usize] as u32;
+        for plane in 0..self.ctx.num_planes as usize {
+            let unit_size = self.frame_header.lr_params.loop_restoration_size[plane] as u32;
+            if self.frame_header.lr_params.frame_restoration_type[plane] == RestorationType::None
+                || unit_size == 0
+            {
+                continue;
+            }
+
+            let color_config = &self.sequence_header.color_config;
+            let (sub_x, sub_y) = if plane == 0 {
+                (0, 0)
+            } else {
+                (
+                    color_config.subsampling_x as u32,
+                    color_config.subsampling_y as u32,
+                )
+            };
+
+            let lr_units = &self.ctx.lr_units[plane];
+            let (unit_rows, unit_cols) = (lr_units.unit_rows, lr_units.unit_cols);
+            let unit_row_start = (r * (MI_SIZE as u32 >> sub_y)).div_ceil(unit_size);
+            let unit_row_end =
+                unit_rows.min(((r + h) * (MI_SIZE as u32 >> sub_y)).div_ceil(unit_size));
+
+            let (numerator, denominator) = if self.ctx.superres_denom != SUPERRES_NUM {
+                (
+                    (MI_SIZE as u32 >> sub_x) * self.ctx.superres_denom as u32,
+                    unit_size * SUPERRES_NUM as u32,
+                )
+            } else {
+                (MI_SIZE as u32 >> sub_x, unit_size)
+            };
+
+            let unit_col_start = (c * numerator).div_ceil(denominator);
+            let unit_col_end = unit_cols.min(((c + w) * numerator).div_ceil(denominator));
+
+            for unit_row in unit_row_start..unit_row_end {
+                for unit_col in unit_col_start..unit_col_end {
+                    let unit = self.read_lr_unit(plane);
+                    self.ctx.lr_units[plane].units[(unit_row * unit_cols + unit_col) as usize] =
+                        unit;
+                }
+            }
+        }
+    }
+
+    /// read_lr_unit( plane, unitRow, unitCol )
+    fn read_lr_unit(&mut self, plane: usize) -> LrUnit {
+        let restoration_type = match self.frame_header.lr_params.frame_restoration_type[plane] {
+            RestorationType::Wiener => {
+                // use_wiener	S()
+                if self.sd.read_symbol(&mut self.cdf.use_wiener_cdf) == 1 {
+                    RestorationType::Wiener
+                } else {
+                    RestorationType::None
+                }
+            }
+            RestorationType::Sgrproj => {
+                // use_sgrproj	S()
+                if self.sd.read_symbol(&mut self.cdf.use_sgrproj_cdf) == 1 {
+                    RestorationType::Sgrproj
+                } else {
+                    RestorationType::None
+                }
+            }
+            // restoration_type	S()
+            _ => match self.sd.read_symbol(&mut self.cdf.restoration_type_cdf) {
+                0 => RestorationType::None,
+                1 => RestorationType::Wiener,
+                _ => RestorationType::Sgrproj,
+            },
+        };
+
+        let mut unit = LrUnit {
+            restoration_type,
+            ..Default::default()
+        };
+
+        match restoration_type {
+            RestorationType::Wiener => {
+                for pass in 0..2 {
+                    let first_coeff = if plane > 0 { 1 } else { 0 };
+                    for j in first_coeff..3 {
+                        let v = self.decode_signed_subexp_with_ref_bool(
+                            WIENER_TAPS_MIN[j],
+                            WIENER_TAPS_MAX[j] + 1,
+                            WIENER_TAPS_K[j],
+                            self.ref_lr_wiener[plane][pass][j],
+                        );
+
+                        unit.wiener[pass][j] = v;
+                        self.ref_lr_wiener[plane][pass][j] = v;
+                    }
+                }
+            }
+            RestorationType::Sgrproj => {
+                // lr_sgr_set	L(SGRPROJ_PARAMS_BITS)
+                let set = self.sd.read_literal(SGRPROJ_PARAMS_BITS as usize) as usize;
+                unit.sgr_set = set as u8;
+                for i in 0..2 {
+                    let radius = SGR_PARAMS[set][i * 2];
+                    let min = SGRPROJ_XQD_MIN[i];
+                    let max = SGRPROJ_XQD_MAX[i];
+                    let v = if radius != 0 {
+                        self.decode_signed_subexp_with_ref_bool(
+                            min,
+                            max + 1,
+                            SGRPROJ_PRJ_SUBEXP_K as u32,
+                            self.ref_sgr_xqd[plane][i],
+                        )
+                    } else if i == 1 {
+                        clip3(
+                            min,
+                            max,
+                            (1 << SGRPROJ_PRJ_BITS) - self.ref_sgr_xqd[plane][0],
+                        )
+                    } else {
+                        0
+                    };
+
+                    unit.sgr_xqd[i] = v;
+                    self.ref_sgr_xqd[plane][i] = v;
+                }
+            }
+            _ => (),
+        }
+
+        unit
+    }
+
+    fn decode_signed_subexp_with_ref_bool(&mut self, low: i32, high: i32, k: u32, r: i32) -> i32 {
+        self.decode_unsigned_subexp_with_ref_bool(high - low, k, r - low) + low
+    }
+
+    fn decode_unsigned_subexp_with_ref_bool(&mut self, mx: i32, k: u32, r: i32) -> i32 {
+        let v = self.decode_subexp_bool(mx, k);
+        if (r << 1) <= mx {
+            inverse_recenter(r, v)
+        } else {
+            mx - 1 - inverse_recenter(mx - 1 - r, v)
+        }
+    }
+
+    fn decode_subexp_bool(&mut self, num_syms: i32, k: u32) -> i32 {
+        let mut i = 0;
+        let mut mk = 0;
+        loop {
+            let b2 = if i > 0 { k + i - 1 } else { k };
+            let a = 1 << b2;
+            if num_syms <= mk + 3 * a {
+                // subexp_unif_bools	NS(numSyms - mk)
+                return self.sd.read_ns((num_syms - mk) as u32) as i32 + mk;
+            }
+
+            // subexp_more_bools	L(1)
+            if self.sd.read_literal(1) == 1 {
+                i += 1;
+                mk += a;
+            } else {
+                // subexp_bools	L(b2)
+                return self.sd.read_literal(b2 as usize) as i32 + mk;
+            }
+        }
+    }
+}
@@ -0,0 +1,702 @@
+use crate::{
+    constants::{
+        BLOCK_4X4, DCT_DCT, MAX_VARTX_DEPTH, MI_SIZE, TX_16X16, TX_16X32, TX_32X16, TX_32X32,
+        TX_4X4, TX_SET_DCTONLY, TX_SET_INTER_1, TX_SET_INTER_2, TX_SET_INTER_3, TX_SET_INTRA_1,
+        TX_SET_INTRA_2, TX_SIZES, TX_SIZES_ALL,
+    },
+    obu::frame_header::TxMode,
+    tables::{
+        BLOCK_HEIGHT, BLOCK_WIDTH, FILTER_INTRA_MODE_TO_INTRA_DIR, MAX_TX_DEPTHS, MAX_TX_SIZE_RECT,
+        MODE_TO_TXFM, NUM_4X4_BLOCKS_HIGH, NUM_4X4_BLOCKS_WIDE, SPLIT_TX_SIZE, SUBSAMPLED_SIZE,
+        TX_HEIGHT, TX_SIZE_SQR, TX_SIZE_SQR_UP, TX_TYPE_INTER_INV_SET1, TX_TYPE_INTER_INV_SET2,
+        TX_TYPE_INTER_INV_SET3, TX_TYPE_INTRA_INV_SET1, TX_TYPE_INTRA_INV_SET2,
+        TX_TYPE_IN_SET_INTER, TX_TYPE_IN_SET_INTRA, TX_WIDTH,
+    },
+};
+
+use super::{coeffs::Coefficients, TileDecoder};
+
+/// A transform block of a block, in the order the residual( ) process
+/// visits them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformBlock {
+    pub plane: u8,
+    /// startX, the position of the transform block in samples of the plane.
+    pub start_x: u32,
+    /// startY
+    pub start_y: u32,
+    /// x, the position of the transform block relative to the block in
+    /// units of 4 samples of the plane, 0 for the transform blocks of the
+    /// luma transform tree of an inter block.
+    pub x: u32,
+    /// y
+    pub y: u32,
+    pub tx_size: u8,
+    /// The coefficients of the transform block, None when the block is
+    /// skipped or all of its coefficients are zero.
+    pub coeffs: Option<Coefficients>,
+}
+
+/// find_tx_size( w, h )
+fn find_tx_size(w: u8, h: u8) -> u8 {
+    (0..TX_SIZES_ALL)
+        .find(|&tx_size| TX_WIDTH[tx_size as usize] == w && TX_HEIGHT[tx_size as usize] == h)
+        .unwrap_or(TX_4X4)
+}
+
+impl TileDecoder<'_> {
+    /// Lossless, the segment of the block is coded losslessly.
+    #[inline]
+    pub fn lossless(&self) -> bool {
+        self.frame_header.lossless_array[self.mi.segment_id as usize]
+    }
+
+    /// read_block_tx_size( ), inter blocks can split the largest transform
+    /// into a tree of smaller transforms when the transform size is
+    /// selected per block.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#block-tx-size-syntax
+    pub fn read_block_tx_size(&mut self) {
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize] as u32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize] as u32;
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        if self.frame_header.tx_mode == TxMode::Select
+            && self.mi.mi_size > BLOCK_4X4
+            && self.mi.is_inter
+            && !self.mi.skip
+            && !self.lossless()
+        {
+            let max_tx_size = MAX_TX_SIZE_RECT[self.mi.mi_size as usize];
+            let tx_w4 = TX_WIDTH[max_tx_size as usize] as u32 / MI_SIZE as u32;
+            let tx_h4 = TX_HEIGHT[max_tx_size as usize] as u32 / MI_SIZE as u32;
+            for row in (mi_row..mi_row + bh4).step_by(tx_h4 as usize) {
+                for col in (mi_col..mi_col + bw4).step_by(tx_w4 as usize) {
+                    self.read_var_tx_size(row, col, max_tx_size, 0);
+                }
+            }
+        } else {
+            self.read_tx_size(!self.mi.skip || !self.mi.is_inter);
+            self.ctx
+                .mode_info
+                .set_inter_tx_size(mi_row, mi_col, bw4, bh4, self.mi.tx_size);
+        }
+    }
+
+    /// read_var_tx_size( row, col, txSz, depth )
+    fn read_var_tx_size(&mut self, row: u32, col: u32, tx_size: u8, depth: u8) {
+        if row >= self.ctx.mi_rows || col >= self.ctx.mi_cols {
+            return;
+        }
+
+        let txfm_split = if tx_size == TX_4X4 || depth == MAX_VARTX_DEPTH {
+            false
+        } else {
+            // txfm_split	S()
+            let ctx = self.txfm_split_ctx(row, col, tx_size);
+            self.sd.read_symbol(&mut self.cdf.txfm_split_cdf[ctx]) == 1
+        };
+
+        let w4 = TX_WIDTH[tx_size as usize] as u32 / MI_SIZE as u32;
+        let h4 = TX_HEIGHT[tx_size as usize] as u32 / MI_SIZE as u32;
+        if txfm_split {
+            let sub_tx_size = SPLIT_TX_SIZE[tx_size as usize];
+            let step_w = TX_WIDTH[sub_tx_size as usize] as u32 / MI_SIZE as u32;
+            let step_h = TX_HEIGHT[sub_tx_size as usize] as u32 / MI_SIZE as u32;
+            for i in (0..h4).step_by(step_h as usize) {
+                for j in (0..w4).step_by(step_w as usize) {
+                    self.read_var_tx_size(row + i, col + j, sub_tx_size, depth + 1);
+                }
+            }
+        } else {
+            self.ctx
+                .mode_info
+                .set_inter_tx_size(row, col, w4, h4, tx_size);
+            self.mi.tx_size = tx_size;
+        }
+    }
+
+    /// read_tx_size( allowSelect )
+    fn read_tx_size(&mut self, allow_select: bool) {
+        if self.lossless() {
+            self.mi.tx_size = TX_4X4;
+            return;
+        }
+
+        let max_rect_tx_size = MAX_TX_SIZE_RECT[self.mi.mi_size as usize];
+        self.mi.tx_size = max_rect_tx_size;
+        if self.mi.mi_size > BLOCK_4X4
+            && allow_select
+            && self.frame_header.tx_mode == TxMode::Select
+        {
+            // tx_depth	S()
+            let ctx = self.tx_depth_ctx(max_rect_tx_size);
+            let cdf = match MAX_TX_DEPTHS[self.mi.mi_size as usize] {
+                4 => &mut self.cdf.tx_64x64_cdf[ctx][..],
+                3 => &mut self.cdf.tx_32x32_cdf[ctx][..],
+                2 => &mut self.cdf.tx_16x16_cdf[ctx][..],
+                _ => &mut self.cdf.tx_8x8_cdf[ctx][..],
+            };
+
+            let tx_depth = self.sd.read_symbol(cdf);
+            for _ in 0..tx_depth {
+                self.mi.tx_size = SPLIT_TX_SIZE[self.mi.tx_size as usize];
+            }
+        }
+    }
+
+    /// The ctx of tx_depth, from the transform sizes of the above and left
+    /// blocks.
+    fn tx_depth_ctx(&self, max_rect_tx_size: u8) -> usize {
+        let (mi_row, mi_col) = (self.block.mi_row, self.block.mi_col);
+        let mode_info = &self.ctx.mode_info;
+        let above_w = if self.block.avail_u {
+            let above = mode_info.get(mi_row - 1, mi_col);
+            if above.is_inter {
+                BLOCK_WIDTH[above.mi_size as usize] as u32
+            } else {
+                self.get_above_tx_width(mi_row, mi_col)
+            }
+        } else {
+            0
+        };
+
+        let left_h = if self.block.avail_l {
+            let left = mode_info.get(mi_row, mi_col - 1);
+            if left.is_inter {
+                BLOCK_HEIGHT[left.mi_size as usize] as u32
+            } else {
+                self.get_left_tx_height(mi_row, mi_col)
+            }
+        } else {
+            0
+        };
+
+        (above_w >= TX_WIDTH[max_rect_tx_size as usize] as u32) as usize
+            + (left_h >= TX_HEIGHT[max_rect_tx_size as usize] as u32) as usize
+    }
+
+    /// The ctx of txfm_split.
+    fn txfm_split_ctx(&self, row: u32, col: u32, tx_size: u8) -> usize {
+        let above = (self.get_above_tx_width(row, col) < TX_WIDTH[tx_size as usize] as u32) as u8;
+        let left = (self.get_left_tx_height(row, col) < TX_HEIGHT[tx_size as usize] as u32) as u8;
+        let size = BLOCK_WIDTH[self.mi.mi_size as usize]
+            .max(BLOCK_HEIGHT[self.mi.mi_size as usize])
+            .min(64);
+        let max_tx_size = find_tx_size(size, size);
+        let tx_size_sqr_up = TX_SIZE_SQR_UP[tx_size as usize];
+        ((tx_size_sqr_up != max_tx_size) as u8 * 3
+            + (TX_SIZES - 1 - max_tx_size) * 6
+            + above
+            + left) as usize
+    }
+
+    /// get_above_tx_width( row, col )
+    fn get_above_tx_width(&self, row: u32, col: u32) -> u32 {
+        let mode_info = &self.ctx.mode_info;
+        if row == self.block.mi_row {
+            if !self.block.avail_u {
+                return 64;
+            }
+
+            let above = mode_info.get(row - 1, col);
+            if above.skip && above.is_inter {
+                return BLOCK_WIDTH[above.mi_size as usize] as u32;
+            }
+        }
+
+        TX_WIDTH[mode_info.inter_tx_size(row - 1, col) as usize] as u32
+    }
+
+    /// get_left_tx_height( row, col )
+    fn get_left_tx_height(&self, row: u32, col: u32) -> u32 {
+        let mode_info = &self.ctx.mode_info;
+        if col == self.block.mi_col {
+            if !self.block.avail_l {
+                return 64;
+            }
+
+            let left = mode_info.get(row, col - 1);
+            if left.skip && left.is_inter {
+                return BLOCK_HEIGHT[left.mi_size as usize] as u32;
+            }
+        }
+
+        TX_HEIGHT[mode_info.inter_tx_size(row, col - 1) as usize] as u32
+    }
+
+    /// get_tx_size( plane, txSz ), the transform size of the chroma planes
+    /// is the largest that fits the subsampled block, limited to 32x32.
+    pub fn get_tx_size(&self, plane: usize, tx_size: u8) -> u8 {
+        if plane == 0 {
+            return tx_size;
+        }
+
+        let uv_tx = MAX_TX_SIZE_RECT[self.plane_residual_size(plane) as usize];
+        if TX_WIDTH[uv_tx as usize] == 64 || TX_HEIGHT[uv_tx as usize] == 64 {
+            if TX_WIDTH[uv_tx as usize] == 16 {
+                return TX_16X32;
+            }
+
+            if TX_HEIGHT[uv_tx as usize] == 16 {
+                return TX_32X16;
+            }
+
+            return TX_32X32;
+        }
+
+        uv_tx
+    }
+
+    /// get_plane_residual_size( MiSize, plane )
+    pub fn plane_residual_size(&self, plane: usize) -> u8 {
+        let (sub_x, sub_y) = self.plane_subsampling(plane);
+        SUBSAMPLED_SIZE[self.mi.mi_size as usize][sub_x as usize][sub_y as usize]
+    }
+
+    /// subX and subY of the plane.
+    #[inline]
+    pub fn plane_subsampling(&self, plane: usize) -> (u32, u32) {
+        if plane == 0 {
+            (0, 0)
+        } else {
+            let color_config = &self.sequence_header.color_config;
+            (
+                color_config.subsampling_x as u32,
+                color_config.subsampling_y as u32,
+            )
+        }
+    }
+
+    /// get_tx_set( txSz )
+    pub fn get_tx_set(&self, tx_size: u8) -> u8 {
+        let tx_size_sqr = TX_SIZE_SQR[tx_size as usize];
+        let tx_size_sqr_up = TX_SIZE_SQR_UP[tx_size as usize];
+        let reduced_tx_set = self.frame_header.reduced_tx_set;
+        if tx_size_sqr_up > TX_32X32 {
+            TX_SET_DCTONLY
+        } else if self.mi.is_inter {
+            if reduced_tx_set || tx_size_sqr_up == TX_32X32 {
+                TX_SET_INTER_3
+            } else if tx_size_sqr == TX_16X16 {
+                TX_SET_INTER_2
+            } else {
+                TX_SET_INTER_1
+            }
+        } else if tx_size_sqr_up == TX_32X32 {
+            TX_SET_DCTONLY
+        } else if reduced_tx_set || tx_size_sqr == TX_16X16 {
+            TX_SET_INTRA_2
+        } else {
+            TX_SET_INTRA_1
+        }
+    }
+
+    /// transform_type( x4, y4, txSz ), reads the transform type of a luma
+    /// transform block with coefficients.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#transform-type-syntax
+    pub fn transform_type(&mut self, x4: u32, y4: u32, tx_size: u8) {
+        let set = self.get_tx_set(tx_size);
+        let qidx = if self.frame_header.segmentation_params.segmentation_enabled {
+            self.get_qidx(true, self.mi.segment_id)
+        } else {
+            self.frame_header.quantization_params.base_q_idx as i32
+        };
+
+        let tx_size_sqr = TX_SIZE_SQR[tx_size as usize] as usize;
+        let tx_type = if set > 0 && qidx > 0 {
+            if self.mi.is_inter {
+                // inter_tx_type	S()
+                match set {
+                    TX_SET_INTER_1 => {
+                        let cdf = &mut self.cdf.inter_tx_type_set1_cdf[tx_size_sqr];
+                        TX_TYPE_INTER_INV_SET1[self.sd.read_symbol(cdf)]
+                    }
+                    TX_SET_INTER_2 => {
+                        let cdf = &mut self.cdf.inter_tx_type_set2_cdf;
+                        TX_TYPE_INTER_INV_SET2[self.sd.read_symbol(cdf)]
+                    }
+                    _ => {
+                        let cdf = &mut self.cdf.inter_tx_type_set3_cdf[tx_size_sqr];
+                        TX_TYPE_INTER_INV_SET3[self.sd.read_symbol(cdf)]
+                    }
+                }
+            } else {
+                let intra_dir = if self.mi.use_filter_intra {
+                    FILTER_INTRA_MODE_TO_INTRA_DIR[self.mi.filter_intra_mode as usize]
+                } else {
+                    self.mi.y_mode
+                } as usize;
+
+                // intra_tx_type	S()
+                if set == TX_SET_INTRA_1 {
+                    let cdf = &mut self.cdf.intra_tx_type_set1_cdf[tx_size_sqr][intra_dir];
+                    TX_TYPE_INTRA_INV_SET1[self.sd.read_symbol(cdf)]
+                } else {
+                    let cdf = &mut self.cdf.intra_tx_type_set2_cdf[tx_size_sqr][intra_dir];
+                    TX_TYPE_INTRA_INV_SET2[self.sd.read_symbol(cdf)]
+                }
+            }
+        } else {
+            DCT_DCT
+        };
+
+        let w4 = TX_WIDTH[tx_size as usize] as u32 >> 2;
+        let h4 = TX_HEIGHT[tx_size as usize] as u32 >> 2;
+        self.ctx.mode_info.set_tx_type(y4, x4, w4, h4, tx_type);
+    }
+
+    /// compute_tx_type( plane, txSz, blockX, blockY ), the transform type of
+    /// a transform block, chroma transform blocks derive it from the luma
+    /// transform type or the chroma intra mode.
+    pub fn compute_tx_type(&self, plane: usize, tx_size: u8, block_x: u32, block_y: u32) -> u8 {
+        if self.lossless() || TX_SIZE_SQR_UP[tx_size as usize] > TX_32X32 {
+            return DCT_DCT;
+        }
+
+        let tx_set = self.get_tx_set(tx_size);
+        if plane == 0 {
+            return self.ctx.mode_info.tx_type(block_y, block_x);
+        }
+
+        let tx_type = if self.mi.is_inter {
+            let (sub_x, sub_y) = self.plane_subsampling(plane);
+            let x4 = self.block.mi_col.max(block_x << sub_x);
+            let y4 = self.block.mi_row.max(block_y << sub_y);
+            self.ctx.mode_info.tx_type(y4, x4)
+        } else {
+            MODE_TO_TXFM[self.mi.uv_mode as usize]
+        };
+
+        if self.is_tx_type_in_set(tx_set, tx_type) {
+            tx_type
+        } else {
+            DCT_DCT
+        }
+    }
+
+    /// is_tx_type_in_set( txSet, txType )
+    fn is_tx_type_in_set(&self, tx_set: u8, tx_type: u8) -> bool {
+        if self.mi.is_inter {
+            TX_TYPE_IN_SET_INTER[tx_set as usize][tx_type as usize]
+        } else {
+            TX_TYPE_IN_SET_INTRA[tx_set as usize][tx_type as usize]
+        }
+    }
+
+    /// The transform blocks of the current block in the order of the
+    /// residual( ) process. Blocks wider or taller than 64 luma samples are
+    /// visited in 64x64 chunks, and transform blocks starting outside of the
+    /// frame are not coded.
+    ///
+    /// see: https://aomediacodec.github.io/av1-spec/#residual-syntax
+    pub fn transform_blocks(&self) -> Vec<TransformBlock> {
+        let mut blocks = Vec::new();
+        let bw4 = NUM_4X4_BLOCKS_WIDE[self.mi.mi_size as usize] as u32;
+        let bh4 = NUM_4X4_BLOCKS_HIGH[self.mi.mi_size as usize] as u32;
+        let width_chunks = (bw4 >> 4).max(1);
+        let height_chunks = (bh4 >> 4).max(1);
+        let num_planes = if self.block.has_chroma { 3 } else { 1 };
+        let lossless = self.lossless();
+
+        for chunk_y in 0..height_chunks {
+            for chunk_x in 0..width_chunks {
+                let mi_row_chunk = self.block.mi_row + (chunk_y << 4);
+                let mi_col_chunk = self.block.mi_col + (chunk_x << 4);
+                for plane in 0..num_planes {
+                    let tx_size = if lossless {
+                        TX_4X4
+                    } else {
+                        self.get_tx_size(plane, self.mi.tx_size)
+                    };
+
+                    let step_x = TX_WIDTH[tx_size as usize] as u32 >> 2;
+                    let step_y = TX_HEIGHT[tx_size as usize] as u32 >> 2;
+                    let plane_size = self.plane_residual_size(plane) as usize;
+                    let (sub_x, sub_y) = self.plane_subsampling(plane);
+                    let num4x4_w = (NUM_4X4_BLOCKS_WIDE[plane_size] as u32).min(16 >> sub_x);
+                    let num4x4_h = (NUM_4X4_BLOCKS_HIGH[plane_size] as u32).min(16 >> sub_y);
+                    if self.mi.is_inter && !lossless && plane == 0 {
+                        let base_x = mi_col_chunk * MI_SIZE as u32;
+                        let base_y = mi_row_chunk * MI_SIZE as u32;
+                        self.transform_tree(
+                            &mut blocks,
+                            base_x,
+                            base_y,
+                            num4x4_w * 4,
+                            num4x4_h * 4,
+                        );
+                        continue;
+                    }
+
+                    let base_x = (self.block.mi_col >> sub_x) * MI_SIZE as u32;
+                    let base_y = (self.block.mi_row >> sub_y) * MI_SIZE as u32;
+                    let max_x = (self.ctx.mi_cols * MI_SIZE as u32) >> sub_x;
+                    let max_y = (self.ctx.mi_rows * MI_SIZE as u32) >> sub_y;
+                    for y in (0..num4x4_h).step_by(step_y as usize) {
+                        for x in (0..num4x4_w).step_by(step_x as usize) {
+                            let x = x + ((chunk_x << 4) >> sub_x);
+                            let y = y + ((chunk_y << 4) >> sub_y);
+                            let start_x = base_x + 4 * x;
+                            let start_y = base_y + 4 * y;
+                            if start_x < max_x && start_y < max_y {
+                                blocks.push(TransformBlock {
+                                    plane: plane as u8,
+                                    start_x,
+                                    start_y,
+                                    x,
+                                    y,
+                                    tx_size,
+                                    coeffs: None,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        blocks
+    }
+
+    /// transform_tree( startX, startY, w, h ), the luma transform blocks of
+    /// an inter block follow the transform sizes read by read_var_tx_size.
+    fn transform_tree(
+        &self,
+        blocks: &mut Vec<TransformBlock>,
+        start_x: u32,
+        start_y: u32,
+        w: u32,
+        h: u32,
+    ) {
+        let max_x = self.ctx.mi_cols * MI_SIZE as u32;
+        let max_y = self.ctx.mi_rows * MI_SIZE as u32;
+        if start_x >= max_x || start_y >= max_y {
+            return;
+        }
+
+        let row = start_y / MI_SIZE as u32;
+        let col = start_x / MI_SIZE as u32;
+        let tx_size = self.ctx.mode_info.inter_tx_size(row, col);
+        let tx_w = TX_WIDTH[tx_size as usize] as u32;
+        let tx_h = TX_HEIGHT[tx_size as usize] as u32;
+        if w <= tx_w && h <= tx_h {
+            blocks.push(TransformBlock {
+                plane: 0,
+                start_x,
+                start_y,
+                x: 0,
+                y: 0,
+                tx_size,
+                coeffs: None,
+            });
+        } else if w > h {
+            self.transform_tree(blocks, start_x, start_y, w / 2, h);
+            self.transform_tree(blocks, start_x + w / 2, start_y, w / 2, h);
+        } else if w < h {
+            self.transform_tree(blocks, start_x, start_y, w, h / 2);
+            self.transform_tree(blocks, start_x, start_y + h / 2, w, h / 2);
+        } else {
+            self.transform_tree(blocks, start_x, start_y, w / 2, h / 2);
+            self.transform_tree(blocks, start_x + w / 2, start_y, w / 2, h / 2);
+            self.transform_tree(blocks, start_x, start_y + h / 2, w / 2, h / 2);
+            self.transform_tree(blocks, start_x + w / 2, start_y + h / 2, w / 2, h / 2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{
+            ADST_ADST, ADST_DCT, BLOCK_32X32, BLOCK_64X64, BLOCK_8X8, DC_PRED, IDTX, TX_64X64,
+            TX_8X8, V_PRED,
+        },
+        tile::test_tiles::{force_symbol, key_frame_context, set_block, tile_decoder},
+    };
+
+    /// A lossy frame with the transform size selected per block.
+    fn select_tx_size(d: &mut TileDecoder) {
+        d.frame_header.lossless_array = [false; 8];
+        d.frame_header.quantization_params.base_q_idx = 100;
+        d.frame_header.tx_mode = TxMode::Select;
+    }
+
+    #[test]
+    fn var_tx_trees_stop_at_the_max_depth() {
+        let mut ctx = key_frame_context(64, 64, false);
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+        select_tx_size(&mut d);
+        for cdf in &mut d.cdf.txfm_split_cdf {
+            force_symbol(cdf, 1);
+        }
+
+        // 64x64 is split into 32x32 and then into 16x16 without reading
+        // txfm_split at MAX_VARTX_DEPTH.
+        set_block(&mut d, 0, 0, BLOCK_64X64);
+        d.mi.is_inter = true;
+        d.read_block_tx_size();
+        assert_eq!(d.mi.tx_size, TX_16X16);
+        let reads: u16 = d.cdf.txfm_split_cdf.iter().map(|cdf| cdf[2]).sum();
+        assert_eq!(reads, 1 + 4);
+        for (row, col) in [(0, 0), (3, 4), (8, 12), (15, 15)] {
+            assert_eq!(d.ctx.mode_info.inter_tx_size(row, col), TX_16X16);
+        }
+
+        // 4x4 transforms are not split any further.
+        set_block(&mut d, 0, 0, BLOCK_8X8);
+        d.mi.is_inter = true;
+        d.read_block_tx_size();
+        assert_eq!(d.mi.tx_size, TX_4X4);
+        let reads: u16 = d.cdf.txfm_split_cdf.iter().map(|cdf| cdf[2]).sum();
+        assert_eq!(reads, 1 + 4 + 1);
+
+        // a skipped inter block keeps the largest transform size.
+        set_block(&mut d, 0, 0, BLOCK_64X64);
+        d.mi.is_inter = true;
+        d.mi.skip = true;
+        d.read_block_tx_size();
+        assert_eq!(d.mi.tx_size, TX_64X64);
+        assert_eq!(d.ctx.mode_info.inter_tx_size(15, 15), TX_64X64);
+    }
+
+    #[test]
+    fn tx_size_contexts() {
+        let mut ctx = key_frame_context(64, 64, false);
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+        select_tx_size(&mut d);
+
+        // an intra block above with 32x32 transforms, an inter 8x8 block
+        // to the left.
+        d.ctx.mode_info.set_inter_tx_size(7, 8, 8, 1, TX_32X32);
+        let left = d.ctx.mode_info.get_mut(8, 7);
+        left.is_inter = true;
+        left.mi_size = BLOCK_8X8;
+        set_block(&mut d, 8, 8, BLOCK_32X32);
+        assert_eq!(d.tx_depth_ctx(TX_32X32), 1);
+        assert_eq!(d.tx_depth_ctx(TX_8X8), 2);
+
+        // tx_depth 2 of a 64x64 block is read with the 64x64 cdf.
+        set_block(&mut d, 0, 0, BLOCK_64X64);
+        force_symbol(&mut d.cdf.tx_64x64_cdf[0], 2);
+        d.read_block_tx_size();
+        assert_eq!(d.mi.tx_size, TX_16X16);
+        assert_eq!(d.cdf.tx_64x64_cdf[0][3], 1);
+
+        // without neighbours the transforms are 64 wide and high, the ctx
+        // of txfm_split counts the depth from the largest transform of the
+        // block size.
+        assert_eq!(d.txfm_split_ctx(0, 0, TX_64X64), 0);
+        assert_eq!(d.txfm_split_ctx(0, 0, TX_32X32), 3);
+        set_block(&mut d, 0, 0, BLOCK_8X8);
+        assert_eq!(d.txfm_split_ctx(0, 0, TX_8X8), 18);
+
+        // the split 16x16 transforms of the block above and to the left
+        // are narrower than 32x32.
+        d.ctx.mode_info.set_inter_tx_size(0, 0, 16, 16, TX_16X16);
+        set_block(&mut d, 4, 4, BLOCK_32X32);
+        assert_eq!(d.txfm_split_ctx(4, 4, TX_32X32), 6 + 2);
+    }
+
+    #[test]
+    fn tx_sets_of_the_transform_sizes() {
+        let mut ctx = key_frame_context(64, 64, false);
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+
+        let sets =
+            |d: &TileDecoder| [TX_4X4, TX_16X16, TX_32X32, TX_64X64].map(|t| d.get_tx_set(t));
+        assert_eq!(
+            sets(&d),
+            [
+                TX_SET_INTRA_1,
+                TX_SET_INTRA_2,
+                TX_SET_DCTONLY,
+                TX_SET_DCTONLY
+            ]
+        );
+
+        d.mi.is_inter = true;
+        assert_eq!(
+            sets(&d),
+            [
+                TX_SET_INTER_1,
+                TX_SET_INTER_2,
+                TX_SET_INTER_3,
+                TX_SET_DCTONLY
+            ]
+        );
+
+        // the reduced sets.
+        d.frame_header.reduced_tx_set = true;
+        assert_eq!(
+            sets(&d),
+            [
+                TX_SET_INTER_3,
+                TX_SET_INTER_3,
+                TX_SET_INTER_3,
+                TX_SET_DCTONLY
+            ]
+        );
+
+        d.mi.is_inter = false;
+        assert_eq!(
+            sets(&d),
+            [
+                TX_SET_INTRA_2,
+                TX_SET_INTRA_2,
+                TX_SET_DCTONLY,
+                TX_SET_DCTONLY
+            ]
+        );
+    }
+
+    #[test]
+    fn tx_types_are_read_from_the_tx_set() {
+        let mut ctx = key_frame_context(64, 64, false);
+        let data = [0x80; 1024];
+        let mut d = tile_decoder(&mut ctx, &data);
+        set_block(&mut d, 0, 0, BLOCK_8X8);
+
+        // the transform type of a lossless frame is not read.
+        force_symbol(&mut d.cdf.intra_tx_type_set1_cdf[0][DC_PRED as usize], 0);
+        d.transform_type(0, 0, TX_4X4);
+        assert_eq!(d.ctx.mode_info.tx_type(0, 0), DCT_DCT);
+        assert_eq!(d.cdf.intra_tx_type_set1_cdf[0][DC_PRED as usize][7], 0);
+
+        select_tx_size(&mut d);
+        d.transform_type(0, 0, TX_4X4);
+        assert_eq!(d.ctx.mode_info.tx_type(0, 0), IDTX);
+
+        // the reduced intra set is read with the cdf of the mode.
+        d.frame_header.reduced_tx_set = true;
+        d.mi.y_mode = V_PRED;
+        force_symbol(&mut d.cdf.intra_tx_type_set2_cdf[1][V_PRED as usize], 2);
+        d.transform_type(0, 0, TX_8X8);
+        assert_eq!(d.ctx.mode_info.tx_type(1, 1), ADST_ADST);
+
+        // the reduced inter set has IDTX and DCT_DCT only.
+        d.mi.is_inter = true;
+        force_symbol(&mut d.cdf.inter_tx_type_set3_cdf[1], 1);
+        d.transform_type(0, 0, TX_8X8);
+        assert_eq!(d.ctx.mode_info.tx_type(0, 0), DCT_DCT);
+
+        // chroma derives the type from the chroma mode of an intra block,
+        // DCT_DCT is the only type of the 32x32 intra set.
+        d.mi.is_inter = false;
+        d.mi.uv_mode = V_PRED;
+        assert_eq!(d.compute_tx_type(1, TX_4X4, 0, 0), ADST_DCT);
+        assert_eq!(d.compute_tx_type(1, TX_32X32, 0, 0), DCT_DCT);
+
+        // and from the luma type of an inter block, replaced by DCT_DCT
+        // when it is not in the set.
+        d.mi.is_inter = true;
+        d.ctx.mode_info.set_tx_type(0, 0, 2, 2, ADST_ADST);
+        assert_eq!(d.compute_tx_type(1, TX_4X4, 0, 0), DCT_DCT);
+        d.frame_header.reduced_tx_set = false;
+        assert_eq!(d.compute_tx_type(1, TX_4X4, 0, 0), ADST_ADST);
+    }
+}
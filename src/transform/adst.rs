@@ -0,0 +1,268 @@
+//! The inverse ADST processes.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#inverse-adst-process
+
+use super::{butterfly as b, hadamard as h};
+
+/// SINPI_1_9
+pub const SINPI_1_9: i32 = 1321;
+/// SINPI_2_9
+pub const SINPI_2_9: i32 = 2482;
+/// SINPI_3_9
+pub const SINPI_3_9: i32 = 3344;
+/// SINPI_4_9
+pub const SINPI_4_9: i32 = 3803;
+
+/// The inverse ADST input array permutation process, interleaves the first
+/// 2^n elements of T with the reversed elements.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-adst-input-array-permutation-process
+pub fn inverse_adst_input_array_permutation(t: &mut [i32], n: u32) {
+    let n0 = 1 << n;
+    let mut copy = [0; 16];
+    copy[..n0].copy_from_slice(&t[..n0]);
+    for (i, v) in t[..n0].iter_mut().enumerate() {
+        let idx = if i & 1 == 1 { i - 1 } else { n0 - i - 1 };
+        *v = copy[idx];
+    }
+}
+
+/// The inverse ADST output array permutation process, reorders the first 2^n
+/// elements of T in gray code order and negates the odd elements.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-adst-output-array-permutation-process
+pub fn inverse_adst_output_array_permutation(t: &mut [i32], n: u32) {
+    let n0 = 1 << n;
+    let mut copy = [0; 16];
+    copy[..n0].copy_from_slice(&t[..n0]);
+    for (i, v) in t[..n0].iter_mut().enumerate() {
+        let a = (i >> 3) & 1;
+        let b = ((i >> 2) & 1) ^ ((i >> 3) & 1);
+        let c = ((i >> 1) & 1) ^ ((i >> 2) & 1);
+        let d = (i & 1) ^ ((i >> 1) & 1);
+        let idx = ((d << 3) | (c << 2) | (b << 1) | a) >> (4 - n);
+        *v = if i & 1 == 1 { -copy[idx] } else { copy[idx] };
+    }
+}
+
+/// The inverse ADST4 process.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-adst4-process
+pub fn inverse_adst4(t: &mut [i32]) {
+    // the intermediate values need up to r + 12 bits, more than an i32 holds
+    // for the row transforms of 12 bit streams.
+    let [x0, x1, x2, x3] = [t[0] as i64, t[1] as i64, t[2] as i64, t[3] as i64];
+    let mut s0 = SINPI_1_9 as i64 * x0;
+    let mut s1 = SINPI_2_9 as i64 * x0;
+    let mut s2 = SINPI_3_9 as i64 * x1;
+    let mut s3 = SINPI_4_9 as i64 * x2;
+    let s4 = SINPI_1_9 as i64 * x2;
+    let s5 = SINPI_2_9 as i64 * x3;
+    let s6 = SINPI_4_9 as i64 * x3;
+
+    let a7 = x0 - x2;
+    let b7 = a7 + x3;
+
+    s0 += s3;
+    s1 -= s4;
+    s3 = s2;
+    s2 = SINPI_3_9 as i64 * b7;
+
+    s0 += s5;
+    s1 -= s6;
+
+    let x0 = s0 + s3;
+    let x1 = s1 + s3;
+    let x2 = s2;
+    let x3 = s0 + s1 - s3;
+
+    for (v, x) in t.iter_mut().zip([x0, x1, x2, x3]) {
+        *v = ((x + (1 << 11)) >> 12) as i32;
+    }
+}
+
+/// The inverse ADST8 process.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-adst8-process
+pub fn inverse_adst8(t: &mut [i32], r: u32) {
+    inverse_adst_input_array_permutation(t, 3);
+
+    for i in 0..4 {
+        b(t, 2 * i, 2 * i + 1, 60 - 16 * i as i32, true);
+    }
+
+    for i in 0..4 {
+        h(t, i, 4 + i, false, r);
+    }
+
+    for i in 0..2 {
+        b(t, 4 + 3 * i, 5 + i, 48 - 32 * i as i32, true);
+    }
+
+    for i in 0..2 {
+        for j in 0..2 {
+            h(t, 4 * j + i, 2 + 4 * j + i, false, r);
+        }
+    }
+
+    for i in 0..2 {
+        b(t, 2 + 4 * i, 3 + 4 * i, 32, true);
+    }
+
+    inverse_adst_output_array_permutation(t, 3);
+}
+
+/// The inverse ADST16 process.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-adst16-process
+pub fn inverse_adst16(t: &mut [i32], r: u32) {
+    inverse_adst_input_array_permutation(t, 4);
+
+    for i in 0..8 {
+        b(t, 2 * i, 2 * i + 1, 62 - 8 * i as i32, true);
+    }
+
+    for i in 0..8 {
+        h(t, i, 8 + i, false, r);
+    }
+
+    for i in 0..2 {
+        b(t, 8 + 2 * i, 9 + 2 * i, 56 - 32 * i as i32, true);
+        b(t, 13 + 2 * i, 12 + 2 * i, 8 + 32 * i as i32, true);
+    }
+
+    for i in 0..4 {
+        for j in 0..2 {
+            h(t, 8 * j + i, 4 + 8 * j + i, false, r);
+        }
+    }
+
+    for i in 0..2 {
+        for j in 0..2 {
+            b(
+                t,
+                4 + 8 * j + 3 * i,
+                5 + 8 * j + i,
+                48 - 32 * i as i32,
+                true,
+            );
+        }
+    }
+
+    for i in 0..2 {
+        for j in 0..4 {
+            h(t, 4 * j + i, 2 + 4 * j + i, false, r);
+        }
+    }
+
+    for i in 0..4 {
+        b(t, 2 + 4 * i, 3 + 4 * i, 32, true);
+    }
+
+    inverse_adst_output_array_permutation(t, 4);
+}
+
+/// The inverse ADST process, applies the 2^n point inverse ADST in place to
+/// the first 2^n elements of T, `n` is in the range 2..=4.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-adst-process
+pub fn inverse_adst(t: &mut [i32], n: u32, r: u32) {
+    match n {
+        2 => inverse_adst4(t),
+        3 => inverse_adst8(t, r),
+        _ => inverse_adst16(t, r),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{sin128, Xorshift};
+
+    // the intermediate clamping range of the column transforms of 8 bit
+    // streams.
+    const R: u32 = 16;
+
+    fn adst(input: &[i32]) -> Vec<i32> {
+        let mut t = [0; 16];
+        t[..input.len()].copy_from_slice(input);
+        inverse_adst(&mut t, input.len().trailing_zeros(), R);
+        t[..input.len()].to_vec()
+    }
+
+    // 4096 * 2 * sqrt(2) / 3 * sin(m * pi / 9) from the SINPI_k_9 constants.
+    fn sinpi9(m: usize) -> i64 {
+        let m = m % 18;
+        let (m, sign) = if m > 9 { (m - 9, -1) } else { (m, 1) };
+        let m = if m > 4 { 9 - m } else { m };
+        sign * [0, SINPI_1_9, SINPI_2_9, SINPI_3_9, SINPI_4_9][m] as i64
+    }
+
+    // the inverse ADST as a matrix product with the sines of the spec, in a
+    // 12 bit fixed point and rounded once.
+    fn direct_adst(x: &[i32]) -> Vec<i32> {
+        let n = x.len();
+        (0..n)
+            .map(|i| {
+                let sum = x.iter().enumerate().fold(0, |sum, (k, &v)| {
+                    let sin = if n == 4 {
+                        sinpi9((i + 1) * (2 * k + 1))
+                    } else {
+                        sin128(((2 * i + 1) * (2 * k + 1) * 32 / n) as i32) as i64
+                    };
+
+                    sum + v as i64 * sin
+                });
+
+                ((sum + (1 << 11)) >> 12) as i32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn array_permutations() {
+        let mut t: Vec<i32> = (0..8).collect();
+        inverse_adst_input_array_permutation(&mut t, 3);
+        assert_eq!(t, [7, 0, 5, 2, 3, 4, 1, 6]);
+
+        let mut t: Vec<i32> = (0..8).collect();
+        inverse_adst_output_array_permutation(&mut t, 3);
+        assert_eq!(t, [0, -4, 6, -2, 3, -7, 5, -1]);
+
+        let mut t: Vec<i32> = (0..16).collect();
+        inverse_adst_output_array_permutation(&mut t, 4);
+        assert_eq!(
+            t,
+            [0, -8, 12, -4, 6, -14, 10, -2, 3, -11, 15, -7, 5, -13, 9, -1]
+        );
+    }
+
+    #[test]
+    fn adst4_impulses() {
+        assert_eq!(adst(&[4096, 0, 0, 0]), [1321, 2482, 3344, 3803]);
+        assert_eq!(adst(&[0, 4096, 0, 0]), [3344, 3344, 0, -3344]);
+        assert_eq!(adst(&[0, 0, 4096, 0]), [3803, -1321, -3344, 2482]);
+        assert_eq!(adst(&[0, 0, 0, 4096]), [2482, -3803, 3344, -1321]);
+    }
+
+    #[test]
+    fn close_to_direct_transform() {
+        let mut rng = Xorshift(0x5851f42d4c957f2d);
+        for n in 2..=4 {
+            let size = 1 << n;
+            for i in 0..size + 5000 {
+                let mut input = vec![0; size];
+                if i < size {
+                    input[i] = 4000;
+                } else {
+                    input.iter_mut().for_each(|v| *v = rng.next(1024));
+                }
+
+                let output = adst(&input);
+                for (a, b) in output.iter().zip(direct_adst(&input)) {
+                    assert!((a - b).abs() <= n * 2, "n {n} {input:?}");
+                }
+            }
+        }
+    }
+}
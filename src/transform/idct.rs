@@ -0,0 +1,514 @@
+//! The inverse DCT process.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#inverse-dct-process
+
+use super::{brev, butterfly as b, hadamard as h};
+
+/// The inverse DCT array permutation process, reorders the first 2^n
+/// elements of T into bit reversed order.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-dct-array-permutation-process
+pub fn inverse_dct_array_permutation(t: &mut [i32], n: u32) {
+    let mut copy = [0; 64];
+    copy[..1 << n].copy_from_slice(&t[..1 << n]);
+    for (i, v) in t[..1 << n].iter_mut().enumerate() {
+        *v = copy[brev(n, i)];
+    }
+}
+
+/// The inverse DCT process, applies the 2^n point inverse DCT in place to the
+/// first 2^n elements of T, `n` is in the range 2..=6 and `r` is the number
+/// of bits the intermediate results are clamped to.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-dct-process
+pub fn inverse_dct(t: &mut [i32], n: u32, r: u32) {
+    inverse_dct_array_permutation(t, n);
+
+    if n == 6 {
+        for i in 0..16 {
+            b(t, 32 + i, 63 - i, 63 - 4 * brev(4, i) as i32, false);
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..8 {
+            b(t, 16 + i, 31 - i, 6 + ((brev(3, 7 - i) as i32) << 3), false);
+        }
+    }
+
+    if n == 6 {
+        for i in 0..16 {
+            h(t, 32 + i * 2, 33 + i * 2, i & 1 == 1, r);
+        }
+    }
+
+    if n >= 4 {
+        for i in 0..4 {
+            b(t, 8 + i, 15 - i, 12 + ((brev(2, 3 - i) as i32) << 4), false);
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..8 {
+            h(t, 16 + 2 * i, 17 + 2 * i, i & 1 == 1, r);
+        }
+    }
+
+    if n == 6 {
+        for i in 0..4 {
+            for j in 0..2 {
+                let angle = 60 - 16 * brev(2, i) as i32 + 64 * j as i32;
+                b(t, 62 - i * 4 - j, 33 + i * 4 + j, angle, true);
+            }
+        }
+    }
+
+    if n >= 3 {
+        for i in 0..2 {
+            b(t, 4 + i, 7 - i, 56 - 32 * i as i32, false);
+        }
+    }
+
+    if n >= 4 {
+        for i in 0..4 {
+            h(t, 8 + 2 * i, 9 + 2 * i, i & 1 == 1, r);
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..2 {
+            for j in 0..2 {
+                let angle = 24 + ((j as i32) << 6) + ((1 - i as i32) << 5);
+                b(t, 30 - 4 * i - j, 17 + 4 * i + j, angle, true);
+            }
+        }
+    }
+
+    if n == 6 {
+        for i in 0..8 {
+            for j in 0..2 {
+                h(t, 32 + i * 4 + j, 35 + i * 4 - j, i & 1 == 1, r);
+            }
+        }
+    }
+
+    for i in 0..2 {
+        b(t, 2 * i, 2 * i + 1, 32 + 16 * i as i32, i == 0);
+    }
+
+    if n >= 3 {
+        for i in 0..2 {
+            h(t, 4 + 2 * i, 5 + 2 * i, i == 1, r);
+        }
+    }
+
+    for i in 0..2 {
+        h(t, i, 3 - i, false, r);
+    }
+
+    if n >= 3 {
+        b(t, 6, 5, 32, true);
+    }
+
+    if n >= 4 {
+        for i in 0..2 {
+            b(t, 14 - i, 9 + i, 48 + 64 * i as i32, true);
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..4 {
+            for j in 0..2 {
+                h(t, 16 + 4 * i + j, 19 + 4 * i - j, i & 1 == 1, r);
+            }
+        }
+    }
+
+    if n == 6 {
+        for i in 0..2 {
+            for j in 0..4 {
+                let angle = 56 - i as i32 * 32 + (j as i32 >> 1) * 64;
+                b(t, 61 - i * 8 - j, 34 + i * 8 + j, angle, true);
+            }
+        }
+    }
+
+    if n >= 3 {
+        for i in 0..4 {
+            h(t, i, 7 - i, false, r);
+        }
+    }
+
+    if n >= 4 {
+        for i in 0..2 {
+            for j in 0..2 {
+                h(t, 8 + 4 * i + j, 11 + 4 * i - j, i == 1, r);
+            }
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..2 {
+            for j in 0..2 {
+                b(
+                    t,
+                    29 - i * 2 - j,
+                    18 + i * 2 + j,
+                    48 + ((i as i32) << 6),
+                    true,
+                );
+            }
+        }
+    }
+
+    if n == 6 {
+        for i in 0..4 {
+            for j in 0..4 {
+                h(t, 32 + 8 * i + j, 39 + 8 * i - j, i & 1 == 1, r);
+            }
+        }
+    }
+
+    if n >= 4 {
+        for i in 0..2 {
+            b(t, 13 - i, 10 + i, 32, true);
+        }
+    }
+
+    if n >= 4 {
+        for i in 0..8 {
+            h(t, i, 15 - i, false, r);
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..2 {
+            for j in 0..4 {
+                h(t, 16 + 8 * i + j, 23 + 8 * i - j, i == 1, r);
+            }
+        }
+    }
+
+    if n == 6 {
+        for i in 0..2 {
+            for j in 0..4 {
+                b(
+                    t,
+                    59 - i * 4 - j,
+                    36 + i * 4 + j,
+                    48 + ((i as i32) << 6),
+                    true,
+                );
+            }
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..4 {
+            b(t, 27 - i, 20 + i, 32, true);
+        }
+    }
+
+    if n == 6 {
+        for i in 0..2 {
+            for j in 0..8 {
+                h(t, 32 + i * 16 + j, 47 + i * 16 - j, i == 1, r);
+            }
+        }
+    }
+
+    if n >= 5 {
+        for i in 0..16 {
+            h(t, i, 31 - i, false, r);
+        }
+    }
+
+    if n == 6 {
+        for i in 0..8 {
+            b(t, 55 - i, 40 + i, 32, true);
+        }
+    }
+
+    if n == 6 {
+        for i in 0..32 {
+            h(t, i, 63 - i, false, r);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::{cos128, Xorshift};
+
+    // the intermediate clamping range of the row transforms of 8 bit streams.
+    const R: u32 = 16;
+
+    fn idct(input: &[i32]) -> Vec<i32> {
+        let mut t = [0; 64];
+        t[..input.len()].copy_from_slice(input);
+        inverse_dct(&mut t, input.len().trailing_zeros(), R);
+        t[..input.len()].to_vec()
+    }
+
+    fn half_btf(w0: i32, in0: i32, w1: i32, in1: i32) -> i32 {
+        (w0 * in0 + w1 * in1 + (1 << 11)) >> 12
+    }
+
+    // the 4 point inverse DCT written out stage by stage from the flow graph
+    // of the spec.
+    fn reference_idct4(x: &[i32]) -> [i32; 4] {
+        let s0 = half_btf(2896, x[0], 2896, x[2]);
+        let s1 = half_btf(2896, x[0], -2896, x[2]);
+        let s2 = half_btf(1567, x[1], -3784, x[3]);
+        let s3 = half_btf(3784, x[1], 1567, x[3]);
+        [s0 + s3, s1 + s2, s1 - s2, s0 - s3]
+    }
+
+    // the 8 point inverse DCT, the even half is the 4 point inverse DCT.
+    fn reference_idct8(x: &[i32]) -> [i32; 8] {
+        let even = reference_idct4(&[x[0], x[2], x[4], x[6]]);
+        let s4 = half_btf(799, x[1], -4017, x[7]);
+        let s5 = half_btf(3406, x[5], -2276, x[3]);
+        let s6 = half_btf(2276, x[5], 3406, x[3]);
+        let s7 = half_btf(4017, x[1], 799, x[7]);
+        let (a4, a5) = (s4 + s5, s4 - s5);
+        let (a6, a7) = (s7 - s6, s7 + s6);
+        let b5 = half_btf(-2896, a5, 2896, a6);
+        let b6 = half_btf(2896, a5, 2896, a6);
+        [
+            even[0] + a7,
+            even[1] + b6,
+            even[2] + b5,
+            even[3] + a4,
+            even[3] - a4,
+            even[2] - b5,
+            even[1] - b6,
+            even[0] - a7,
+        ]
+    }
+
+    fn clamp(v: i32) -> i32 {
+        v.clamp(-(1 << (R - 1)), (1 << (R - 1)) - 1)
+    }
+
+    // the 2^n point inverse DCT written out as the stages of the butterfly
+    // flow graph of the spec, for any n. The inputs are bit reversed, the
+    // even half of the 2^k point stage holds the 2^(k-1) point inverse DCT
+    // and its odd half is rotated by the input angles and then by pairs of
+    // Hadamard and rotation stages of growing size, before the halves are
+    // added together.
+    fn reference_idct(x: &[i32]) -> Vec<i32> {
+        let size = x.len();
+        let n = size.trailing_zeros();
+        let cospi = |angle: usize| cos128(angle as i32);
+        let mut t: Vec<i32> = (0..size).map(|i| x[brev(n, i)]).collect();
+
+        let (t0, t1) = (t[0], t[1]);
+        t[0] = half_btf(cospi(32), t0, cospi(32), t1);
+        t[1] = half_btf(cospi(32), t0, -cospi(32), t1);
+
+        let mut s = 4;
+        while s <= size {
+            let (o, l) = (s / 2, s / 2);
+            let mirror = |a: usize| o + l - 1 - (a - o);
+
+            for i in 0..s / 4 {
+                let (a, b) = (o + i, s - 1 - i);
+                let c = 64 - 64 / s * brev(s.trailing_zeros(), o + i);
+                let (ta, tb) = (t[a], t[b]);
+                t[a] = half_btf(cospi(c), ta, -cospi(64 - c), tb);
+                t[b] = half_btf(cospi(64 - c), ta, cospi(c), tb);
+            }
+
+            let mut h = 2;
+            while h <= l / 2 {
+                for bs in (o..o + l).step_by(2 * h) {
+                    for i in 0..h / 2 {
+                        let (p, q) = (bs + i, bs + h - 1 - i);
+                        let (tp, tq) = (t[p], t[q]);
+                        t[p] = clamp(tp + tq);
+                        t[q] = clamp(tp - tq);
+
+                        let (p, q) = (bs + h + i, bs + 2 * h - 1 - i);
+                        let (tp, tq) = (t[p], t[q]);
+                        t[p] = clamp(tq - tp);
+                        t[q] = clamp(tp + tq);
+                    }
+                }
+
+                if h == l / 2 {
+                    for a in o + l / 4..o + l / 2 {
+                        let b = mirror(a);
+                        let (ta, tb) = (t[a], t[b]);
+                        t[a] = half_btf(-cospi(32), ta, cospi(32), tb);
+                        t[b] = half_btf(cospi(32), ta, cospi(32), tb);
+                    }
+                } else {
+                    let blocks = l / (4 * h);
+                    for j in 0..blocks {
+                        let angle = 128 * h / s * (1 + 4 * brev(blocks.trailing_zeros(), j));
+                        let bs = o + j * 2 * h;
+                        for a in bs + h / 2..bs + 3 * h / 2 {
+                            let b = mirror(a);
+                            let (ta, tb) = (t[a], t[b]);
+                            if a < bs + h {
+                                t[a] = half_btf(-cospi(angle), ta, cospi(64 - angle), tb);
+                                t[b] = half_btf(cospi(64 - angle), ta, cospi(angle), tb);
+                            } else {
+                                t[a] = half_btf(-cospi(64 - angle), ta, -cospi(angle), tb);
+                                t[b] = half_btf(-cospi(angle), ta, cospi(64 - angle), tb);
+                            }
+                        }
+                    }
+                }
+
+                h *= 2;
+            }
+
+            for i in 0..s / 2 {
+                let (ti, tj) = (t[i], t[s - 1 - i]);
+                t[i] = clamp(ti + tj);
+                t[s - 1 - i] = clamp(ti - tj);
+            }
+
+            s *= 2;
+        }
+
+        t
+    }
+
+    // the inverse DCT as a matrix product with the cosines of the spec, in
+    // a 12 bit fixed point and rounded once.
+    fn direct_idct(x: &[i32]) -> Vec<i32> {
+        let n = x.len() as i32;
+        (0..n)
+            .map(|i| {
+                let sum = x.iter().enumerate().fold(0, |sum, (k, &v)| {
+                    let angle = if k == 0 {
+                        32
+                    } else {
+                        (2 * i + 1) * k as i32 * 64 / n
+                    };
+                    sum + v as i64 * cos128(angle) as i64
+                });
+
+                ((sum + (1 << 11)) >> 12) as i32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn array_permutation() {
+        let mut t: Vec<i32> = (0..16).collect();
+        inverse_dct_array_permutation(&mut t, 4);
+        assert_eq!(t, [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15]);
+    }
+
+    #[test]
+    fn dc_only() {
+        for n in 2..=6 {
+            for dc in [-4096, -1000, -1, 0, 1, 7, 1000, 4095] {
+                let mut input = vec![0; 1 << n];
+                input[0] = dc;
+                let expected = (dc * 2896 + (1 << 11)) >> 12;
+                assert!(idct(&input).iter().all(|&v| v == expected), "n {n} dc {dc}");
+            }
+        }
+    }
+
+    #[test]
+    fn matches_staged_reference() {
+        let mut rng = Xorshift(0x2545f4914f6cdd1d);
+        for i in 0..20000 {
+            let mut input = [0; 8];
+            if i < 8 {
+                input[i] = 1000;
+            } else {
+                input.iter_mut().for_each(|v| *v = rng.next(4096));
+            }
+
+            assert_eq!(idct(&input[..4]), reference_idct4(&input[..4]));
+            assert_eq!(idct(&input), reference_idct8(&input));
+        }
+    }
+
+    #[test]
+    fn even_coefficients_recurse() {
+        let mut rng = Xorshift(0x9e3779b97f4a7c15);
+        for n in 3..=6 {
+            let size = 1 << n;
+            for _ in 0..2000 {
+                let half: Vec<i32> = (0..size / 2).map(|_| rng.next(4096)).collect();
+                let mut input = vec![0; size];
+                for (i, &v) in half.iter().enumerate() {
+                    input[2 * i] = v;
+                }
+
+                let half = idct(&half);
+                let output = idct(&input);
+                for i in 0..size / 2 {
+                    assert_eq!(output[i], half[i]);
+                    assert_eq!(output[size - 1 - i], half[i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_butterfly_reference() {
+        let mut rng = Xorshift(0x6a09e667f3bcc908);
+        let (min, max) = (i16::MIN as i32, i16::MAX as i32);
+        for n in 2..=6 {
+            let size = 1 << n;
+            for i in 0..size + 20000 {
+                // impulses, the extremes of the clamping range and random
+                // inputs.
+                let input: Vec<i32> = if i < size {
+                    (0..size).map(|k| if k == i { max } else { 0 }).collect()
+                } else if i < size + 10000 {
+                    (0..size)
+                        .map(|_| [min, max][rng.next_u64() as usize & 1])
+                        .collect()
+                } else {
+                    (0..size).map(|_| rng.next(1 << 15)).collect()
+                };
+
+                assert_eq!(idct(&input), reference_idct(&input), "n {n} {input:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn close_to_direct_transform() {
+        let mut rng = Xorshift(0xdeadbeefcafe);
+        for n in 2..=6 {
+            let size = 1 << n;
+            for i in 0..size + 2000 {
+                let mut input = vec![0; size];
+                if i < size {
+                    input[i] = 4000;
+                } else {
+                    input.iter_mut().for_each(|v| *v = rng.next(1024));
+                }
+
+                let output = idct(&input);
+                for (a, b) in output.iter().zip(direct_idct(&input)) {
+                    assert!((a - b).abs() <= n * 2, "n {n} {input:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn intermediate_results_are_clamped() {
+        let mut input = [0; 64];
+        input[0] = i16::MAX as i32;
+        input[1] = i16::MAX as i32;
+        let mut t = input;
+        inverse_dct(&mut t, 6, R);
+        assert!(t
+            .iter()
+            .all(|&v| (i16::MIN as i32..=i16::MAX as i32).contains(&v)));
+    }
+}
@@ -0,0 +1,373 @@
+//! The inverse transform processes, which turn the dequantized coefficients
+//! of a transform block into residuals.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#inverse-transform-process
+
+pub mod adst;
+pub mod idct;
+pub mod wht;
+
+use crate::{
+    constants::{
+        ADST_ADST, ADST_DCT, ADST_FLIPADST, DCT_ADST, DCT_DCT, DCT_FLIPADST, FLIPADST_ADST,
+        FLIPADST_DCT, FLIPADST_FLIPADST, H_ADST, H_DCT, IDTX, V_ADST, V_DCT, V_FLIPADST, WHT_WHT,
+    },
+    tables::{TX_HEIGHT_LOG2, TX_WIDTH_LOG2},
+    util::{clip3, round2},
+};
+
+/// Cos128_Lookup
+pub const COS128_LOOKUP: [i32; 65] = [
+    4096, 4095, 4091, 4085, 4076, 4065, 4052, 4036, 4017, 3996, 3973, 3948, 3920, 3889, 3857, 3822,
+    3784, 3745, 3703, 3659, 3612, 3564, 3513, 3461, 3406, 3349, 3290, 3229, 3166, 3102, 3035, 2967,
+    2896, 2824, 2751, 2675, 2598, 2520, 2440, 2359, 2276, 2191, 2106, 2019, 1931, 1842, 1751, 1660,
+    1567, 1474, 1380, 1285, 1189, 1092, 995, 897, 799, 700, 601, 501, 401, 301, 201, 101, 0,
+];
+
+/// Transform_Row_Shift
+pub const TRANSFORM_ROW_SHIFT: [u32; 19] =
+    [0, 1, 2, 2, 2, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2];
+
+/// brev( numBits, x ), the bit reversal of the lowest numBits bits of x.
+#[inline]
+pub fn brev(num_bits: u32, x: usize) -> usize {
+    (0..num_bits).fold(0, |result, i| {
+        result | (((x >> i) & 1) << (num_bits - 1 - i))
+    })
+}
+
+/// cos128( angle ), 4096 times the cosine of angle * pi / 128.
+#[inline]
+pub fn cos128(angle: i32) -> i32 {
+    let angle = (angle & 255) as usize;
+    match angle {
+        0..=64 => COS128_LOOKUP[angle],
+        65..=128 => -COS128_LOOKUP[128 - angle],
+        129..=192 => -COS128_LOOKUP[angle - 128],
+        _ => COS128_LOOKUP[256 - angle],
+    }
+}
+
+/// sin128( angle )
+#[inline]
+pub fn sin128(angle: i32) -> i32 {
+    cos128(angle - 64)
+}
+
+/// B( a, b, angle, flip ), a butterfly rotation of T[a] and T[b] by the
+/// angle, the results are exchanged when flip is set.
+#[inline]
+pub fn butterfly(t: &mut [i32], a: usize, b: usize, angle: i32, flip: bool) {
+    let (cos, sin) = (cos128(angle) as i64, sin128(angle) as i64);
+    let x = t[a] as i64 * cos - t[b] as i64 * sin;
+    let y = t[a] as i64 * sin + t[b] as i64 * cos;
+    t[a] = ((x + (1 << 11)) >> 12) as i32;
+    t[b] = ((y + (1 << 11)) >> 12) as i32;
+    if flip {
+        t.swap(a, b);
+    }
+}
+
+/// H( a, b, flip, r ), a Hadamard rotation of T[a] and T[b], the arguments
+/// are exchanged when flip is set.
+///
+/// Conformant streams keep the results in the range of a signed r bit
+/// integer, the results are clamped to that range like the intermediate
+/// values of the reference decoder.
+#[inline]
+pub fn hadamard(t: &mut [i32], a: usize, b: usize, flip: bool, r: u32) {
+    let (a, b) = if flip { (b, a) } else { (a, b) };
+    let (x, y) = (t[a], t[b]);
+    let max = (1 << (r - 1)) - 1;
+    let min = -(1 << (r - 1));
+    t[a] = clip3(min, max, x + y);
+    t[b] = clip3(min, max, x - y);
+}
+
+/// The 1D inverse transforms of the rows and of the columns of a transform
+/// type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform1D {
+    Dct,
+    Adst,
+    FlipAdst,
+    Identity,
+}
+
+impl Transform1D {
+    /// The 1D transforms of the columns and of the rows of the transform type.
+    pub fn from_tx_type(tx_type: u8) -> (Self, Self) {
+        use Transform1D::*;
+
+        match tx_type {
+            DCT_DCT => (Dct, Dct),
+            ADST_DCT => (Adst, Dct),
+            DCT_ADST => (Dct, Adst),
+            ADST_ADST => (Adst, Adst),
+            FLIPADST_DCT => (FlipAdst, Dct),
+            DCT_FLIPADST => (Dct, FlipAdst),
+            FLIPADST_FLIPADST => (FlipAdst, FlipAdst),
+            ADST_FLIPADST => (Adst, FlipAdst),
+            FLIPADST_ADST => (FlipAdst, Adst),
+            IDTX => (Identity, Identity),
+            V_DCT => (Dct, Identity),
+            H_DCT => (Identity, Dct),
+            V_ADST => (Adst, Identity),
+            H_ADST => (Identity, Adst),
+            V_FLIPADST => (FlipAdst, Identity),
+            // H_FLIPADST
+            _ => (Identity, FlipAdst),
+        }
+    }
+
+    /// Applies the 2^n point inverse transform in place to the first 2^n
+    /// elements of T, the output of FLIPADST is reversed.
+    pub fn inverse(self, t: &mut [i32], n: u32, r: u32) {
+        match self {
+            Self::Dct => idct::inverse_dct(t, n, r),
+            Self::Adst => adst::inverse_adst(t, n, r),
+            Self::FlipAdst => {
+                adst::inverse_adst(t, n, r);
+                t[..1 << n].reverse();
+            }
+            Self::Identity => inverse_identity(t, n),
+        }
+    }
+}
+
+/// The inverse identity transform process, scales the first 2^n elements of
+/// T, `n` is in the range 2..=5.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-identity-transform-process
+pub fn inverse_identity(t: &mut [i32], n: u32) {
+    for v in &mut t[..1 << n] {
+        *v = match n {
+            // the products need more than 32 bits for the 12 bit streams.
+            2 => ((*v as i64 * 5793 + (1 << 11)) >> 12) as i32,
+            3 => *v * 2,
+            4 => ((*v as i64 * 11586 + (1 << 11)) >> 12) as i32,
+            _ => *v * 4,
+        };
+    }
+}
+
+/// The 2D inverse transform process, applies the 1D inverse transforms of
+/// the transform type to the rows and then to the columns of the
+/// dequantized coefficients.
+///
+/// `dequant` is Dequant in raster order of the coded area of the transform
+/// block (at most 32x32), the residual is returned in raster order of the
+/// whole transform block, with the flips of FLIPADST already applied. The
+/// transform blocks of lossless segments are WHT_WHT.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#2d-inverse-transform-process
+pub fn inverse_transform_2d(dequant: &[i32], tx_size: u8, tx_type: u8, bit_depth: u8) -> Vec<i32> {
+    if tx_type == WHT_WHT {
+        return wht::inverse_wht_2d(dequant);
+    }
+
+    let log2w = TX_WIDTH_LOG2[tx_size as usize] as usize;
+    let log2h = TX_HEIGHT_LOG2[tx_size as usize] as usize;
+    let (w, h) = (1 << log2w, 1 << log2h);
+    let tw = w.min(32);
+    let (col_tx, row_tx) = Transform1D::from_tx_type(tx_type);
+    let row_shift = TRANSFORM_ROW_SHIFT[tx_size as usize];
+    let row_clamp_range = bit_depth as u32 + 8;
+    let col_clamp_range = (bit_depth as u32 + 6).max(16);
+
+    let mut residual = vec![0; w * h];
+    let mut t = [0; 64];
+    // the rows past the coded area have no coefficients and stay zero.
+    for i in 0..h.min(32) {
+        t.fill(0);
+        t[..tw].copy_from_slice(&dequant[i * tw..(i + 1) * tw]);
+        let max = (1 << (row_clamp_range - 1)) - 1;
+        let min = -(1 << (row_clamp_range - 1));
+        for v in &mut t[..w] {
+            if log2w.abs_diff(log2h) == 1 {
+                *v = round2(*v * 2896, 12);
+            }
+
+            *v = clip3(min, max, *v);
+        }
+
+        row_tx.inverse(&mut t, log2w as u32, row_clamp_range);
+        for (j, v) in t[..w].iter().enumerate() {
+            residual[i * w + j] = round2(*v, row_shift);
+        }
+    }
+
+    let max = (1 << (col_clamp_range - 1)) - 1;
+    let min = -(1 << (col_clamp_range - 1));
+    for j in 0..w {
+        for i in 0..h {
+            t[i] = clip3(min, max, residual[i * w + j]);
+        }
+
+        col_tx.inverse(&mut t, log2h as u32, col_clamp_range);
+        for i in 0..h {
+            residual[i * w + j] = round2(t[i], 4);
+        }
+    }
+
+    residual
+}
+
+/// A xorshift generator of the pseudo random inputs of the transform tests.
+#[cfg(test)]
+pub(crate) struct Xorshift(pub u64);
+
+#[cfg(test)]
+impl Xorshift {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A value in -amplitude..amplitude.
+    pub fn next(&mut self, amplitude: i32) -> i32 {
+        (self.next_u64() % (2 * amplitude as u64)) as i32 - amplitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{H_FLIPADST, TX_SIZES_ALL};
+
+    #[test]
+    fn cos128_and_sin128() {
+        assert_eq!(cos128(0), 4096);
+        assert_eq!(cos128(32), 2896);
+        assert_eq!(cos128(64), 0);
+        assert_eq!(cos128(96), -2896);
+        assert_eq!(cos128(128), -4096);
+        assert_eq!(cos128(224), 2896);
+        assert_eq!(cos128(-32), 2896);
+        assert_eq!(sin128(0), 0);
+        assert_eq!(sin128(64), 4096);
+        assert_eq!(sin128(160), -2896);
+    }
+
+    #[test]
+    fn brev_reverses_bits() {
+        assert_eq!(brev(2, 1), 2);
+        assert_eq!(brev(3, 1), 4);
+        assert_eq!(brev(4, 3), 12);
+        assert_eq!(brev(6, 0b100110), 0b011001);
+    }
+
+    #[test]
+    fn dc_only_blocks_are_flat() {
+        for tx_size in 0..TX_SIZES_ALL {
+            let log2w = TX_WIDTH_LOG2[tx_size as usize];
+            let log2h = TX_HEIGHT_LOG2[tx_size as usize];
+            let area = (1 << log2w.min(5)) * (1 << log2h.min(5));
+            for dc in [-2000, -64, 64, 1000, 32767] {
+                let mut dequant = vec![0; area];
+                dequant[0] = dc;
+
+                let mut v = dc;
+                if log2w.abs_diff(log2h) == 1 {
+                    v = round2(v * 2896, 12);
+                }
+
+                v = round2(round2(v * 2896, 12), TRANSFORM_ROW_SHIFT[tx_size as usize]);
+                let expected = round2(round2(v * 2896, 12), 4);
+
+                let residual = inverse_transform_2d(&dequant, tx_size, DCT_DCT, 8);
+                assert_eq!(residual.len(), 1 << (log2w + log2h));
+                assert!(residual.iter().all(|&r| r == expected), "{tx_size} {dc}");
+            }
+        }
+    }
+
+    #[test]
+    fn coded_area_spans_the_whole_block() {
+        // TX_64X64, a single coefficient in the second row of the coded area
+        // is a vertical half cosine over all of the 64 rows.
+        let mut dequant = vec![0; 32 * 32];
+        dequant[32] = 1024;
+        let residual = inverse_transform_2d(&dequant, 4, DCT_DCT, 8);
+        for i in 0..64 {
+            let row = &residual[i * 64..(i + 1) * 64];
+            assert!(row.iter().all(|&r| r == row[0]));
+            assert!((row[0] + residual[(63 - i) * 64]).abs() <= 1);
+        }
+
+        assert!(residual[0] > 0);
+    }
+
+    #[test]
+    fn transform_types_of_the_rows_and_columns() {
+        use Transform1D::*;
+
+        assert_eq!(Transform1D::from_tx_type(ADST_DCT), (Adst, Dct));
+        assert_eq!(Transform1D::from_tx_type(DCT_FLIPADST), (Dct, FlipAdst));
+        assert_eq!(Transform1D::from_tx_type(V_DCT), (Dct, Identity));
+        assert_eq!(Transform1D::from_tx_type(H_FLIPADST), (Identity, FlipAdst));
+    }
+
+    #[test]
+    fn identity_scaling() {
+        for (n, expected) in [(2, 5793), (3, 8192), (4, 11586), (5, 16384)] {
+            let mut t = [4096; 32];
+            inverse_identity(&mut t, n);
+            assert!(t[..1 << n].iter().all(|&v| v == expected));
+            assert!(t[1 << n..].iter().all(|&v| v == 4096));
+        }
+    }
+
+    #[test]
+    fn identity_scaling_of_12_bit_extremes() {
+        // the inputs of the row transforms of 12 bit streams are clamped to
+        // 20 bits.
+        let max = (1 << 19) - 1;
+        for (n, scale) in [(2, 5793), (3, 8192), (4, 11586), (5, 16384)] {
+            for v in [max, -max, -max - 1] {
+                let mut t = [v; 32];
+                inverse_identity(&mut t, n);
+                let expected = ((v as i64 * scale + (1 << 11)) >> 12) as i32;
+                assert!(t[..1 << n].iter().all(|&it| it == expected), "n {n} {v}");
+            }
+        }
+    }
+
+    #[test]
+    fn identity_keeps_samples_in_place() {
+        // TX_8X8, the identity transforms double the impulse twice, the row
+        // shift and the column shift divide it by 2 and 16.
+        let mut dequant = vec![0; 64];
+        dequant[19] = 128;
+        let residual = inverse_transform_2d(&dequant, 1, IDTX, 8);
+        for (pos, r) in residual.into_iter().enumerate() {
+            assert_eq!(r, if pos == 19 { 16 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn flipadst_flips_the_residual() {
+        let mut rng = Xorshift(0x853c49e6748fea9b);
+        for tx_size in [0, 1, 2, 5, 6, 9, 10, 13, 14] {
+            let log2w = TX_WIDTH_LOG2[tx_size as usize];
+            let log2h = TX_HEIGHT_LOG2[tx_size as usize];
+            let (w, h) = (1 << log2w, 1 << log2h);
+            let dequant: Vec<i32> = (0..w * h).map(|_| rng.next(256)).collect();
+
+            let adst = inverse_transform_2d(&dequant, tx_size, ADST_ADST, 10);
+            let flip_ud = inverse_transform_2d(&dequant, tx_size, FLIPADST_ADST, 10);
+            let flip_lr = inverse_transform_2d(&dequant, tx_size, ADST_FLIPADST, 10);
+            let flip = inverse_transform_2d(&dequant, tx_size, FLIPADST_FLIPADST, 10);
+            for i in 0..h {
+                for j in 0..w {
+                    let r = adst[i * w + j];
+                    assert_eq!(flip_ud[(h - 1 - i) * w + j], r);
+                    assert_eq!(flip_lr[i * w + w - 1 - j], r);
+                    assert_eq!(flip[(h - 1 - i) * w + w - 1 - j], r);
+                }
+            }
+        }
+    }
+}
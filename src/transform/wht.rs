@@ -0,0 +1,149 @@
+//! The inverse Walsh-Hadamard transform process of lossless blocks.
+//!
+//! see: https://aomediacodec.github.io/av1-spec/#inverse-walsh-hadamard-transform-process
+
+/// The inverse WHT process, applies the 4 point inverse WHT in place to the
+/// first 4 elements of T after shifting them right by `shift`.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#inverse-walsh-hadamard-transform-process
+pub fn inverse_wht(t: &mut [i32], shift: u32) {
+    let mut a = t[0] >> shift;
+    let mut c = t[1] >> shift;
+    let mut d = t[2] >> shift;
+    let mut b = t[3] >> shift;
+    a += c;
+    d -= b;
+    let e = (a - d) >> 1;
+    b = e - b;
+    c = e - c;
+    a -= b;
+    d += c;
+    t[..4].copy_from_slice(&[a, b, c, d]);
+}
+
+/// The 2D inverse transform process of lossless blocks, applies the inverse
+/// WHT to the rows and then to the columns of the 4x4 dequantized
+/// coefficients, without the intermediate rounding of the other transforms.
+///
+/// see: https://aomediacodec.github.io/av1-spec/#2d-inverse-transform-process
+pub fn inverse_wht_2d(dequant: &[i32]) -> Vec<i32> {
+    let mut residual = dequant[..16].to_vec();
+    for row in residual.chunks_exact_mut(4) {
+        inverse_wht(row, 2);
+    }
+
+    let mut t = [0; 4];
+    for j in 0..4 {
+        for i in 0..4 {
+            t[i] = residual[i * 4 + j];
+        }
+
+        inverse_wht(&mut t, 0);
+        for i in 0..4 {
+            residual[i * 4 + j] = t[i];
+        }
+    }
+
+    residual
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{NUM_QM_LEVELS, TX_4X4, WHT_WHT},
+        quant::{ac_q, dc_q, Dequantizer},
+        tile::coeffs::Coefficients,
+        transform::{inverse_transform_2d, Xorshift},
+        util::clip1,
+    };
+
+    // the forward WHT of the reference encoder, the coefficients are scaled
+    // by 4 like the ones of the other transforms.
+    fn forward_wht(residual: &[i32]) -> Vec<i32> {
+        fn wht(v: [i32; 4]) -> [i32; 4] {
+            let [mut a, mut b, mut c, mut d] = v;
+            a += b;
+            d -= c;
+            let e = (a - d) >> 1;
+            b = e - b;
+            c = e - c;
+            a -= c;
+            d += b;
+            [a, c, d, b]
+        }
+
+        let mut coeffs = vec![0; 16];
+        for j in 0..4 {
+            let column = wht([0, 1, 2, 3].map(|i| residual[i * 4 + j]));
+            for i in 0..4 {
+                coeffs[i * 4 + j] = column[i];
+            }
+        }
+
+        for row in coeffs.chunks_exact_mut(4) {
+            let v = wht([row[0], row[1], row[2], row[3]]);
+            for (c, v) in row.iter_mut().zip(v) {
+                *c = v * 4;
+            }
+        }
+
+        coeffs
+    }
+
+    #[test]
+    fn impulses() {
+        let mut t = [4, 0, 0, 0];
+        inverse_wht(&mut t, 2);
+        assert_eq!(t, [1, 0, 0, 0]);
+
+        let mut t = [0, 0, 0, 1];
+        inverse_wht(&mut t, 0);
+        assert_eq!(t, [1, -1, 0, -1]);
+    }
+
+    #[test]
+    fn lossless_round_trip() {
+        let mut rng = Xorshift(0x2545f4914f6cdd1d);
+        let mut next = |n: u64| rng.next_u64() % n;
+
+        for bit_depth in [8, 10, 12] {
+            let max = (1 << bit_depth) - 1;
+            let dequantizer = Dequantizer {
+                dc_quant: dc_q(bit_depth, 0),
+                ac_quant: ac_q(bit_depth, 0),
+                qm_level: NUM_QM_LEVELS - 1,
+                plane: 0,
+                bit_depth,
+            };
+
+            for _ in 0..2000 {
+                let source: Vec<i32> = (0..16).map(|_| next(max + 1) as i32).collect();
+                let prediction: Vec<i32> = (0..16).map(|_| next(max + 1) as i32).collect();
+                let residual: Vec<i32> =
+                    source.iter().zip(&prediction).map(|(s, p)| s - p).collect();
+
+                // the quantizer of lossless blocks is 4, the coefficients are
+                // coded exactly.
+                let quant: Vec<i32> = forward_wht(&residual).iter().map(|c| c / 4).collect();
+                let coefficients = Coefficients {
+                    // PlaneTxType of lossless blocks is DCT_DCT.
+                    tx_type: 0,
+                    eob: 16,
+                    quant,
+                };
+
+                let dequant = dequantizer.dequantize(TX_4X4, &coefficients);
+                let decoded = inverse_transform_2d(&dequant, TX_4X4, WHT_WHT, bit_depth);
+                assert_eq!(decoded, residual);
+
+                let reconstructed: Vec<i32> = prediction
+                    .iter()
+                    .zip(&decoded)
+                    .map(|(&p, &r)| clip1(p + r, bit_depth) as i32)
+                    .collect();
+                assert_eq!(reconstructed, source);
+            }
+        }
+    }
+}
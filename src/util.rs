@@ -73,3 +73,83 @@ impl<T> AtomicOption<T> {
         )
     }
 }
+
+/// Clip3( x, y, z ), clamps z into the range x..=y.
+#[inline]
+pub fn clip3<T: PartialOrd>(x: T, y: T, z: T) -> T {
+    if z < x {
+        x
+    } else if z > y {
+        y
+    } else {
+        z
+    }
+}
+
+/// Clip1( x ), clamps x into the range of a sample with the bit depth.
+#[inline]
+pub fn clip1(x: i32, bit_depth: u8) -> u16 {
+    clip3(0, (1 << bit_depth) - 1, x) as u16
+}
+
+/// Round2( x, n ), rounds x to the nearest multiple of 2^n, then shift right
+/// by n.
+#[inline]
+pub fn round2(x: i32, n: u32) -> i32 {
+    if n == 0 {
+        x
+    } else {
+        (x + (1 << (n - 1))) >> n
+    }
+}
+
+/// Round2Signed( x, n ), Round2 that rounds negative values symmetrically.
+#[inline]
+pub fn round2signed(x: i32, n: u32) -> i32 {
+    if x >= 0 {
+        round2(x, n)
+    } else {
+        -round2(-x, n)
+    }
+}
+
+/// FloorLog2( x ), requires x to be greater than 0.
+#[inline]
+pub fn floor_log2(x: u32) -> u32 {
+    31 - x.leading_zeros()
+}
+
+/// CeilLog2( x ), returns 0 when x is less than 2.
+#[inline]
+pub fn ceil_log2(x: u32) -> u32 {
+    if x < 2 {
+        0
+    } else {
+        32 - (x - 1).leading_zeros()
+    }
+}
+
+/// Returns the smallest value k such that blk_size << k is greater than or
+/// equal to target.
+#[inline]
+pub fn tile_log2(blk_size: u32, target: u32) -> u32 {
+    let mut k = 0;
+    while (blk_size << k) < target {
+        k += 1;
+    }
+
+    k
+}
+
+/// inverse_recenter( r, v ), maps v back to a value that was recentered
+/// around r.
+#[inline]
+pub fn inverse_recenter(r: i32, v: i32) -> i32 {
+    if v > 2 * r {
+        v
+    } else if v & 1 != 0 {
+        r - ((v + 1) >> 1)
+    } else {
+        r + (v >> 1)
+    }
+}